@@ -0,0 +1,244 @@
+use std::{
+    borrow::Borrow,
+    collections::hash_map::RandomState,
+    fmt::{Debug, Formatter},
+    hash::{BuildHasher, Hash},
+    slice,
+};
+
+use indexmap::{set, IndexSet};
+use smallvec::SmallVec;
+
+use crate::MAX_INLINE_SIZE;
+
+/// A set that keeps its items in insertion order. It stores up to `I` items
+/// inline without allocating, which is cheaper than an [IndexSet] when most
+/// sets are small, and switches to an [IndexSet] when it grows larger. Unlike
+/// [crate::AutoSet] the items are not hashed while they are inline.
+#[derive(Clone)]
+pub enum AutoIndexSet<K, H = RandomState, const I: usize = MAX_INLINE_SIZE> {
+    Inline(SmallVec<[K; I]>),
+    Set(Box<IndexSet<K, H>>),
+}
+
+impl<K, H, const I: usize> Default for AutoIndexSet<K, H, I> {
+    fn default() -> Self {
+        Self::Inline(SmallVec::new())
+    }
+}
+
+impl<K: Debug, H, const I: usize> Debug for AutoIndexSet<K, H, I> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K> AutoIndexSet<K, RandomState> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, H, const I: usize> AutoIndexSet<K, H, I> {
+    pub fn len(&self) -> usize {
+        match self {
+            AutoIndexSet::Inline(list) => list.len(),
+            AutoIndexSet::Set(set) => set.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            AutoIndexSet::Inline(list) => list.is_empty(),
+            AutoIndexSet::Set(set) => set.is_empty(),
+        }
+    }
+
+    /// Iterates over the items in insertion order.
+    pub fn iter(&self) -> Iter<'_, K> {
+        match self {
+            AutoIndexSet::Inline(list) => Iter::Inline(list.iter()),
+            AutoIndexSet::Set(set) => Iter::Set(set.iter()),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::Inline(SmallVec::new());
+    }
+}
+
+impl<K: Eq + Hash, H: BuildHasher + Default, const I: usize> AutoIndexSet<K, H, I> {
+    /// Adds the item at the end. Returns true when the item was not in the set
+    /// before, the position of an existing item doesn't change.
+    pub fn insert(&mut self, key: K) -> bool {
+        match self {
+            AutoIndexSet::Inline(list) => {
+                if list.contains(&key) {
+                    return false;
+                }
+                if list.len() < I {
+                    list.push(key);
+                    return true;
+                }
+                let mut set = IndexSet::with_capacity_and_hasher(I * 2, H::default());
+                set.extend(list.drain(..));
+                set.insert(key);
+                *self = AutoIndexSet::Set(Box::new(set));
+                true
+            }
+            AutoIndexSet::Set(set) => set.insert(key),
+        }
+    }
+
+    /// Removes the item and keeps the order of the remaining items. Returns
+    /// true when the item was in the set.
+    pub fn remove<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self {
+            AutoIndexSet::Inline(list) => match list.iter().position(|item| item.borrow() == key) {
+                Some(index) => {
+                    list.remove(index);
+                    true
+                }
+                None => false,
+            },
+            AutoIndexSet::Set(set) => set.shift_remove(key),
+        }
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self {
+            AutoIndexSet::Inline(list) => list.iter().any(|item| item.borrow() == key),
+            AutoIndexSet::Set(set) => set.contains(key),
+        }
+    }
+}
+
+impl<K: Eq + Hash, H: BuildHasher + Default, const I: usize> FromIterator<K>
+    for AutoIndexSet<K, H, I>
+{
+    fn from_iter<T: IntoIterator<Item = K>>(iter: T) -> Self {
+        let mut set = Self::default();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<K: Eq + Hash, H: BuildHasher + Default, const I: usize> Extend<K> for AutoIndexSet<K, H, I> {
+    fn extend<T: IntoIterator<Item = K>>(&mut self, iter: T) {
+        for key in iter {
+            self.insert(key);
+        }
+    }
+}
+
+pub enum Iter<'a, K> {
+    Inline(slice::Iter<'a, K>),
+    Set(set::Iter<'a, K>),
+}
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::Inline(iter) => iter.next(),
+            Iter::Set(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Iter::Inline(iter) => iter.size_hint(),
+            Iter::Set(iter) => iter.size_hint(),
+        }
+    }
+}
+
+pub enum IntoIter<K, const I: usize> {
+    Inline(smallvec::IntoIter<[K; I]>),
+    Set(set::IntoIter<K>),
+}
+
+impl<K, const I: usize> Iterator for IntoIter<K, I> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            IntoIter::Inline(iter) => iter.next(),
+            IntoIter::Set(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            IntoIter::Inline(iter) => iter.size_hint(),
+            IntoIter::Set(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<K, H, const I: usize> IntoIterator for AutoIndexSet<K, H, I> {
+    type Item = K;
+    type IntoIter = IntoIter<K, I>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            AutoIndexSet::Inline(list) => IntoIter::Inline(list.into_iter()),
+            AutoIndexSet::Set(set) => IntoIter::Set(set.into_iter()),
+        }
+    }
+}
+
+impl<'a, K, H, const I: usize> IntoIterator for &'a AutoIndexSet<K, H, I> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_insertion_order() {
+        let mut set: AutoIndexSet<_, RandomState, 2> = AutoIndexSet::default();
+        assert!(set.insert(3));
+        assert!(set.insert(1));
+        assert!(!set.insert(3));
+        assert!(matches!(set, AutoIndexSet::Inline(_)));
+        assert!(set.insert(2));
+        assert!(matches!(set, AutoIndexSet::Set(_)));
+        assert!(!set.insert(1));
+        assert_eq!(set.iter().copied().collect::<Vec<_>>(), vec![3, 1, 2]);
+        assert_eq!(set.clone().into_iter().collect::<Vec<_>>(), vec![3, 1, 2]);
+    }
+
+    #[test]
+    fn remove_keeps_order() {
+        let mut inline: AutoIndexSet<_> = [1, 2, 3].into_iter().collect();
+        let mut set: AutoIndexSet<_> = (0..10).collect();
+        assert!(inline.remove(&2));
+        assert!(!inline.remove(&2));
+        assert!(set.remove(&2));
+        assert_eq!(inline.iter().copied().collect::<Vec<_>>(), vec![1, 3]);
+        assert_eq!(
+            set.iter().copied().collect::<Vec<_>>(),
+            vec![0, 1, 3, 4, 5, 6, 7, 8, 9]
+        );
+        assert!(set.contains(&9) && !set.contains(&2));
+        set.clear();
+        assert!(set.is_empty());
+        assert!(matches!(set, AutoIndexSet::Inline(_)));
+    }
+}
@@ -0,0 +1,17 @@
+pub mod index_set;
+pub mod map;
+pub mod set;
+
+pub use index_set::AutoIndexSet;
+pub use map::AutoMap;
+pub use set::AutoSet;
+
+/// The default maximum number of entries that are stored in a list before
+/// switching to a hash map. It can be changed per type with the const
+/// parameter of [AutoMap] and [AutoSet].
+pub const MAX_LIST_SIZE: usize = 16;
+
+/// The default number of items that an [AutoIndexSet] stores inline before
+/// switching to an index set. It can be changed per type with the const
+/// parameter of [AutoIndexSet].
+pub const MAX_INLINE_SIZE: usize = 4;
@@ -0,0 +1,575 @@
+use std::{
+    borrow::Borrow,
+    collections::{
+        hash_map::{self, RandomState},
+        HashMap,
+    },
+    fmt::{Debug, Formatter},
+    hash::{BuildHasher, Hash},
+    mem::take,
+};
+
+use crate::MAX_LIST_SIZE;
+
+/// A hash map that stores a few entries in a list to save memory and hashing.
+/// It switches to a [HashMap] when it grows larger than `I` entries, which
+/// defaults to [MAX_LIST_SIZE].
+#[derive(Clone)]
+pub enum AutoMap<K, V, H = RandomState, const I: usize = MAX_LIST_SIZE> {
+    List(Vec<(K, V)>),
+    Map(Box<HashMap<K, V, H>>),
+}
+
+impl<K, V, H, const I: usize> Default for AutoMap<K, V, H, I> {
+    fn default() -> Self {
+        Self::List(Default::default())
+    }
+}
+
+impl<K: Debug, V: Debug, H, const I: usize> Debug for AutoMap<K, V, H, I> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K, V> AutoMap<K, V, RandomState> {
+    pub fn new() -> Self {
+        AutoMap::List(Vec::new())
+    }
+}
+
+impl<K, V, H, const I: usize> AutoMap<K, V, H, I> {
+    pub fn len(&self) -> usize {
+        match self {
+            AutoMap::List(list) => list.len(),
+            AutoMap::Map(map) => map.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            AutoMap::List(list) => list.is_empty(),
+            AutoMap::Map(map) => map.is_empty(),
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        match self {
+            AutoMap::List(list) => Iter::List(list.iter()),
+            AutoMap::Map(map) => Iter::Map(map.iter()),
+        }
+    }
+
+    /// Iterates over all entries with mutable references to the values.
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        match self {
+            AutoMap::List(list) => IterMut::List(list.iter_mut()),
+            AutoMap::Map(map) => IterMut::Map(map.iter_mut()),
+        }
+    }
+
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys(self.iter())
+    }
+
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values(self.iter())
+    }
+
+    pub fn values_mut(&mut self) -> ValuesMut<'_, K, V> {
+        ValuesMut(self.iter_mut())
+    }
+
+    /// Keeps only the entries for which the predicate returns true.
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        match self {
+            AutoMap::List(list) => list.retain_mut(|(k, v)| f(k, v)),
+            AutoMap::Map(map) => map.retain(|k, v| f(k, v)),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        *self = AutoMap::List(Vec::new());
+    }
+}
+
+impl<K: Eq + Hash, V, H: BuildHasher + Default, const I: usize> AutoMap<K, V, H, I> {
+    /// Creates a map with room for `capacity` entries, so filling it up to
+    /// that size allocates once.
+    pub fn with_capacity(capacity: usize) -> Self {
+        if capacity <= I {
+            AutoMap::List(Vec::with_capacity(capacity))
+        } else {
+            AutoMap::Map(Box::new(HashMap::with_capacity_and_hasher(
+                capacity,
+                H::default(),
+            )))
+        }
+    }
+
+    fn convert_to_map(&mut self) -> &mut HashMap<K, V, H> {
+        if let AutoMap::List(list) = self {
+            let mut map = HashMap::with_capacity_and_hasher(I * 2, H::default());
+            map.extend(take(list));
+            *self = AutoMap::Map(Box::new(map));
+        }
+        match self {
+            AutoMap::Map(map) => map,
+            AutoMap::List(_) => unreachable!(),
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self {
+            AutoMap::List(list) => {
+                for (k, v) in list.iter_mut() {
+                    if *k == key {
+                        return Some(std::mem::replace(v, value));
+                    }
+                }
+                if list.len() < I {
+                    list.push((key, value));
+                    None
+                } else {
+                    self.convert_to_map().insert(key, value)
+                }
+            }
+            AutoMap::Map(map) => map.insert(key, value),
+        }
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self {
+            AutoMap::List(list) => {
+                let index = list.iter().position(|(k, _)| k.borrow() == key)?;
+                Some(list.swap_remove(index).1)
+            }
+            AutoMap::Map(map) => map.remove(key),
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self {
+            AutoMap::List(list) => list
+                .iter()
+                .find_map(|(k, v)| (k.borrow() == key).then_some(v)),
+            AutoMap::Map(map) => map.get(key),
+        }
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self {
+            AutoMap::List(list) => list
+                .iter_mut()
+                .find_map(|(k, v)| ((*k).borrow() == key).then_some(v)),
+            AutoMap::Map(map) => map.get_mut(key),
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        if let AutoMap::List(list) = self {
+            if list.len() >= I && !list.iter().any(|(k, _)| *k == key) {
+                self.convert_to_map();
+            }
+        }
+        match self {
+            AutoMap::List(list) => match list.iter().position(|(k, _)| *k == key) {
+                Some(index) => Entry::Occupied(OccupiedEntry::List { list, index }),
+                None => Entry::Vacant(VacantEntry::List { list, key }),
+            },
+            AutoMap::Map(map) => match map.entry(key) {
+                hash_map::Entry::Occupied(entry) => Entry::Occupied(OccupiedEntry::Map(entry)),
+                hash_map::Entry::Vacant(entry) => Entry::Vacant(VacantEntry::Map(entry)),
+            },
+        }
+    }
+
+    /// Returns the value of the key, after inserting the default value when
+    /// the key is missing.
+    pub fn get_mut_or_default(&mut self, key: K) -> &mut V
+    where
+        V: Default,
+    {
+        self.entry(key).or_default()
+    }
+
+    /// Updates the value of the key in place, starting from the default value
+    /// when the key is missing. The key is removed when `update` returns
+    /// false, e.g. when a count dropped to zero. Only a single lookup is
+    /// needed in both representations.
+    pub fn update(&mut self, key: K, update: impl FnOnce(&mut V) -> bool)
+    where
+        V: Default,
+    {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => {
+                if !update(entry.get_mut()) {
+                    entry.remove();
+                }
+            }
+            Entry::Vacant(entry) => {
+                let mut value = V::default();
+                if update(&mut value) {
+                    entry.insert(value);
+                }
+            }
+        }
+    }
+}
+
+impl<K: Eq + Hash, V: PartialEq, H: BuildHasher, const I: usize> PartialEq for AutoMap<K, V, H, I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self.iter().all(|(k, v)| match other {
+                AutoMap::List(list) => list.iter().any(|(ok, ov)| ok == k && ov == v),
+                AutoMap::Map(map) => map.get(k) == Some(v),
+            })
+    }
+}
+
+impl<K: Eq + Hash, V: Eq, H: BuildHasher, const I: usize> Eq for AutoMap<K, V, H, I> {}
+
+impl<K: Eq + Hash, V, H: BuildHasher + Default, const I: usize> FromIterator<(K, V)>
+    for AutoMap<K, V, H, I>
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = Self::default();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Eq + Hash, V, H: BuildHasher + Default, const I: usize> Extend<(K, V)>
+    for AutoMap<K, V, H, I>
+{
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+pub enum Entry<'a, K, V> {
+    Occupied(OccupiedEntry<'a, K, V>),
+    Vacant(VacantEntry<'a, K, V>),
+}
+
+impl<'a, K: Eq + Hash, V> Entry<'a, K, V> {
+    pub fn or_insert_with(self, default: impl FnOnce() -> V) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+}
+
+impl<'a, K: Eq + Hash, V: Default> Entry<'a, K, V> {
+    pub fn or_default(self) -> &'a mut V {
+        self.or_insert_with(V::default)
+    }
+}
+
+pub enum OccupiedEntry<'a, K, V> {
+    List {
+        list: &'a mut Vec<(K, V)>,
+        index: usize,
+    },
+    Map(hash_map::OccupiedEntry<'a, K, V>),
+}
+
+impl<'a, K, V> OccupiedEntry<'a, K, V> {
+    pub fn get(&self) -> &V {
+        match self {
+            OccupiedEntry::List { list, index } => &list[*index].1,
+            OccupiedEntry::Map(entry) => entry.get(),
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        match self {
+            OccupiedEntry::List { list, index } => &mut list[*index].1,
+            OccupiedEntry::Map(entry) => entry.get_mut(),
+        }
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        match self {
+            OccupiedEntry::List { list, index } => &mut list[index].1,
+            OccupiedEntry::Map(entry) => entry.into_mut(),
+        }
+    }
+
+    pub fn remove(self) -> V {
+        match self {
+            OccupiedEntry::List { list, index } => list.swap_remove(index).1,
+            OccupiedEntry::Map(entry) => entry.remove(),
+        }
+    }
+}
+
+pub enum VacantEntry<'a, K, V> {
+    List { list: &'a mut Vec<(K, V)>, key: K },
+    Map(hash_map::VacantEntry<'a, K, V>),
+}
+
+impl<'a, K, V> VacantEntry<'a, K, V> {
+    pub fn insert(self, value: V) -> &'a mut V {
+        match self {
+            VacantEntry::List { list, key } => {
+                list.push((key, value));
+                &mut list.last_mut().unwrap().1
+            }
+            VacantEntry::Map(entry) => entry.insert(value),
+        }
+    }
+}
+
+pub enum Iter<'a, K, V> {
+    List(std::slice::Iter<'a, (K, V)>),
+    Map(hash_map::Iter<'a, K, V>),
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Iter::List(iter) => iter.next().map(|(k, v)| (k, v)),
+            Iter::Map(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Iter::List(iter) => iter.size_hint(),
+            Iter::Map(iter) => iter.size_hint(),
+        }
+    }
+}
+
+pub enum IterMut<'a, K, V> {
+    List(std::slice::IterMut<'a, (K, V)>),
+    Map(hash_map::IterMut<'a, K, V>),
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            IterMut::List(iter) => iter.next().map(|(k, v)| (&*k, v)),
+            IterMut::Map(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            IterMut::List(iter) => iter.size_hint(),
+            IterMut::Map(iter) => iter.size_hint(),
+        }
+    }
+}
+
+pub struct Keys<'a, K, V>(Iter<'a, K, V>);
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+pub struct Values<'a, K, V>(Iter<'a, K, V>);
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+pub struct ValuesMut<'a, K, V>(IterMut<'a, K, V>);
+
+impl<'a, K, V> Iterator for ValuesMut<'a, K, V> {
+    type Item = &'a mut V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(_, v)| v)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+pub enum IntoIter<K, V> {
+    List(std::vec::IntoIter<(K, V)>),
+    Map(hash_map::IntoIter<K, V>),
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            IntoIter::List(iter) => iter.next(),
+            IntoIter::Map(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            IntoIter::List(iter) => iter.size_hint(),
+            IntoIter::Map(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<K, V, H, const I: usize> IntoIterator for AutoMap<K, V, H, I> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self {
+            AutoMap::List(list) => IntoIter::List(list.into_iter()),
+            AutoMap::Map(map) => IntoIter::Map(map.into_iter()),
+        }
+    }
+}
+
+impl<'a, K, V, H, const I: usize> IntoIterator for &'a AutoMap<K, V, H, I> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, H, const I: usize> IntoIterator for &'a mut AutoMap<K, V, H, I> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_and_map() {
+        let mut map = AutoMap::new();
+        for i in 0..MAX_LIST_SIZE * 2 {
+            assert!(map.insert(i, i).is_none());
+            assert_eq!(matches!(map, AutoMap::List(_)), i < MAX_LIST_SIZE);
+        }
+        assert_eq!(map.len(), MAX_LIST_SIZE * 2);
+        assert_eq!(map.insert(1, 10), Some(1));
+        assert_eq!(map.get(&1), Some(&10));
+        assert_eq!(map.remove(&1), Some(10));
+        assert!(!map.contains_key(&1));
+        *map.entry(1).or_default() += 5;
+        assert_eq!(map.get(&1), Some(&5));
+    }
+
+    #[test]
+    fn custom_list_size() {
+        let mut map: AutoMap<_, _, RandomState, 4> = AutoMap::default();
+        for i in 0..8 {
+            map.insert(i, i);
+            assert_eq!(matches!(map, AutoMap::List(_)), i < 4);
+        }
+        *map.entry(8).or_default() += 1;
+        assert_eq!(map.get(&8), Some(&1));
+        assert_eq!(map.len(), 9);
+    }
+
+    #[test]
+    fn update_in_place() {
+        let mut map: AutoMap<_, i32, RandomState, 2> = AutoMap::default();
+        for i in 0..4 {
+            *map.get_mut_or_default(i) += 1;
+        }
+        *map.get_mut_or_default(0) += 1;
+        assert_eq!(map.get(&0), Some(&2));
+        let decrement = |count: &mut i32| {
+            *count -= 1;
+            *count != 0
+        };
+        map.update(0, decrement);
+        assert_eq!(map.get(&0), Some(&1));
+        map.update(0, decrement);
+        assert!(!map.contains_key(&0));
+        map.update(4, |count| {
+            *count += 3;
+            true
+        });
+        assert_eq!(map.get(&4), Some(&3));
+        map.update(5, |_| false);
+        assert!(!map.contains_key(&5));
+
+        let mut map: AutoMap<_, i32> = AutoMap::new();
+        map.update(1, |count| {
+            *count += 1;
+            true
+        });
+        map.update(1, decrement);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn iterators() {
+        let mut map: AutoMap<_, _> = [(1, 1), (2, 2), (3, 3)].into_iter().collect();
+        for value in map.values_mut() {
+            *value *= 10;
+        }
+        for (key, value) in map.iter_mut() {
+            *value += key;
+        }
+        let mut keys: Vec<_> = map.keys().copied().collect();
+        keys.sort();
+        assert_eq!(keys, vec![1, 2, 3]);
+        let mut values: Vec<_> = map.values().copied().collect();
+        values.sort();
+        assert_eq!(values, vec![11, 22, 33]);
+        map.retain(|key, _| *key != 2);
+        assert_eq!(map, [(3, 33), (1, 11)].into_iter().collect());
+    }
+}
@@ -0,0 +1,251 @@
+use std::{
+    borrow::Borrow,
+    collections::hash_map::RandomState,
+    fmt::{Debug, Formatter},
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{map, AutoMap, MAX_LIST_SIZE};
+
+/// A hash set that stores a few items in a list to save memory and hashing.
+/// See [AutoMap].
+#[derive(Clone)]
+pub struct AutoSet<K, H = RandomState, const I: usize = MAX_LIST_SIZE> {
+    map: AutoMap<K, (), H, I>,
+}
+
+impl<K, H, const I: usize> Default for AutoSet<K, H, I> {
+    fn default() -> Self {
+        Self {
+            map: Default::default(),
+        }
+    }
+}
+
+impl<K: Debug, H, const I: usize> Debug for AutoSet<K, H, I> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<K> AutoSet<K, RandomState> {
+    pub fn new() -> Self {
+        Self {
+            map: AutoMap::new(),
+        }
+    }
+}
+
+impl<K, H, const I: usize> AutoSet<K, H, I> {
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn iter(&self) -> Iter<'_, K> {
+        Iter(self.map.keys())
+    }
+
+    /// Keeps only the items for which the predicate returns true.
+    pub fn retain(&mut self, mut f: impl FnMut(&K) -> bool) {
+        self.map.retain(|k, _| f(k))
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear()
+    }
+}
+
+impl<K: Eq + Hash, H: BuildHasher + Default, const I: usize> AutoSet<K, H, I> {
+    /// Creates a set with room for `capacity` items, see
+    /// [AutoMap::with_capacity].
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            map: AutoMap::with_capacity(capacity),
+        }
+    }
+
+    /// Returns true when the item was not in the set before.
+    pub fn insert(&mut self, key: K) -> bool {
+        self.map.insert(key, ()).is_none()
+    }
+
+    /// Returns true when the item was in the set.
+    pub fn remove<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.remove(key).is_some()
+    }
+
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.contains_key(key)
+    }
+
+    /// Returns the items that are in this set but not in `other`.
+    pub fn difference<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a K> + 'a {
+        self.iter().filter(move |key| !other.contains(*key))
+    }
+
+    /// Returns the items that are in this set, in `other` or in both, the
+    /// items of this set first.
+    pub fn union<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a K> + 'a {
+        self.iter().chain(other.difference(self))
+    }
+
+    /// Returns the items that are in both sets. The smaller set is iterated
+    /// and its items are looked up in the larger one, so two small sets are
+    /// compared by scanning their lists without hashing.
+    pub fn intersection<'a>(&'a self, other: &'a Self) -> impl Iterator<Item = &'a K> + 'a {
+        let (small, large) = if self.len() <= other.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        small.iter().filter(move |key| large.contains(*key))
+    }
+
+    /// Returns true when every item of this set is in `other`.
+    pub fn is_subset(&self, other: &Self) -> bool {
+        self.len() <= other.len() && self.iter().all(|key| other.contains(key))
+    }
+}
+
+impl<K: Eq + Hash, H: BuildHasher, const I: usize> PartialEq for AutoSet<K, H, I> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<K: Eq + Hash, H: BuildHasher, const I: usize> Eq for AutoSet<K, H, I> {}
+
+impl<K: Eq + Hash, H: BuildHasher + Default, const I: usize> FromIterator<K> for AutoSet<K, H, I> {
+    fn from_iter<T: IntoIterator<Item = K>>(iter: T) -> Self {
+        let mut set = Self::default();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<K: Eq + Hash, H: BuildHasher + Default, const I: usize> Extend<K> for AutoSet<K, H, I> {
+    fn extend<T: IntoIterator<Item = K>>(&mut self, iter: T) {
+        self.map.extend(iter.into_iter().map(|k| (k, ())))
+    }
+}
+
+pub struct Iter<'a, K>(map::Keys<'a, K, ()>);
+
+impl<'a, K> Iterator for Iter<'a, K> {
+    type Item = &'a K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+pub struct IntoIter<K>(map::IntoIter<K, ()>);
+
+impl<K> Iterator for IntoIter<K> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, _)| k)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, H, const I: usize> IntoIterator for AutoSet<K, H, I> {
+    type Item = K;
+    type IntoIter = IntoIter<K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self.map.into_iter())
+    }
+}
+
+impl<'a, K, H, const I: usize> IntoIterator for &'a AutoSet<K, H, I> {
+    type Item = &'a K;
+    type IntoIter = Iter<'a, K>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_remove_retain() {
+        let mut set = AutoSet::new();
+        for i in 0..40 {
+            assert!(set.insert(i));
+        }
+        assert!(!set.insert(3));
+        assert!(set.remove(&3));
+        assert!(!set.contains(&3));
+        set.retain(|i| i % 2 == 0);
+        assert_eq!(set.len(), 20);
+        let mut items: Vec<_> = set.iter().copied().collect();
+        items.sort();
+        assert_eq!(items, (0..40).step_by(2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn difference_extend() {
+        let mut a: AutoSet<_> = [1, 2, 3].into_iter().collect();
+        let b: AutoSet<_> = [2, 3, 4].into_iter().collect();
+        assert_eq!(a.difference(&b).copied().collect::<Vec<_>>(), vec![1]);
+        a.extend(b.iter().copied());
+        assert_eq!(a.len(), 4);
+        assert_eq!(b.difference(&a).count(), 0);
+    }
+
+    #[test]
+    fn set_algebra() {
+        let sorted = |items: &mut dyn Iterator<Item = &i32>| {
+            let mut items: Vec<_> = items.copied().collect();
+            items.sort();
+            items
+        };
+        // Lists
+        let a: AutoSet<_> = [1, 2, 3].into_iter().collect();
+        let b: AutoSet<_> = [2, 3, 4].into_iter().collect();
+        assert_eq!(sorted(&mut a.union(&b)), vec![1, 2, 3, 4]);
+        assert_eq!(sorted(&mut a.intersection(&b)), vec![2, 3]);
+        assert!(!a.is_subset(&b));
+        // A list and a map
+        let c: AutoSet<_> = (0..40).collect();
+        assert_eq!(sorted(&mut a.union(&c)), (0..40).collect::<Vec<_>>());
+        assert_eq!(sorted(&mut c.intersection(&b)), vec![2, 3, 4]);
+        assert!(a.is_subset(&c) && b.is_subset(&c));
+        assert!(!c.is_subset(&a));
+        assert!(AutoSet::new().is_subset(&a));
+    }
+
+    #[test]
+    fn custom_list_size() {
+        let mut set: AutoSet<_, RandomState, 2> = AutoSet::default();
+        set.extend([1, 2]);
+        assert!(matches!(set.map, AutoMap::List(_)));
+        set.insert(3);
+        assert!(matches!(set.map, AutoMap::Map(_)));
+        assert!(set.contains(&1) && set.contains(&3));
+    }
+}
@@ -10,8 +10,8 @@ use turbo_tasks_memory::{
 };
 use turbopack_core::asset::AssetContentVc;
 use turbopack_dev_server::source::{
-    ContentSource, ContentSourceContent, ContentSourceData, ContentSourceDataFilter,
-    ContentSourceDataVary, ContentSourceResultVc, ContentSourceVc,
+    query::QueryValue, ContentSource, ContentSourceContent, ContentSourceData,
+    ContentSourceDataFilter, ContentSourceDataVary, ContentSourceResultVc, ContentSourceVc,
 };
 
 #[turbo_tasks::value(serialization = "none", eq = "manual", cell = "new", into = "new")]
@@ -76,7 +76,7 @@ impl ContentSource for TurboTasksSource {
                     let mut stats = Stats::new();
                     let b = tt.backend();
                     let active_only = query.contains_key("active");
-                    b.with_all_cached_tasks(|task| {
+                    let mut add = |task| {
                         stats.add_id_conditional(b, task, |_, info| {
                             (!active_only || info.active)
                                 && info
@@ -84,7 +84,13 @@ impl ContentSource for TurboTasksSource {
                                     .map(|executions| executions > 0)
                                     .unwrap_or(true)
                         });
-                    });
+                    };
+                    // Only shows the tasks with the tag, see `turbo_tasks::tag_task`
+                    if let Some(QueryValue::String(tag)) = query.get("tag") {
+                        b.tasks_with_tag(tag).into_iter().for_each(add);
+                    } else {
+                        b.with_all_cached_tasks(add);
+                    }
                     let tree = stats.treeify(ReferenceType::Dependency);
                     let table = viz::table::create_table(tree, tt.stats_type());
                     viz::table::wrap_html(&table)
@@ -95,7 +101,7 @@ impl ContentSource for TurboTasksSource {
                             path: path.to_string(),
                             vary: ContentSourceDataVary {
                                 query: Some(ContentSourceDataFilter::Subset(
-                                    ["active".to_string()].into(),
+                                    ["active".to_string(), "tag".to_string()].into(),
                                 )),
                                 ..Default::default()
                             },
@@ -0,0 +1,29 @@
+use auto_hash_map::AutoMap;
+use criterion::{black_box, BenchmarkId, Criterion};
+
+/// Measures [AutoMap] around the size where it switches from a list to a
+/// hash map.
+pub fn transition(c: &mut Criterion) {
+    let mut group = c.benchmark_group("turbo_tasks_bench_auto_map");
+
+    for size in [8u32, 15, 16, 17, 32] {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::new("insert", size), &size, |b, size| {
+            b.iter(|| {
+                let mut map = AutoMap::new();
+                for i in 0..*size {
+                    map.insert(i, i);
+                }
+                map
+            })
+        });
+        let map: AutoMap<u32, u32> = (0..size).map(|i| (i, i)).collect();
+        group.bench_with_input(BenchmarkId::new("get", size), &size, |b, size| {
+            b.iter(|| {
+                for i in 0..*size {
+                    black_box(map.get(&i));
+                }
+            })
+        });
+    }
+}
@@ -0,0 +1,91 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hash::BuildHasherDefault,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use auto_hash_map::AutoIndexSet;
+use criterion::{BenchmarkId, Criterion};
+use indexmap::IndexSet;
+use rustc_hash::FxHasher;
+use turbo_tasks::TaskId;
+
+type FxIndexSet = IndexSet<TaskId, BuildHasherDefault<FxHasher>>;
+type InlineSet = AutoIndexSet<TaskId, BuildHasherDefault<FxHasher>, 4>;
+
+const TASKS: usize = 1_000_000;
+
+/// Counts the allocated bytes, to report the memory used by the sets.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+fn children_sets<S: Default + Extend<TaskId>>(children: usize) -> Vec<S> {
+    (0..TASKS)
+        .map(|task| {
+            let mut set = S::default();
+            set.extend((0..children).map(|child| TaskId::from(task + child)));
+            set
+        })
+        .collect()
+}
+
+/// Returns the bytes used by the children sets of a million tasks, including
+/// the sets themselves.
+fn memory_per_million_tasks<S: Default + Extend<TaskId>>(children: usize) -> usize {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let sets = children_sets::<S>(children);
+    let used = ALLOCATED.load(Ordering::Relaxed).saturating_sub(before);
+    drop(sets);
+    used
+}
+
+/// Compares the memory and the time to build the children sets of a million
+/// tasks, with an [IndexSet] and with an [AutoIndexSet] that stores 4
+/// children inline.
+pub fn children_set(c: &mut Criterion) {
+    let mut group = c.benchmark_group("turbo_tasks_bench_children_set");
+    group.sample_size(10);
+
+    for children in [0, 1, 3, 4, 8] {
+        let mib = |bytes: usize| bytes as f64 / (1024.0 * 1024.0);
+        println!(
+            "{children} children per task, memory per 1M tasks: IndexSet {:.1} MiB, AutoIndexSet \
+             {:.1} MiB",
+            mib(memory_per_million_tasks::<FxIndexSet>(children)),
+            mib(memory_per_million_tasks::<InlineSet>(children)),
+        );
+        group.throughput(criterion::Throughput::Elements(TASKS as u64));
+        group.bench_with_input(
+            BenchmarkId::new("IndexSet", children),
+            &children,
+            |b, children| b.iter_with_large_drop(|| children_sets::<FxIndexSet>(*children)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("AutoIndexSet", children),
+            &children,
+            |b, children| b.iter_with_large_drop(|| children_sets::<InlineSet>(*children)),
+        );
+    }
+}
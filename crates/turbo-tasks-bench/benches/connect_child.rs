@@ -0,0 +1,50 @@
+use anyhow::Result;
+use criterion::{BenchmarkId, Criterion};
+use turbo_tasks::{CompletionVc, TryJoinIterExt, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+
+use super::register;
+
+/// Measures calling many new tasks from a single task, which connects all of
+/// them as children.
+pub fn fanout(c: &mut Criterion) {
+    register();
+
+    let mut group = c.benchmark_group("turbo_tasks_bench_connect_child");
+    group.sample_size(20);
+
+    for size in [10, 100, 1000, 10000] {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::new("fanout", size), &size, |b, size| {
+            let rt = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            let size = *size;
+
+            b.to_async(rt).iter_with_large_drop(move || {
+                let tt = TurboTasks::new(MemoryBackend::new());
+                async move {
+                    tt.run_once(async move {
+                        fanout_children(size).await?;
+                        Ok(())
+                    })
+                    .await
+                    .unwrap();
+                    tt
+                }
+            })
+        });
+    }
+}
+
+#[turbo_tasks::function]
+async fn fanout_children(size: u32) -> Result<CompletionVc> {
+    (0..size).map(child).try_join().await?;
+    Ok(CompletionVc::new())
+}
+
+#[turbo_tasks::function]
+fn child(_index: u32) -> CompletionVc {
+    CompletionVc::new()
+}
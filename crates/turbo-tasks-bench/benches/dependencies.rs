@@ -0,0 +1,73 @@
+use anyhow::Result;
+use criterion::{BenchmarkId, Criterion};
+use turbo_tasks::{CompletionVc, State, TryJoinIterExt, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+
+use super::register;
+
+/// Measures re-executing a task that has read many cells, which clears all
+/// dependencies of its previous execution.
+pub fn clear_dependencies(c: &mut Criterion) {
+    register();
+
+    let mut group = c.benchmark_group("turbo_tasks_bench_dependencies");
+    group.sample_size(20);
+
+    for size in [10, 100, 1000, 10000] {
+        group.throughput(criterion::Throughput::Elements(size as u64));
+        group.bench_with_input(BenchmarkId::new("clear", size), &size, |b, size| {
+            let rt = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            let size = *size;
+            let (tt, input, reader) = rt.block_on(async move {
+                let tt = TurboTasks::new(MemoryBackend::new());
+                let (input, reader) = tt
+                    .run_once(async move {
+                        let input = InputVc::cell(Input {
+                            value: State::new(0),
+                        });
+                        let reader = read_cells(input, size);
+                        reader.await?;
+                        Ok((input, reader))
+                    })
+                    .await
+                    .unwrap();
+                (tt, input, reader)
+            });
+
+            b.to_async(&rt).iter(|| {
+                let tt = tt.clone();
+                async move {
+                    tt.run_once(async move {
+                        input.await?.value.update(|value| *value += 1);
+                        // The reader is not active, so reading it executes it again
+                        reader.await?;
+                        Ok(())
+                    })
+                    .await
+                    .unwrap();
+                }
+            })
+        });
+    }
+}
+
+#[turbo_tasks::value(serialization = "none", cell = "new", eq = "manual")]
+struct Input {
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    value: State<u32>,
+}
+
+#[turbo_tasks::function]
+async fn read_cells(input: InputVc, size: u32) -> Result<CompletionVc> {
+    let _ = *input.await?.value.get();
+    (0..size).map(cell).try_join().await?;
+    Ok(CompletionVc::new())
+}
+
+#[turbo_tasks::function]
+fn cell(_index: u32) -> CompletionVc {
+    CompletionVc::new()
+}
@@ -0,0 +1,22 @@
+#![feature(min_specialization)]
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+pub(crate) mod auto_map;
+pub(crate) mod children_set;
+pub(crate) mod connect_child;
+pub(crate) mod dependencies;
+pub(crate) mod scope;
+
+criterion_group!(
+    name = turbo_tasks_bench;
+    config = Criterion::default();
+    targets = connect_child::fanout, scope::add_remove_scope, dependencies::clear_dependencies,
+        auto_map::transition, children_set::children_set
+);
+criterion_main!(turbo_tasks_bench);
+
+pub fn register() {
+    turbo_tasks::register();
+    include!(concat!(env!("OUT_DIR"), "/register_benches.rs"));
+}
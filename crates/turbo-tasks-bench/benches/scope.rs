@@ -0,0 +1,67 @@
+use anyhow::Result;
+use criterion::{BenchmarkId, Criterion};
+use turbo_tasks::{CompletionVc, RawVc, TryJoinIterExt, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+
+use super::register;
+
+/// Measures adding a tree of tasks to a scope and removing it again, which
+/// walks the tree with the add-to-scope and remove-from-scope queues.
+pub fn add_remove_scope(c: &mut Criterion) {
+    register();
+
+    let mut group = c.benchmark_group("turbo_tasks_bench_scope");
+    group.sample_size(20);
+
+    for depth in [2, 4, 6, 8] {
+        // A binary tree of tasks
+        let tasks = (1u64 << (depth + 1)) - 1;
+        group.throughput(criterion::Throughput::Elements(tasks));
+        group.bench_with_input(
+            BenchmarkId::new("add_remove", format_args!("{tasks} tasks")),
+            &depth,
+            |b, depth| {
+                let rt = tokio::runtime::Builder::new_multi_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+                let depth = *depth;
+                let (tt, root) = rt.block_on(async move {
+                    let tt = TurboTasks::new(MemoryBackend::new());
+                    // The tree is no longer in a scope when the once task has finished
+                    let root = tt
+                        .run_once(async move {
+                            let root = tree(depth, 0);
+                            root.await?;
+                            Ok(RawVc::from(root))
+                        })
+                        .await
+                        .unwrap();
+                    (tt, root)
+                });
+
+                b.to_async(&rt).iter(|| {
+                    let tt = tt.clone();
+                    async move {
+                        let guard = tt.keep_alive(root);
+                        tt.wait_foreground_done().await;
+                        drop(guard);
+                        tt.wait_foreground_done().await;
+                    }
+                })
+            },
+        );
+    }
+}
+
+#[turbo_tasks::function]
+async fn tree(depth: u32, index: u32) -> Result<CompletionVc> {
+    if depth > 0 {
+        [index * 2, index * 2 + 1]
+            .into_iter()
+            .map(|index| tree(depth - 1, index))
+            .try_join()
+            .await?;
+    }
+    Ok(CompletionVc::new())
+}
@@ -0,0 +1,4 @@
+//! Benchmarks of the operations of the memory backend that are sensitive to
+//! changes in `turbo-tasks-memory/src/task.rs`, like connecting children,
+//! scope updates and dependency tracking. Run them with
+//! `cargo bench -p turbo-tasks-bench`.
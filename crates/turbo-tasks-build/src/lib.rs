@@ -9,8 +9,8 @@ use std::{
 use anyhow::{Context, Result};
 use glob::glob;
 use syn::{
-    Attribute, Ident, Item, ItemEnum, ItemFn, ItemImpl, ItemMod, ItemStruct, ItemTrait, Path,
-    PathArguments, PathSegment, TraitItem, TraitItemMethod, Type, TypePath,
+    Attribute, Ident, Item, ItemEnum, ItemFn, ItemImpl, ItemMod, ItemStatic, ItemStruct, ItemTrait,
+    Path, PathArguments, PathSegment, TraitItem, TraitItemMethod, Type, TypePath,
 };
 use turbo_tasks_macros_shared::{
     get_function_ident, get_impl_function_ident, get_ref_ident, get_register_trait_methods_ident,
@@ -197,6 +197,7 @@ impl<'a> RegisterContext<'a> {
             Item::Fn(fn_item) => self.process_fn(fn_item),
             Item::Impl(impl_item) => self.process_impl(impl_item),
             Item::Mod(mod_item) => self.process_mod(mod_item),
+            Item::Static(static_item) => self.process_static(static_item),
             Item::Struct(struct_item) => self.process_struct(struct_item),
             Item::Trait(trait_item) => self.process_trait(trait_item),
             _ => Ok(()),
@@ -292,6 +293,24 @@ impl<'a> RegisterContext<'a> {
         Ok(())
     }
 
+    fn process_static(&mut self, static_item: ItemStatic) -> Result<()> {
+        // `static NAME: Projection<T, P> = Projection::new(...)`
+        if let Type::Path(TypePath {
+            qself: None,
+            path: Path { segments, .. },
+        }) = &*static_item.ty
+        {
+            if segments
+                .last()
+                .map_or(false, |segment| segment.ident == "Projection")
+            {
+                let ident = &static_item.ident;
+                self.register(ident, self.get_global_name(&[ident]))?;
+            }
+        }
+        Ok(())
+    }
+
     fn process_struct(&mut self, struct_item: ItemStruct) -> Result<()> {
         if has_attribute(&struct_item.attrs, "value") {
             self.add_value(&struct_item.ident);
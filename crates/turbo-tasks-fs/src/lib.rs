@@ -18,7 +18,7 @@ use std::{
     collections::{HashMap, HashSet},
     fmt::{self, Debug, Display, Formatter},
     fs::FileType,
-    io::{self, ErrorKind},
+    io::{self, ErrorKind, Read},
     mem::take,
     path::{Path, PathBuf, MAIN_SEPARATOR},
     sync::{
@@ -39,7 +39,7 @@ use read_glob::read_glob;
 pub use read_glob::{ReadGlobResult, ReadGlobResultVc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use tokio::{fs, io::AsyncReadExt};
+use tokio::fs;
 use turbo_tasks::{
     primitives::{BoolVc, StringReadRef, StringVc},
     spawn_thread,
@@ -298,7 +298,7 @@ impl FileSystem for DiskFileSystem {
         let full_path = self.to_sys_path(fs_path).await?;
         self.register_invalidator(&full_path, true);
 
-        let content = match retry_future(|| File::from_path(full_path.clone())).await {
+        let content = match File::from_path(full_path.clone()).await {
             Ok(file) => FileContent::new(file),
             Err(e) if e.kind() == ErrorKind::NotFound => FileContent::NotFound,
             Err(e) => {
@@ -1186,18 +1186,24 @@ pub struct File {
 }
 
 impl File {
-    /// Reads a [File] from the given path
+    /// Reads a [File] from the given path, retrying on transient errors.
+    ///
+    /// The file is opened and read in a single blocking call, instead of a
+    /// blocking call per operation and read chunk as with `tokio::fs`.
     async fn from_path(p: PathBuf) -> io::Result<Self> {
-        let mut file = fs::File::open(p).await?;
-        let metadata = file.metadata().await?;
+        retry_blocking(p, |path| {
+            let mut file = std::fs::File::open(path)?;
+            let metadata = file.metadata()?;
 
-        let mut output = Vec::with_capacity(metadata.len() as usize);
-        file.read_to_end(&mut output).await?;
+            let mut output = Vec::with_capacity(metadata.len() as usize);
+            file.read_to_end(&mut output)?;
 
-        Ok(File {
-            meta: metadata.into(),
-            content: Rope::from(output),
+            Ok(File {
+                meta: metadata.into(),
+                content: Rope::from(output),
+            })
         })
+        .await
     }
 
     /// Creates a [File] from raw bytes.
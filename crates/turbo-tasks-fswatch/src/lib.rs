@@ -0,0 +1,181 @@
+//! Invalidates tasks when the files they read change, without gluing a file
+//! watcher and [turbo_tasks::Invalidator]s together by hand.
+//!
+//! Tasks call [FileWatcher::register] with the paths they read. Events of the
+//! operating system are batched and deduplicated per path before the tasks
+//! registered for these paths are invalidated. A path stays watched until all
+//! tasks that registered for it have executed again without registering, or
+//! are no longer part of any scope.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, RecvError, TryRecvError},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use notify::{watcher, DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use turbo_tasks::{spawn_thread, InvalidationSource, ResourceWatcher};
+
+/// Watches the directories of the registered paths with [notify]. Watching
+/// the directory instead of the path itself also reports paths that don't
+/// exist yet, once they are created.
+pub struct NotifyWatcher {
+    /// `None` once the [FileWatcher] has been dropped.
+    watcher: Arc<Mutex<Option<RecommendedWatcher>>>,
+    /// The number of registered paths in each watched directory.
+    dirs: Mutex<HashMap<PathBuf, usize>>,
+}
+
+fn watched_dir(path: &Path) -> &Path {
+    path.parent().unwrap_or(path)
+}
+
+impl ResourceWatcher for NotifyWatcher {
+    type Key = PathBuf;
+
+    fn watch(&self, path: &PathBuf) {
+        let dir = watched_dir(path);
+        let mut dirs = self.dirs.lock().unwrap();
+        let count = dirs.entry(dir.to_path_buf()).or_default();
+        *count += 1;
+        if *count > 1 {
+            return;
+        }
+        if let Some(watcher) = &mut *self.watcher.lock().unwrap() {
+            if let Err(err) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+                tracing::warn!("unable to watch {}: {}", dir.display(), err);
+            }
+        }
+    }
+
+    fn unwatch(&self, path: &PathBuf) {
+        let dir = watched_dir(path);
+        let mut dirs = self.dirs.lock().unwrap();
+        let Some(count) = dirs.get_mut(dir) else {
+            return;
+        };
+        *count -= 1;
+        if *count > 0 {
+            return;
+        }
+        dirs.remove(dir);
+        if let Some(watcher) = &mut *self.watcher.lock().unwrap() {
+            // The directory might have been removed in the meantime
+            let _ = watcher.unwatch(dir);
+        }
+    }
+}
+
+/// Maps file system paths to the tasks that read them, see the crate
+/// documentation. Clones share the same watcher, which stops when the last
+/// clone is dropped.
+#[derive(Clone)]
+pub struct FileWatcher {
+    source: InvalidationSource<NotifyWatcher>,
+    _guard: Arc<WatcherGuard>,
+}
+
+/// Drops the watcher, which disconnects the event thread.
+struct WatcherGuard(Arc<Mutex<Option<RecommendedWatcher>>>);
+
+impl Drop for WatcherGuard {
+    fn drop(&mut self) {
+        self.0.lock().unwrap().take();
+    }
+}
+
+impl FileWatcher {
+    /// Starts watching. Events of the same path within `delay` are reported
+    /// once. Needs to be called on the runtime that executes the tasks, as
+    /// events are processed on a thread of it.
+    pub fn new(delay: Duration) -> Result<Self> {
+        let (tx, rx) = channel();
+        let watcher = Arc::new(Mutex::new(Some(watcher(tx, delay)?)));
+        let source = InvalidationSource::new(NotifyWatcher {
+            watcher: watcher.clone(),
+            dirs: Mutex::new(HashMap::new()),
+        });
+        let events_source = source.clone();
+        spawn_thread(move || process_events(rx, events_source));
+        Ok(Self {
+            source,
+            _guard: Arc::new(WatcherGuard(watcher)),
+        })
+    }
+
+    /// Registers the current task for the absolute `path`, so it's
+    /// invalidated when the file or directory at that path changes, is
+    /// created or is removed.
+    pub fn register(&self, path: impl Into<PathBuf>) {
+        self.source.register(path.into());
+    }
+
+    /// Returns true when any task is registered for the path.
+    pub fn is_watched(&self, path: &Path) -> bool {
+        self.source.is_watched(&path.to_path_buf())
+    }
+
+    /// The number of watched paths.
+    pub fn watched_count(&self) -> usize {
+        self.source.watched_count()
+    }
+}
+
+/// Invalidates the paths of batches of events until the watcher is dropped.
+fn process_events(rx: Receiver<DebouncedEvent>, source: InvalidationSource<NotifyWatcher>) {
+    let mut paths = HashSet::new();
+    loop {
+        let mut event = rx.recv().map_err(|RecvError| TryRecvError::Disconnected);
+        let mut invalidate_all = false;
+        loop {
+            match event {
+                Ok(DebouncedEvent::Write(path)) => {
+                    paths.insert(path);
+                }
+                Ok(DebouncedEvent::Create(path)) | Ok(DebouncedEvent::Remove(path)) => {
+                    // Listings of the parent directory changed too
+                    paths.insert(watched_dir(&path).to_path_buf());
+                    paths.insert(path);
+                }
+                Ok(DebouncedEvent::Rename(source, destination)) => {
+                    paths.insert(watched_dir(&source).to_path_buf());
+                    paths.insert(watched_dir(&destination).to_path_buf());
+                    paths.insert(source);
+                    paths.insert(destination);
+                }
+                Ok(DebouncedEvent::Rescan) | Ok(DebouncedEvent::Error(_, None)) => {
+                    invalidate_all = true;
+                }
+                Ok(DebouncedEvent::Error(err, Some(path))) => {
+                    tracing::warn!("watch error ({:?}): {:?}", path, err);
+                    paths.insert(path);
+                }
+                Ok(DebouncedEvent::Chmod(_))
+                | Ok(DebouncedEvent::NoticeRemove(_))
+                | Ok(DebouncedEvent::NoticeWrite(_)) => {
+                    // ignored
+                }
+                Err(TryRecvError::Disconnected) => {
+                    // The watcher has been dropped
+                    return;
+                }
+                Err(TryRecvError::Empty) => {
+                    break;
+                }
+            }
+            event = rx.try_recv();
+        }
+        if invalidate_all {
+            paths.clear();
+            source.invalidate_all();
+        }
+        for path in paths.drain() {
+            source.invalidate(&path);
+        }
+    }
+}
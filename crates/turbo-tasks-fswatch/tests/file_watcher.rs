@@ -0,0 +1,77 @@
+#![feature(min_specialization)]
+
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use turbo_tasks::{primitives::StringVc, NothingVc, TurboTasks};
+use turbo_tasks_fswatch::FileWatcher;
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static WATCHER: Mutex<Option<FileWatcher>> = Mutex::new(None);
+static WATCH: AtomicBool = AtomicBool::new(true);
+static EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn invalidates_tasks_of_changed_paths() {
+    lazy_static::initialize(&REGISTER);
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().canonicalize().unwrap().join("file.txt");
+    fs::write(&path, "a").unwrap();
+    let watcher = FileWatcher::new(Duration::from_millis(50)).unwrap();
+    *WATCHER.lock().unwrap() = Some(watcher.clone());
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let file = path.to_string_lossy().to_string();
+    tt.spawn_root_task(move || {
+        let file = file.clone();
+        Box::pin(async move {
+            read_file(file).await?;
+            Ok(NothingVc::new().into())
+        })
+    });
+    tt.await_all_roots().await;
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 1);
+    assert!(watcher.is_watched(&path));
+
+    // Writes within the delay are reported once
+    fs::write(&path, "b").unwrap();
+    fs::write(&path, "c").unwrap();
+    wait_until(|| EXECUTIONS.load(Ordering::SeqCst) == 2).await;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 2);
+    assert!(watcher.is_watched(&path));
+
+    // The path is released when the task executes again without registering
+    WATCH.store(false, Ordering::SeqCst);
+    fs::write(&path, "d").unwrap();
+    wait_until(|| !watcher.is_watched(&path)).await;
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 3);
+    assert_eq!(watcher.watched_count(), 0);
+}
+
+async fn wait_until(condition: impl Fn() -> bool) {
+    for _ in 0..100 {
+        if condition() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("timed out");
+}
+
+#[turbo_tasks::function]
+fn read_file(path: String) -> StringVc {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    if WATCH.load(Ordering::SeqCst) {
+        WATCHER.lock().unwrap().as_ref().unwrap().register(&path);
+    }
+    StringVc::cell(fs::read_to_string(&path).unwrap_or_default())
+}
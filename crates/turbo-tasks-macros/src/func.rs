@@ -1,9 +1,12 @@
 use proc_macro2::{Ident, Span, TokenStream as TokenStream2};
 use proc_macro_error::abort;
-use quote::quote;
+use quote::{quote, ToTokens};
 use syn::{
-    punctuated::Punctuated, spanned::Spanned, FnArg, Pat, PatIdent, PatType, Receiver, ReturnType,
-    Signature, Token, Type, TypePath, TypeReference,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    spanned::Spanned,
+    Attribute, Error, FnArg, LitInt, Pat, PatIdent, PatType, Receiver, ReturnType, Signature,
+    Token, Type, TypePath, TypeReference,
 };
 
 use crate::util::*;
@@ -15,6 +18,66 @@ pub enum SelfType<'a> {
     ValueTrait,
 }
 
+/// The arguments of `#[turbo_tasks::function(...)]`.
+#[derive(Default)]
+pub struct FunctionArguments {
+    /// Tasks of the function are executed on the blocking thread pool.
+    pub blocking: bool,
+    /// Only the first input is resolved before the task is looked up.
+    pub shallow_resolve: bool,
+    /// The maximum number of tasks of the function that execute at the same
+    /// time.
+    pub max_concurrency: Option<usize>,
+}
+
+impl Parse for FunctionArguments {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut args = FunctionArguments::default();
+        while !input.is_empty() {
+            let ident: Ident = input.parse()?;
+            match ident.to_string().as_str() {
+                "blocking" => args.blocking = true,
+                "shallow_resolve" => args.shallow_resolve = true,
+                "max_concurrency" => {
+                    input.parse::<Token![=]>()?;
+                    let lit: LitInt = input.parse()?;
+                    let max_concurrency = lit.base10_parse()?;
+                    if max_concurrency == 0 {
+                        return Err(Error::new_spanned(
+                            &lit,
+                            "`max_concurrency` must be at least 1",
+                        ));
+                    }
+                    args.max_concurrency = Some(max_concurrency);
+                }
+                _ => {
+                    return Err(Error::new_spanned(
+                        &ident,
+                        "expected `blocking`, `shallow_resolve` or `max_concurrency = <n>`",
+                    ))
+                }
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+        Ok(args)
+    }
+}
+
+impl FunctionArguments {
+    /// Parses the arguments of a `#[turbo_tasks::function]` attribute on a
+    /// method.
+    pub fn from_attribute(attr: &Attribute) -> syn::Result<Self> {
+        if attr.tokens.is_empty() {
+            Ok(Self::default())
+        } else {
+            attr.parse_args()
+        }
+    }
+}
+
 pub fn gen_native_function_code(
     name_code: TokenStream2,
     original_function: TokenStream2,
@@ -24,6 +87,8 @@ pub fn gen_native_function_code(
     inputs: &Punctuated<FnArg, Token![,]>,
     output_type: &Type,
     self_ref_type: Option<(&Ident, SelfType<'_>)>,
+    args: &FunctionArguments,
+    fingerprint: u64,
 ) -> (TokenStream2, Vec<TokenStream2>) {
     let mut input_extraction = Vec::new();
     let mut input_convert = Vec::new();
@@ -170,6 +235,16 @@ pub fn gen_native_function_code(
         },
         (false, false) => quote! { Ok(#original_call_code.into()) },
     };
+    let blocking = args.blocking;
+    let resolve_mode = if args.shallow_resolve {
+        quote! { turbo_tasks::ResolveMode::Shallow }
+    } else {
+        quote! { turbo_tasks::ResolveMode::Deep }
+    };
+    let max_concurrency = match args.max_concurrency {
+        Some(max_concurrency) => quote! { Some(#max_concurrency) },
+        None => quote! { None },
+    };
     (
         quote! {
             #[doc(hidden)]
@@ -190,6 +265,13 @@ pub fn gen_native_function_code(
                             })
                         }))
                     })
+                    .with_blocking(#blocking)
+                    .with_resolve_mode(#resolve_mode)
+                    .with_max_concurrency(#max_concurrency)
+                    .with_fingerprint(
+                        #fingerprint,
+                        concat!(env!("CARGO_PKG_NAME"), "@", env!("CARGO_PKG_VERSION")),
+                    )
                 });
 
             #[doc(hidden)]
@@ -202,6 +284,20 @@ pub fn gen_native_function_code(
     )
 }
 
+/// Hashes the tokens of a function implementation (FNV-1a), so persisted tasks
+/// can detect that the implementation has changed since they were computed.
+/// The crate version is mixed in at runtime by
+/// `NativeFunction::with_fingerprint`.
+pub fn fingerprint(tokens: &impl ToTokens) -> u64 {
+    tokens
+        .to_token_stream()
+        .to_string()
+        .bytes()
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
 pub fn split_signature(sig: &Signature) -> (Signature, Signature, Type, TokenStream2) {
     let output_type = get_return_type(&sig.output);
     let inline_ident = get_internal_function_ident(&sig.ident);
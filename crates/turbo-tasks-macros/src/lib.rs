@@ -52,6 +52,13 @@ pub fn derive_deterministic_hash(input: TokenStream) -> TokenStream {
 /// `into: shared`: Compares with the existing value in the cell, before
 /// overriding it. Requires Value to implement [Eq].
 ///
+/// `link_eq` argument (`#[turbo_tasks::value(link_eq)]`)
+///
+/// When a task output is linked to a different cell than before, the cells
+/// are compared with [PartialEq] and readers of the output are only notified
+/// when the values differ. Readers that kept the previous cell keep reading
+/// it until it changes.
+///
 /// TODO: add more documentation: presets, traits
 #[allow_internal_unstable(min_specialization, into_future, trivial_bounds)]
 #[proc_macro_error]
@@ -67,6 +74,26 @@ pub fn value_trait(args: TokenStream, input: TokenStream) -> TokenStream {
     value_trait_macro::value_trait(args, input)
 }
 
+/// Turns a function into a turbo-tasks function, whose calls are cached and
+/// executed as tasks.
+///
+/// `blocking` argument (`#[turbo_tasks::function(blocking)]`)
+///
+/// Executes the tasks of the function on the blocking thread pool instead of
+/// the async executor. Useful for CPU heavy functions like parsers.
+///
+/// `shallow_resolve` argument (`#[turbo_tasks::function(shallow_resolve)]`)
+///
+/// Only resolves the first argument (`self` of methods) of calls with
+/// unresolved arguments, instead of all arguments. Avoids reading arguments
+/// that the function ignores or only passes on, at the cost of separate tasks
+/// for different Vcs of the same value.
+///
+/// `max_concurrency` argument (`#[turbo_tasks::function(max_concurrency = 8)]`)
+///
+/// Limits how many tasks of the function execute at the same time. Further
+/// executions wait in the order they have been scheduled. Useful for
+/// functions that wrap external processes or memory hungry libraries.
 #[allow_internal_unstable(min_specialization, into_future, trivial_bounds)]
 #[proc_macro_error]
 #[proc_macro_attribute]
@@ -11,7 +11,7 @@ use turbo_tasks_macros_shared::{
 };
 
 use crate::{
-    func::{gen_native_function_code, split_signature, SelfType},
+    func::{fingerprint, gen_native_function_code, split_signature, FunctionArguments, SelfType},
     util::*,
 };
 
@@ -78,6 +78,11 @@ pub fn value_impl(_args: TokenStream, input: TokenStream) -> TokenStream {
             }) = item
             {
                 let function_attr = attrs.iter().find(|attr| is_attribute(attr, "function"));
+                let args = match function_attr.map(FunctionArguments::from_attribute) {
+                    Some(Ok(args)) => args,
+                    Some(Err(err)) => return err.to_compile_error(),
+                    None => FunctionArguments::default(),
+                };
                 let attrs = if function_attr.is_none() {
                     item.span()
                         .unwrap()
@@ -110,6 +115,8 @@ pub fn value_impl(_args: TokenStream, input: TokenStream) -> TokenStream {
                     &sig.inputs,
                     &output_type,
                     Some((vc_ident, SelfType::Ref)),
+                    &args,
+                    fingerprint(item),
                 );
 
                 functions.push(quote! {
@@ -155,6 +162,11 @@ pub fn value_impl(_args: TokenStream, input: TokenStream) -> TokenStream {
             }) = item
             {
                 let function_attr = attrs.iter().find(|attr| is_attribute(attr, "function"));
+                let args = match function_attr.map(FunctionArguments::from_attribute) {
+                    Some(Ok(args)) => args,
+                    Some(Err(err)) => return err.to_compile_error(),
+                    None => FunctionArguments::default(),
+                };
                 let attrs = if function_attr.is_none() {
                     item.span()
                         .unwrap()
@@ -195,6 +207,8 @@ pub fn value_impl(_args: TokenStream, input: TokenStream) -> TokenStream {
                     inputs,
                     &output_type,
                     Some((&ref_ident, SelfType::Value(struct_ident))),
+                    &args,
+                    fingerprint(item),
                 );
                 let mut new_sig = sig.clone();
                 new_sig.ident = internal_function_ident;
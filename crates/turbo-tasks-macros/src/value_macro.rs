@@ -128,6 +128,7 @@ struct ValueArguments {
     into_mode: IntoMode,
     cell_mode: CellMode,
     manual_eq: bool,
+    link_eq: bool,
     transparent: bool,
 }
 
@@ -138,6 +139,7 @@ impl Parse for ValueArguments {
             into_mode: IntoMode::None,
             cell_mode: CellMode::Shared,
             manual_eq: false,
+            link_eq: false,
             transparent: false,
         };
         let punctuated: Punctuated<Meta, Token![,]> = input.parse_terminated(Meta::parse)?;
@@ -190,6 +192,9 @@ impl Parse for ValueArguments {
                         return Err(Error::new_spanned(&str, "expected \"manual\""));
                     };
                 }
+                ("link_eq", Meta::Path(_)) => {
+                    result.link_eq = true;
+                }
                 ("transparent", Meta::Path(_)) => {
                     result.transparent = true;
                 }
@@ -198,7 +203,7 @@ impl Parse for ValueArguments {
                         &meta,
                         format!(
                             "unexpected {:?}, expected \"shared\", \"into\", \"serialization\", \
-                             \"cell\", \"eq\", \"transparent\"",
+                             \"cell\", \"eq\", \"link_eq\", \"transparent\"",
                             meta
                         ),
                     ))
@@ -217,6 +222,7 @@ pub fn value(args: TokenStream, input: TokenStream) -> TokenStream {
         into_mode,
         cell_mode,
         manual_eq,
+        link_eq,
         transparent,
     } = parse_macro_input!(args as ValueArguments);
 
@@ -388,6 +394,14 @@ pub fn value(args: TokenStream, input: TokenStream) -> TokenStream {
         }
     };
 
+    let new_value_type = if link_eq {
+        quote! {
+            #new_value_type.with_link_eq::<#ident>()
+        }
+    } else {
+        new_value_type
+    };
+
     let for_input_marker = match serialization_mode {
         SerializationMode::None | SerializationMode::Auto | SerializationMode::Custom => quote! {},
         SerializationMode::AutoForInput | SerializationMode::CustomForInput => quote! {
@@ -436,24 +450,39 @@ pub fn value(args: TokenStream, input: TokenStream) -> TokenStream {
     };
 
     let strongly_consistent = {
-        let (return_type, read) = if let Some(inner_type) = inner_type {
+        let (return_type, consistent_return_type, read, read_at_least) = if let Some(inner_type) =
+            inner_type
+        {
             (
                 quote! {
                    turbo_tasks::ReadRawVcFuture<#ident, #inner_type>
                 },
+                quote! {
+                   turbo_tasks::ConsistentReadFuture<#ident, #inner_type>
+                },
                 quote! {
                     /// SAFETY: Types are binary identical via #[repr(transparent)]
                     unsafe { self.node.into_transparent_strongly_consistent_read::<#ident, #inner_type>() }
                 },
+                quote! {
+                    /// SAFETY: Types are binary identical via #[repr(transparent)]
+                    unsafe { self.node.into_transparent_read_consistent_at_least::<#ident, #inner_type>(generation) }
+                },
             )
         } else {
             (
                 quote! {
                     turbo_tasks::ReadRawVcFuture<#ident>
                 },
+                quote! {
+                    turbo_tasks::ConsistentReadFuture<#ident>
+                },
                 quote! {
                     self.node.into_strongly_consistent_read::<#ident>()
                 },
+                quote! {
+                    self.node.into_read_consistent_at_least::<#ident>(generation)
+                },
             )
         };
         quote! {
@@ -478,6 +507,23 @@ pub fn value(args: TokenStream, input: TokenStream) -> TokenStream {
             pub fn strongly_consistent(self) -> #return_type {
                 #read
             }
+
+            /// see [turbo_tasks::RawVc::into_read_consistent_at_least]
+            #[must_use]
+            pub fn read_consistent_at_least(self, generation: usize) -> #return_type {
+                #read_at_least
+            }
+
+            /// Reads with the given [turbo_tasks::ReadConsistency], see
+            /// [turbo_tasks::ReadRawVcFuture::with_consistency].
+            #[must_use]
+            pub fn read_with(
+                self,
+                consistency: turbo_tasks::ReadConsistency,
+            ) -> #consistent_return_type {
+                let read = { #read };
+                read.with_consistency(consistency)
+            }
         }
     };
 
@@ -575,6 +621,8 @@ pub fn value(args: TokenStream, input: TokenStream) -> TokenStream {
         }
 
         impl turbo_tasks::Typed for #ident {
+            type Vc = #ref_ident;
+
             fn get_value_type_id() -> turbo_tasks::ValueTypeId {
                 *#value_type_id_ident
             }
@@ -612,17 +660,46 @@ pub fn value(args: TokenStream, input: TokenStream) -> TokenStream {
                 Ok(Self { node: self.node.resolve_strongly_consistent().await? })
             }
 
+            /// see [turbo_tasks::RawVc::into_result]
+            pub async fn into_result(self) -> turbo_tasks::Result<std::result::Result<Self, turbo_tasks::TaskError>> {
+                Ok(self.node.into_result().await?.map(|node| Self { node }))
+            }
+
             /// see [turbo_tasks::RawVc::cell_local]
             pub async fn cell_local(self) -> turbo_tasks::Result<Self> {
                 Ok(Self { node: self.node.cell_local().await? })
             }
 
+            /// see [turbo_tasks::RawVc::prefetch]
+            pub fn prefetch(self) {
+                self.node.prefetch()
+            }
+
+            /// see [turbo_tasks::RawVc::prefetch_with_children]
+            pub fn prefetch_with_children(self) {
+                self.node.prefetch_with_children()
+            }
+
+            /// see [turbo_tasks::RawVc::project]
+            pub fn project<P>(self, projection: &'static turbo_tasks::Projection<#ident, P>) -> <P as turbo_tasks::Typed>::Vc
+            where
+                P: turbo_tasks::Typed + std::cmp::PartialEq + std::marker::Send + std::marker::Sync + 'static,
+            {
+                std::convert::From::from(self.node.project(projection))
+            }
+
             pub async fn resolve_from(super_trait_vc: impl std::convert::Into<turbo_tasks::RawVc>) -> Result<Option<Self>, turbo_tasks::ResolveTypeError> {
                 let raw_vc: turbo_tasks::RawVc = super_trait_vc.into();
                 let raw_vc = raw_vc.resolve_value(*#value_type_id_ident).await?;
                 Ok(raw_vc.map(|raw_vc| #ref_ident { node: raw_vc }))
             }
 
+            /// Casts to the trait `T`, or returns `None` when the value type
+            /// doesn't implement it.
+            pub fn try_cast<T: turbo_tasks::ValueTraitVc>(self) -> Option<T> {
+                #value_type_ident.has_trait(&T::get_trait_type_id()).then(|| T::from(self.node))
+            }
+
             #strongly_consistent
         }
 
@@ -10,7 +10,7 @@ use turbo_tasks_macros_shared::{
 };
 
 use crate::{
-    func::{gen_native_function_code, split_signature, SelfType},
+    func::{fingerprint, gen_native_function_code, split_signature, FunctionArguments, SelfType},
     util::*,
 };
 
@@ -115,6 +115,8 @@ pub fn value_trait(args: TokenStream, input: TokenStream) -> TokenStream {
                 inputs,
                 &output_type,
                 Some((&ref_ident, SelfType::ValueTrait)),
+                &FunctionArguments::default(),
+                fingerprint(&quote! { #sig #default }),
             );
 
             trait_fns.push(quote! {
@@ -188,6 +190,7 @@ pub fn value_trait(args: TokenStream, input: TokenStream) -> TokenStream {
             turbo_tasks::macro_helpers::Lazy::new(|| {
                 let mut trait_type = turbo_tasks::TraitType::new(std::any::type_name::<#ref_ident>().to_string());;
                 #(#default_method_registers)*
+                #(trait_type.register_supertrait(<#supertrait_refs as turbo_tasks::ValueTraitVc>::get_trait_type_id);)*
                 trait_type
             });
         #[doc(hidden)]
@@ -212,11 +215,26 @@ pub fn value_trait(args: TokenStream, input: TokenStream) -> TokenStream {
                 Ok(Self { node: self.node.resolve_strongly_consistent().await? })
             }
 
+            /// see [turbo_tasks::RawVc::into_result]
+            pub async fn into_result(self) -> turbo_tasks::Result<std::result::Result<Self, turbo_tasks::TaskError>> {
+                Ok(self.node.into_result().await?.map(|node| Self { node }))
+            }
+
             /// see [turbo_tasks::RawVc::cell_local]
             pub async fn cell_local(self) -> turbo_tasks::Result<Self> {
                 Ok(Self { node: self.node.cell_local().await? })
             }
 
+            /// see [turbo_tasks::RawVc::prefetch]
+            pub fn prefetch(self) {
+                self.node.prefetch()
+            }
+
+            /// see [turbo_tasks::RawVc::prefetch_with_children]
+            pub fn prefetch_with_children(self) {
+                self.node.prefetch_with_children()
+            }
+
             pub async fn resolve_from(super_trait_vc: impl std::convert::Into<turbo_tasks::RawVc>) -> Result<Option<Self>, turbo_tasks::ResolveTypeError> {
                 let raw_vc: turbo_tasks::RawVc = super_trait_vc.into();
                 let raw_vc = raw_vc.resolve_trait(*#trait_type_id_ident).await?;
@@ -228,6 +246,16 @@ pub fn value_trait(args: TokenStream, input: TokenStream) -> TokenStream {
                 #ref_ident { node: raw_vc }
             }
 
+            /// Casts to the trait `T`, or returns `None` when the value doesn't
+            /// implement it. Upcasting to a supertrait doesn't need to resolve
+            /// the value.
+            pub async fn try_cast<T: turbo_tasks::ValueTraitVc>(self) -> turbo_tasks::Result<Option<T>> {
+                if turbo_tasks::registry::is_supertrait(*#trait_type_id_ident, T::get_trait_type_id()) {
+                    return Ok(Some(T::from(self.node)));
+                }
+                self.node.try_cast().await
+            }
+
             #(pub #trait_fns)*
         }
 
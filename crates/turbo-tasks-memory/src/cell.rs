@@ -1,6 +1,9 @@
-use std::{collections::HashSet, fmt::Debug};
+use std::{
+    collections::HashSet,
+    fmt::{self, Debug, Display, Formatter},
+};
 
-use turbo_tasks::{backend::CellContent, TaskId, TurboTasksBackendApi};
+use turbo_tasks::{backend::CellContent, CellId, TaskId, TurboTasksBackendApi};
 
 #[derive(Default, Debug)]
 pub struct Cell {
@@ -10,6 +13,12 @@ pub struct Cell {
 }
 
 impl Cell {
+    /// Returns `true` when the cell has been assigned by an execution of its
+    /// task, even if the content is empty.
+    pub fn is_assigned(&self) -> bool {
+        self.updates > 0
+    }
+
     pub fn read_content(&mut self, reader: TaskId) -> CellContent {
         self.dependent_tasks.insert(reader);
         self.read_content_untracked()
@@ -34,3 +43,27 @@ impl Cell {
         }
     }
 }
+
+/// The error of reading a cell that the task never created, or that a later
+/// execution of the task removed. Stale [turbo_tasks::RawVc]s fail with this
+/// error instead of reading an empty cell. The reader is still notified when
+/// the cell is created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellNotFound {
+    pub task: TaskId,
+    pub index: CellId,
+    /// The number of cells of the same type the task has created.
+    pub created: usize,
+}
+
+impl Display for CellNotFound {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} doesn't exist, the task has created {} cells of that type",
+            self.index, self.task, self.created
+        )
+    }
+}
+
+impl std::error::Error for CellNotFound {}
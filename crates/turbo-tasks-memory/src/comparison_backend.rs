@@ -0,0 +1,1063 @@
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    collections::HashSet,
+    fmt::{Debug, Display},
+    future::Future,
+    ops::Deref,
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use parking_lot::Mutex;
+use turbo_tasks::{
+    backend::{
+        Backend, BackendJobId, CellContent, CellCounts, PersistentTaskType, TaskExecutionSpec,
+        TransientTaskRoot, TransientTaskType,
+    },
+    event::EventListener,
+    platform::Instant,
+    util::SharedError,
+    CellId, FunctionId, MemoTable, NotifyBatch, RawVc, RootTaskState, StatsType, TaskId,
+    TaskIdProvider, TaskInput, TraitTypeId, TurboTasksBackendApi, TurboTasksCallApi,
+};
+
+thread_local! {
+    /// The id the candidate backend has to use for the task it creates, which
+    /// is the id the reference backend has created it with.
+    static CANDIDATE_TASK_ID: Cell<Option<TaskId>> = Cell::new(None);
+}
+
+/// A difference between the results of the two backends of a
+/// [ComparisonBackend].
+#[derive(Debug, Clone)]
+pub struct BackendMismatch {
+    /// The [Backend] method that returned different results.
+    pub operation: &'static str,
+    pub task: TaskId,
+    /// The description of the task, from the reference backend.
+    pub description: String,
+    pub reference: String,
+    pub candidate: String,
+}
+
+impl Display for BackendMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} of {} ({}) differs between the backends\nreference: {}\ncandidate: {}",
+            self.operation, self.description, self.task, self.reference, self.candidate
+        )
+    }
+}
+
+/// A [Backend] that forwards every call to two backends and compares their
+/// observable results, to validate a new backend implementation against a
+/// reference, e.g. the [crate::MemoryBackend].
+///
+/// The reference backend drives the execution. Its results are returned and
+/// only its scheduled tasks are executed, the execution is reported to both
+/// backends. The candidate backend creates its tasks with the ids of the
+/// reference backend, so task ids can be compared and passed to both. Its
+/// own scheduling and notifications are dropped, as the calls of the
+/// reference backend that result from them are forwarded to it anyway.
+///
+/// Results are only compared when both backends have them ready, as
+/// readiness depends on details like strong consistency. Errors are compared
+/// by their presence only. Differences are logged and can be queried with
+/// [ComparisonBackend::mismatches].
+///
+/// The backends must not share state that is local to tasks or threads, so
+/// two instances of the same backend can't be compared.
+pub struct ComparisonBackend<R: Backend, C: Backend> {
+    reference: R,
+    candidate: C,
+    panic_on_mismatch: bool,
+    mismatches: Mutex<Vec<BackendMismatch>>,
+}
+
+impl<R: Backend, C: Backend> ComparisonBackend<R, C> {
+    pub fn new(reference: R, candidate: C) -> Self {
+        Self {
+            reference,
+            candidate,
+            panic_on_mismatch: false,
+            mismatches: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Panics on the first mismatch instead of logging it, which points to
+    /// the call that caused it.
+    pub fn with_panic_on_mismatch(mut self, panic_on_mismatch: bool) -> Self {
+        self.panic_on_mismatch = panic_on_mismatch;
+        self
+    }
+
+    pub fn reference(&self) -> &R {
+        &self.reference
+    }
+
+    pub fn candidate(&self) -> &C {
+        &self.candidate
+    }
+
+    /// Returns the mismatches found so far.
+    pub fn mismatches(&self) -> Vec<BackendMismatch> {
+        self.mismatches.lock().clone()
+    }
+
+    fn reference_api<'a>(
+        &self,
+        turbo_tasks: &'a dyn TurboTasksBackendApi,
+    ) -> ComparisonApi<&'a dyn TurboTasksBackendApi> {
+        ComparisonApi {
+            turbo_tasks,
+            side: Side::Reference,
+        }
+    }
+
+    fn candidate_api<'a>(
+        &self,
+        turbo_tasks: &'a dyn TurboTasksBackendApi,
+    ) -> ComparisonApi<&'a dyn TurboTasksBackendApi> {
+        ComparisonApi {
+            turbo_tasks,
+            side: Side::Candidate,
+        }
+    }
+
+    fn mismatch(
+        &self,
+        operation: &'static str,
+        task: TaskId,
+        reference: String,
+        candidate: String,
+    ) {
+        let mismatch = BackendMismatch {
+            operation,
+            task,
+            description: self.reference.get_task_description(task),
+            reference,
+            candidate,
+        };
+        if self.panic_on_mismatch {
+            panic!("{mismatch}");
+        }
+        tracing::error!("{mismatch}");
+        self.mismatches.lock().push(mismatch);
+    }
+
+    fn compare<T: PartialEq + Debug>(
+        &self,
+        operation: &'static str,
+        task: TaskId,
+        reference: &T,
+        candidate: &T,
+    ) {
+        if reference != candidate {
+            self.mismatch(
+                operation,
+                task,
+                format!("{reference:?}"),
+                format!("{candidate:?}"),
+            );
+        }
+    }
+
+    /// Compares the results of reads, when both backends have them ready.
+    fn compare_read<T: Debug>(
+        &self,
+        operation: &'static str,
+        task: TaskId,
+        reference: &Result<Result<T, EventListener>>,
+        candidate: &Result<Result<T, EventListener>>,
+        eq: impl Fn(&T, &T) -> bool,
+    ) {
+        let (reference, candidate) = match (ready(reference), ready(candidate)) {
+            (Some(reference), Some(candidate)) => (reference, candidate),
+            _ => return,
+        };
+        let equal = match (&reference, &candidate) {
+            (Ok(reference), Ok(candidate)) => eq(reference, candidate),
+            (Err(_), Err(_)) => true,
+            _ => false,
+        };
+        if !equal {
+            self.mismatch(
+                operation,
+                task,
+                format!("{reference:?}"),
+                format!("{candidate:?}"),
+            );
+        }
+    }
+}
+
+fn ready<T>(result: &Result<Result<T, EventListener>>) -> Option<Result<&T, &anyhow::Error>> {
+    match result {
+        Ok(Ok(value)) => Some(Ok(value)),
+        Ok(Err(_)) => None,
+        Err(err) => Some(Err(err)),
+    }
+}
+
+/// Cells are equal when they hold the same value, not only an equal one.
+fn cell_content_eq(reference: &CellContent, candidate: &CellContent) -> bool {
+    reference.0 == candidate.0
+}
+
+/// Runs `f` with the id the candidate backend has to create its task with.
+fn with_candidate_task_id<T>(task: TaskId, f: impl FnOnce() -> T) -> T {
+    let previous = CANDIDATE_TASK_ID.with(|id| id.replace(Some(task)));
+    let result = f();
+    CANDIDATE_TASK_ID.with(|id| id.set(previous));
+    result
+}
+
+/// The placeholder for the functions of transient tasks of the candidate
+/// backend. Transient tasks are only executed by the reference backend.
+async fn not_executed() -> Result<RawVc> {
+    bail!("transient tasks are executed by the reference backend")
+}
+
+fn candidate_transient_task(task_type: &TransientTaskType) -> TransientTaskType {
+    match task_type {
+        TransientTaskType::Root(_) => {
+            TransientTaskType::Root(Box::new(|| Box::pin(not_executed())))
+        }
+        TransientTaskType::Once(_) => TransientTaskType::Once(Box::pin(not_executed())),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Reference = 0,
+    Candidate = 1,
+}
+
+/// Backend jobs of both backends are scheduled on the same turbo tasks, so
+/// their ids are tagged with the backend.
+fn encode_job_id(side: Side, id: BackendJobId) -> BackendJobId {
+    BackendJobId::from(*id * 2 + side as usize)
+}
+
+fn decode_job_id(id: BackendJobId) -> (Side, BackendJobId) {
+    let side = if *id % 2 == 0 {
+        Side::Reference
+    } else {
+        Side::Candidate
+    };
+    (side, BackendJobId::from(*id / 2))
+}
+
+/// The [TurboTasksBackendApi] that is passed to one of the backends.
+struct ComparisonApi<T> {
+    turbo_tasks: T,
+    side: Side,
+}
+
+impl<T> TaskIdProvider for ComparisonApi<T>
+where
+    T: Deref + Send + Sync,
+    T::Target: TurboTasksBackendApi,
+{
+    fn get_fresh_task_id(&self) -> TaskId {
+        if self.side == Side::Candidate {
+            if let Some(task) = CANDIDATE_TASK_ID.with(|id| id.get()) {
+                return task;
+            }
+        }
+        self.turbo_tasks.get_fresh_task_id()
+    }
+
+    unsafe fn reuse_task_id(&self, id: TaskId) {
+        if self.side == Side::Candidate && CANDIDATE_TASK_ID.with(|task| task.get()) == Some(id) {
+            // The id is owned by the reference backend
+            return;
+        }
+        unsafe { self.turbo_tasks.reuse_task_id(id) }
+    }
+}
+
+impl<T> TurboTasksCallApi for ComparisonApi<T>
+where
+    T: Deref + Send + Sync,
+    T::Target: TurboTasksBackendApi,
+{
+    fn dynamic_call(&self, func: FunctionId, inputs: Vec<TaskInput>) -> RawVc {
+        self.turbo_tasks.dynamic_call(func, inputs)
+    }
+
+    fn native_call(&self, func: FunctionId, inputs: Vec<TaskInput>) -> RawVc {
+        self.turbo_tasks.native_call(func, inputs)
+    }
+
+    fn trait_call(
+        &self,
+        trait_type: TraitTypeId,
+        trait_fn_name: Cow<'static, str>,
+        inputs: Vec<TaskInput>,
+    ) -> RawVc {
+        self.turbo_tasks
+            .trait_call(trait_type, trait_fn_name, inputs)
+    }
+
+    fn run_once(
+        &self,
+        future: Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>,
+    ) -> TaskId {
+        self.turbo_tasks.run_once(future)
+    }
+
+    fn run_once_process(
+        &self,
+        future: Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>,
+    ) -> TaskId {
+        self.turbo_tasks.run_once_process(future)
+    }
+}
+
+impl<T> TurboTasksBackendApi for ComparisonApi<T>
+where
+    T: Deref + Send + Sync,
+    T::Target: TurboTasksBackendApi,
+{
+    fn pin(&self) -> Arc<dyn TurboTasksBackendApi> {
+        Arc::new(ComparisonApi {
+            turbo_tasks: self.turbo_tasks.pin(),
+            side: self.side,
+        })
+    }
+
+    fn schedule(&self, task: TaskId) {
+        if self.side == Side::Reference {
+            self.turbo_tasks.schedule(task);
+        }
+    }
+
+    fn schedule_invalidated(&self, task: TaskId, priority: bool) {
+        if self.side == Side::Reference {
+            self.turbo_tasks.schedule_invalidated(task, priority);
+        }
+    }
+
+    fn schedule_backend_background_job(&self, id: BackendJobId) {
+        self.turbo_tasks
+            .schedule_backend_background_job(encode_job_id(self.side, id));
+    }
+
+    fn schedule_backend_foreground_job(&self, id: BackendJobId) {
+        self.turbo_tasks
+            .schedule_backend_foreground_job(encode_job_id(self.side, id));
+    }
+
+    fn task_released(&self, task: TaskId) {
+        if self.side == Side::Reference {
+            self.turbo_tasks.task_released(task);
+        }
+    }
+
+    fn idle_workers(&self) -> usize {
+        self.turbo_tasks.idle_workers()
+    }
+
+    fn try_foreground_done(&self) -> Result<(), EventListener> {
+        self.turbo_tasks.try_foreground_done()
+    }
+
+    fn schedule_notify_tasks(&self, tasks: &[TaskId]) {
+        if self.side == Side::Reference {
+            self.turbo_tasks.schedule_notify_tasks(tasks);
+        }
+    }
+
+    fn schedule_notify_tasks_set(&self, tasks: &HashSet<TaskId>) {
+        if self.side == Side::Reference {
+            self.turbo_tasks.schedule_notify_tasks_set(tasks);
+        }
+    }
+
+    fn batch_notify_tasks(&self) -> NotifyBatch<'_> {
+        self.turbo_tasks.batch_notify_tasks()
+    }
+
+    fn stats_type(&self) -> StatsType {
+        self.turbo_tasks.stats_type()
+    }
+
+    fn set_stats_type(&self, stats_type: StatsType) {
+        self.turbo_tasks.set_stats_type(stats_type)
+    }
+
+    fn program_duration_until(&self, instant: Instant) -> Duration {
+        self.turbo_tasks.program_duration_until(instant)
+    }
+
+    fn update_generation(&self) -> usize {
+        self.turbo_tasks.update_generation()
+    }
+}
+
+impl<R: Backend, C: Backend> Backend for ComparisonBackend<R, C> {
+    fn initialize(&mut self, task_id_provider: &dyn TaskIdProvider) {
+        self.reference.initialize(task_id_provider);
+        self.candidate.initialize(task_id_provider);
+    }
+
+    fn startup(&self, turbo_tasks: &dyn TurboTasksBackendApi) -> Result<()> {
+        self.reference.startup(&self.reference_api(turbo_tasks))?;
+        self.candidate.startup(&self.candidate_api(turbo_tasks))
+    }
+
+    fn stop(&self, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.reference.stop(&self.reference_api(turbo_tasks));
+        self.candidate.stop(&self.candidate_api(turbo_tasks));
+    }
+
+    fn invalidate_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.reference
+            .invalidate_task(task, &self.reference_api(turbo_tasks));
+        self.candidate
+            .invalidate_task(task, &self.candidate_api(turbo_tasks));
+    }
+
+    fn invalidate_tasks(&self, tasks: Vec<TaskId>, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.reference
+            .invalidate_tasks(tasks.clone(), &self.reference_api(turbo_tasks));
+        self.candidate
+            .invalidate_tasks(tasks, &self.candidate_api(turbo_tasks));
+    }
+
+    fn invalidate_function_tasks(
+        &self,
+        function: FunctionId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.reference
+            .invalidate_function_tasks(function, &self.reference_api(turbo_tasks));
+        self.candidate
+            .invalidate_function_tasks(function, &self.candidate_api(turbo_tasks));
+    }
+
+    fn task_done_listener(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Option<EventListener> {
+        self.reference
+            .task_done_listener(task, &self.reference_api(turbo_tasks))
+    }
+
+    fn pending_root_state(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Option<RootTaskState> {
+        self.reference
+            .pending_root_state(task, &self.reference_api(turbo_tasks))
+    }
+
+    fn is_blocking_task(&self, task: TaskId) -> bool {
+        self.reference.is_blocking_task(task)
+    }
+
+    fn get_task_description(&self, task: TaskId) -> String {
+        self.reference.get_task_description(task)
+    }
+
+    fn memo_table(&self) -> Option<&MemoTable> {
+        self.reference.memo_table()
+    }
+
+    fn get_task_function(&self, task: TaskId) -> Option<FunctionId> {
+        let reference = self.reference.get_task_function(task);
+        let candidate = self.candidate.get_task_function(task);
+        self.compare("get_task_function", task, &reference, &candidate);
+        reference
+    }
+
+    fn mark_task_final(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.reference
+            .mark_task_final(task, &self.reference_api(turbo_tasks));
+        self.candidate
+            .mark_task_final(task, &self.candidate_api(turbo_tasks));
+    }
+
+    fn set_task_label(&self, task: TaskId, label: String, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.reference
+            .set_task_label(task, label.clone(), &self.reference_api(turbo_tasks));
+        self.candidate
+            .set_task_label(task, label, &self.candidate_api(turbo_tasks));
+    }
+
+    fn add_task_tag(&self, task: TaskId, tag: String, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.reference
+            .add_task_tag(task, tag.clone(), &self.reference_api(turbo_tasks));
+        self.candidate
+            .add_task_tag(task, tag, &self.candidate_api(turbo_tasks));
+    }
+
+    fn tasks_with_tag(&self, tag: &str) -> Vec<TaskId> {
+        self.reference.tasks_with_tag(tag)
+    }
+
+    type ExecutionScopeFuture<T: Future<Output = Result<()>> + Send + 'static> =
+        R::ExecutionScopeFuture<C::ExecutionScopeFuture<T>>;
+
+    fn execution_scope<T: Future<Output = Result<()>> + Send + 'static>(
+        &self,
+        task: TaskId,
+        future: T,
+    ) -> Self::ExecutionScopeFuture<T> {
+        self.reference
+            .execution_scope(task, self.candidate.execution_scope(task, future))
+    }
+
+    fn try_start_task_execution(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Option<TaskExecutionSpec> {
+        let reference = self
+            .reference
+            .try_start_task_execution(task, &self.reference_api(turbo_tasks));
+        // The execution of the reference backend is reported to both
+        let candidate = self
+            .candidate
+            .try_start_task_execution(task, &self.candidate_api(turbo_tasks));
+        self.compare(
+            "try_start_task_execution",
+            task,
+            &reference.is_some(),
+            &candidate.is_some(),
+        );
+        reference
+    }
+
+    fn task_execution_cell_counts(
+        &self,
+        task: TaskId,
+        cell_counts: &CellCounts,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.reference.task_execution_cell_counts(
+            task,
+            cell_counts,
+            &self.reference_api(turbo_tasks),
+        );
+        self.candidate.task_execution_cell_counts(
+            task,
+            cell_counts,
+            &self.candidate_api(turbo_tasks),
+        );
+    }
+
+    fn task_execution_result(
+        &self,
+        task: TaskId,
+        result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        let (reference, candidate) = match result {
+            Ok(Ok(vc)) => (Ok(Ok(vc)), Ok(Ok(vc))),
+            Ok(Err(err)) => {
+                let err = SharedError::new(err);
+                (Ok(Err(err.clone().into())), Ok(Err(err.into())))
+            }
+            Err(message) => (Err(message.clone()), Err(message)),
+        };
+        self.reference
+            .task_execution_result(task, reference, &self.reference_api(turbo_tasks));
+        self.candidate
+            .task_execution_result(task, candidate, &self.candidate_api(turbo_tasks));
+    }
+
+    fn task_execution_completed(
+        &self,
+        task: TaskId,
+        duration: Duration,
+        instant: Instant,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> bool {
+        let reference = self.reference.task_execution_completed(
+            task,
+            duration,
+            instant,
+            &self.reference_api(turbo_tasks),
+        );
+        let candidate = self.candidate.task_execution_completed(
+            task,
+            duration,
+            instant,
+            &self.candidate_api(turbo_tasks),
+        );
+        self.compare("task_execution_completed", task, &reference, &candidate);
+        reference
+    }
+
+    fn run_backend_job<'a>(
+        &'a self,
+        id: BackendJobId,
+        turbo_tasks: &'a dyn TurboTasksBackendApi,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            match decode_job_id(id) {
+                (Side::Reference, id) => {
+                    let api = self.reference_api(turbo_tasks);
+                    self.reference.run_backend_job(id, &api).await
+                }
+                (Side::Candidate, id) => {
+                    let api = self.candidate_api(turbo_tasks);
+                    self.candidate.run_backend_job(id, &api).await
+                }
+            }
+        })
+    }
+
+    fn try_read_task_output(
+        &self,
+        task: TaskId,
+        reader: TaskId,
+        strongly_consistent: bool,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<Result<RawVc, EventListener>> {
+        let reference = self.reference.try_read_task_output(
+            task,
+            reader,
+            strongly_consistent,
+            &self.reference_api(turbo_tasks),
+        );
+        let candidate = self.candidate.try_read_task_output(
+            task,
+            reader,
+            strongly_consistent,
+            &self.candidate_api(turbo_tasks),
+        );
+        self.compare_read(
+            "try_read_task_output",
+            task,
+            &reference,
+            &candidate,
+            RawVc::eq,
+        );
+        reference
+    }
+
+    fn try_read_task_outputs(
+        &self,
+        tasks: &[TaskId],
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Vec<Result<Result<RawVc, EventListener>>> {
+        let reference =
+            self.reference
+                .try_read_task_outputs(tasks, reader, &self.reference_api(turbo_tasks));
+        let candidate =
+            self.candidate
+                .try_read_task_outputs(tasks, reader, &self.candidate_api(turbo_tasks));
+        for ((task, reference), candidate) in tasks.iter().zip(&reference).zip(&candidate) {
+            self.compare_read(
+                "try_read_task_outputs",
+                *task,
+                reference,
+                candidate,
+                RawVc::eq,
+            );
+        }
+        reference
+    }
+
+    fn try_read_task_output_stale(
+        &self,
+        task: TaskId,
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<Result<RawVc, EventListener>> {
+        let reference = self.reference.try_read_task_output_stale(
+            task,
+            reader,
+            &self.reference_api(turbo_tasks),
+        );
+        let candidate = self.candidate.try_read_task_output_stale(
+            task,
+            reader,
+            &self.candidate_api(turbo_tasks),
+        );
+        self.compare_read(
+            "try_read_task_output_stale",
+            task,
+            &reference,
+            &candidate,
+            RawVc::eq,
+        );
+        reference
+    }
+
+    fn try_read_task_output_untracked(
+        &self,
+        task: TaskId,
+        strongly_consistent: bool,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<Result<RawVc, EventListener>> {
+        let reference = self.reference.try_read_task_output_untracked(
+            task,
+            strongly_consistent,
+            &self.reference_api(turbo_tasks),
+        );
+        let candidate = self.candidate.try_read_task_output_untracked(
+            task,
+            strongly_consistent,
+            &self.candidate_api(turbo_tasks),
+        );
+        self.compare_read(
+            "try_read_task_output_untracked",
+            task,
+            &reference,
+            &candidate,
+            RawVc::eq,
+        );
+        reference
+    }
+
+    fn track_read_task_output(
+        &self,
+        task: TaskId,
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.reference
+            .track_read_task_output(task, reader, &self.reference_api(turbo_tasks));
+        self.candidate
+            .track_read_task_output(task, reader, &self.candidate_api(turbo_tasks));
+    }
+
+    fn try_read_task_cell(
+        &self,
+        task: TaskId,
+        index: CellId,
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<Result<CellContent, EventListener>> {
+        let reference = self.reference.try_read_task_cell(
+            task,
+            index,
+            reader,
+            &self.reference_api(turbo_tasks),
+        );
+        let candidate = self.candidate.try_read_task_cell(
+            task,
+            index,
+            reader,
+            &self.candidate_api(turbo_tasks),
+        );
+        self.compare_read(
+            "try_read_task_cell",
+            task,
+            &reference,
+            &candidate,
+            cell_content_eq,
+        );
+        reference
+    }
+
+    fn try_read_task_cell_untracked(
+        &self,
+        task: TaskId,
+        index: CellId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<Result<CellContent, EventListener>> {
+        let reference = self.reference.try_read_task_cell_untracked(
+            task,
+            index,
+            &self.reference_api(turbo_tasks),
+        );
+        let candidate = self.candidate.try_read_task_cell_untracked(
+            task,
+            index,
+            &self.candidate_api(turbo_tasks),
+        );
+        self.compare_read(
+            "try_read_task_cell_untracked",
+            task,
+            &reference,
+            &candidate,
+            cell_content_eq,
+        );
+        reference
+    }
+
+    fn try_read_own_task_cell_untracked(
+        &self,
+        current_task: TaskId,
+        index: CellId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<CellContent> {
+        let reference = self.reference.try_read_own_task_cell_untracked(
+            current_task,
+            index,
+            &self.reference_api(turbo_tasks),
+        );
+        let candidate = self.candidate.try_read_own_task_cell_untracked(
+            current_task,
+            index,
+            &self.candidate_api(turbo_tasks),
+        );
+        let equal = match (&reference, &candidate) {
+            (Ok(reference), Ok(candidate)) => cell_content_eq(reference, candidate),
+            (Err(_), Err(_)) => true,
+            _ => false,
+        };
+        if !equal {
+            self.mismatch(
+                "try_read_own_task_cell_untracked",
+                current_task,
+                format!("{reference:?}"),
+                format!("{candidate:?}"),
+            );
+        }
+        reference
+    }
+
+    fn track_read_task_cell(
+        &self,
+        task: TaskId,
+        index: CellId,
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.reference
+            .track_read_task_cell(task, index, reader, &self.reference_api(turbo_tasks));
+        self.candidate
+            .track_read_task_cell(task, index, reader, &self.candidate_api(turbo_tasks));
+    }
+
+    fn try_read_task_collectibles(
+        &self,
+        task: TaskId,
+        trait_id: TraitTypeId,
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<Result<HashSet<RawVc>, EventListener>> {
+        let reference = self.reference.try_read_task_collectibles(
+            task,
+            trait_id,
+            reader,
+            &self.reference_api(turbo_tasks),
+        );
+        let candidate = self.candidate.try_read_task_collectibles(
+            task,
+            trait_id,
+            reader,
+            &self.candidate_api(turbo_tasks),
+        );
+        self.compare_read(
+            "try_read_task_collectibles",
+            task,
+            &reference,
+            &candidate,
+            HashSet::eq,
+        );
+        reference
+    }
+
+    fn emit_collectible(
+        &self,
+        trait_type: TraitTypeId,
+        collectible: RawVc,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.reference.emit_collectible(
+            trait_type,
+            collectible,
+            task,
+            &self.reference_api(turbo_tasks),
+        );
+        self.candidate.emit_collectible(
+            trait_type,
+            collectible,
+            task,
+            &self.candidate_api(turbo_tasks),
+        );
+    }
+
+    fn unemit_collectible(
+        &self,
+        trait_type: TraitTypeId,
+        collectible: RawVc,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.reference.unemit_collectible(
+            trait_type,
+            collectible,
+            task,
+            &self.reference_api(turbo_tasks),
+        );
+        self.candidate.unemit_collectible(
+            trait_type,
+            collectible,
+            task,
+            &self.candidate_api(turbo_tasks),
+        );
+    }
+
+    fn update_task_cell(
+        &self,
+        task: TaskId,
+        index: CellId,
+        content: CellContent,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.reference.update_task_cell(
+            task,
+            index,
+            content.clone(),
+            &self.reference_api(turbo_tasks),
+        );
+        self.candidate
+            .update_task_cell(task, index, content, &self.candidate_api(turbo_tasks));
+    }
+
+    fn update_task_cells(
+        &self,
+        task: TaskId,
+        updates: Vec<(CellId, CellContent)>,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.reference
+            .update_task_cells(task, updates.clone(), &self.reference_api(turbo_tasks));
+        self.candidate
+            .update_task_cells(task, updates, &self.candidate_api(turbo_tasks));
+    }
+
+    fn check_task_creation(
+        &self,
+        task_type: &PersistentTaskType,
+        parent_task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<()> {
+        let reference = self.reference.check_task_creation(
+            task_type,
+            parent_task,
+            &self.reference_api(turbo_tasks),
+        );
+        let candidate = self.candidate.check_task_creation(
+            task_type,
+            parent_task,
+            &self.candidate_api(turbo_tasks),
+        );
+        self.compare(
+            "check_task_creation",
+            parent_task,
+            &reference.is_ok(),
+            &candidate.is_ok(),
+        );
+        reference
+    }
+
+    fn get_or_create_persistent_task(
+        &self,
+        task_type: PersistentTaskType,
+        parent_task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> TaskId {
+        let reference = self.reference.get_or_create_persistent_task(
+            task_type.clone(),
+            parent_task,
+            &self.reference_api(turbo_tasks),
+        );
+        let candidate = with_candidate_task_id(reference, || {
+            self.candidate.get_or_create_persistent_task(
+                task_type,
+                parent_task,
+                &self.candidate_api(turbo_tasks),
+            )
+        });
+        self.compare(
+            "get_or_create_persistent_task",
+            reference,
+            &reference,
+            &candidate,
+        );
+        reference
+    }
+
+    fn create_transient_task(
+        &self,
+        task_type: TransientTaskType,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> TaskId {
+        let candidate_type = candidate_transient_task(&task_type);
+        let reference = self
+            .reference
+            .create_transient_task(task_type, &self.reference_api(turbo_tasks));
+        let candidate = with_candidate_task_id(reference, || {
+            self.candidate
+                .create_transient_task(candidate_type, &self.candidate_api(turbo_tasks))
+        });
+        self.compare("create_transient_task", reference, &reference, &candidate);
+        reference
+    }
+
+    fn replace_root_task(
+        &self,
+        task: TaskId,
+        functor: TransientTaskRoot,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<()> {
+        let reference =
+            self.reference
+                .replace_root_task(task, functor, &self.reference_api(turbo_tasks));
+        let candidate = self.candidate.replace_root_task(
+            task,
+            Box::new(|| Box::pin(not_executed())),
+            &self.candidate_api(turbo_tasks),
+        );
+        self.compare(
+            "replace_root_task",
+            task,
+            &reference.is_ok(),
+            &candidate.is_ok(),
+        );
+        reference
+    }
+
+    fn keep_task_alive(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.reference
+            .keep_task_alive(task, &self.reference_api(turbo_tasks));
+        self.candidate
+            .keep_task_alive(task, &self.candidate_api(turbo_tasks));
+    }
+
+    fn release_task_keep_alive(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.reference
+            .release_task_keep_alive(task, &self.reference_api(turbo_tasks));
+        self.candidate
+            .release_task_keep_alive(task, &self.candidate_api(turbo_tasks));
+    }
+
+    fn prefetch_task(
+        &self,
+        task: TaskId,
+        with_children: bool,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.reference
+            .prefetch_task(task, with_children, &self.reference_api(turbo_tasks));
+        self.candidate
+            .prefetch_task(task, with_children, &self.candidate_api(turbo_tasks));
+    }
+
+    fn is_task_active_and_done(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> bool {
+        // Only a hint, which depends on the scheduling
+        self.reference
+            .is_task_active_and_done(task, &self.reference_api(turbo_tasks))
+    }
+}
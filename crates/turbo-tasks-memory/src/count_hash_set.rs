@@ -43,6 +43,11 @@ impl<T, H> CountHashSet<T, H> {
         self.inner.len() - self.negative_entries
     }
 
+    /// Returns true when an item has been removed more often than added.
+    pub fn has_negative_entries(&self) -> bool {
+        self.negative_entries > 0
+    }
+
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
@@ -123,12 +128,28 @@ impl<T: Eq + Hash, H: BuildHasher> CountHashSet<T, H> {
         self.remove_count(item, 1)
     }
 
+    /// Returns true when the value is visible from outside.
+    pub fn contains(&self, item: &T) -> bool {
+        self.inner.get(item).map_or(false, |count| *count > 0)
+    }
+
+    /// Returns the count of the item, which is negative when it has been
+    /// removed more often than added.
+    pub fn count(&self, item: &T) -> isize {
+        self.inner.get(item).copied().unwrap_or_default()
+    }
+
     pub fn iter(&self) -> CountHashSetIter<'_, T> {
         CountHashSetIter {
             inner: self.inner.iter().filter_map(filter),
         }
     }
 
+    /// Iterates over all items with their counts, including negative ones.
+    pub fn counts(&self) -> impl Iterator<Item = (&T, isize)> {
+        self.inner.iter().map(|(item, count)| (item, *count))
+    }
+
     pub fn into_counts(self) -> IntoIter<T, isize> {
         self.inner.into_iter()
     }
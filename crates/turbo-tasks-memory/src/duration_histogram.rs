@@ -0,0 +1,71 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// The number of buckets of a [DurationHistogram]. The last bucket covers all
+/// durations of 2^(BUCKETS - 2) microseconds (about 8.4 seconds) and longer.
+const BUCKETS: usize = 25;
+
+/// Counts execution durations in buckets of powers of two microseconds, so
+/// percentiles can be estimated without keeping every duration. Bucket `i`
+/// covers durations from 2^(i - 1) up to 2^i microseconds, bucket 0 durations
+/// below 1 microsecond.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DurationHistogram {
+    buckets: [u32; BUCKETS],
+}
+
+fn bucket_of(duration: Duration) -> usize {
+    let micros = duration.as_micros();
+    if micros == 0 {
+        return 0;
+    }
+    let bucket = (u128::BITS - micros.leading_zeros()) as usize;
+    bucket.min(BUCKETS - 1)
+}
+
+impl DurationHistogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, duration: Duration) {
+        let bucket = &mut self.buckets[bucket_of(duration)];
+        *bucket = bucket.saturating_add(1);
+    }
+
+    pub fn merge(&mut self, other: &DurationHistogram) {
+        for (bucket, other) in self.buckets.iter_mut().zip(other.buckets.iter()) {
+            *bucket = bucket.saturating_add(*other);
+        }
+    }
+
+    /// The number of recorded durations.
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().map(|&count| count as u64).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.iter().all(|&count| count == 0)
+    }
+
+    /// Estimates the duration that `percentile` (e.g. `0.95`) of the recorded
+    /// durations don't exceed. This is the upper bound of the bucket the
+    /// percentile falls into, so it's off by up to a factor of 2. `None` when
+    /// nothing has been recorded.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((count as f64 * percentile.clamp(0.0, 1.0)).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (i, &bucket) in self.buckets.iter().enumerate() {
+            seen += bucket as u64;
+            if seen >= rank {
+                return Some(Duration::from_micros(1 << i));
+            }
+        }
+        unreachable!("rank is at most the total count")
+    }
+}
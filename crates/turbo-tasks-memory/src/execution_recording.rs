@@ -0,0 +1,343 @@
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    fs::File,
+    io::{self, BufRead, BufReader, LineWriter, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use dashmap::DashMap;
+use nohash_hasher::BuildNoHashHasher;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use turbo_tasks::{
+    backend::{CellContent, PersistentTaskType},
+    macro_helpers::find_cell_by_type,
+    turbo_tasks, with_task_id_mapping, CellId, IdMapping, RawVc, TaskId, TurboTasksCallApi,
+    ValueTypeId,
+};
+
+use crate::stable_task_ids::{StableTaskId, StableTaskIds};
+
+/// An execution of a task in a recording, see
+/// [crate::MemoryBackend::with_execution_recording]. Tasks are referred to by
+/// their [StableTaskId]s, so it can be replayed in another run of the same
+/// build.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct RecordedExecution {
+    pub task: StableTaskId,
+    /// The description of the task, to make recordings readable.
+    pub description: String,
+    /// The tasks the execution called, in call order.
+    calls: Vec<serde_json::Value>,
+    /// The cell updates of the execution, in update order.
+    cells: Vec<(CellId, serde_json::Value)>,
+    /// The output, or the message of the error the execution failed with.
+    output: Result<serde_json::Value, String>,
+}
+
+/// Maps tasks to their stable ids while serializing and back while
+/// deserializing. Remembers when a task doesn't have a counterpart.
+pub(crate) struct StableIdMapping<'a> {
+    ids: &'a StableTaskIds,
+    missing: Cell<bool>,
+}
+
+impl<'a> StableIdMapping<'a> {
+    pub fn new(ids: &'a StableTaskIds) -> Self {
+        Self {
+            ids,
+            missing: Cell::new(false),
+        }
+    }
+
+    pub fn serialize(&self, value: &impl Serialize) -> Result<serde_json::Value> {
+        let value = with_task_id_mapping(self, || serde_json::to_value(value))?;
+        if self.missing.take() {
+            bail!("refers to a task without stable id");
+        }
+        Ok(value)
+    }
+
+    pub fn deserialize<T: DeserializeOwned>(&self, value: &serde_json::Value) -> Result<T> {
+        let value = with_task_id_mapping(self, || T::deserialize(value))?;
+        if self.missing.take() {
+            bail!("refers to a task that doesn't exist in this run");
+        }
+        Ok(value)
+    }
+}
+
+impl<'a> IdMapping<TaskId> for StableIdMapping<'a> {
+    fn forward(&self, task: TaskId) -> usize {
+        match self.ids.get(task) {
+            Some(id) => id.0 as usize,
+            None => {
+                self.missing.set(true);
+                0
+            }
+        }
+    }
+
+    fn backward(&self, id: usize) -> TaskId {
+        match self.ids.task(StableTaskId(id as u64)) {
+            Some(task) => task,
+            None => {
+                self.missing.set(true);
+                TaskId::from(0)
+            }
+        }
+    }
+}
+
+impl RecordedExecution {
+    /// The tasks the execution called, in call order.
+    pub(crate) fn calls(&self, ids: &StableTaskIds) -> Result<Vec<PersistentTaskType>> {
+        let mapping = StableIdMapping::new(ids);
+        self.calls
+            .iter()
+            .map(|call| mapping.deserialize(call))
+            .collect()
+    }
+
+    /// Repeats the execution in the current task: the recorded calls are made
+    /// again, so the task graph is the same as in the recorded run, the
+    /// recorded cells are updated and the recorded output is returned.
+    pub(crate) fn replay(&self, ids: &StableTaskIds) -> Result<RawVc> {
+        let mapping = StableIdMapping::new(ids);
+        let tt = turbo_tasks();
+        for call in &self.calls {
+            // The inputs already contain the context, so the calls are not
+            // made with [turbo_tasks::dynamic_call]
+            match mapping.deserialize(call)? {
+                PersistentTaskType::Native(function, inputs)
+                | PersistentTaskType::ResolveNative(function, inputs) => {
+                    tt.dynamic_call(function, inputs);
+                }
+                PersistentTaskType::ResolveTrait(trait_type, name, inputs) => {
+                    tt.trait_call(trait_type, name, inputs);
+                }
+            }
+        }
+        // Cells are counted like in the recorded execution, so unused cells are
+        // cleaned up the same way
+        let mut cell_counts: HashMap<ValueTypeId, u32> = HashMap::new();
+        for (index, content) in &self.cells {
+            let count = cell_counts.entry(index.type_id).or_default();
+            *count = (*count).max(index.index + 1);
+            tt.update_current_task_cell(*index, mapping.deserialize(content)?);
+        }
+        for (type_id, count) in cell_counts {
+            for _ in 0..count {
+                find_cell_by_type(type_id);
+            }
+        }
+        match &self.output {
+            Ok(output) => mapping.deserialize(output),
+            Err(message) => Err(anyhow!("{message}")),
+        }
+    }
+}
+
+/// The executions of a recording, to serve them instead of executing the
+/// tasks, see [crate::MemoryBackend::with_execution_replay].
+#[derive(Default)]
+pub struct ExecutionRecording {
+    executions: Mutex<HashMap<StableTaskId, VecDeque<RecordedExecution>>>,
+    len: usize,
+}
+
+impl ExecutionRecording {
+    /// Reads a recording written by
+    /// [crate::MemoryBackend::with_execution_recording].
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("unable to open recording {}", path.display()))?;
+        let mut executions: HashMap<StableTaskId, VecDeque<RecordedExecution>> = HashMap::new();
+        let mut len = 0;
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let execution: RecordedExecution = serde_json::from_str(&line).with_context(|| {
+                format!(
+                    "invalid execution in line {} of {}",
+                    number + 1,
+                    path.display()
+                )
+            })?;
+            executions
+                .entry(execution.task)
+                .or_default()
+                .push_back(execution);
+            len += 1;
+        }
+        Ok(Self {
+            executions: Mutex::new(executions),
+            len,
+        })
+    }
+
+    /// Returns the number of recorded executions.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the next recorded execution of the task. Executions are served
+    /// in recording order, the last one is served again when the task
+    /// executes more often than recorded.
+    pub(crate) fn next(&self, task: StableTaskId) -> Option<RecordedExecution> {
+        let mut executions = self.executions.lock().unwrap();
+        let executions = executions.get_mut(&task)?;
+        if executions.len() > 1 {
+            executions.pop_front()
+        } else {
+            executions.front().cloned()
+        }
+    }
+}
+
+/// The calls and cell updates of an execution that hasn't finished yet.
+#[derive(Default)]
+struct PendingExecution {
+    calls: Vec<PersistentTaskType>,
+    cells: Vec<(CellId, CellContent)>,
+}
+
+/// Writes the executions of tasks with stable ids to a file, one JSON line
+/// per execution, see [crate::MemoryBackend::with_execution_recording].
+pub(crate) struct ExecutionRecorder {
+    path: PathBuf,
+    /// Opened on the first recorded execution.
+    file: Mutex<Option<LineWriter<File>>>,
+    pending: DashMap<TaskId, PendingExecution, BuildNoHashHasher<TaskId>>,
+    recorded: AtomicUsize,
+    skipped: AtomicUsize,
+}
+
+impl ExecutionRecorder {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            file: Mutex::new(None),
+            pending: DashMap::default(),
+            recorded: AtomicUsize::new(0),
+            skipped: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn execution_started(&self, task: TaskId) {
+        self.pending.insert(task, PendingExecution::default());
+    }
+
+    pub fn call(&self, task: TaskId, task_type: &PersistentTaskType) {
+        if let Some(mut pending) = self.pending.get_mut(&task) {
+            pending.calls.push(task_type.clone());
+        }
+    }
+
+    pub fn cell_updated(&self, task: TaskId, index: CellId, content: &CellContent) {
+        if let Some(mut pending) = self.pending.get_mut(&task) {
+            pending.cells.push((index, content.clone()));
+        }
+    }
+
+    /// Writes the execution of the task. Executions that can't be serialized,
+    /// e.g. because they have transient inputs or values that are not
+    /// serializable, are skipped.
+    pub fn execution_finished(
+        &self,
+        task: TaskId,
+        description: impl FnOnce() -> String,
+        result: &Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        ids: &StableTaskIds,
+    ) {
+        let Some((_, pending)) = self.pending.remove(&task) else {
+            return;
+        };
+        let Some(stable_id) = ids.get(task) else {
+            return;
+        };
+        let description = description();
+        let execution = match Self::serialize(stable_id, description.clone(), pending, result, ids)
+        {
+            Ok(execution) => execution,
+            Err(err) => {
+                self.skipped.fetch_add(1, Ordering::Relaxed);
+                tracing::debug!("execution of {description} is not recorded: {err:#}");
+                return;
+            }
+        };
+        if let Err(err) = self.write(&execution) {
+            self.skipped.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(
+                "unable to write execution recording {}: {err}",
+                self.path.display()
+            );
+            return;
+        }
+        self.recorded.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn serialize(
+        task: StableTaskId,
+        description: String,
+        pending: PendingExecution,
+        result: &Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        ids: &StableTaskIds,
+    ) -> Result<RecordedExecution> {
+        let mapping = StableIdMapping::new(ids);
+        let calls = pending
+            .calls
+            .iter()
+            .map(|call| mapping.serialize(call))
+            .collect::<Result<_>>()?;
+        let cells = pending
+            .cells
+            .iter()
+            .map(|(index, content)| Ok((*index, mapping.serialize(content)?)))
+            .collect::<Result<_>>()?;
+        let output = match result {
+            Ok(Ok(output)) => Ok(mapping.serialize(output)?),
+            Ok(Err(err)) => Err(format!("{err:#}")),
+            Err(Some(message)) => Err(format!("A task panicked: {message}")),
+            Err(None) => Err("A task panicked".to_string()),
+        };
+        Ok(RecordedExecution {
+            task,
+            description,
+            calls,
+            cells,
+            output,
+        })
+    }
+
+    fn write(&self, execution: &RecordedExecution) -> io::Result<()> {
+        let line = serde_json::to_string(execution)?;
+        let mut file = self.file.lock().unwrap();
+        let file = match &mut *file {
+            Some(file) => file,
+            None => file.insert(LineWriter::new(File::create(&self.path)?)),
+        };
+        writeln!(file, "{line}")
+    }
+
+    pub fn recorded(&self) -> usize {
+        self.recorded.load(Ordering::Relaxed)
+    }
+
+    pub fn skipped(&self) -> usize {
+        self.skipped.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,136 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use parking_lot::Mutex;
+use turbo_tasks::TaskId;
+
+/// The reason why a task has been executed in a generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExecutionReason {
+    /// The task has been executed for the first time.
+    Initial,
+    /// The task has been executed again after it has been invalidated, either
+    /// by an external [turbo_tasks::Invalidator] or by a changed dependency.
+    Invalidated,
+}
+
+/// Everything that happened in a single update generation.
+#[derive(Debug, Default, Clone)]
+pub struct GenerationRecord {
+    /// Tasks that executed in the generation and why they did.
+    pub executed: HashMap<TaskId, ExecutionReason>,
+    /// Tasks that have been invalidated in the generation.
+    pub invalidated: HashSet<TaskId>,
+}
+
+/// The difference between two update generations. All lists are sorted by
+/// task id.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GenerationDiff {
+    /// Tasks that executed in the second generation, but not in the first one.
+    pub newly_executed: Vec<(TaskId, ExecutionReason)>,
+    /// Tasks that executed in the first generation, but were reused from cache
+    /// in the second one.
+    pub skipped: Vec<TaskId>,
+    /// Tasks that have been invalidated in the second generation.
+    pub invalidated: Vec<TaskId>,
+}
+
+/// Keeps records of the last few update generations.
+pub(crate) struct Generations {
+    /// The number of generations to keep. 0 means tracking is disabled.
+    max_generations: AtomicUsize,
+    records: Mutex<VecDeque<(usize, GenerationRecord)>>,
+}
+
+impl Generations {
+    pub fn new() -> Self {
+        Self {
+            max_generations: AtomicUsize::new(0),
+            records: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_generations.load(Ordering::Acquire) != 0
+    }
+
+    pub fn set_max_generations(&self, max_generations: usize) {
+        self.max_generations
+            .store(max_generations, Ordering::Release);
+        let mut records = self.records.lock();
+        while records.len() > max_generations {
+            records.pop_front();
+        }
+    }
+
+    fn with_record(&self, generation: usize, func: impl FnOnce(&mut GenerationRecord)) {
+        let max_generations = self.max_generations.load(Ordering::Acquire);
+        if max_generations == 0 {
+            return;
+        }
+        let mut records = self.records.lock();
+        if let Some((_, record)) = records.iter_mut().rev().find(|(g, _)| *g == generation) {
+            func(record);
+            return;
+        }
+        if matches!(records.back(), Some((last, _)) if *last > generation) {
+            // The generation is older than the recorded ones, it has already been evicted.
+            return;
+        }
+        let mut record = GenerationRecord::default();
+        func(&mut record);
+        records.push_back((generation, record));
+        while records.len() > max_generations {
+            records.pop_front();
+        }
+    }
+
+    pub fn record_execution(&self, generation: usize, task: TaskId, reason: ExecutionReason) {
+        self.with_record(generation, |record| {
+            record.executed.entry(task).or_insert(reason);
+        });
+    }
+
+    pub fn record_invalidation(&self, generation: usize, task: TaskId) {
+        self.with_record(generation, |record| {
+            record.invalidated.insert(task);
+        });
+    }
+
+    pub fn get(&self, generation: usize) -> Option<GenerationRecord> {
+        self.records
+            .lock()
+            .iter()
+            .find(|(g, _)| *g == generation)
+            .map(|(_, record)| record.clone())
+    }
+
+    pub fn diff(&self, a: usize, b: usize) -> GenerationDiff {
+        let a = self.get(a).unwrap_or_default();
+        let b = self.get(b).unwrap_or_default();
+        let mut newly_executed: Vec<_> = b
+            .executed
+            .iter()
+            .filter(|(task, _)| !a.executed.contains_key(task))
+            .map(|(task, reason)| (*task, *reason))
+            .collect();
+        newly_executed.sort_by_key(|(task, _)| *task);
+        let mut skipped: Vec<_> = a
+            .executed
+            .keys()
+            .filter(|task| !b.executed.contains_key(task))
+            .copied()
+            .collect();
+        skipped.sort();
+        let mut invalidated: Vec<_> = b.invalidated.into_iter().collect();
+        invalidated.sort();
+        GenerationDiff {
+            newly_executed,
+            skipped,
+            invalidated,
+        }
+    }
+}
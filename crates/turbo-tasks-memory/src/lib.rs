@@ -3,15 +3,43 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 
 mod cell;
+mod comparison_backend;
 mod count_hash_set;
+mod duration_histogram;
+mod execution_recording;
+mod generations;
 mod memory_backend;
 mod memory_backend_with_pg;
 mod output;
+mod panic_dump;
 mod scope;
+mod scope_audit;
+mod scope_profile;
+mod scope_quota;
+mod slow_ops;
+mod stable_task_ids;
 pub mod stats;
 mod task;
 mod task_stats;
+mod tracked_dependencies;
 pub mod viz;
+mod warmup;
 
-pub use memory_backend::MemoryBackend;
+pub use cell::CellNotFound;
+pub use comparison_backend::{BackendMismatch, ComparisonBackend};
+pub use duration_histogram::DurationHistogram;
+pub use execution_recording::ExecutionRecording;
+pub use generations::{ExecutionReason, GenerationDiff, GenerationRecord};
+pub use memory_backend::{
+    DependencyChainStep, ErrorPolicy, InvariantPolicy, MemoryBackend, NearDuplicateTasks,
+    ScopeMembershipStep,
+};
 pub use memory_backend_with_pg::MemoryBackendWithPersistedGraph;
+pub use output::{OutputContent, OutputHistoryEntry, OutputNotComputed};
+pub use scope::{TaskScope, TaskScopeId};
+pub use scope_audit::{ScopeAuditIssue, ScopeAuditReport};
+pub use scope_profile::ScopeProfile;
+pub use scope_quota::{QuotaContributor, ScopeQuota, ScopeQuotaExceeded};
+pub use slow_ops::{SlowOpReporter, SlowOpThresholds, SlowOperation, TracingSlowOpReporter};
+pub use stable_task_ids::StableTaskId;
+pub use warmup::{WarmupOrder, WarmupProfile};
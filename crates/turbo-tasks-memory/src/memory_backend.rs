@@ -1,44 +1,240 @@
 use std::{
     borrow::Cow,
     cell::RefCell,
-    collections::{HashSet, VecDeque},
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
     future::Future,
     hash::BuildHasherDefault,
+    mem::size_of,
+    path::PathBuf,
     pin::Pin,
-    time::{Duration, Instant},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 
-use anyhow::{bail, Result};
-use dashmap::{mapref::entry::Entry, DashMap};
+use anyhow::{anyhow, bail, Error, Result};
+use auto_hash_map::AutoSet;
+use dashmap::{mapref::entry::Entry, DashMap, DashSet};
+use nohash_hasher::BuildNoHashHasher;
+use parking_lot::Mutex;
 use rustc_hash::FxHasher;
-use tokio::task::futures::TaskLocalFuture;
 use turbo_tasks::{
     backend::{
-        Backend, BackendJobId, CellContent, PersistentTaskType, TaskExecutionSpec,
-        TransientTaskType,
+        Backend, BackendJobId, CellContent, CellCounts, PersistentTaskType, TaskExecutionSpec,
+        TransientTaskRoot, TransientTaskType,
     },
     event::EventListener,
-    util::{IdFactory, NoMoveVec},
-    CellId, RawVc, TaskId, TraitTypeId, TurboTasksBackendApi,
+    platform::{Instant, TaskLocalFuture},
+    registry,
+    util::{IdFactory, NoMoveVec, SharedError},
+    CellId, FunctionId, MemoTable, MemoTableStats, RawVc, RootTaskState, TaskId, TaskInput,
+    TraitTypeId, TurboTasksBackendApi,
 };
 
 use crate::{
-    output::Output,
+    execution_recording::{ExecutionRecorder, ExecutionRecording},
+    generations::{ExecutionReason, GenerationDiff, GenerationRecord, Generations},
+    output::{Output, OutputContent, OutputHistoryEntry},
+    panic_dump::PanicDumps,
     scope::{TaskScope, TaskScopeId},
+    scope_audit::{ScopeAudit, ScopeAuditIssue, ScopeAuditReport},
+    scope_profile::ScopeProfile,
+    scope_quota::{ScopeQuota, ScopeQuotaState},
+    slow_ops::{SlowOpReporter, SlowOpThresholds, SlowOperation, SlowOps},
+    stable_task_ids::{StableTaskId, StableTaskIds},
     task::{
-        run_add_to_scope_queue, run_remove_from_scope_queue, Task, TaskDependency,
-        DEPENDENCIES_TO_TRACK,
+        run_add_to_scope_queue, run_remove_from_scope_queue, run_update_scopes_queue, FxIndexSet,
+        ScopeUpdate, Task, TaskDependency, DEPENDENCIES_TO_TRACK,
     },
+    tracked_dependencies::TrackedDependencies,
+    warmup::{run_warmup, WarmupOrder, WarmupProfile, WarmupTask},
 };
 
+/// The number of update generations a result of [turbo_tasks::memoize] is
+/// kept without being used, see [MemoryBackend::with_memo_eviction].
+const DEFAULT_MEMO_MAX_IDLE_GENERATIONS: usize = 8;
+/// How long a merged root scope that became a root scope again is not merged
+/// again, see [MemoryBackend::with_max_scopes].
+const REROOTED_SCOPE_COOLDOWN: Duration = Duration::from_secs(60);
+
 pub struct MemoryBackend {
     memory_tasks: NoMoveVec<Task, 13>,
+    /// The highest id in `memory_tasks`. Ids of tasks that lost a race to
+    /// be created are left empty.
+    max_task_id: AtomicUsize,
     memory_task_scopes: NoMoveVec<TaskScope>,
     scope_id_factory: IdFactory<TaskScopeId>,
+    /// The number of scopes that have been created, including the initial
+    /// scope. Scopes are never freed.
+    scope_count: AtomicUsize,
+    /// The root scopes that have been merged into their parents, by the task
+    /// that owned them. The scope is reused when the task becomes root scoped
+    /// again, see [MemoryBackend::with_max_scopes].
+    merged_scopes: DashMap<TaskId, TaskScopeId, BuildNoHashHasher<TaskId>>,
+    /// When merged root scopes became root scopes again, by scope.
+    rerooted_scopes: DashMap<TaskScopeId, Instant, BuildNoHashHasher<TaskScopeId>>,
+    max_scopes: Option<usize>,
+    /// Set while a [Job::ConsolidateScopes] is scheduled or running.
+    consolidating_scopes: AtomicBool,
+    /// The tasks that own a root scope, by their root scope.
+    root_scopes: DashMap<TaskScopeId, TaskId, BuildNoHashHasher<TaskScopeId>>,
     pub(crate) initial_scope: TaskScopeId,
     backend_jobs: NoMoveVec<Job>,
     backend_job_id_factory: IdFactory<BackendJobId>,
     task_cache: DashMap<PersistentTaskType, TaskId, BuildHasherDefault<FxHasher>>,
+    /// The native tasks of each function that are in a scope. Released tasks
+    /// are removed and added again when they are used again. Functions
+    /// without tasks are removed.
+    tasks_by_function: DashMap<FunctionId, FxIndexSet<TaskId>, BuildNoHashHasher<FunctionId>>,
+    /// The number of native tasks that have been created for each function.
+    created_by_function: DashMap<FunctionId, usize, BuildNoHashHasher<FunctionId>>,
+    /// The number of executions of native tasks of each function.
+    executions_by_function: DashMap<FunctionId, usize, BuildNoHashHasher<FunctionId>>,
+    /// The number of invalidations of native tasks of each function.
+    invalidations_by_function: DashMap<FunctionId, usize, BuildNoHashHasher<FunctionId>>,
+    /// The tasks with each tag, in tagging order. Tags without tasks are
+    /// removed.
+    tasks_by_tag: DashMap<String, FxIndexSet<TaskId>, BuildHasherDefault<FxHasher>>,
+    /// The tags of each task, to remove them when the task executes again.
+    tags_by_task: DashMap<TaskId, Vec<String>, BuildNoHashHasher<TaskId>>,
+    generations: Generations,
+    /// Functions whose tasks became root scoped in this run because they
+    /// exceeded the optimization threshold.
+    root_scoped_functions: DashSet<FunctionId, BuildNoHashHasher<FunctionId>>,
+    /// Functions whose tasks are root scoped on creation.
+    pre_root_scoped_functions: HashSet<FunctionId, BuildNoHashHasher<FunctionId>>,
+    slow_ops: Option<SlowOps>,
+    /// The number of past output contents kept per task.
+    output_history: usize,
+    scope_audit: Option<ScopeAudit>,
+    error_policy: ErrorPolicy,
+    /// The first failure in each root scope, with [ErrorPolicy::FailFast].
+    failed_scopes: DashMap<TaskScopeId, SharedError, BuildNoHashHasher<TaskScopeId>>,
+    panic_dumps: Option<PanicDumps>,
+    invariant_policy: InvariantPolicy,
+    /// The number of invariant violations that have been recovered from.
+    recovered_invariant_violations: AtomicUsize,
+    /// The number of reads of final tasks that didn't store a dependency.
+    pruned_dependencies: AtomicUsize,
+    /// The number of tasks visited to move them between scopes.
+    scope_update_visits: AtomicUsize,
+    /// Quotas by the root scope they apply to.
+    scope_quotas: DashMap<TaskScopeId, ScopeQuotaState, BuildNoHashHasher<TaskScopeId>>,
+    /// Functions whose outputs are waited for instead of failing with
+    /// [crate::OutputNotComputed] when read before their first execution.
+    wait_for_first_execution: DashSet<FunctionId, BuildNoHashHasher<FunctionId>>,
+    stable_task_ids: Option<Arc<StableTaskIds>>,
+    execution_recorder: Option<ExecutionRecorder>,
+    execution_replay: Option<ExecutionRecording>,
+    /// The number of executions that have been served from the recording.
+    replayed_executions: AtomicUsize,
+    /// Taken by the warmup on startup, see
+    /// [MemoryBackend::with_warmup_profile].
+    warmup_profile: Mutex<Option<WarmupProfile>>,
+    /// The number of tasks that have been scheduled by the warmup.
+    warmed_up_tasks: AtomicUsize,
+    memo_table: MemoTable,
+}
+
+/// How errors of task executions are handled, see
+/// [MemoryBackend::with_error_policy].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Errors are stored in the output of the task and surfaced when it's
+    /// read. Other tasks continue to execute.
+    #[default]
+    Store,
+    /// The first error fails the root scopes of the task and their parent root
+    /// scopes. Strongly consistent reads of these scopes return the error right
+    /// away, and tasks in them are aborted instead of executed. Tasks that are
+    /// already executing can't be interrupted, they run to completion and
+    /// their results are only visible to reads that are not strongly
+    /// consistent. Failures are permanent, so this is meant for one-off builds
+    /// where partial results are useless.
+    FailFast,
+}
+
+/// How violations of invariants of the backend are handled, e.g. a task
+/// execution that completes while the task is not in progress. See
+/// [MemoryBackend::with_invariant_policy].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvariantPolicy {
+    /// Violations panic.
+    #[default]
+    Panic,
+    /// Violations panic in debug builds. In release builds they are logged as
+    /// errors and the backend recovers as well as it can, e.g. by ignoring
+    /// the execution. The affected tasks might be stale until they are
+    /// invalidated again, which is better than crashing a long running dev
+    /// server.
+    LogInRelease,
+}
+
+/// A step in the resolution of a Vc that a task has read, see
+/// [MemoryBackend::dependency_chain].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DependencyChainStep {
+    pub vc: RawVc,
+    /// The reader depends on this step, so it's invalidated when it changes.
+    pub tracked: bool,
+}
+
+/// A connect_child call that put a task into a scope, see
+/// [MemoryBackend::explain_scope_membership].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScopeMembershipStep {
+    pub parent: TaskId,
+    pub child: TaskId,
+    /// The root scope of the child, when the child is root scoped. The child
+    /// is then in the scope because its root scope is nested into it.
+    pub child_root_scope: Option<TaskScopeId>,
+}
+
+/// Tasks of one function whose inputs only differ in noisy inputs, see
+/// [MemoryBackend::near_duplicate_tasks].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NearDuplicateTasks {
+    pub function: FunctionId,
+    pub tasks: Vec<TaskId>,
+}
+
+/// Replaces noisy inputs with [TaskInput::Nothing], also within lists.
+fn mask_noisy_input(input: &TaskInput, is_noisy: &impl Fn(&TaskInput) -> bool) -> TaskInput {
+    if is_noisy(input) {
+        return TaskInput::Nothing;
+    }
+    match input {
+        TaskInput::List(list) => TaskInput::List(
+            list.iter()
+                .map(|input| mask_noisy_input(input, is_noisy))
+                .collect(),
+        ),
+        input => input.clone(),
+    }
+}
+
+/// Returns the name of the function of the call and the estimated size of the
+/// task it creates, see [ScopeQuota::max_bytes].
+fn quota_usage(task_type: &PersistentTaskType) -> (String, usize) {
+    let (function, inputs) = match task_type {
+        PersistentTaskType::Native(fn_id, inputs)
+        | PersistentTaskType::ResolveNative(fn_id, inputs) => {
+            (registry::get_function(*fn_id).name.clone(), inputs)
+        }
+        PersistentTaskType::ResolveTrait(trait_type, name, inputs) => (
+            format!("{}::{name}", registry::get_trait(*trait_type).name),
+            inputs,
+        ),
+    };
+    let bytes = size_of::<Task>()
+        + inputs
+            .iter()
+            .map(|input| input.estimated_size())
+            .sum::<usize>();
+    (function, bytes)
 }
 
 impl Default for MemoryBackend {
@@ -57,52 +253,1181 @@ impl MemoryBackend {
         }
         Self {
             memory_tasks: NoMoveVec::new(),
+            max_task_id: AtomicUsize::new(0),
             memory_task_scopes,
             scope_id_factory,
+            scope_count: AtomicUsize::new(1),
+            merged_scopes: DashMap::default(),
+            rerooted_scopes: DashMap::default(),
+            max_scopes: None,
+            consolidating_scopes: AtomicBool::new(false),
+            root_scopes: DashMap::default(),
             initial_scope,
             backend_jobs: NoMoveVec::new(),
             backend_job_id_factory: IdFactory::new(),
             task_cache: DashMap::default(),
+            tasks_by_function: DashMap::default(),
+            created_by_function: DashMap::default(),
+            executions_by_function: DashMap::default(),
+            invalidations_by_function: DashMap::default(),
+            tasks_by_tag: DashMap::default(),
+            tags_by_task: DashMap::default(),
+            generations: Generations::new(),
+            root_scoped_functions: DashSet::default(),
+            pre_root_scoped_functions: HashSet::default(),
+            slow_ops: None,
+            output_history: 0,
+            scope_audit: None,
+            error_policy: ErrorPolicy::default(),
+            failed_scopes: DashMap::default(),
+            panic_dumps: None,
+            invariant_policy: InvariantPolicy::default(),
+            recovered_invariant_violations: AtomicUsize::new(0),
+            pruned_dependencies: AtomicUsize::new(0),
+            scope_update_visits: AtomicUsize::new(0),
+            scope_quotas: DashMap::default(),
+            wait_for_first_execution: DashSet::default(),
+            stable_task_ids: None,
+            execution_recorder: None,
+            execution_replay: None,
+            replayed_executions: AtomicUsize::new(0),
+            warmup_profile: Mutex::new(None),
+            warmed_up_tasks: AtomicUsize::new(0),
+            memo_table: MemoTable::new(DEFAULT_MEMO_MAX_IDLE_GENERATIONS),
+        }
+    }
+
+    /// Uses a [ScopeProfile] from a previous run to give tasks of functions
+    /// that ended up root scoped a root scope right away. Functions are
+    /// resolved by their global name, so this need to be called after
+    /// registration.
+    pub fn with_scope_profile(mut self, profile: &ScopeProfile) -> Self {
+        self.pre_root_scoped_functions
+            .extend(profile.function_ids());
+        self
+    }
+
+    /// Reports internal operations that exceed the thresholds to the
+    /// reporter. Without a reporter these operations are not measured.
+    pub fn with_slow_op_reporter(
+        mut self,
+        reporter: impl SlowOpReporter + 'static,
+        thresholds: SlowOpThresholds,
+    ) -> Self {
+        self.slow_ops = Some(SlowOps::new(Box::new(reporter), thresholds));
+        self
+    }
+
+    /// Keeps the last `len` contents of the output of every task, e.g. to find
+    /// out what a value changed from when debugging spurious invalidations.
+    /// See [MemoryBackend::output_history].
+    pub fn with_output_history(mut self, len: usize) -> Self {
+        self.output_history = len;
+        self
+    }
+
+    /// Audits the scope invariants at most once per interval, while no tasks
+    /// are executing, and logs an error with the report when they are
+    /// violated. See [MemoryBackend::audit_scopes]. The audit walks all tasks,
+    /// so it only runs in debug builds.
+    pub fn with_scope_audit(mut self, interval: Duration) -> Self {
+        self.scope_audit = Some(ScopeAudit::new(interval));
+        self
+    }
+
+    /// Chooses how errors of task executions are handled, see [ErrorPolicy].
+    pub fn with_error_policy(mut self, policy: ErrorPolicy) -> Self {
+        self.error_policy = policy;
+        self
+    }
+
+    /// Writes a diagnostics bundle to a file in `dir` when a task violates an
+    /// invariant of the backend, e.g. when its execution starts in an
+    /// unexpected state. The bundle contains the task, the states of its
+    /// scopes, its parents and children and its recent state transitions, to
+    /// be attached to bug reports. Recording transitions costs an allocation
+    /// per change of a task.
+    pub fn with_panic_dumps(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.panic_dumps = Some(PanicDumps::new(dir.into()));
+        self
+    }
+
+    /// Gives every task a [StableTaskId] derived from its function and inputs,
+    /// so tasks can be referred to across runs of the same build, e.g. in
+    /// cache files or when comparing graphs. [TaskId]s are still assigned in
+    /// creation order, as tasks are stored by them. See
+    /// [MemoryBackend::stable_task_id].
+    pub fn with_stable_task_ids(mut self) -> Self {
+        self.stable_task_ids = Some(Default::default());
+        self
+    }
+
+    /// Records the executions of native tasks to a file at `path`, one JSON
+    /// line per execution with the tasks it called, the cells it updated and
+    /// its output. The recording can be replayed in another run of the same
+    /// build with [MemoryBackend::with_execution_replay], e.g. to reproduce a
+    /// bug on a machine without the original inputs. Tasks are referred to by
+    /// their [StableTaskId]s, so this enables them. Executions that refer to
+    /// tasks without stable id or to values that are not serializable are not
+    /// recorded. State and emitted collectibles are not recorded.
+    pub fn with_execution_recording(mut self, path: impl Into<PathBuf>) -> Self {
+        self.execution_recorder = Some(ExecutionRecorder::new(path.into()));
+        self.stable_task_ids.get_or_insert_default();
+        self
+    }
+
+    /// Serves the executions of native tasks from a recording instead of
+    /// executing their functions, see
+    /// [MemoryBackend::with_execution_recording]. Tasks without recorded
+    /// execution are executed. This enables [StableTaskId]s.
+    pub fn with_execution_replay(mut self, recording: ExecutionRecording) -> Self {
+        self.execution_replay = Some(recording);
+        self.stable_task_ids.get_or_insert_default();
+        self
+    }
+
+    /// Returns the number of executions that have been written to the
+    /// recording and the number that couldn't be recorded, see
+    /// [MemoryBackend::with_execution_recording].
+    pub fn recorded_executions(&self) -> (usize, usize) {
+        match &self.execution_recorder {
+            Some(recorder) => (recorder.recorded(), recorder.skipped()),
+            None => (0, 0),
+        }
+    }
+
+    /// Returns the number of executions that have been served from the
+    /// recording, see [MemoryBackend::with_execution_replay].
+    pub fn replayed_executions(&self) -> usize {
+        self.replayed_executions.load(Ordering::Relaxed)
+    }
+
+    /// Creates the tasks of a [WarmupProfile] from a previous run on startup
+    /// and schedules them at background priority, in batches that only start
+    /// while no other tasks are scheduled. This shrinks the latency of the
+    /// first request after a cold start. Tasks are referred to by their
+    /// [StableTaskId]s, so this enables them.
+    pub fn with_warmup_profile(mut self, profile: WarmupProfile) -> Self {
+        *self.warmup_profile.get_mut() = Some(profile);
+        self.stable_task_ids.get_or_insert_default();
+        self
+    }
+
+    /// Returns a [WarmupProfile] of the `limit` hottest tasks by `order`,
+    /// together with the tasks they take as inputs. Tasks with Vcs in their
+    /// inputs are only included with [MemoryBackend::with_stable_task_ids].
+    pub fn warmup_profile(&self, order: WarmupOrder, limit: usize) -> WarmupProfile {
+        let task_types: HashMap<TaskId, PersistentTaskType> = self
+            .task_cache
+            .iter()
+            .map(|entry| (*entry.value(), entry.key().clone()))
+            .collect();
+        let mut hot: Vec<(u128, TaskId)> = task_types
+            .keys()
+            .filter_map(|&task| {
+                let score = order.score(&self.with_task(task, |task| task.get_stats_info(self)));
+                (score > 0).then_some((score, task))
+            })
+            .collect();
+        hot.sort_by_key(|&(score, task)| (Reverse(score), task));
+        hot.truncate(limit);
+        let no_stable_task_ids = StableTaskIds::default();
+        WarmupProfile::from_tasks(
+            hot.into_iter().map(|(_, task)| task),
+            &task_types,
+            |task| self.with_task(task, |task| task.get_description()),
+            self.stable_task_ids
+                .as_deref()
+                .unwrap_or(&no_stable_task_ids),
+        )
+    }
+
+    /// Returns the number of tasks that have been scheduled by the warmup, see
+    /// [MemoryBackend::with_warmup_profile].
+    pub fn warmed_up_tasks(&self) -> usize {
+        self.warmed_up_tasks.load(Ordering::Relaxed)
+    }
+
+    /// Creates a task of the [WarmupProfile] without parent task and schedules
+    /// it, like a prefetch.
+    pub(crate) fn warm_up_task(
+        &self,
+        task_type: PersistentTaskType,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        let task = self.get_or_create_task(task_type, turbo_tasks);
+        self.with_task(task, |task| task.schedule_when_dirty(turbo_tasks));
+        self.warmed_up_tasks.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Evicts results of [turbo_tasks::memoize] that haven't been used in the
+    /// last `max_idle_generations` update generations. Defaults to 8.
+    pub fn with_memo_eviction(mut self, max_idle_generations: usize) -> Self {
+        self.memo_table = MemoTable::new(max_idle_generations);
+        self
+    }
+
+    /// Bounds the number of scopes in use, see [MemoryBackend::scope_count].
+    /// When more root scopes are created, a background job merges root scopes
+    /// back into the scopes that contain them, starting with the ones with
+    /// the fewest tasks, until at most `max` scopes are in use. Root scopes
+    /// with unfinished tasks or a quota are kept. A merged scope is kept for
+    /// its task and reused when the task becomes root scoped again, e.g. when
+    /// it's read strongly consistent, so at most one scope is allocated per
+    /// task that has ever been root scoped. Such a scope is not merged again
+    /// for a minute, even when that exceeds `max`, so reading it repeatedly
+    /// doesn't merge it and make it a root scope again every time.
+    pub fn with_max_scopes(mut self, max: usize) -> Self {
+        self.max_scopes = Some(max);
+        self
+    }
+
+    /// Chooses how violations of invariants of the backend are handled, see
+    /// [InvariantPolicy]. Diagnostics bundles are written either way, see
+    /// [MemoryBackend::with_panic_dumps].
+    pub fn with_invariant_policy(mut self, policy: InvariantPolicy) -> Self {
+        self.invariant_policy = policy;
+        self
+    }
+
+    /// Returns the number of invariant violations that have been logged and
+    /// recovered from instead of panicking, see [InvariantPolicy].
+    pub fn recovered_invariant_violations(&self) -> usize {
+        self.recovered_invariant_violations.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of reads that didn't store a dependency, because
+    /// the task they read was final or frozen, see [turbo_tasks::mark_final]
+    /// and [MemoryBackend::freeze_subtree].
+    pub fn pruned_dependencies(&self) -> usize {
+        self.pruned_dependencies.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of tasks that have been visited to move them between
+    /// scopes. Each task is visited once for all scopes it's moved between.
+    pub fn scope_update_visits(&self) -> usize {
+        self.scope_update_visits.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn scope_update_visited(&self) {
+        self.scope_update_visits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns true when reads of the task don't need to be tracked, as it's
+    /// final or frozen, and counts the pruned dependency.
+    fn prune_dependency(&self, task: TaskId) -> bool {
+        let is_final = self.with_task(task, |task| task.is_final() || task.is_frozen());
+        if is_final {
+            self.pruned_dependencies.fetch_add(1, Ordering::Relaxed);
+        }
+        is_final
+    }
+
+    /// Freezes the task and its children, transitively, e.g. for a subtree
+    /// that reads inputs which never change during the session, like
+    /// installed packages. Frozen tasks drop their dependencies and the
+    /// readers of their output and cells, ignore invalidations and serve
+    /// their output and cells without tracking reads, which saves the memory
+    /// and time of this bookkeeping. Only tasks that are done are frozen,
+    /// tasks that haven't settled yet are skipped with their children. They
+    /// stay in their scopes, so strongly consistent reads and collectibles
+    /// still work. Returns the number of frozen tasks.
+    pub fn freeze_subtree(&self, task: TaskId) -> usize {
+        let mut frozen = 0;
+        let mut queue = vec![task];
+        while let Some(task) = queue.pop() {
+            if let Some(children) = self.with_task(task, |task| task.freeze(self)) {
+                frozen += 1;
+                queue.extend(children);
+            }
+        }
+        frozen
+    }
+
+    /// Unfreezes the task and its frozen children, transitively, see
+    /// [MemoryBackend::freeze_subtree]. The tasks are invalidated, so their
+    /// next executions track their dependencies again. Tasks that read them
+    /// while they were frozen are not tracked and won't be notified when
+    /// they change, they need to be invalidated separately. Returns the
+    /// number of unfrozen tasks.
+    pub fn unfreeze_subtree(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) -> usize {
+        let mut unfrozen = Vec::new();
+        let mut queue = vec![task];
+        while let Some(task) = queue.pop() {
+            if let Some(children) = self.with_task(task, |task| task.unfreeze()) {
+                unfrozen.push(task);
+                queue.extend(children);
+            }
+        }
+        // All tasks are unfrozen before any is invalidated, so a task that
+        // executes right away doesn't read a child that is still frozen
+        for &task in &unfrozen {
+            self.with_task(task, |task| task.invalidate(self, turbo_tasks));
+        }
+        unfrozen.len()
+    }
+
+    /// Returns true when the task is frozen, see
+    /// [MemoryBackend::freeze_subtree].
+    pub fn is_task_frozen(&self, task: TaskId) -> bool {
+        self.with_task(task, |task| task.is_frozen())
+    }
+
+    /// Reports the violation of an invariant by the task, after writing a
+    /// diagnostics bundle for the task when enabled, see
+    /// [MemoryBackend::with_panic_dumps]. Panics unless the [InvariantPolicy]
+    /// allows recovering, the caller has to recover when this returns.
+    pub(crate) fn invariant_violation(&self, task: &Task, message: String) {
+        let message = match &self.panic_dumps {
+            Some(panic_dumps) => {
+                match panic_dumps.write(task.id(), &message, &task.diagnostics(self)) {
+                    Ok(path) => format!("{message}\ndiagnostics written to {}", path.display()),
+                    Err(err) => {
+                        tracing::error!("failed to write diagnostics: {err}");
+                        message
+                    }
+                }
+            }
+            None => message,
+        };
+        match self.invariant_policy {
+            InvariantPolicy::LogInRelease if !cfg!(debug_assertions) => {
+                self.recovered_invariant_violations
+                    .fetch_add(1, Ordering::Relaxed);
+                tracing::error!("{message}");
+            }
+            _ => panic!("{message}"),
+        }
+    }
+
+    /// Gives the task its own root scope with the quota. Once the tasks created
+    /// in the scope (including nested scopes) exceed the quota, calls that
+    /// would create more tasks fail with [crate::ScopeQuotaExceeded], which
+    /// names the functions that created the most tasks. Tasks that have been
+    /// created before the quota was set are not counted.
+    pub fn set_scope_quota(
+        &self,
+        task: TaskId,
+        quota: ScopeQuota,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        let scope = self.with_task(task, |task| task.root_scope(self, turbo_tasks));
+        self.scope_quotas
+            .insert(scope, ScopeQuotaState::new(task, quota));
+    }
+
+    /// Visits the scopes of the task and their parent scopes, each scope once,
+    /// except for the initial scope. The parents of a scope are skipped when
+    /// `visit` returns false.
+    fn visit_task_scopes(&self, task: &Task, mut visit: impl FnMut(TaskScopeId) -> bool) {
+        let mut queue = task.scopes();
+        let mut visited = HashSet::new();
+        while let Some(scope) = queue.pop() {
+            if scope == self.initial_scope || !visited.insert(scope) {
+                continue;
+            }
+            if visit(scope) {
+                queue.extend(self.with_scope(scope, |scope| scope.parents()));
+            }
+        }
+    }
+
+    /// Returns the scopes with a quota that contain the scopes of the task,
+    /// directly or through parent scopes.
+    fn quota_scopes(&self, task: TaskId) -> Vec<TaskScopeId> {
+        let mut scopes = Vec::new();
+        self.with_task(task, |task| {
+            self.visit_task_scopes(task, |scope| {
+                if self.scope_quotas.contains_key(&scope) {
+                    scopes.push(scope);
+                }
+                true
+            })
+        });
+        scopes
+    }
+
+    /// Counts a task that has been created by a call from the parent task
+    /// against the quotas of the scopes of the parent task.
+    fn count_quota_task(&self, task: TaskId, function: &str, bytes: usize, parent_task: TaskId) {
+        for scope in self.quota_scopes(parent_task) {
+            if let Some(quota) = self.scope_quotas.get(&scope) {
+                quota.add(task, function, bytes);
+            }
+        }
+    }
+
+    /// Stops counting a task that is no longer used against the quotas and
+    /// stops listing it for its function and its root scope, if any.
+    pub(crate) fn task_released(&self, task: TaskId, root_scope: Option<TaskScopeId>) {
+        if let Some(scope) = root_scope {
+            self.root_scopes.remove(&scope);
+        }
+        for quota in self.scope_quotas.iter() {
+            quota.remove(task);
+        }
+        if let Some(function) = self.with_task(task, |task| task.function_id()) {
+            if let Entry::Occupied(mut entry) = self.tasks_by_function.entry(function) {
+                entry.get_mut().remove(&task);
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+    }
+
+    /// Lists a task that has been added to a scope for its function and its
+    /// root scope, if any.
+    pub(crate) fn task_acquired(&self, task: TaskId, root_scope: Option<TaskScopeId>) {
+        if let Some(scope) = root_scope {
+            self.root_scopes.insert(scope, task);
+        }
+        let Some(function) = self.with_task(task, |task| task.function_id()) else {
+            return;
+        };
+        let listed = self
+            .tasks_by_function
+            .get(&function)
+            .map_or(false, |tasks| tasks.contains(&task));
+        if !listed {
+            self.tasks_by_function
+                .entry(function)
+                .or_default()
+                .insert(task);
+        }
+    }
+
+    /// Returns the tasks whose last execution called the task, for walking
+    /// the task graph upwards.
+    pub fn parent_tasks(&self, id: TaskId) -> Vec<TaskId> {
+        self.with_task(id, |task| task.parents())
+    }
+
+    /// Returns the failure of the scope with [ErrorPolicy::FailFast].
+    pub(crate) fn scope_failure(&self, scope: TaskScopeId) -> Option<SharedError> {
+        if self.failed_scopes.is_empty() {
+            return None;
+        }
+        self.failed_scopes.get(&scope).map(|error| error.clone())
+    }
+
+    /// Fails the scopes of the task and all parent scopes, except for the
+    /// initial scope. Scopes keep their first failure.
+    fn fail_fast(&self, task: &Task, error: Error) {
+        let error = SharedError::new(error.context(format!("{} failed", task.get_description())));
+        self.visit_task_scopes(task, |id| {
+            match self.failed_scopes.entry(id) {
+                Entry::Occupied(_) => return false,
+                Entry::Vacant(entry) => {
+                    entry.insert(error.clone());
+                }
+            }
+            self.with_scope(id, |scope| scope.notify_readers());
+            true
+        });
+    }
+
+    /// Logs every state transition, scope change, dependency change and
+    /// invalidation of the task at info level, to study a single misbehaving
+    /// task without the logs of the whole graph.
+    pub fn trace_task(&self, id: TaskId, enabled: bool) {
+        self.with_task(id, |task| {
+            task.set_traced(enabled);
+            if enabled {
+                tracing::info!(task = %id, "tracing {}", task.get_description());
+            }
+        });
+    }
+
+    /// Verifies the bookkeeping of task scopes: the task counter of each scope
+    /// matches the tasks that are in it, counters didn't become negative, and
+    /// child scopes and parent scopes refer to each other. A broken invariant
+    /// usually only causes a panic much later, so this helps to find the
+    /// operation that broke it. Only reliable while no tasks are executing.
+    pub fn audit_scopes(&self) -> ScopeAuditReport {
+        let mut issues = Vec::new();
+        let mut tasks_by_scope = HashMap::<TaskScopeId, usize>::new();
+        for id in 1..=self.max_task_id.load(Ordering::Acquire) {
+            let Some(task) = self.memory_tasks.get(id) else {
+                continue;
+            };
+            for (scope, count) in task.scope_counts() {
+                match count.cmp(&0) {
+                    std::cmp::Ordering::Less => {
+                        issues.push(ScopeAuditIssue::NegativeScopeCount {
+                            task: id.into(),
+                            scope,
+                            count,
+                        });
+                    }
+                    std::cmp::Ordering::Greater => {
+                        *tasks_by_scope.entry(scope).or_default() += 1;
+                    }
+                    std::cmp::Ordering::Equal => {}
+                }
+            }
+        }
+        let mut parents_by_scope = HashMap::new();
+        let mut children_by_scope = HashMap::new();
+        for id in 1..=self.scope_count.load(Ordering::Acquire) {
+            let Some(scope) = self.memory_task_scopes.get(id) else {
+                continue;
+            };
+            let id = TaskScopeId::from(id);
+            let counts = scope.audit_counts();
+            let tasks = tasks_by_scope.get(&id).copied().unwrap_or_default();
+            if counts.tasks != tasks {
+                issues.push(ScopeAuditIssue::TaskCount {
+                    scope: id,
+                    counter: counts.tasks,
+                    tasks,
+                });
+            }
+            if counts.unfinished_tasks < 0 {
+                issues.push(ScopeAuditIssue::NegativeUnfinishedTasks {
+                    scope: id,
+                    count: counts.unfinished_tasks,
+                });
+            }
+            parents_by_scope.insert(id, counts.parents);
+            children_by_scope.insert(id, counts.children);
+        }
+        let mut edges = HashMap::<(TaskScopeId, TaskScopeId), (isize, isize)>::new();
+        for (parent, children) in children_by_scope {
+            for (child, count) in children {
+                edges.entry((parent, child)).or_default().0 = count;
+            }
+        }
+        for (child, parents) in parents_by_scope {
+            for (parent, count) in parents {
+                edges.entry((parent, child)).or_default().1 = count;
+            }
+        }
+        let mut mismatches = edges
+            .into_iter()
+            .filter(|(_, (child_count, parent_count))| {
+                *parent_count != isize::from(*child_count > 0) || *child_count < 0
+            })
+            .collect::<Vec<_>>();
+        mismatches.sort_by_key(|((parent, child), _)| (*parent, *child));
+        issues.extend(mismatches.into_iter().map(
+            |((parent, child), (child_count, parent_count))| ScopeAuditIssue::ChildParentMismatch {
+                parent,
+                child,
+                child_count,
+                parent_count,
+            },
+        ));
+        ScopeAuditReport { issues }
+    }
+
+    fn run_scope_audit(&self) {
+        let report = self.audit_scopes();
+        if !report.is_empty() {
+            tracing::error!("task scope invariants are violated:\n{report}");
+        }
+        if let Some(audit) = &self.scope_audit {
+            audit.finished();
+        }
+    }
+
+    /// Runs the operation and reports it when it was slow.
+    pub(crate) fn measure_slow_op<T>(
+        &self,
+        operation: SlowOperation,
+        task: TaskId,
+        func: impl FnOnce() -> T,
+    ) -> T {
+        match &self.slow_ops {
+            Some(slow_ops) => {
+                let start = Instant::now();
+                let result = func();
+                slow_ops.report(operation, task, start.elapsed());
+                result
+            }
+            None => func(),
+        }
+    }
+
+    pub(crate) fn output_history_len(&self) -> usize {
+        self.output_history
+    }
+
+    /// Returns the last contents of the output of the task, oldest first.
+    /// Only recorded when enabled with [MemoryBackend::with_output_history].
+    pub fn output_history(&self, task: TaskId) -> Vec<OutputHistoryEntry> {
+        self.with_task(task, |task| {
+            task.with_output(|output| output.history().iter().cloned().collect())
+        })
+    }
+
+    /// Returns a [ScopeProfile] of all functions whose tasks became root scoped
+    /// by exceeding the optimization threshold, including the ones from the
+    /// loaded profile. Root scopes of strongly consistent reads are not
+    /// included.
+    pub fn scope_profile(&self) -> ScopeProfile {
+        ScopeProfile::from_function_ids(
+            self.root_scoped_functions
+                .iter()
+                .map(|id| *id)
+                .chain(self.pre_root_scoped_functions.iter().copied()),
+        )
+    }
+
+    pub(crate) fn record_root_scoped(&self, function: FunctionId) {
+        self.root_scoped_functions.insert(function);
+    }
+
+    /// Reads of the output of a task of the function that has never been
+    /// computed wait for its first execution to complete, instead of failing
+    /// with [crate::OutputNotComputed].
+    pub fn wait_for_first_execution(&self, function: FunctionId) {
+        self.wait_for_first_execution.insert(function);
+    }
+
+    pub(crate) fn waits_for_first_execution(&self, function: FunctionId) -> bool {
+        self.wait_for_first_execution.contains(&function)
+    }
+
+    /// Enables recording which tasks executed and which tasks have been
+    /// invalidated in the last `max_generations` update generations. Passing
+    /// 0 disables the tracking.
+    pub fn track_generations(&self, max_generations: usize) {
+        self.generations.set_max_generations(max_generations);
+    }
+
+    /// Returns the record of an update generation, if it is still known.
+    pub fn generation_record(&self, generation: usize) -> Option<GenerationRecord> {
+        self.generations.get(generation)
+    }
+
+    /// Compares two update generations. This explains why the generation `b`
+    /// did more or less work than generation `a`. Generations that are not
+    /// recorded are treated as empty.
+    pub fn diff_generations(&self, a: usize, b: usize) -> GenerationDiff {
+        self.generations.diff(a, b)
+    }
+
+    fn connect_task_child(
+        &self,
+        parent: TaskId,
+        child: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.with_task(parent, |parent| {
+            parent.connect_child(child, self, turbo_tasks)
+        });
+    }
+
+    pub(crate) fn create_backend_job(&self, job: Job) -> BackendJobId {
+        let id = self.backend_job_id_factory.get();
+        // SAFETY: This is a fresh id
+        unsafe {
+            self.backend_jobs.insert(*id, job);
+        }
+        id
+    }
+
+    fn try_get_output<T, F: FnOnce(&Output) -> Result<T>>(
+        &self,
+        id: TaskId,
+        strongly_consistent: bool,
+        note: impl Fn() -> String + Sync + Send + 'static,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+        func: F,
+    ) -> Result<Result<T, EventListener>> {
+        self.with_task(id, |task| {
+            task.get_or_wait_output(strongly_consistent, func, note, self, turbo_tasks)
+        })
+    }
+
+    pub fn with_all_cached_tasks(&self, mut func: impl FnMut(TaskId)) {
+        for id in self.task_cache.clone().into_read_only().values() {
+            func(*id);
+        }
+    }
+
+    /// Returns the native tasks of the function that are in use, i.e. in a
+    /// scope, e.g. to find out how many of them exist and how many of them are
+    /// dirty. Released tasks are listed again when they are used again.
+    pub fn tasks_for_function(&self, function: FunctionId) -> Vec<TaskId> {
+        self.tasks_by_function
+            .get(&function)
+            .map(|tasks| tasks.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns how many native tasks have been created for the function,
+    /// including released ones.
+    pub fn created_for_function(&self, function: FunctionId) -> usize {
+        self.created_by_function
+            .get(&function)
+            .map_or(0, |count| *count)
+    }
+
+    /// Returns how often native tasks of the function have been executed,
+    /// without replayed executions.
+    pub fn executions_for_function(&self, function: FunctionId) -> usize {
+        self.executions_by_function
+            .get(&function)
+            .map_or(0, |count| *count)
+    }
+
+    /// Returns how often native tasks of the function have been invalidated.
+    pub fn invalidations_for_function(&self, function: FunctionId) -> usize {
+        self.invalidations_by_function
+            .get(&function)
+            .map_or(0, |count| *count)
+    }
+
+    /// Returns the cell that the output of the task resolves to, when the
+    /// output is linked to the output of another task and the cell has been
+    /// memoized to skip that hop on reads.
+    pub fn memoized_output_cell(&self, task: TaskId) -> Option<RawVc> {
+        self.with_task(task, |task| {
+            task.with_output(|output| match output.content {
+                OutputContent::Link(RawVc::TaskOutput(_)) => output.resolved_cell(),
+                _ => None,
+            })
+        })
+    }
+
+    /// Memoizes the cell that the output of `target` resolves to on the output
+    /// of `task`, which is linked to it. See [Output::memoize_resolved].
+    fn memoize_output_link(&self, task: TaskId, target: TaskId) {
+        let resolved = self.with_task(target, |target| {
+            target.with_output_mut(|output| {
+                let cell = output.resolved_cell()?;
+                output.memoized_by.insert(task);
+                Some((cell, output.resolved_generation()))
+            })
+        });
+        if let Some((cell, generation)) = resolved {
+            self.with_task(task, |task| {
+                task.with_output_mut(|output| output.memoize_resolved(target, generation, cell))
+            });
+        }
+    }
+
+    /// Clears the memoized cells of the outputs of `tasks` after the cell that
+    /// the output of `target` resolves to changed, and transitively of the
+    /// outputs that memoized them. With `notify` their readers are notified,
+    /// as they read the memoized cell without depending on `target`.
+    pub(crate) fn clear_memoized_links(
+        &self,
+        target: TaskId,
+        generation: u32,
+        tasks: AutoSet<TaskId>,
+        notify: bool,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        let mut queue: Vec<_> = tasks
+            .into_iter()
+            .map(|task| (task, target, generation))
+            .collect();
+        while let Some((task_id, target, generation)) = queue.pop() {
+            self.with_task(task_id, |task| {
+                task.with_output_mut(|output| {
+                    let Some(memoized_by) = output.clear_resolved(target, generation) else {
+                        return;
+                    };
+                    if notify {
+                        output.notify_dependent_tasks(turbo_tasks);
+                    }
+                    let generation = output.resolved_generation();
+                    queue.extend(
+                        memoized_by
+                            .into_iter()
+                            .map(|task| (task, task_id, generation)),
+                    );
+                })
+            });
+        }
+    }
+
+    /// Returns the tasks with the tag, see [turbo_tasks::tag_task].
+    pub fn tasks_with_tag(&self, tag: &str) -> Vec<TaskId> {
+        self.tasks_by_tag
+            .get(tag)
+            .map(|tasks| tasks.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Removes the tags of the task, before an execution sets them again.
+    fn clear_task_tags(&self, task: TaskId) {
+        let Some((_, tags)) = self.tags_by_task.remove(&task) else {
+            return;
+        };
+        for tag in tags {
+            if let Entry::Occupied(mut entry) = self.tasks_by_tag.entry(tag) {
+                entry.get_mut().remove(&task);
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+    }
+
+    fn record_invalidation(&self, task: &Task) {
+        if let Some(function) = task.function_id() {
+            *self.invalidations_by_function.entry(function).or_default() += 1;
+        }
+    }
+
+    /// Returns the stable id of the task, when enabled with
+    /// [MemoryBackend::with_stable_task_ids]. Root and once tasks, and tasks
+    /// with inputs that are transient or refer to tasks without stable id,
+    /// don't have one.
+    pub fn stable_task_id(&self, task: TaskId) -> Option<StableTaskId> {
+        self.stable_task_ids.as_ref()?.get(task)
+    }
+
+    /// Returns the task with the stable id, if it has been created in this
+    /// run.
+    pub fn task_by_stable_id(&self, id: StableTaskId) -> Option<TaskId> {
+        self.stable_task_ids.as_ref()?.task(id)
+    }
+
+    /// Returns a future that replays the recorded execution of the task, when
+    /// there is one, see [MemoryBackend::with_execution_replay].
+    fn replay_execution(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Option<Pin<Box<dyn Future<Output = Result<RawVc>> + Send>>> {
+        let recording = self.execution_replay.as_ref()?;
+        let stable_task_ids = self.stable_task_ids.clone()?;
+        let execution = recording.next(stable_task_ids.get(task)?)?;
+        self.replayed_executions.fetch_add(1, Ordering::Relaxed);
+        // The called tasks are known up front, so they are connected with a
+        // single lock of the task. The replayed calls find them connected.
+        if let Ok(calls) = execution.calls(&stable_task_ids) {
+            let children = calls
+                .into_iter()
+                .map(|task_type| self.get_or_create_task(task_type, turbo_tasks))
+                .collect::<Vec<_>>();
+            self.with_task(task, |task| {
+                task.connect_children(&children, self, turbo_tasks)
+            });
+        }
+        Some(Box::pin(async move { execution.replay(&stable_task_ids) }))
+    }
+
+    /// Looks up the task of the type or creates it, without connecting it to
+    /// a parent task.
+    fn get_or_create_task(
+        &self,
+        task_type: PersistentTaskType,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> TaskId {
+        self.get_or_create_task_inner(task_type, turbo_tasks).0
+    }
+
+    /// Like [MemoryBackend::get_or_create_task], but also returns true when
+    /// the task has been created by this call.
+    fn get_or_create_task_inner(
+        &self,
+        task_type: PersistentTaskType,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> (TaskId, bool) {
+        if let Some(task) = self.task_cache.get(&task_type).map(|task| *task) {
+            // fast pass without creating a new task
+            return (task, false);
+        }
+        // slow pass with key lock
+        let id = turbo_tasks.get_fresh_task_id();
+        let mut task = match &task_type {
+            PersistentTaskType::Native(fn_id, inputs) => {
+                // TODO inputs doesn't need to be cloned when are would be able to get a
+                // reference to the task type stored inside of the task
+                Task::new_native(id, inputs.clone(), *fn_id, turbo_tasks.stats_type())
+            }
+            PersistentTaskType::ResolveNative(fn_id, inputs) => {
+                Task::new_resolve_native(id, inputs.clone(), *fn_id, turbo_tasks.stats_type())
+            }
+            PersistentTaskType::ResolveTrait(trait_type, trait_fn_name, inputs) => {
+                Task::new_resolve_trait(
+                    id,
+                    *trait_type,
+                    trait_fn_name.clone(),
+                    inputs.clone(),
+                    turbo_tasks.stats_type(),
+                )
+            }
+        };
+        if self.panic_dumps.is_some() {
+            task.record_transitions();
+        }
+        // Safety: We have a fresh task id that nobody knows about yet
+        unsafe {
+            self.memory_tasks.insert(*id, task);
+        }
+        self.max_task_id.fetch_max(*id, Ordering::AcqRel);
+        match self.task_cache.entry(task_type) {
+            Entry::Vacant(entry) => {
+                // This is the most likely case
+                let native_fn_id = match entry.key() {
+                    PersistentTaskType::Native(fn_id, _) => Some(*fn_id),
+                    _ => None,
+                };
+                let pre_root_scoped = native_fn_id.map_or(false, |fn_id| {
+                    self.pre_root_scoped_functions.contains(&fn_id)
+                });
+                if let Some(stable_task_ids) = &self.stable_task_ids {
+                    stable_task_ids.assign(id, entry.key());
+                }
+                entry.insert(id);
+                if let Some(fn_id) = native_fn_id {
+                    *self.created_by_function.entry(fn_id).or_default() += 1;
+                }
+                if pre_root_scoped {
+                    self.with_task(id, |task| task.make_root_scoped(self, turbo_tasks));
+                }
+                (id, true)
+            }
+            Entry::Occupied(entry) => {
+                // Safety: We have a fresh task id that nobody knows about yet
+                unsafe {
+                    self.memory_tasks.remove(*id);
+                    turbo_tasks.reuse_task_id(id);
+                }
+                (*entry.get(), false)
+            }
         }
     }
 
-    fn connect_task_child(
+    /// Returns the counters of the table of [turbo_tasks::memoize].
+    pub fn memo_table_stats(&self) -> MemoTableStats {
+        self.memo_table.stats()
+    }
+
+    /// Returns the number of task scopes in use, including the initial scope.
+    /// Root scopes that are merged into their parents are not counted.
+    pub fn scope_count(&self) -> usize {
+        self.allocated_scope_count() - self.merged_scope_count()
+    }
+
+    /// Returns the number of task scopes that have been allocated, including
+    /// the ones that are merged into their parents.
+    pub fn allocated_scope_count(&self) -> usize {
+        self.scope_count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of root scopes that are merged into their parents,
+    /// see [MemoryBackend::with_max_scopes].
+    pub fn merged_scope_count(&self) -> usize {
+        self.merged_scopes.len()
+    }
+
+    /// Returns the root scopes of tasks in use with the task that owns them.
+    /// The initial scope of transient tasks is not included.
+    pub fn root_scopes(&self) -> Vec<(TaskScopeId, TaskId)> {
+        self.root_scopes
+            .iter()
+            .map(|entry| (*entry.key(), *entry.value()))
+            .collect()
+    }
+
+    /// Returns a scope to become the root scope of the task. That's the scope
+    /// it owned before it was merged into its parents, if any, so tasks that
+    /// switch between both don't allocate new scopes.
+    pub(crate) fn create_root_scope(&self, task: TaskId) -> TaskScopeId {
+        match self.merged_scopes.remove(&task) {
+            Some((_, scope)) => {
+                self.rerooted_scopes.insert(scope, Instant::now());
+                scope
+            }
+            None => self.create_new_scope(0),
+        }
+    }
+
+    pub(crate) fn record_root_scope(
         &self,
-        parent: TaskId,
-        child: TaskId,
+        scope: TaskScopeId,
+        task: TaskId,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) {
-        self.with_task(parent, |parent| {
-            parent.connect_child(child, self, turbo_tasks)
-        });
+        self.root_scopes.insert(scope, task);
+        if matches!(self.max_scopes, Some(max) if self.scope_count() > max)
+            && !self.consolidating_scopes.swap(true, Ordering::AcqRel)
+        {
+            // Background jobs wait until no tasks are executing
+            turbo_tasks
+                .schedule_backend_background_job(self.create_backend_job(Job::ConsolidateScopes));
+        }
     }
 
-    pub(crate) fn create_backend_job(&self, job: Job) -> BackendJobId {
-        let id = self.backend_job_id_factory.get();
-        // SAFETY: This is a fresh id
-        unsafe {
-            self.backend_jobs.insert(*id, job);
+    /// Merges root scopes into their parents until the number of scopes is
+    /// within [MemoryBackend::with_max_scopes]. Small root scopes are merged
+    /// first. Scopes that became root scopes again recently are skipped, as
+    /// they are read strongly consistent and would become root scopes again on
+    /// the next read.
+    fn consolidate_scopes(&self, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.consolidating_scopes.store(false, Ordering::Release);
+        let Some(max) = self.max_scopes else {
+            return;
+        };
+        self.rerooted_scopes
+            .retain(|_, rerooted| rerooted.elapsed() < REROOTED_SCOPE_COOLDOWN);
+        let mut candidates = self
+            .root_scopes
+            .iter()
+            .map(|entry| (*entry.key(), *entry.value()))
+            .filter(|(scope, _)| {
+                !self.scope_quotas.contains_key(scope) && !self.rerooted_scopes.contains_key(scope)
+            })
+            .filter_map(|(scope, task)| {
+                let (tasks, unfinished) = self.with_scope(scope, |scope| {
+                    (scope.task_count(), scope.unfinished_tasks())
+                });
+                (unfinished == 0).then_some((tasks, scope, task))
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_unstable();
+        for (_, scope, task) in candidates {
+            if self.scope_count() <= max {
+                break;
+            }
+            if self.with_task(task, |task| task.merge_root_scope(scope, self, turbo_tasks)) {
+                self.root_scopes.remove(&scope);
+                self.merged_scopes.insert(task, scope);
+            }
         }
-        id
     }
 
-    fn try_get_output<T, F: FnOnce(&mut Output) -> Result<T>>(
+    /// Follows the output links of `vc` until it points to a cell, the way a
+    /// read of it by `reader` resolves it. Each step tells if the last
+    /// completed execution of `reader` depends on it, so a missing edge shows
+    /// up as an untracked step.
+    pub fn dependency_chain(&self, reader: TaskId, vc: RawVc) -> Vec<DependencyChainStep> {
+        let mut chain = Vec::new();
+        let mut current = Some(vc);
+        while let Some(vc) = current.take() {
+            if chain.iter().any(|step: &DependencyChainStep| step.vc == vc) {
+                break;
+            }
+            let dependency = match vc {
+                RawVc::TaskOutput(task) => {
+                    current = self.with_task(task, |task| task.output_link());
+                    TaskDependency::TaskOutput(task)
+                }
+                RawVc::TaskCell(task, index) => TaskDependency::TaskCell(task, index),
+            };
+            chain.push(DependencyChainStep {
+                vc,
+                tracked: self.with_task(reader, |task| task.has_dependency(&dependency)),
+            });
+        }
+        chain
+    }
+
+    /// Explains why the task is in the scope. Returns the connect_child calls
+    /// from the task that owns the scope (or a task that has been added to it
+    /// directly, e.g. to the initial scope) down to the task, or `None` when
+    /// the task is not in the scope. Nested root scopes count as part of the
+    /// scope. This walks all tasks for every step, so it's meant for
+    /// debugging only.
+    pub fn explain_scope_membership(
         &self,
-        id: TaskId,
-        strongly_consistent: bool,
-        note: impl Fn() -> String + Sync + Send + 'static,
-        turbo_tasks: &dyn TurboTasksBackendApi,
-        func: F,
-    ) -> Result<Result<T, EventListener>> {
-        self.with_task(id, |task| {
-            task.get_or_wait_output(strongly_consistent, func, note, self, turbo_tasks)
+        task: TaskId,
+        scope: TaskScopeId,
+    ) -> Option<Vec<ScopeMembershipStep>> {
+        if !self.is_task_in_scope(task, scope) {
+            return None;
+        }
+        let owner = self.root_scopes.get(&scope).map(|owner| *owner);
+        // The child of every visited parent, towards the task
+        let mut child_of = HashMap::new();
+        let mut queue = VecDeque::from([task]);
+        let mut start = task;
+        while let Some(current) = queue.pop_front() {
+            if owner == Some(current) {
+                start = current;
+                break;
+            }
+            let parents = self
+                .parent_tasks(current)
+                .into_iter()
+                .filter(|parent| self.is_task_in_scope(*parent, scope))
+                .collect::<Vec<_>>();
+            if parents.is_empty() && owner.is_none() {
+                // Added to the scope directly
+                start = current;
+                break;
+            }
+            for parent in parents {
+                if parent != task && !child_of.contains_key(&parent) {
+                    child_of.insert(parent, current);
+                    queue.push_back(parent);
+                }
+            }
+        }
+        let mut steps = Vec::new();
+        let mut parent = start;
+        while let Some(&child) = child_of.get(&parent) {
+            let child_root_scope = self.with_task(child, |task| task.own_root_scope());
+            steps.push(ScopeMembershipStep {
+                parent,
+                child,
+                child_root_scope,
+            });
+            parent = child;
+        }
+        Some(steps)
+    }
+
+    /// Returns true when the task is in the scope, directly or through nested
+    /// scopes.
+    fn is_task_in_scope(&self, task: TaskId, scope: TaskScopeId) -> bool {
+        self.with_task(task, |task| {
+            if task.is_in_scope(scope) {
+                return true;
+            }
+            let mut found = false;
+            self.visit_task_scopes(task, |current| {
+                found |= current == scope;
+                !found
+            });
+            found
         })
     }
 
-    pub fn with_all_cached_tasks(&self, mut func: impl FnMut(TaskId)) {
-        for id in self.task_cache.clone().into_read_only().values() {
-            func(*id);
+    /// Finds native tasks of the same function whose inputs are equal once
+    /// the inputs matching `is_noisy` (e.g. timestamps) are ignored. These are
+    /// usually caused by arguments that accidentally bust the cache. Clusters
+    /// are sorted by size, largest first.
+    pub fn near_duplicate_tasks(
+        &self,
+        is_noisy: impl Fn(&TaskInput) -> bool,
+    ) -> Vec<NearDuplicateTasks> {
+        let mut clusters: HashMap<(FunctionId, Vec<TaskInput>), Vec<TaskId>> = HashMap::new();
+        for entry in self.task_cache.iter() {
+            if let PersistentTaskType::Native(function, inputs) = entry.key() {
+                let inputs = inputs
+                    .iter()
+                    .map(|input| mask_noisy_input(input, &is_noisy))
+                    .collect();
+                clusters
+                    .entry((*function, inputs))
+                    .or_default()
+                    .push(*entry.value());
+            }
         }
+        let mut clusters = clusters
+            .into_iter()
+            .filter(|(_, tasks)| tasks.len() > 1)
+            .map(|((function, _), mut tasks)| {
+                tasks.sort();
+                NearDuplicateTasks { function, tasks }
+            })
+            .collect::<Vec<_>>();
+        clusters.sort_by(|a, b| {
+            b.tasks
+                .len()
+                .cmp(&a.tasks.len())
+                .then(a.tasks.cmp(&b.tasks))
+        });
+        clusters
     }
 
     pub fn with_task<T>(&self, id: TaskId, func: impl FnOnce(&Task) -> T) -> T {
@@ -113,8 +1438,16 @@ impl MemoryBackend {
         func(self.memory_task_scopes.get(*id).unwrap())
     }
 
+    /// Returns the root scope of the task. The task gets its own root scope
+    /// when it doesn't have one yet. Use [TaskScope::done_listener] to wait
+    /// until all tasks in the scope are finished.
+    pub fn root_scope(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) -> TaskScopeId {
+        self.with_task(task, |task| task.root_scope(self, turbo_tasks))
+    }
+
     pub fn create_new_scope(&self, tasks: usize) -> TaskScopeId {
         let id = self.scope_id_factory.get();
+        self.scope_count.fetch_add(1, Ordering::Relaxed);
         unsafe {
             self.memory_task_scopes
                 .insert(*id, TaskScope::new(id, tasks));
@@ -187,24 +1520,129 @@ impl MemoryBackend {
 }
 
 impl Backend for MemoryBackend {
+    fn startup(&self, turbo_tasks: &dyn TurboTasksBackendApi) -> Result<()> {
+        if let Some(profile) = self.warmup_profile.lock().take() {
+            if !profile.is_empty() {
+                turbo_tasks.schedule_backend_background_job(
+                    self.create_backend_job(Job::Warmup(profile.into_tasks())),
+                );
+            }
+        }
+        Ok(())
+    }
+
     fn invalidate_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
-        self.with_task(task, |task| task.invalidate(self, turbo_tasks));
+        self.generations
+            .record_invalidation(turbo_tasks.update_generation(), task);
+        self.with_task(task, |task| {
+            self.record_invalidation(task);
+            task.invalidate(self, turbo_tasks)
+        });
+    }
+
+    fn task_done_listener(
+        &self,
+        task: TaskId,
+        _turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Option<EventListener> {
+        // Active dependents are in the same scopes as the task or in parent
+        // scopes
+        let mut listener = None;
+        self.with_task(task, |task| {
+            self.visit_task_scopes(task, |scope| {
+                if listener.is_none() {
+                    listener = self.with_scope(scope, |scope| scope.active_done_listener());
+                }
+                listener.is_none()
+            })
+        });
+        listener
+    }
+
+    fn pending_root_state(
+        &self,
+        task: TaskId,
+        _turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Option<RootTaskState> {
+        self.with_task(task, |task| {
+            if task.is_pending() {
+                return Some(RootTaskState::Executing);
+            }
+            let scope = task.own_root_scope()?;
+            self.with_scope(scope, |scope| scope.has_unfinished_tasks())
+                .then_some(RootTaskState::Settling)
+        })
     }
 
     fn invalidate_tasks(&self, tasks: Vec<TaskId>, turbo_tasks: &dyn TurboTasksBackendApi) {
         for task in tasks.into_iter() {
+            self.generations
+                .record_invalidation(turbo_tasks.update_generation(), task);
             self.with_task(task, |task| {
+                self.record_invalidation(task);
                 task.invalidate(self, turbo_tasks);
             });
         }
     }
 
+    fn invalidate_function_tasks(
+        &self,
+        function: FunctionId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        // Released tasks are invalidated too, they might be used again
+        let tasks = self
+            .task_cache
+            .iter()
+            .filter_map(|entry| match entry.key() {
+                PersistentTaskType::Native(fn_id, _) if *fn_id == function => Some(*entry.value()),
+                _ => None,
+            })
+            .collect();
+        self.invalidate_tasks(tasks, turbo_tasks);
+    }
+
+    fn is_blocking_task(&self, task: TaskId) -> bool {
+        self.with_task(task, |task| task.is_blocking())
+    }
+
     fn get_task_description(&self, task: TaskId) -> String {
         self.with_task(task, |task| task.get_description())
     }
 
+    fn memo_table(&self) -> Option<&MemoTable> {
+        Some(&self.memo_table)
+    }
+
+    fn get_task_function(&self, task: TaskId) -> Option<FunctionId> {
+        self.with_task(task, |task| task.function_id())
+    }
+
+    fn mark_task_final(&self, task: TaskId, _turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.with_task(task, |task| task.mark_final())
+    }
+
+    fn set_task_label(&self, task: TaskId, label: String, _turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.with_task(task, |task| task.set_label(label))
+    }
+
+    fn add_task_tag(&self, task: TaskId, tag: String, _turbo_tasks: &dyn TurboTasksBackendApi) {
+        if self
+            .tasks_by_tag
+            .entry(tag.clone())
+            .or_default()
+            .insert(task)
+        {
+            self.tags_by_task.entry(task).or_default().push(tag);
+        }
+    }
+
+    fn tasks_with_tag(&self, tag: &str) -> Vec<TaskId> {
+        self.tasks_with_tag(tag)
+    }
+
     type ExecutionScopeFuture<T: Future<Output = Result<()>> + Send + 'static> =
-        TaskLocalFuture<RefCell<HashSet<TaskDependency>>, T>;
+        TaskLocalFuture<RefCell<TrackedDependencies>, T>;
     fn execution_scope<T: Future<Output = Result<()>> + Send + 'static>(
         &self,
         _task: TaskId,
@@ -215,11 +1653,46 @@ impl Backend for MemoryBackend {
 
     fn try_start_task_execution(
         &self,
-        task: TaskId,
+        task_id: TaskId,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> Option<TaskExecutionSpec> {
-        self.with_task(task, |task| {
+        self.with_task(task_id, |task| {
+            let reason = if !self.generations.is_enabled() {
+                None
+            } else if task.has_executed() {
+                Some(ExecutionReason::Invalidated)
+            } else {
+                Some(ExecutionReason::Initial)
+            };
             if task.execution_started(self, turbo_tasks) {
+                self.clear_task_tags(task_id);
+                if let Some(reason) = reason {
+                    self.generations.record_execution(
+                        turbo_tasks.update_generation(),
+                        task_id,
+                        reason,
+                    );
+                }
+                let failure = task
+                    .scopes()
+                    .into_iter()
+                    .find_map(|scope| self.scope_failure(scope));
+                if let Some(error) = failure {
+                    return Some(TaskExecutionSpec {
+                        future: Box::pin(async move {
+                            Err(Error::new(error).context("aborted after a failure"))
+                        }),
+                    });
+                }
+                if let Some(function) = task.function_id() {
+                    if let Some(recorder) = &self.execution_recorder {
+                        recorder.execution_started(task_id);
+                    }
+                    if let Some(future) = self.replay_execution(task_id, turbo_tasks) {
+                        return Some(TaskExecutionSpec { future });
+                    }
+                    *self.executions_by_function.entry(function).or_default() += 1;
+                }
                 Some(TaskExecutionSpec {
                     future: task.execute(turbo_tasks),
                 })
@@ -229,14 +1702,49 @@ impl Backend for MemoryBackend {
         })
     }
 
+    fn task_execution_cell_counts(
+        &self,
+        task: TaskId,
+        cell_counts: &CellCounts,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        self.with_task(task, |task| task.compact_cells(cell_counts, turbo_tasks))
+    }
+
     fn task_execution_result(
         &self,
         task: TaskId,
         result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) {
+        if let (Some(recorder), Some(stable_task_ids)) =
+            (&self.execution_recorder, &self.stable_task_ids)
+        {
+            recorder.execution_finished(
+                task,
+                || self.with_task(task, |task| task.get_description()),
+                &result,
+                stable_task_ids,
+            );
+        }
         self.with_task(task, |task| {
-            task.execution_result(result, turbo_tasks);
+            let result = match result {
+                Ok(Err(error)) if self.error_policy == ErrorPolicy::FailFast => {
+                    let error = SharedError::new(error);
+                    self.fail_fast(task, Error::new(error.clone()));
+                    Ok(Err(Error::new(error)))
+                }
+                Err(message) if self.error_policy == ErrorPolicy::FailFast => {
+                    let error = match &message {
+                        Some(message) => anyhow!("A task panicked: {message}"),
+                        None => anyhow!("A task panicked"),
+                    };
+                    self.fail_fast(task, error);
+                    Err(message)
+                }
+                result => result,
+            };
+            task.execution_result(result, self, turbo_tasks);
         })
     }
 
@@ -247,6 +1755,12 @@ impl Backend for MemoryBackend {
         instant: Instant,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> bool {
+        if cfg!(debug_assertions)
+            && matches!(&self.scope_audit, Some(audit) if audit.start_if_due())
+        {
+            // Background jobs wait until no tasks are executing
+            turbo_tasks.schedule_backend_background_job(self.create_backend_job(Job::AuditScopes));
+        }
         self.with_task(task, |task| {
             task.execution_completed(duration, instant, self, turbo_tasks)
         })
@@ -262,16 +1776,109 @@ impl Backend for MemoryBackend {
         if task == reader {
             bail!("reading it's own output is not possible");
         }
-        self.try_get_output(
+        if self.prune_dependency(task) {
+            return self.try_get_output(
+                task,
+                strongly_consistent,
+                move || format!("reading final task output from {reader}"),
+                turbo_tasks,
+                |output| output.read_untracked(),
+            );
+        }
+        let result = self.try_get_output(
             task,
             strongly_consistent,
             move || format!("reading task output from {reader}"),
             turbo_tasks,
             |output| {
                 Task::add_dependency_to_current(TaskDependency::TaskOutput(task));
-                output.read(reader)
+                // Strongly consistent reads need to wait for every hop
+                if strongly_consistent {
+                    output.read(reader)
+                } else {
+                    output.read_resolved(reader)
+                }
             },
-        )
+        );
+        if let (false, Ok(Ok(RawVc::TaskOutput(target)))) = (strongly_consistent, &result) {
+            self.memoize_output_link(task, *target);
+        }
+        result
+    }
+
+    fn try_read_task_outputs(
+        &self,
+        tasks: &[TaskId],
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Vec<Result<Result<RawVc, EventListener>>> {
+        let mut dependencies = Vec::with_capacity(tasks.len());
+        let results = tasks
+            .iter()
+            .map(|&task| {
+                if task == reader {
+                    bail!("reading it's own output is not possible");
+                }
+                if self.prune_dependency(task) {
+                    return self.try_get_output(
+                        task,
+                        false,
+                        move || format!("reading final task output from {reader}"),
+                        turbo_tasks,
+                        |output| output.read_untracked(),
+                    );
+                }
+                // The dependency is tracked whenever the reader is added to the
+                // output, also when the output is an error, like in
+                // try_read_task_output.
+                self.try_get_output(
+                    task,
+                    false,
+                    move || format!("reading task output from {reader}"),
+                    turbo_tasks,
+                    |output| {
+                        dependencies.push(TaskDependency::TaskOutput(task));
+                        output.read(reader)
+                    },
+                )
+            })
+            .collect();
+        Task::add_dependencies_to_current(dependencies);
+        results
+    }
+
+    fn try_read_task_output_stale(
+        &self,
+        task: TaskId,
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<Result<RawVc, EventListener>> {
+        if task == reader {
+            bail!("reading it's own output is not possible");
+        }
+        let tracked = !self.prune_dependency(task);
+        // The output of the last execution stays in place until the current
+        // execution completes
+        let current = self.with_task(task, |task| {
+            task.with_output(|output| {
+                if output.is_empty() {
+                    None
+                } else if tracked {
+                    Some(output.read(reader))
+                } else {
+                    Some(output.read_untracked())
+                }
+            })
+        });
+        match current {
+            Some(result) => {
+                if tracked {
+                    Task::add_dependency_to_current(TaskDependency::TaskOutput(task));
+                }
+                Ok(Ok(result?))
+            }
+            None => self.try_read_task_output(task, reader, false, turbo_tasks),
+        }
     }
 
     fn try_read_task_output_untracked(
@@ -295,9 +1902,9 @@ impl Backend for MemoryBackend {
         reader: TaskId,
         _turbo_tasks: &dyn TurboTasksBackendApi,
     ) {
-        if task != reader {
+        if task != reader && !self.prune_dependency(task) {
             self.with_task(task, |t| {
-                t.with_output_mut(|output| {
+                t.with_output(|output| {
                     Task::add_dependency_to_current(TaskDependency::TaskOutput(task));
                     output.track_read(reader);
                 })
@@ -316,11 +1923,13 @@ impl Backend for MemoryBackend {
             Ok(Ok(self.with_task(task, |task| {
                 task.with_cell_mut(index, |cell| cell.read_content_untracked())
             })))
+        } else if self.prune_dependency(task) {
+            let content = self.with_task(task, |task| task.read_cell(index, None))?;
+            Ok(Ok(content))
         } else {
             Task::add_dependency_to_current(TaskDependency::TaskCell(task, index));
-            Ok(Ok(self.with_task(task, |task| {
-                task.with_cell_mut(index, |cell| cell.read_content(reader))
-            })))
+            let content = self.with_task(task, |task| task.read_cell(index, Some(reader)))?;
+            Ok(Ok(content))
         }
     }
 
@@ -330,9 +1939,20 @@ impl Backend for MemoryBackend {
         index: CellId,
         _turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> Result<Result<CellContent, EventListener>> {
-        Ok(Ok(self.with_task(task, |task| {
+        let content = self.with_task(task, |task| task.read_cell(index, None))?;
+        Ok(Ok(content))
+    }
+
+    fn try_read_own_task_cell_untracked(
+        &self,
+        current_task: TaskId,
+        index: CellId,
+        _turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<CellContent> {
+        // The current execution might not have assigned the cell yet
+        Ok(self.with_task(current_task, |task| {
             task.with_cell(index, |cell| cell.read_content_untracked())
-        })))
+        }))
     }
 
     fn track_read_task_cell(
@@ -342,7 +1962,7 @@ impl Backend for MemoryBackend {
         reader: TaskId,
         _turbo_tasks: &dyn TurboTasksBackendApi,
     ) {
-        if task != reader {
+        if task != reader && !self.prune_dependency(task) {
             Task::add_dependency_to_current(TaskDependency::TaskCell(task, index));
             self.with_task(task, |task| {
                 task.with_cell_mut(index, |cell| cell.track_read(reader))
@@ -393,11 +2013,28 @@ impl Backend for MemoryBackend {
         content: CellContent,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) {
+        if let Some(recorder) = &self.execution_recorder {
+            recorder.cell_updated(task, index, &content);
+        }
         self.with_task(task, |task| {
             task.with_cell_mut(index, |cell| cell.assign(content, turbo_tasks))
         })
     }
 
+    fn update_task_cells(
+        &self,
+        task: TaskId,
+        updates: Vec<(CellId, CellContent)>,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        if let Some(recorder) = &self.execution_recorder {
+            for (index, content) in &updates {
+                recorder.cell_updated(task, *index, content);
+            }
+        }
+        self.with_task(task, |task| task.assign_cells(updates, turbo_tasks))
+    }
+
     /// SAFETY: Must only called once with the same id
     fn run_backend_job<'a>(
         &'a self,
@@ -407,7 +2044,11 @@ impl Backend for MemoryBackend {
         // SAFETY: id will not be reused until with job is done
         if let Some(job) = unsafe { self.backend_jobs.take(*id) } {
             Box::pin(async move {
-                job.run(self, turbo_tasks).await;
+                {
+                    // Scope changes of a job notify many tasks
+                    let _batch = turbo_tasks.batch_notify_tasks();
+                    job.run(self, turbo_tasks);
+                }
                 // SAFETY: This id will no longer be used
                 unsafe {
                     self.backend_job_id_factory.reuse(id);
@@ -418,64 +2059,46 @@ impl Backend for MemoryBackend {
         }
     }
 
+    fn check_task_creation(
+        &self,
+        task_type: &PersistentTaskType,
+        parent_task: TaskId,
+        _turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<()> {
+        if self.scope_quotas.is_empty() || self.task_cache.contains_key(task_type) {
+            return Ok(());
+        }
+        let scopes = self.quota_scopes(parent_task);
+        if scopes.is_empty() {
+            return Ok(());
+        }
+        let (function, bytes) = quota_usage(task_type);
+        for scope in scopes {
+            if let Some(quota) = self.scope_quotas.get(&scope) {
+                quota.check(&function, bytes)?;
+            }
+        }
+        Ok(())
+    }
+
     fn get_or_create_persistent_task(
         &self,
         task_type: PersistentTaskType,
         parent_task: TaskId,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> TaskId {
-        let result = if let Some(task) = self.task_cache.get(&task_type).map(|task| *task) {
-            // fast pass without creating a new task
-            self.connect_task_child(parent_task, task, turbo_tasks);
-
-            // TODO maybe force (background) scheduling to avoid inactive tasks hanging in
-            // "in progress" until they become active
-            task
-        } else {
-            // slow pass with key lock
-            let id = turbo_tasks.get_fresh_task_id();
-            let task = match &task_type {
-                PersistentTaskType::Native(fn_id, inputs) => {
-                    // TODO inputs doesn't need to be cloned when are would be able to get a
-                    // reference to the task type stored inside of the task
-                    Task::new_native(id, inputs.clone(), *fn_id, turbo_tasks.stats_type())
-                }
-                PersistentTaskType::ResolveNative(fn_id, inputs) => {
-                    Task::new_resolve_native(id, inputs.clone(), *fn_id, turbo_tasks.stats_type())
-                }
-                PersistentTaskType::ResolveTrait(trait_type, trait_fn_name, inputs) => {
-                    Task::new_resolve_trait(
-                        id,
-                        *trait_type,
-                        trait_fn_name.clone(),
-                        inputs.clone(),
-                        turbo_tasks.stats_type(),
-                    )
-                }
-            };
-            // Safety: We have a fresh task id that nobody knows about yet
-            unsafe {
-                self.memory_tasks.insert(*id, task);
-            }
-            let result_task = match self.task_cache.entry(task_type) {
-                Entry::Vacant(entry) => {
-                    // This is the most likely case
-                    entry.insert(id);
-                    id
-                }
-                Entry::Occupied(entry) => {
-                    // Safety: We have a fresh task id that nobody knows about yet
-                    unsafe {
-                        self.memory_tasks.remove(*id);
-                        turbo_tasks.reuse_task_id(id);
-                    }
-                    *entry.get()
-                }
-            };
-            self.connect_task_child(parent_task, result_task, turbo_tasks);
-            result_task
-        };
-        result
+        if let Some(recorder) = &self.execution_recorder {
+            recorder.call(parent_task, &task_type);
+        }
+        // Only the call that created the task counts it, even when multiple
+        // calls passed the check of the quota at the same time
+        let usage = (!self.scope_quotas.is_empty()).then(|| quota_usage(&task_type));
+        let (task, created) = self.get_or_create_task_inner(task_type, turbo_tasks);
+        if let (true, Some((function, bytes))) = (created, usage) {
+            self.count_quota_task(task, &function, bytes, parent_task);
+        }
+        self.connect_task_child(parent_task, task, turbo_tasks);
+        task
     }
 
     fn create_transient_task(
@@ -491,22 +2114,82 @@ impl Backend for MemoryBackend {
             scope.increment_unfinished_tasks(self);
         });
         let stats_type = turbo_tasks.stats_type();
-        let task = match task_type {
+        let mut task = match task_type {
             TransientTaskType::Root(f) => Task::new_root(id, scope, move || f() as _, stats_type),
             TransientTaskType::Once(f) => Task::new_once(id, scope, f, stats_type),
         };
+        if self.panic_dumps.is_some() {
+            task.record_transitions();
+        }
         // SAFETY: We have a fresh task id where nobody knows about yet
         #[allow(unused_variables)]
         let task = unsafe { self.memory_tasks.insert(*id, task) };
+        self.max_task_id.fetch_max(*id, Ordering::AcqRel);
         #[cfg(feature = "print_scope_updates")]
         println!("new {scope} for {task}");
         id
     }
+
+    fn prefetch_task(
+        &self,
+        task: TaskId,
+        with_children: bool,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        let children = self.with_task(task, |task| {
+            task.schedule_when_dirty(turbo_tasks);
+            with_children.then(|| task.children())
+        });
+        for child in children.into_iter().flatten() {
+            self.with_task(child, |child| child.schedule_when_dirty(turbo_tasks));
+        }
+    }
+
+    fn is_task_active_and_done(
+        &self,
+        task: TaskId,
+        _turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> bool {
+        self.with_task(task, |task| task.is_active_and_done(self))
+    }
+
+    fn replace_root_task(
+        &self,
+        task: TaskId,
+        functor: TransientTaskRoot,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<()> {
+        let replaced = self.with_task(task, |task| {
+            task.replace_root(Box::new(move || functor() as _))
+        });
+        if !replaced {
+            bail!(
+                "{} is not a root task",
+                self.with_task(task, |task| task.get_description())
+            );
+        }
+        // The scope and the children stay as they are, only the next execution
+        // differs
+        self.invalidate_task(task, turbo_tasks);
+        Ok(())
+    }
+
+    fn keep_task_alive(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
+        // The initial scope is always active, so the task and its children stay
+        // active and in scope while they are part of it
+        self.with_task(task, |task| {
+            task.add_to_scope_internal(self.initial_scope, false, self, turbo_tasks)
+        });
+    }
+
+    fn release_task_keep_alive(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.with_task(task, |task| {
+            task.remove_from_scope(self.initial_scope, self, turbo_tasks)
+        });
+    }
 }
 
 pub(crate) enum Job {
-    RemoveFromScopes(HashSet<TaskId>, Vec<TaskScopeId>),
-    RemoveFromScope(HashSet<TaskId>, TaskScopeId),
     ScheduleWhenDirty(Vec<TaskId>),
     /// Add tasks from a scope. Scheduled by `run_add_from_scope_queue` to
     /// split off work.
@@ -514,25 +2197,20 @@ pub(crate) enum Job {
     /// Remove tasks from a scope. Scheduled by `run_remove_from_scope_queue` to
     /// split off work.
     RemoveFromScopeQueue(VecDeque<TaskId>, TaskScopeId),
+    /// Add tasks to scopes and remove them from other scopes in a single
+    /// traversal. Scheduled by `run_update_scopes_queue` to split off work.
+    UpdateScopes(VecDeque<ScopeUpdate>),
+    /// See [MemoryBackend::with_scope_audit].
+    AuditScopes,
+    /// The remaining tasks of the [WarmupProfile].
+    Warmup(VecDeque<WarmupTask>),
+    /// See [MemoryBackend::with_max_scopes].
+    ConsolidateScopes,
 }
 
 impl Job {
-    async fn run(self, backend: &MemoryBackend, turbo_tasks: &dyn TurboTasksBackendApi) {
+    fn run(self, backend: &MemoryBackend, turbo_tasks: &dyn TurboTasksBackendApi) {
         match self {
-            Job::RemoveFromScopes(tasks, scopes) => {
-                for task in tasks {
-                    backend.with_task(task, |task| {
-                        task.remove_from_scopes(scopes.iter().cloned(), backend, turbo_tasks)
-                    });
-                }
-            }
-            Job::RemoveFromScope(tasks, scope) => {
-                for task in tasks {
-                    backend.with_task(task, |task| {
-                        task.remove_from_scope(scope, backend, turbo_tasks)
-                    });
-                }
-            }
             Job::ScheduleWhenDirty(tasks) => {
                 for task in tasks.into_iter() {
                     backend.with_task(task, |task| {
@@ -546,6 +2224,16 @@ impl Job {
             Job::RemoveFromScopeQueue(queue, id) => {
                 run_remove_from_scope_queue(queue, id, backend, turbo_tasks);
             }
+            Job::UpdateScopes(queue) => {
+                run_update_scopes_queue(queue, backend, turbo_tasks);
+            }
+            Job::AuditScopes => backend.run_scope_audit(),
+            Job::ConsolidateScopes => backend.consolidate_scopes(turbo_tasks),
+            Job::Warmup(tasks) => {
+                if let Some(stable_task_ids) = &backend.stable_task_ids {
+                    run_warmup(tasks, stable_task_ids, backend, turbo_tasks);
+                }
+            }
         }
     }
 }
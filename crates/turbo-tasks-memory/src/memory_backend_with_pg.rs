@@ -9,10 +9,10 @@ use std::{
         atomic::{AtomicU32, AtomicUsize, Ordering},
         Mutex, MutexGuard,
     },
-    time::{Duration, Instant},
+    time::Duration,
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use concurrent_queue::ConcurrentQueue;
 use dashmap::{mapref::entry::Entry, DashMap, DashSet};
 use turbo_tasks::{
@@ -25,6 +25,8 @@ use turbo_tasks::{
         ActivateResult, DeactivateResult, PersistResult, PersistTaskState, PersistedGraph,
         PersistedGraphApi, ReadTaskState, TaskCell, TaskData,
     },
+    platform::Instant,
+    registry,
     util::{IdFactory, NoMoveVec, SharedError},
     CellId, RawVc, TaskId, TraitTypeId, TurboTasksBackendApi,
 };
@@ -902,7 +904,8 @@ impl<P: PersistedGraph> MemoryBackendWithPersistedGraph<P> {
 }
 
 impl<P: PersistedGraph> Backend for MemoryBackendWithPersistedGraph<P> {
-    fn startup(&self, turbo_tasks: &dyn TurboTasksBackendApi) {
+    fn startup(&self, turbo_tasks: &dyn TurboTasksBackendApi) -> Result<()> {
+        self.pg_invalidate_changed_functions(turbo_tasks)?;
         let (tasks_to_activate, tasks_to_deactivate) =
             self.pg_get_pending_active_update(turbo_tasks);
         let tasks = self.pg_get_active_external_tasks(turbo_tasks);
@@ -970,6 +973,7 @@ impl<P: PersistedGraph> Backend for MemoryBackendWithPersistedGraph<P> {
             }
             self.schedule_background_job(BackgroundJob::DeactivatePersisted(task), turbo_tasks);
         }
+        Ok(())
     }
 
     fn stop(&self, turbo_tasks: &dyn TurboTasksBackendApi) {
@@ -1846,6 +1850,38 @@ impl<P: PersistedGraph> MemoryBackendWithPersistedGraph<P> {
             .unwrap()
     }
 
+    /// Makes the stored tasks of functions dirty whose implementation has
+    /// changed since they were computed.
+    fn pg_invalidate_changed_functions(
+        &self,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<()> {
+        let api = MemoryBackendPersistedGraphApi {
+            backend: self,
+            turbo_tasks,
+        };
+        for function in registry::get_function_ids() {
+            let native_fn = registry::get_function(function);
+            let fingerprint = native_fn.fingerprint;
+            let stored = self
+                .pg
+                .read_function_fingerprint(function, &api)
+                .with_context(|| format!("loading the fingerprint of {}", native_fn.name))?;
+            if stored == Some(fingerprint) {
+                continue;
+            }
+            if stored.is_some() {
+                self.pg
+                    .make_function_tasks_dirty(function, &api)
+                    .with_context(|| format!("invalidating the tasks of {}", native_fn.name))?;
+            }
+            self.pg
+                .store_function_fingerprint(function, fingerprint, &api)
+                .with_context(|| format!("storing the fingerprint of {}", native_fn.name))?;
+        }
+        Ok(())
+    }
+
     #[must_use]
     fn pg_get_dirty_active_tasks(&self, turbo_tasks: &dyn TurboTasksBackendApi) -> Vec<TaskId> {
         self.pg
@@ -1,17 +1,63 @@
 use std::{
     borrow::Cow,
-    collections::HashSet,
+    collections::VecDeque,
     fmt::{Debug, Display},
+    hash::BuildHasherDefault,
+    mem::take,
+    sync::atomic::{AtomicU32, Ordering},
 };
 
 use anyhow::{anyhow, Error, Result};
-use turbo_tasks::{util::SharedError, RawVc, TaskId, TurboTasksBackendApi};
+use auto_hash_map::AutoSet;
+use parking_lot::Mutex;
+use rustc_hash::FxHasher;
+use turbo_tasks::{
+    platform::Instant, util::SharedError, CellId, RawVc, TaskId, TurboTasksBackendApi,
+};
+
+/// The number of readers an output keeps in a list before switching to a hash
+/// set. Every tracked read checks the list, so it's kept short.
+const LISTED_READERS: usize = 8;
+
+type OutputReaders = AutoSet<TaskId, BuildHasherDefault<FxHasher>, LISTED_READERS>;
 
 #[derive(Default, Debug)]
 pub struct Output {
     pub(crate) content: OutputContent,
-    updates: u32,
-    pub(crate) dependent_tasks: HashSet<TaskId>,
+    /// Increased on every update of the content.
+    generation: u32,
+    /// The number of tracked reads by other tasks, to compare with the number
+    /// of updates in stats.
+    reads: AtomicU32,
+    /// Tasks that read the output since it was updated last. They are
+    /// notified once on the next update. It has its own lock, so reads only
+    /// need a read lock on the state of the task and don't serialize.
+    dependent_tasks: Mutex<OutputReaders>,
+    /// The last contents, oldest first. Only recorded when enabled with
+    /// [crate::MemoryBackend::with_output_history].
+    history: VecDeque<OutputHistoryEntry>,
+    /// The cell that a link to the output of another task resolves to, so
+    /// reads skip that hop. See [Output::memoize_resolved].
+    resolved: Option<RawVc>,
+    /// Increased whenever the cell this output resolves to might change,
+    /// which includes updates of the content and of the memoized cell.
+    resolved_generation: u32,
+    /// The latest `resolved_generation` of the linked output that has been
+    /// reported to [Output::clear_resolved]. Memos from older generations
+    /// raced with an update and are rejected.
+    target_generation: Option<(TaskId, u32)>,
+    /// Tasks whose outputs memoized the cell this output resolves to. They
+    /// are cleared when it changes.
+    pub(crate) memoized_by: AutoSet<TaskId>,
+}
+
+/// A content of an [Output] in the history of the output.
+#[derive(Clone, Debug)]
+pub struct OutputHistoryEntry {
+    pub content: OutputContent,
+    /// The update generation in which the content was assigned.
+    pub generation: usize,
+    pub time: Instant,
 }
 
 #[derive(Clone, Debug)]
@@ -40,17 +86,53 @@ impl Display for OutputContent {
     }
 }
 
+/// The error of a read of an output that has never been computed. That's the
+/// case when the first execution of the task has been aborted, because the
+/// task was invalidated while executing and no active scope needed it. Reads
+/// of functions set up with [crate::MemoryBackend::wait_for_first_execution]
+/// execute the task again and wait for it instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputNotComputed {
+    pub task: TaskId,
+}
+
+impl Display for OutputNotComputed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "output of {} has not been computed yet", self.task)
+    }
+}
+
+impl std::error::Error for OutputNotComputed {}
+
 impl Output {
-    pub fn read(&mut self, reader: TaskId) -> Result<RawVc> {
-        self.dependent_tasks.insert(reader);
+    /// Returns `true` when no execution has completed yet.
+    pub fn is_empty(&self) -> bool {
+        matches!(self.content, OutputContent::Empty)
+    }
+
+    pub fn read(&self, reader: TaskId) -> Result<RawVc> {
+        self.track_read(reader);
         self.read_untracked()
     }
 
+    /// Like [Output::read], but returns the memoized cell instead of the
+    /// linked output when there is one. The reader is notified when the
+    /// memoized cell changes.
+    pub fn read_resolved(&self, reader: TaskId) -> Result<RawVc> {
+        self.track_read(reader);
+        match self.resolved {
+            Some(cell) => Ok(cell),
+            None => self.read_untracked(),
+        }
+    }
+
     /// INVALIDATION: Be careful with this, it will not track dependencies, so
     /// using it could break cache invalidation.
-    pub fn read_untracked(&mut self) -> Result<RawVc> {
+    pub fn read_untracked(&self) -> Result<RawVc> {
         match &self.content {
-            OutputContent::Empty => Err(anyhow!("Output is empty")),
+            OutputContent::Empty => {
+                unreachable!("reads of empty outputs fail with OutputNotComputed")
+            }
             OutputContent::Error(err) => Err(err.clone().into()),
             OutputContent::Link(raw_vc) => Ok(*raw_vc),
             OutputContent::Panic(Some(message)) => Err(anyhow!("A task panicked: {message}")),
@@ -58,8 +140,110 @@ impl Output {
         }
     }
 
-    pub fn track_read(&mut self, reader: TaskId) {
-        self.dependent_tasks.insert(reader);
+    /// Increased on every update of the content.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// The number of tracked reads by other tasks.
+    pub fn reads(&self) -> u32 {
+        self.reads.load(Ordering::Relaxed)
+    }
+
+    pub fn resolved_generation(&self) -> u32 {
+        self.resolved_generation
+    }
+
+    /// Returns the cell the output is linked to, directly or through a
+    /// memoized link to another output.
+    pub fn resolved_cell(&self) -> Option<RawVc> {
+        match self.content {
+            OutputContent::Link(link @ RawVc::TaskCell(..)) => Some(link),
+            OutputContent::Link(RawVc::TaskOutput(_)) => self.resolved,
+            _ => None,
+        }
+    }
+
+    fn linked_output(&self) -> Option<TaskId> {
+        match self.content {
+            OutputContent::Link(RawVc::TaskOutput(task)) => Some(task),
+            _ => None,
+        }
+    }
+
+    /// Memoizes the cell that the linked output of `target` resolved to at
+    /// `target_generation`. Ignored when the output has been linked elsewhere
+    /// or the target changed in the meantime.
+    pub fn memoize_resolved(&mut self, target: TaskId, target_generation: u32, cell: RawVc) {
+        if self.linked_output() != Some(target) {
+            return;
+        }
+        if let Some((task, generation)) = self.target_generation {
+            if task == target && generation > target_generation {
+                return;
+            }
+        }
+        self.resolved = Some(cell);
+    }
+
+    /// Called when the cell that the linked output of `target` resolves to
+    /// changed. Returns the tasks that memoized this output when the memoized
+    /// cell has been cleared, as they need to be cleared too.
+    pub fn clear_resolved(
+        &mut self,
+        target: TaskId,
+        target_generation: u32,
+    ) -> Option<AutoSet<TaskId>> {
+        if self.linked_output() != Some(target) {
+            return None;
+        }
+        self.target_generation = Some((target, target_generation));
+        self.resolved.take()?;
+        self.resolved_generation += 1;
+        Some(take(&mut self.memoized_by))
+    }
+
+    /// The last contents of the output, oldest first, including the current
+    /// one.
+    pub fn history(&self) -> &VecDeque<OutputHistoryEntry> {
+        &self.history
+    }
+
+    /// Adds the current content to the history and drops the oldest entries
+    /// above `max_len`.
+    pub(crate) fn record_history(
+        &mut self,
+        max_len: usize,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        if self.history.len() >= max_len {
+            self.history.drain(..=self.history.len() - max_len);
+        }
+        self.history.push_back(OutputHistoryEntry {
+            content: self.content.clone(),
+            generation: turbo_tasks.update_generation(),
+            time: Instant::now(),
+        });
+    }
+
+    pub fn track_read(&self, reader: TaskId) {
+        self.dependent_tasks.lock().insert(reader);
+        // Saturates instead of wrapping around
+        let _ = self
+            .reads
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |reads| {
+                reads.checked_add(1)
+            });
+    }
+
+    /// Forgets all readers, they won't be notified on the next update.
+    pub fn clear_dependent_tasks(&mut self) {
+        *self.dependent_tasks.get_mut() = OutputReaders::default();
+    }
+
+    /// Removes a reader that no longer depends on the output.
+    pub fn remove_dependent_task(&self, reader: TaskId) {
+        self.dependent_tasks.lock().remove(&reader);
     }
 
     pub fn link(&mut self, target: RawVc, turbo_tasks: &dyn TurboTasksBackendApi) {
@@ -91,34 +275,57 @@ impl Output {
         }
     }
 
-    pub fn error(&mut self, error: Error, turbo_tasks: &dyn TurboTasksBackendApi) {
-        self.content = OutputContent::Error(SharedError::new(error));
-        self.updates += 1;
-        // notify
-        if !self.dependent_tasks.is_empty() {
-            turbo_tasks.schedule_notify_tasks_set(&self.dependent_tasks);
+    /// Links the output to a cell that is equal to the current target, see
+    /// [turbo_tasks::ValueType::link_eq]. Readers are not notified, as they
+    /// would read the same value. Returns the readers, which need to depend
+    /// on the new cell instead, so they are notified when it changes.
+    pub fn link_equal(&mut self, target: RawVc) -> Vec<TaskId> {
+        self.content = OutputContent::Link(target);
+        self.generation += 1;
+        self.reset_resolved();
+        self.dependent_tasks.get_mut().iter().copied().collect()
+    }
+
+    /// Returns the cell the output is linked to, if any.
+    pub fn linked_cell(&self) -> Option<(TaskId, CellId)> {
+        match self.content {
+            OutputContent::Link(RawVc::TaskCell(task, index)) => Some((task, index)),
+            _ => None,
         }
     }
 
+    pub fn error(&mut self, error: Error, turbo_tasks: &dyn TurboTasksBackendApi) {
+        self.assign(OutputContent::Error(SharedError::new(error)), turbo_tasks)
+    }
+
     pub fn panic(
         &mut self,
         message: Option<Cow<'static, str>>,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) {
-        self.content = OutputContent::Panic(message);
-        self.updates += 1;
-        // notify
-        if !self.dependent_tasks.is_empty() {
-            turbo_tasks.schedule_notify_tasks_set(&self.dependent_tasks);
-        }
+        self.assign(OutputContent::Panic(message), turbo_tasks)
     }
 
     pub fn assign(&mut self, content: OutputContent, turbo_tasks: &dyn TurboTasksBackendApi) {
         self.content = content;
-        self.updates += 1;
-        // notify
-        if !self.dependent_tasks.is_empty() {
-            turbo_tasks.schedule_notify_tasks_set(&self.dependent_tasks);
+        self.generation += 1;
+        self.reset_resolved();
+        self.notify_dependent_tasks(turbo_tasks);
+    }
+
+    fn reset_resolved(&mut self) {
+        self.resolved = None;
+        self.resolved_generation += 1;
+    }
+
+    pub(crate) fn notify_dependent_tasks(&mut self, turbo_tasks: &dyn TurboTasksBackendApi) {
+        // Notified tasks will be invalidated and need to read the output again to
+        // observe the new content, which makes them dependent again. Until then further
+        // updates don't need to notify them again, so multiple updates in one turn are
+        // coalesced into a single notification.
+        let dependent_tasks = take(&mut *self.dependent_tasks.lock());
+        if !dependent_tasks.is_empty() {
+            turbo_tasks.schedule_notify_tasks(&dependent_tasks.into_iter().collect::<Vec<_>>());
         }
     }
 }
@@ -0,0 +1,33 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use turbo_tasks::TaskId;
+
+/// Writes diagnostics bundles of tasks that violated an invariant of the
+/// backend, see [crate::MemoryBackend::with_panic_dumps].
+pub(crate) struct PanicDumps {
+    dir: PathBuf,
+    /// Keeps file names unique when a task panics more than once.
+    counter: AtomicUsize,
+}
+
+impl PanicDumps {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            counter: AtomicUsize::new(0),
+        }
+    }
+
+    /// Writes the bundle to a new file in the directory and returns its path.
+    pub fn write(&self, task: TaskId, message: &str, diagnostics: &str) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let index = self.counter.fetch_add(1, Ordering::Relaxed);
+        let path = self.dir.join(format!("task-{}-{index}.txt", *task));
+        fs::write(&path, format!("{message}\n\n{diagnostics}"))?;
+        Ok(path)
+    }
+}
@@ -9,6 +9,7 @@ use std::{
 
 use nohash_hasher::BuildNoHashHasher;
 use parking_lot::Mutex;
+use smallvec::SmallVec;
 use turbo_tasks::{
     event::{Event, EventListener},
     RawVc, TaskId, TraitTypeId,
@@ -60,20 +61,21 @@ impl From<usize> for TaskScopeId {
 
 impl nohash_hasher::IsEnabled for TaskScopeId {}
 
+/// A task is either the root of its own scope or an inner task of other
+/// scopes. A task is the root of one scope only. A task that is shared by
+/// several root scopes, e.g. a library that several entries depend on, is an
+/// inner task of each of them, counted in a [TaskScopeList].
 #[derive(Clone, Debug)]
 pub enum TaskScopes {
     Root(TaskScopeId),
     /// inner scopes and a counter for changes to start optimized when a
     /// threshold is reached
-    Inner(
-        CountHashSet<TaskScopeId, BuildNoHashHasher<TaskScopeId>>,
-        usize,
-    ),
+    Inner(TaskScopeList, usize),
 }
 
 impl Default for TaskScopes {
     fn default() -> Self {
-        TaskScopes::Inner(CountHashSet::default(), 0)
+        TaskScopes::Inner(TaskScopeList::default(), 0)
     }
 }
 
@@ -81,19 +83,35 @@ impl TaskScopes {
     pub fn iter(&self) -> TaskScopesIterator {
         match self {
             TaskScopes::Root(r) => TaskScopesIterator::Root(*r),
-            TaskScopes::Inner(set, _) => TaskScopesIterator::Inner(set.iter()),
+            TaskScopes::Inner(list, _) => TaskScopesIterator::Inner(list.iter()),
         }
     }
 
     pub fn is_root(&self) -> bool {
         matches!(self, TaskScopes::Root(_))
     }
+
+    /// Returns true when the task is directly in the scope.
+    pub fn contains(&self, scope: TaskScopeId) -> bool {
+        match self {
+            TaskScopes::Root(root) => *root == scope,
+            TaskScopes::Inner(list, _) => list.contains(scope),
+        }
+    }
+
+    /// Returns the scopes with their counts, including negative ones.
+    pub fn counts(&self) -> Vec<(TaskScopeId, isize)> {
+        match self {
+            TaskScopes::Root(root) => vec![(*root, 1)],
+            TaskScopes::Inner(list, _) => list.counts(),
+        }
+    }
 }
 
 pub enum TaskScopesIterator<'a> {
     Done,
     Root(TaskScopeId),
-    Inner(CountHashSetIter<'a, TaskScopeId>),
+    Inner(TaskScopeListIter<'a>),
 }
 
 impl<'a> Iterator for TaskScopesIterator<'a> {
@@ -111,6 +129,181 @@ impl<'a> Iterator for TaskScopesIterator<'a> {
     }
 }
 
+/// The number of scopes a [TaskScopeList] stores inline.
+const INLINE_SCOPES: usize = 4;
+
+/// The scopes of a task that is not root scoped, with a count of how often
+/// the task has been added to each of them. It behaves like a
+/// [CountHashSet], but tasks are usually in a few scopes only, e.g. tasks
+/// shared by a few root scopes, so these are counted in a small inline array
+/// that is sorted by scope, so lookups are binary searches. It only moves to
+/// a [CountHashSet] when it grows beyond [INLINE_SCOPES] or when a scope is
+/// removed before it has been added, as the inline array only holds positive
+/// counts. It moves back when it has shrunk to half of that and all counts are
+/// positive again, so a task that is added to and removed from a scope
+/// repeatedly doesn't switch back and forth.
+#[derive(Clone, Debug)]
+pub enum TaskScopeList {
+    Inline(SmallVec<[(TaskScopeId, usize); INLINE_SCOPES]>),
+    Set(CountHashSet<TaskScopeId, BuildNoHashHasher<TaskScopeId>>),
+}
+
+impl Default for TaskScopeList {
+    fn default() -> Self {
+        TaskScopeList::Inline(SmallVec::new())
+    }
+}
+
+impl<const N: usize> From<[TaskScopeId; N]> for TaskScopeList {
+    fn from(scopes: [TaskScopeId; N]) -> Self {
+        let mut list = TaskScopeList::default();
+        for scope in scopes {
+            list.add(scope);
+        }
+        list
+    }
+}
+
+impl TaskScopeList {
+    pub fn len(&self) -> usize {
+        match self {
+            TaskScopeList::Inline(list) => list.len(),
+            TaskScopeList::Set(set) => set.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            TaskScopeList::Inline(list) => list.is_empty(),
+            TaskScopeList::Set(set) => set.is_empty(),
+        }
+    }
+
+    /// Returns true when the scope is in the list with a positive count.
+    pub fn contains(&self, scope: TaskScopeId) -> bool {
+        match self {
+            TaskScopeList::Inline(list) => list.binary_search_by_key(&scope, |(id, _)| *id).is_ok(),
+            TaskScopeList::Set(set) => set.contains(&scope),
+        }
+    }
+
+    /// Moves the inline counts to a [CountHashSet].
+    fn make_set(&mut self) -> &mut CountHashSet<TaskScopeId, BuildNoHashHasher<TaskScopeId>> {
+        if let TaskScopeList::Inline(list) = self {
+            let mut set = CountHashSet::default();
+            for (id, count) in list.drain(..) {
+                set.add_count(id, count);
+            }
+            *self = TaskScopeList::Set(set);
+        }
+        match self {
+            TaskScopeList::Set(set) => set,
+            TaskScopeList::Inline(_) => unreachable!(),
+        }
+    }
+
+    /// Returns true when the scope has been added, and not only its count
+    /// has been increased.
+    pub fn add(&mut self, scope: TaskScopeId) -> bool {
+        if let TaskScopeList::Inline(list) = self {
+            match list.binary_search_by_key(&scope, |(id, _)| *id) {
+                Ok(index) => {
+                    list[index].1 += 1;
+                    return false;
+                }
+                Err(index) if list.len() < INLINE_SCOPES => {
+                    list.insert(index, (scope, 1));
+                    return true;
+                }
+                Err(_) => {}
+            }
+        }
+        self.make_set().add(scope)
+    }
+
+    /// Returns true when the scope has been removed, and not only its count
+    /// has been decreased.
+    pub fn remove(&mut self, scope: TaskScopeId) -> bool {
+        if let TaskScopeList::Inline(list) = self {
+            if let Ok(index) = list.binary_search_by_key(&scope, |(id, _)| *id) {
+                let count = &mut list[index].1;
+                *count -= 1;
+                if *count == 0 {
+                    list.remove(index);
+                    return true;
+                }
+                return false;
+            }
+        }
+        // Removing before adding is possible in race conditions, the negative
+        // count is kept in the set
+        let set = self.make_set();
+        let removed = set.remove(scope);
+        if set.len() <= INLINE_SCOPES / 2 && !set.has_negative_entries() {
+            let set = take(set);
+            let mut list: SmallVec<_> = set
+                .into_counts()
+                .map(|(id, count)| (id, count as usize))
+                .collect();
+            list.sort_unstable_by_key(|(id, _)| *id);
+            *self = TaskScopeList::Inline(list);
+        }
+        removed
+    }
+
+    pub fn iter(&self) -> TaskScopeListIter<'_> {
+        match self {
+            TaskScopeList::Inline(list) => TaskScopeListIter::Inline(list.iter()),
+            TaskScopeList::Set(set) => TaskScopeListIter::Set(set.iter()),
+        }
+    }
+
+    /// Returns the scopes with their counts, including negative ones.
+    pub fn counts(&self) -> Vec<(TaskScopeId, isize)> {
+        match self {
+            TaskScopeList::Inline(list) => list
+                .iter()
+                .map(|(id, count)| (*id, *count as isize))
+                .collect(),
+            TaskScopeList::Set(set) => set.counts().map(|(id, count)| (*id, count)).collect(),
+        }
+    }
+
+    pub fn into_counts(self) -> Vec<(TaskScopeId, isize)> {
+        match self {
+            TaskScopeList::Inline(list) => list
+                .into_iter()
+                .map(|(id, count)| (id, count as isize))
+                .collect(),
+            TaskScopeList::Set(set) => set.into_counts().collect(),
+        }
+    }
+}
+
+pub enum TaskScopeListIter<'a> {
+    Inline(std::slice::Iter<'a, (TaskScopeId, usize)>),
+    Set(CountHashSetIter<'a, TaskScopeId>),
+}
+
+impl<'a> Iterator for TaskScopeListIter<'a> {
+    type Item = &'a TaskScopeId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            TaskScopeListIter::Inline(it) => it.next().map(|(id, _)| id),
+            TaskScopeListIter::Set(it) => it.next(),
+        }
+    }
+}
+
+/// The counters of a [TaskScope], see [MemoryBackend::audit_scopes].
+pub(crate) struct TaskScopeCounts {
+    pub tasks: usize,
+    pub unfinished_tasks: isize,
+    pub children: Vec<(TaskScopeId, isize)>,
+    pub parents: Vec<(TaskScopeId, isize)>,
+}
+
 pub struct TaskScope {
     #[cfg(feature = "print_scope_updates")]
     pub id: TaskScopeId,
@@ -142,6 +335,10 @@ pub struct TaskScopeState {
     children: CountHashSet<TaskScopeId, BuildNoHashHasher<TaskScopeId>>,
     /// flag if this scope has unfinished tasks
     has_unfinished_tasks: bool,
+    /// flag if the scope has been read strongly consistently. Such reads are
+    /// usually repeated after changes, so the flag is kept when all tasks are
+    /// done and invalidated tasks of the scope are prioritized right away.
+    has_strongly_consistent_readers: bool,
     /// Event that will be notified when all unfinished tasks and children are
     /// done
     event: Event,
@@ -177,6 +374,7 @@ impl TaskScope {
                     return "TaskScope::event".to_owned();
                 }),
                 has_unfinished_tasks: false,
+                has_strongly_consistent_readers: false,
                 parents: CountHashSet::new(),
             }),
         }
@@ -204,11 +402,53 @@ impl TaskScope {
                     return "TaskScope::event".to_owned();
                 }),
                 has_unfinished_tasks: false,
+                has_strongly_consistent_readers: false,
                 parents: CountHashSet::new(),
             }),
         }
     }
 
+    /// Returns the number of tasks in the scope.
+    pub fn task_count(&self) -> usize {
+        self.tasks.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of unfinished tasks in the scope, including
+    /// unfinished child scopes. This is only an estimate while tasks are
+    /// executing.
+    pub fn unfinished_tasks(&self) -> usize {
+        self.unfinished_tasks.load(Ordering::Relaxed).max(0) as usize
+    }
+
+    /// Describes the counters and the state of the scope for a diagnostics
+    /// bundle. The state lock is only tried.
+    pub(crate) fn diagnostics(&self) -> String {
+        let tasks = self.tasks.load(Ordering::Acquire);
+        let unfinished_tasks = self.unfinished_tasks.load(Ordering::Acquire);
+        let Some(state) = self.state.try_lock() else {
+            return format!("{tasks} tasks, {unfinished_tasks} unfinished, state is locked");
+        };
+        format!(
+            "{tasks} tasks, {unfinished_tasks} unfinished, active {}, {} dirty tasks, {} \
+             children, {} parents, has unfinished tasks: {}",
+            state.active,
+            state.dirty_tasks.len(),
+            state.children.len(),
+            state.parents.len(),
+            state.has_unfinished_tasks
+        )
+    }
+
+    pub(crate) fn audit_counts(&self) -> TaskScopeCounts {
+        let state = self.state.lock();
+        TaskScopeCounts {
+            tasks: self.tasks.load(Ordering::Acquire),
+            unfinished_tasks: self.unfinished_tasks.load(Ordering::Acquire),
+            children: state.children.counts().map(|(id, c)| (*id, c)).collect(),
+            parents: state.parents.counts().map(|(id, c)| (*id, c)).collect(),
+        }
+    }
+
     pub fn increment_tasks(&self) {
         self.tasks.fetch_add(1, Ordering::Relaxed);
     }
@@ -309,7 +549,14 @@ impl TaskScope {
         }
     }
 
-    pub fn has_unfinished_tasks(&self) -> Option<EventListener> {
+    /// Returns a listener that is notified when all tasks of the scope and its
+    /// child scopes are finished, or `None` when they are already finished.
+    ///
+    /// Tasks are only executed in active scopes, so the scope need to be kept
+    /// active, e.g. with [turbo_tasks::TurboTasks::keep_alive]. Pending
+    /// foreground jobs might still add tasks to the scope, see
+    /// [turbo_tasks::TurboTasks::wait_foreground_done].
+    pub fn done_listener(&self) -> Option<EventListener> {
         let state = self.state.lock();
         if state.has_unfinished_tasks {
             Some(state.event.listen())
@@ -318,6 +565,46 @@ impl TaskScope {
         }
     }
 
+    /// Returns true when tasks of the scope or its child scopes are not
+    /// finished yet.
+    pub fn has_unfinished_tasks(&self) -> bool {
+        self.state.lock().has_unfinished_tasks
+    }
+
+    /// Like [TaskScope::done_listener], but `None` when the scope is not
+    /// active, as its dirty tasks are not executed until they are read.
+    pub fn active_done_listener(&self) -> Option<EventListener> {
+        let state = self.state.lock();
+        if state.is_active() && state.has_unfinished_tasks {
+            Some(state.event.listen())
+        } else {
+            None
+        }
+    }
+
+    /// Like [TaskScope::done_listener], but also marks the scope so
+    /// that its tasks are prioritized when invalidated, including the first
+    /// invalidation after all tasks are done.
+    pub fn wait_for_unfinished_tasks(&self) -> Option<EventListener> {
+        let mut state = self.state.lock();
+        state.has_strongly_consistent_readers = true;
+        if state.has_unfinished_tasks {
+            Some(state.event.listen())
+        } else {
+            None
+        }
+    }
+
+    /// Wakes up the strongly consistent readers, so they can see a failure of
+    /// the scope.
+    pub(crate) fn notify_readers(&self) {
+        self.state.lock().event.notify(usize::MAX);
+    }
+
+    pub(crate) fn parents(&self) -> Vec<TaskScopeId> {
+        self.state.lock().parents.iter().copied().collect()
+    }
+
     pub fn read_collectibles(
         &self,
         self_id: TaskScopeId,
@@ -404,6 +691,10 @@ pub struct ScopeCollectibleChangeEffect {
 }
 
 impl TaskScopeState {
+    pub fn has_strongly_consistent_readers(&self) -> bool {
+        self.has_strongly_consistent_readers
+    }
+
     pub fn is_active(&self) -> bool {
         self.active > 0
     }
@@ -501,6 +792,12 @@ impl TaskScopeState {
         }
     }
 
+    /// Returns how often the scope has been added as child, see
+    /// [TaskScopeState::add_child_count].
+    pub fn child_count(&self, child: TaskScopeId) -> isize {
+        self.children.count(&child)
+    }
+
     pub fn add_dirty_task(&mut self, id: TaskId) {
         self.dirty_tasks.insert(id);
         log_scope_update!("add_dirty_task {} -> {}", *self.id, *id);
@@ -0,0 +1,122 @@
+use std::{
+    fmt::{Display, Formatter},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use parking_lot::Mutex;
+use turbo_tasks::{platform::Instant, TaskId};
+
+use crate::TaskScopeId;
+
+/// A violated invariant of the task scopes, found by
+/// [crate::MemoryBackend::audit_scopes].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScopeAuditIssue {
+    /// The task counter of the scope doesn't match the number of tasks that
+    /// are in the scope.
+    TaskCount {
+        scope: TaskScopeId,
+        counter: usize,
+        tasks: usize,
+    },
+    /// More unfinished tasks have been removed from the scope than added.
+    NegativeUnfinishedTasks { scope: TaskScopeId, count: isize },
+    /// The task has been removed from the scope more often than added.
+    NegativeScopeCount {
+        task: TaskId,
+        scope: TaskScopeId,
+        count: isize,
+    },
+    /// The parent has the child scope with `child_count`, but the child has
+    /// the parent scope with `parent_count`, which should be 1 when the child
+    /// count is positive and 0 otherwise.
+    ChildParentMismatch {
+        parent: TaskScopeId,
+        child: TaskScopeId,
+        child_count: isize,
+        parent_count: isize,
+    },
+}
+
+impl Display for ScopeAuditIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScopeAuditIssue::TaskCount {
+                scope,
+                counter,
+                tasks,
+            } => write!(f, "{scope} counts {counter} tasks, but contains {tasks}"),
+            ScopeAuditIssue::NegativeUnfinishedTasks { scope, count } => {
+                write!(f, "{scope} has {count} unfinished tasks")
+            }
+            ScopeAuditIssue::NegativeScopeCount { task, scope, count } => {
+                write!(f, "{task} is in {scope} with count {count}")
+            }
+            ScopeAuditIssue::ChildParentMismatch {
+                parent,
+                child,
+                child_count,
+                parent_count,
+            } => write!(
+                f,
+                "{parent} has child {child} with count {child_count}, but the child has the \
+                 parent with count {parent_count}"
+            ),
+        }
+    }
+}
+
+/// The result of [crate::MemoryBackend::audit_scopes].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScopeAuditReport {
+    pub issues: Vec<ScopeAuditIssue>,
+}
+
+impl ScopeAuditReport {
+    pub fn is_empty(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl Display for ScopeAuditReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for issue in self.issues.iter() {
+            writeln!(f, "{issue}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Schedules audits configured with [crate::MemoryBackend::with_scope_audit]
+/// at most once per interval.
+pub(crate) struct ScopeAudit {
+    interval: Duration,
+    next: Mutex<Instant>,
+    scheduled: AtomicBool,
+}
+
+impl ScopeAudit {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: Mutex::new(Instant::now()),
+            scheduled: AtomicBool::new(false),
+        }
+    }
+
+    /// Returns true when an audit is due and not yet scheduled. The caller
+    /// must run it and call [ScopeAudit::finished] afterwards.
+    pub fn start_if_due(&self) -> bool {
+        Instant::now() >= *self.next.lock()
+            && self
+                .scheduled
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+    }
+
+    pub fn finished(&self) {
+        *self.next.lock() = Instant::now() + self.interval;
+        self.scheduled.store(false, Ordering::Release);
+    }
+}
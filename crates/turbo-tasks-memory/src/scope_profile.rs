@@ -0,0 +1,95 @@
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display},
+    fs,
+    io::ErrorKind,
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{Context, Result};
+use turbo_tasks::{registry, FunctionId};
+
+/// A profile of functions whose tasks ended up with their own root scope.
+///
+/// It can be stored between runs and loaded with
+/// [crate::MemoryBackend::with_scope_profile], which gives tasks of these
+/// functions a root scope right when they are created. That avoids
+/// restructuring scopes in the middle of a build when a task has exceeded the
+/// optimization threshold.
+///
+/// The profile is stored as a list of function global names, one per line.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ScopeProfile {
+    functions: BTreeSet<String>,
+}
+
+impl ScopeProfile {
+    pub(crate) fn from_function_ids(ids: impl IntoIterator<Item = FunctionId>) -> Self {
+        Self {
+            functions: ids
+                .into_iter()
+                .map(|id| registry::get_function_global_name(id).to_string())
+                .collect(),
+        }
+    }
+
+    /// Resolves the functions in the profile. Functions that are not
+    /// registered (anymore) are skipped.
+    pub(crate) fn function_ids(&self) -> impl Iterator<Item = FunctionId> + '_ {
+        self.functions
+            .iter()
+            .filter_map(|name| registry::get_function_id_by_global_name(name))
+    }
+
+    /// Returns the global names of the functions in the profile.
+    pub fn functions(&self) -> impl Iterator<Item = &str> {
+        self.functions.iter().map(|name| name.as_str())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.functions.is_empty()
+    }
+
+    /// Reads a profile from a file. A missing file results in an empty
+    /// profile.
+    pub fn read(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(content.parse()?),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => {
+                Err(err).with_context(|| format!("unable to read scope profile {}", path.display()))
+            }
+        }
+    }
+
+    /// Writes the profile to a file.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_string())
+            .with_context(|| format!("unable to write scope profile {}", path.display()))
+    }
+}
+
+impl Display for ScopeProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for name in self.functions.iter() {
+            writeln!(f, "{name}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ScopeProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self {
+            functions: s
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .map(|line| line.to_string())
+                .collect(),
+        })
+    }
+}
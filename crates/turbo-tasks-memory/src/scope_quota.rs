@@ -0,0 +1,174 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt::{self, Display, Formatter},
+};
+
+use parking_lot::Mutex;
+use turbo_tasks::{util::FormatBytes, TaskId};
+
+/// Limits for the tasks created in a root scope, see
+/// [crate::MemoryBackend::set_scope_quota].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ScopeQuota {
+    /// The maximum number of tasks created in the scope.
+    pub max_tasks: Option<usize>,
+    /// The maximum estimated size in bytes of the tasks created in the scope.
+    /// The estimate covers the task itself and its inputs, but not the
+    /// contents of its cells.
+    pub max_bytes: Option<usize>,
+}
+
+/// The functions that created the most tasks in a scope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuotaContributor {
+    pub function: String,
+    pub tasks: usize,
+    pub bytes: usize,
+}
+
+/// The error of a function call that would have exceeded a [ScopeQuota].
+/// Calls of tasks that already exist are not affected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopeQuotaExceeded {
+    /// The task that owns the scope with the quota.
+    pub task: TaskId,
+    pub quota: ScopeQuota,
+    /// The number of tasks created in the scope so far.
+    pub tasks: usize,
+    /// The estimated size of the tasks created in the scope so far.
+    pub bytes: usize,
+    /// The function that was called.
+    pub function: String,
+    /// Sorted by the number of tasks, largest first.
+    pub contributors: Vec<QuotaContributor>,
+}
+
+impl Display for ScopeQuotaExceeded {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "calling {} exceeds the quota of the scope of {} ({} tasks, {} created so far)",
+            self.function,
+            self.task,
+            self.tasks,
+            FormatBytes(self.bytes)
+        )?;
+        if !self.contributors.is_empty() {
+            write!(f, "; biggest contributors:")?;
+            for contributor in self.contributors.iter() {
+                write!(
+                    f,
+                    " {} ({} tasks, {})",
+                    contributor.function,
+                    contributor.tasks,
+                    FormatBytes(contributor.bytes)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ScopeQuotaExceeded {}
+
+/// The number of contributors listed in [ScopeQuotaExceeded].
+const MAX_CONTRIBUTORS: usize = 5;
+
+pub(crate) struct ScopeQuotaState {
+    task: TaskId,
+    quota: ScopeQuota,
+    usage: Mutex<Usage>,
+}
+
+#[derive(Default)]
+struct Usage {
+    tasks: usize,
+    bytes: usize,
+    /// The number of tasks and bytes by function name.
+    by_function: HashMap<String, (usize, usize)>,
+    /// The function and bytes of each counted task, to stop counting it when
+    /// it's released.
+    counted: HashMap<TaskId, (String, usize)>,
+}
+
+impl ScopeQuotaState {
+    pub fn new(task: TaskId, quota: ScopeQuota) -> Self {
+        Self {
+            task,
+            quota,
+            usage: Default::default(),
+        }
+    }
+
+    /// Returns the error when a new task of the function would exceed the
+    /// quota.
+    pub fn check(&self, function: &str, bytes: usize) -> Result<(), ScopeQuotaExceeded> {
+        let usage = self.usage.lock();
+        let exceeds_tasks = self
+            .quota
+            .max_tasks
+            .map_or(false, |max| usage.tasks + 1 > max);
+        let exceeds_bytes = self
+            .quota
+            .max_bytes
+            .map_or(false, |max| usage.bytes + bytes > max);
+        if !exceeds_tasks && !exceeds_bytes {
+            return Ok(());
+        }
+        let mut contributors: Vec<_> = usage
+            .by_function
+            .iter()
+            .map(|(function, (tasks, bytes))| QuotaContributor {
+                function: function.clone(),
+                tasks: *tasks,
+                bytes: *bytes,
+            })
+            .collect();
+        contributors.sort_by(|a, b| {
+            b.tasks
+                .cmp(&a.tasks)
+                .then_with(|| a.function.cmp(&b.function))
+        });
+        contributors.truncate(MAX_CONTRIBUTORS);
+        Err(ScopeQuotaExceeded {
+            task: self.task,
+            quota: self.quota,
+            tasks: usage.tasks,
+            bytes: usage.bytes,
+            function: function.to_string(),
+            contributors,
+        })
+    }
+
+    /// Counts a created task against the quota. Tasks are only counted once.
+    pub fn add(&self, task: TaskId, function: &str, bytes: usize) {
+        let mut usage = self.usage.lock();
+        let Entry::Vacant(entry) = usage.counted.entry(task) else {
+            return;
+        };
+        entry.insert((function.to_string(), bytes));
+        usage.tasks += 1;
+        usage.bytes += bytes;
+        let (tasks, function_bytes) = usage.by_function.entry(function.to_string()).or_default();
+        *tasks += 1;
+        *function_bytes += bytes;
+    }
+
+    /// Stops counting a task that has been released.
+    pub fn remove(&self, task: TaskId) {
+        let mut usage = self.usage.lock();
+        let Some((function, bytes)) = usage.counted.remove(&task) else {
+            return;
+        };
+        usage.tasks -= 1;
+        usage.bytes -= bytes;
+        if let Entry::Occupied(mut entry) = usage.by_function.entry(function) {
+            let (tasks, function_bytes) = entry.get_mut();
+            *tasks -= 1;
+            *function_bytes -= bytes;
+            if *tasks == 0 {
+                entry.remove();
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+use turbo_tasks::{util::FormatDuration, TaskId};
+
+/// An internal operation of the [crate::MemoryBackend] that can be slow for
+/// large graphs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlowOperation {
+    /// Removing the dependencies of a task after it has been executed or
+    /// invalidated.
+    ClearDependencies { count: usize },
+    /// Adding new child tasks (and all their children) to a scope of the
+    /// parent task. It's reported for the parent task.
+    AddToScope,
+    /// Giving a task its own root scope.
+    MakeRootScoped,
+}
+
+/// Receives operations that exceeded the [SlowOpThresholds] configured with
+/// [crate::MemoryBackend::with_slow_op_reporter].
+pub trait SlowOpReporter: Send + Sync {
+    fn report(&self, operation: SlowOperation, task: TaskId, duration: Duration);
+}
+
+/// Limits above which an operation is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlowOpThresholds {
+    pub duration: Duration,
+    /// Clearing dependencies is also reported when the task had more
+    /// dependencies than that, regardless of the duration.
+    pub dependencies: usize,
+}
+
+impl Default for SlowOpThresholds {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_millis(10),
+            dependencies: 10000,
+        }
+    }
+}
+
+impl SlowOpThresholds {
+    fn is_exceeded(&self, operation: SlowOperation, duration: Duration) -> bool {
+        match operation {
+            SlowOperation::ClearDependencies { count } if count > self.dependencies => true,
+            _ => duration >= self.duration,
+        }
+    }
+}
+
+/// A [SlowOpReporter] that emits a `tracing` warning for every slow operation.
+pub struct TracingSlowOpReporter;
+
+impl SlowOpReporter for TracingSlowOpReporter {
+    fn report(&self, operation: SlowOperation, task: TaskId, duration: Duration) {
+        tracing::warn!(
+            "{:?} for {} took {}",
+            operation,
+            task,
+            FormatDuration(duration)
+        );
+    }
+}
+
+pub(crate) struct SlowOps {
+    reporter: Box<dyn SlowOpReporter>,
+    thresholds: SlowOpThresholds,
+}
+
+impl SlowOps {
+    pub fn new(reporter: Box<dyn SlowOpReporter>, thresholds: SlowOpThresholds) -> Self {
+        Self {
+            reporter,
+            thresholds,
+        }
+    }
+
+    pub fn report(&self, operation: SlowOperation, task: TaskId, duration: Duration) {
+        if self.thresholds.is_exceeded(operation, duration) {
+            self.reporter.report(operation, task, duration);
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    hash::{Hash, Hasher},
+};
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use nohash_hasher::BuildNoHashHasher;
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{backend::PersistentTaskType, registry, TaskId, TaskInput};
+use turbo_tasks_hash::{DeterministicHasher, Xxh3Hash64Hasher};
+
+/// An id of a task derived from its function and inputs, so it's the same in
+/// every run of the same build. See
+/// [crate::MemoryBackend::with_stable_task_ids].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct StableTaskId(pub u64);
+
+impl Display for StableTaskId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Feeds [Hash] implementations into a deterministic hasher.
+#[derive(Default)]
+struct StableHasher(Xxh3Hash64Hasher);
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write_bytes(bytes);
+    }
+}
+
+/// Maps tasks to their [StableTaskId]s and back.
+#[derive(Default)]
+pub(crate) struct StableTaskIds {
+    by_task: DashMap<TaskId, StableTaskId, BuildNoHashHasher<TaskId>>,
+    by_id: DashMap<StableTaskId, TaskId>,
+}
+
+impl StableTaskIds {
+    /// Assigns the stable id of a new task. Tasks with inputs that are not
+    /// stable, e.g. transient values or Vcs of tasks without stable id, don't
+    /// get one. When the hash collides with the one of another task, the next
+    /// free id is taken, so the task created later might get a different id
+    /// in the next run.
+    pub fn assign(&self, task: TaskId, task_type: &PersistentTaskType) -> Option<StableTaskId> {
+        let mut hash = self.hash_task_type(task_type)?;
+        loop {
+            match self.by_id.entry(StableTaskId(hash)) {
+                Entry::Vacant(entry) => {
+                    entry.insert(task);
+                    break;
+                }
+                Entry::Occupied(entry) if *entry.get() == task => break,
+                Entry::Occupied(_) => hash = hash.wrapping_add(1),
+            }
+        }
+        let id = StableTaskId(hash);
+        self.by_task.insert(task, id);
+        Some(id)
+    }
+
+    pub fn get(&self, task: TaskId) -> Option<StableTaskId> {
+        self.by_task.get(&task).map(|id| *id)
+    }
+
+    pub fn task(&self, id: StableTaskId) -> Option<TaskId> {
+        self.by_id.get(&id).map(|task| *task)
+    }
+
+    fn hash_task_type(&self, task_type: &PersistentTaskType) -> Option<u64> {
+        let mut hasher = StableHasher::default();
+        let inputs = match task_type {
+            PersistentTaskType::Native(function, inputs) => {
+                0u8.hash(&mut hasher);
+                registry::get_function_global_name(*function).hash(&mut hasher);
+                inputs
+            }
+            PersistentTaskType::ResolveNative(function, inputs) => {
+                1u8.hash(&mut hasher);
+                registry::get_function_global_name(*function).hash(&mut hasher);
+                inputs
+            }
+            PersistentTaskType::ResolveTrait(trait_type, name, inputs) => {
+                2u8.hash(&mut hasher);
+                registry::get_trait_type_global_name(*trait_type).hash(&mut hasher);
+                name.hash(&mut hasher);
+                inputs
+            }
+        };
+        inputs.len().hash(&mut hasher);
+        for input in inputs {
+            self.hash_input(input, &mut hasher)?;
+        }
+        Some(hasher.finish())
+    }
+
+    fn hash_input(&self, input: &TaskInput, hasher: &mut StableHasher) -> Option<()> {
+        // Ids that are assigned by registration or creation order are replaced
+        // by names and stable ids
+        match input {
+            TaskInput::TaskOutput(task) => {
+                0u8.hash(hasher);
+                self.get(*task)?.hash(hasher);
+            }
+            TaskInput::TaskCell(task, index) => {
+                1u8.hash(hasher);
+                self.get(*task)?.hash(hasher);
+                registry::get_value_type_global_name(index.type_id).hash(hasher);
+                index.index.hash(hasher);
+            }
+            TaskInput::List(list) => {
+                2u8.hash(hasher);
+                list.len().hash(hasher);
+                for input in list {
+                    self.hash_input(input, hasher)?;
+                }
+            }
+            TaskInput::SharedValue(value) => {
+                3u8.hash(hasher);
+                value
+                    .0
+                    .map(registry::get_value_type_global_name)
+                    .hash(hasher);
+                value.1.hash(hasher);
+            }
+            TaskInput::TransientSharedValue(_) | TaskInput::SharedReference(_) => return None,
+            input => {
+                4u8.hash(hasher);
+                input.hash(hasher);
+            }
+        }
+        Some(())
+    }
+}
@@ -1,22 +1,30 @@
 use std::{
     cmp::{self, max},
-    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
-    fmt::Display,
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet, VecDeque},
+    fmt::{Display, Write},
+    fs,
+    io::ErrorKind,
     mem::take,
+    path::Path,
     time::Duration,
 };
 
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use turbo_tasks::{registry, FunctionId, TaskId, TraitTypeId};
 
 use crate::{
     scope::TaskScopeId,
     task::{Task, TaskStatsInfo},
-    MemoryBackend,
+    DurationHistogram, MemoryBackend,
 };
 
 pub struct StatsReferences {
     pub tasks: Vec<(ReferenceType, TaskId)>,
     pub scopes: Vec<(ReferenceType, TaskScopeId)>,
+    /// The tasks that have the task as child. They are not part of the
+    /// aggregated stats, as they are the inverse of [ReferenceType::Child].
+    pub parents: Vec<TaskId>,
 }
 
 #[derive(PartialEq, Eq, Hash, Clone, Debug)]
@@ -26,6 +34,9 @@ pub enum TaskType {
     Native(FunctionId),
     ResolveNative(FunctionId),
     ResolveTrait(TraitTypeId, String),
+    /// A task with a label set by [turbo_tasks::set_task_label]. Labeled tasks
+    /// are not grouped with other tasks of the same type.
+    Labeled(Box<TaskType>, String),
 }
 
 impl Display for TaskType {
@@ -40,6 +51,31 @@ impl Display for TaskType {
             TaskType::ResolveTrait(t, n) => {
                 write!(f, "resolve trait {}::{}", registry::get_trait(*t).name, n)
             }
+            TaskType::Labeled(ty, label) => write!(f, "{ty} ({label})"),
+        }
+    }
+}
+
+impl TaskType {
+    /// The name of the function of the task, used to aggregate the stats of
+    /// different task types in a [Report].
+    fn function_name(&self) -> String {
+        match self {
+            TaskType::Root(_) => "root".to_string(),
+            TaskType::Once(_) => "once".to_string(),
+            TaskType::Native(nf) | TaskType::ResolveNative(nf) => {
+                registry::get_function(*nf).name.clone()
+            }
+            TaskType::ResolveTrait(t, n) => format!("{}::{}", registry::get_trait(*t).name, n),
+            TaskType::Labeled(ty, _) => ty.function_name(),
+        }
+    }
+
+    fn is_resolve(&self) -> bool {
+        match self {
+            TaskType::Root(_) | TaskType::Once(_) | TaskType::Native(_) => false,
+            TaskType::ResolveNative(_) | TaskType::ResolveTrait(_, _) => true,
+            TaskType::Labeled(ty, _) => ty.is_resolve(),
         }
     }
 }
@@ -49,7 +85,7 @@ pub struct ReferenceStats {
     pub count: usize,
 }
 
-#[derive(PartialEq, Eq, Hash, Clone, Debug, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Debug, Copy, Serialize, Deserialize)]
 pub enum ReferenceType {
     Child,
     Dependency,
@@ -67,6 +103,12 @@ pub struct ExportedTaskStats {
     pub total_current_duration: Duration,
     pub total_update_duration: Duration,
     pub max_duration: Duration,
+    /// The durations of all executions, only recorded with full stats.
+    pub durations: Option<DurationHistogram>,
+    /// The number of updates of the outputs of the tasks.
+    pub output_updates: usize,
+    /// The number of tracked reads of the outputs of the tasks.
+    pub output_reads: usize,
     pub references: HashMap<(ReferenceType, TaskType), ReferenceStats>,
 }
 
@@ -82,6 +124,9 @@ impl Default for ExportedTaskStats {
             total_current_duration: Duration::ZERO,
             total_update_duration: Duration::ZERO,
             max_duration: Duration::ZERO,
+            durations: None,
+            output_updates: 0,
+            output_reads: 0,
             references: Default::default(),
         }
     }
@@ -127,6 +172,9 @@ impl Stats {
             total_duration,
             last_duration,
             executions,
+            durations,
+            output_updates,
+            output_reads,
             root_scoped,
             child_scopes,
             active,
@@ -147,6 +195,11 @@ impl Stats {
         if let Some(executions) = executions {
             *stats.executions.get_or_insert(0) += executions;
         }
+        if let Some(durations) = durations {
+            stats.durations.get_or_insert_default().merge(&durations);
+        }
+        stats.output_updates += output_updates as usize;
+        stats.output_reads += output_reads as usize;
         if root_scoped {
             stats.roots += 1;
         }
@@ -180,11 +233,15 @@ impl Stats {
         });
     }
 
+    /// Adds the tasks with the tag, see [turbo_tasks::tag_task].
+    pub fn add_tagged(&mut self, backend: &MemoryBackend, tag: &str) {
+        for id in backend.tasks_with_tag(tag) {
+            self.add_id(backend, id);
+        }
+    }
+
     pub fn merge_resolve(&mut self) {
-        self.merge(|ty, _stats| match ty {
-            TaskType::Root(_) | TaskType::Once(_) | TaskType::Native(_) => false,
-            TaskType::ResolveNative(_) | TaskType::ResolveTrait(_, _) => true,
-        })
+        self.merge(|ty, _stats| ty.is_resolve())
     }
 
     pub fn merge(&mut self, mut select: impl FnMut(&TaskType, &ExportedTaskStats) -> bool) {
@@ -219,6 +276,32 @@ impl Stats {
         }
     }
 
+    /// Creates a machine-readable [Report] of the collected stats.
+    pub fn report(&self) -> Report {
+        let mut tasks: Vec<_> = self
+            .tasks
+            .iter()
+            .map(|(ty, stats)| ReportEntry::new(ty.to_string(), stats, |ty| ty.to_string()))
+            .collect();
+        tasks.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut functions: BTreeMap<String, ReportEntry> = BTreeMap::new();
+        for (ty, stats) in self.tasks.iter() {
+            let name = ty.function_name();
+            let entry = ReportEntry::new(name.clone(), stats, |ty| ty.function_name());
+            if let Some(existing) = functions.get_mut(&name) {
+                existing.merge(entry);
+            } else {
+                functions.insert(name, entry);
+            }
+        }
+
+        Report {
+            tasks,
+            functions: functions.into_values().collect(),
+        }
+    }
+
     pub fn treeify(&self, tree_ref_type: ReferenceType) -> GroupTree {
         let mut incoming_references_count = self
             .tasks
@@ -339,3 +422,306 @@ pub struct GroupTree {
     pub children: Vec<GroupTree>,
     pub task_types: Vec<(TaskType, ExportedTaskStats)>,
 }
+
+/// A machine-readable export of [Stats], with one entry per task type and one
+/// entry per function, which aggregates all task types of the function.
+/// Durations are in microseconds.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub tasks: Vec<ReportEntry>,
+    pub functions: Vec<ReportEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReportEntry {
+    pub name: String,
+    pub count: usize,
+    pub active_count: usize,
+    pub executions: Option<u32>,
+    pub roots: usize,
+    pub scopes: usize,
+    pub total_duration_us: Option<u64>,
+    pub total_current_duration_us: u64,
+    pub total_update_duration_us: u64,
+    pub max_duration_us: u64,
+    /// The durations of all executions, only recorded with full stats. See
+    /// [ReportEntry::duration_percentile_us].
+    #[serde(default)]
+    pub durations: Option<DurationHistogram>,
+    /// The number of updates of the outputs, see [ReportEntry::output_churn].
+    #[serde(default)]
+    pub output_updates: u64,
+    /// The number of tracked reads of the outputs by other tasks.
+    #[serde(default)]
+    pub output_reads: u64,
+    /// Sorted by reference type and target.
+    pub references: Vec<ReportReference>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct ReportReference {
+    pub ty: ReferenceType,
+    /// The name of the referenced task type or function.
+    pub target: String,
+    pub count: usize,
+}
+
+const CSV_HEADER: &str = "kind,name,count,active_count,executions,roots,scopes,total_duration_us,\
+                          total_current_duration_us,total_update_duration_us,max_duration_us,\
+                          p50_duration_us,p95_duration_us,p99_duration_us,output_updates,\
+                          output_reads,child_references,dependency_references,input_references";
+
+fn micros(duration: Duration) -> u64 {
+    duration.as_micros() as u64
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+impl ReportEntry {
+    fn new(
+        name: String,
+        stats: &ExportedTaskStats,
+        target_name: impl Fn(&TaskType) -> String,
+    ) -> Self {
+        let mut references: BTreeMap<(ReferenceType, String), usize> = BTreeMap::new();
+        for ((ref_type, ty), ref_stats) in stats.references.iter() {
+            *references.entry((*ref_type, target_name(ty))).or_default() += ref_stats.count;
+        }
+        Self {
+            name,
+            count: stats.count,
+            active_count: stats.active_count,
+            executions: stats.executions,
+            roots: stats.roots,
+            scopes: stats.scopes,
+            total_duration_us: stats.total_duration.map(micros),
+            total_current_duration_us: micros(stats.total_current_duration),
+            total_update_duration_us: micros(stats.total_update_duration),
+            max_duration_us: micros(stats.max_duration),
+            durations: stats.durations.clone(),
+            output_updates: stats.output_updates as u64,
+            output_reads: stats.output_reads as u64,
+            references: references
+                .into_iter()
+                .map(|((ty, target), count)| ReportReference { ty, target, count })
+                .collect(),
+        }
+    }
+
+    fn merge(&mut self, other: ReportEntry) {
+        self.count += other.count;
+        self.active_count += other.active_count;
+        if let Some(executions) = other.executions {
+            *self.executions.get_or_insert(0) += executions;
+        }
+        self.roots += other.roots;
+        self.scopes += other.scopes;
+        if let Some(total_duration) = other.total_duration_us {
+            *self.total_duration_us.get_or_insert(0) += total_duration;
+        }
+        self.total_current_duration_us += other.total_current_duration_us;
+        self.total_update_duration_us += other.total_update_duration_us;
+        self.max_duration_us = max(self.max_duration_us, other.max_duration_us);
+        if let Some(durations) = other.durations {
+            self.durations.get_or_insert_default().merge(&durations);
+        }
+        self.output_updates += other.output_updates;
+        self.output_reads += other.output_reads;
+        let mut references: BTreeMap<(ReferenceType, String), usize> = take(&mut self.references)
+            .into_iter()
+            .map(|r| ((r.ty, r.target), r.count))
+            .collect();
+        for r in other.references {
+            *references.entry((r.ty, r.target)).or_default() += r.count;
+        }
+        self.references = references
+            .into_iter()
+            .map(|((ty, target), count)| ReportReference { ty, target, count })
+            .collect();
+    }
+
+    /// The average duration of an execution, when durations and executions
+    /// have been recorded.
+    pub fn average_duration_us(&self) -> Option<u64> {
+        match (self.total_duration_us, self.executions) {
+            (Some(total), Some(executions)) if executions > 0 => Some(total / executions as u64),
+            _ => None,
+        }
+    }
+
+    /// Estimates the duration that `percentile` (e.g. `0.95`) of the
+    /// executions don't exceed, see [DurationHistogram::percentile].
+    pub fn duration_percentile_us(&self, percentile: f64) -> Option<u64> {
+        self.durations.as_ref()?.percentile(percentile).map(micros)
+    }
+
+    /// The number of updates of the outputs per tracked read, counting no
+    /// reads as one. Outputs that are updated far more often than they are
+    /// read likely recompute values that don't change, or that nobody needs.
+    pub fn output_churn(&self) -> f64 {
+        self.output_updates as f64 / self.output_reads.max(1) as f64
+    }
+
+    fn reference_count(&self, ty: ReferenceType) -> usize {
+        self.references
+            .iter()
+            .filter(|r| r.ty == ty)
+            .map(|r| r.count)
+            .sum()
+    }
+
+    fn write_csv_row(&self, kind: &str, out: &mut String) {
+        fn optional<T: Display>(value: Option<T>) -> String {
+            value.map(|v| v.to_string()).unwrap_or_default()
+        }
+        writeln!(
+            out,
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            kind,
+            csv_field(&self.name),
+            self.count,
+            self.active_count,
+            optional(self.executions),
+            self.roots,
+            self.scopes,
+            optional(self.total_duration_us),
+            self.total_current_duration_us,
+            self.total_update_duration_us,
+            self.max_duration_us,
+            optional(self.duration_percentile_us(0.5)),
+            optional(self.duration_percentile_us(0.95)),
+            optional(self.duration_percentile_us(0.99)),
+            self.output_updates,
+            self.output_reads,
+            self.reference_count(ReferenceType::Child),
+            self.reference_count(ReferenceType::Dependency),
+            self.reference_count(ReferenceType::Input),
+        )
+        .unwrap();
+    }
+}
+
+impl Report {
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("serializing stats report")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).context("parsing stats report")
+    }
+
+    /// Reads a report that has been written by [Report::write], e.g. in a
+    /// previous run. A missing file results in `None`.
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(Some(Self::from_json(&content)?)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => {
+                Err(err).with_context(|| format!("unable to read stats report {}", path.display()))
+            }
+        }
+    }
+
+    /// Writes the report as JSON, so the next run can compare against it with
+    /// [Report::trends].
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_json()?)
+            .with_context(|| format!("unable to write stats report {}", path.display()))
+    }
+
+    /// Returns the functions with high churn outputs, whose
+    /// [ReportEntry::output_churn] is at least `min_churn`, with at least
+    /// `min_updates` updates, highest churn first. They might avoid
+    /// invalidating their readers by returning the previous cell when the
+    /// value didn't change.
+    pub fn high_churn(&self, min_updates: u64, min_churn: f64) -> Vec<&ReportEntry> {
+        let mut entries: Vec<_> = self
+            .functions
+            .iter()
+            .filter(|entry| {
+                entry.output_updates >= min_updates && entry.output_churn() >= min_churn
+            })
+            .collect();
+        entries.sort_by(|a, b| b.output_churn().total_cmp(&a.output_churn()));
+        entries
+    }
+
+    /// Compares the average execution duration of each function with a report
+    /// of a previous run. Only functions whose average changed by at least
+    /// `min_change` (e.g. `0.1` for 10%) are included, the largest changes
+    /// first. Functions without durations in either report are skipped, so
+    /// both runs need full stats.
+    pub fn trends(&self, previous: &Report, min_change: f64) -> Vec<Trend> {
+        let previous: HashMap<&str, &ReportEntry> = previous
+            .functions
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry))
+            .collect();
+        let mut trends: Vec<_> = self
+            .functions
+            .iter()
+            .filter_map(|entry| {
+                let average_us = entry.average_duration_us()?;
+                let previous_average_us =
+                    previous.get(entry.name.as_str())?.average_duration_us()?;
+                if previous_average_us == 0 {
+                    return None;
+                }
+                let change = average_us as f64 / previous_average_us as f64 - 1.0;
+                (change.abs() >= min_change).then(|| Trend {
+                    name: entry.name.clone(),
+                    previous_average_us,
+                    average_us,
+                    change,
+                })
+            })
+            .collect();
+        trends.sort_by(|a, b| b.change.abs().total_cmp(&a.change.abs()));
+        trends
+    }
+
+    /// Exports the report as CSV with one row per task type (kind `task`) and
+    /// per function (kind `function`). References are only included as total
+    /// counts per reference type, so the CSV can't be imported again.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "{CSV_HEADER}").unwrap();
+        for entry in self.tasks.iter() {
+            entry.write_csv_row("task", &mut out);
+        }
+        for entry in self.functions.iter() {
+            entry.write_csv_row("function", &mut out);
+        }
+        out
+    }
+}
+
+/// The change of the average execution duration of a function compared to a
+/// previous run, see [Report::trends].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trend {
+    pub name: String,
+    pub previous_average_us: u64,
+    pub average_us: u64,
+    /// The relative change, e.g. `0.35` for 35% slower.
+    pub change: f64,
+}
+
+impl Display for Trend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} average duration {:+.0}% vs last run ({}us -> {}us)",
+            self.name,
+            self.change * 100.0,
+            self.previous_average_us,
+            self.average_us
+        )
+    }
+}
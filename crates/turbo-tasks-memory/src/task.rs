@@ -2,23 +2,29 @@ use std::{
     borrow::Cow,
     cell::RefCell,
     cmp::Ordering,
-    collections::{HashMap, HashSet, VecDeque},
+    collections::{HashSet, VecDeque},
     fmt::{self, Debug, Display, Formatter, Write},
     future::Future,
-    hash::Hash,
+    hash::{BuildHasherDefault, Hash},
     mem::{replace, take},
     pin::Pin,
-    time::{Duration, Instant},
+    sync::atomic::{self, AtomicBool},
+    time::Duration,
 };
 
 use anyhow::Result;
+use auto_hash_map::{AutoIndexSet, AutoMap, AutoSet};
+use indexmap::IndexSet;
 use parking_lot::{Mutex, RwLock, RwLockWriteGuard};
-use tokio::task_local;
+use rustc_hash::FxHasher;
+use tracing::{Instrument, Level, Span};
 use turbo_tasks::{
-    backend::PersistentTaskType,
+    backend::{CellContent, CellCounts, PersistentTaskType},
     event::{Event, EventListener},
-    get_invalidator, registry, CellId, FunctionId, Invalidator, RawVc, StatsType, TaskId,
-    TaskInput, TraitTypeId, TurboTasksBackendApi, ValueTypeId,
+    get_invalidator,
+    platform::Instant,
+    registry, task_local, CellId, FunctionId, Invalidator, RawVc, StatsType, TaskId, TaskInput,
+    TraitTypeId, TurboTasksBackendApi, ValueTypeId,
 };
 pub type NativeTaskFuture = Pin<Box<dyn Future<Output = Result<RawVc>> + Send>>;
 pub type NativeTaskFn = Box<dyn Fn() -> NativeTaskFuture + Send + Sync>;
@@ -30,7 +36,7 @@ macro_rules! log_scope_update {
     };
 }
 
-#[derive(Hash, Copy, Clone, PartialEq, Eq)]
+#[derive(Hash, Copy, Clone, PartialEq, Eq, Debug)]
 pub enum TaskDependency {
     TaskOutput(TaskId),
     TaskCell(TaskId, CellId),
@@ -41,7 +47,7 @@ pub enum TaskDependency {
 task_local! {
     /// Vc/Scopes that are read during task execution
     /// These will be stored as dependencies when the execution has finished
-    pub(crate) static DEPENDENCIES_TO_TRACK: RefCell<HashSet<TaskDependency>>;
+    pub(crate) static DEPENDENCIES_TO_TRACK: RefCell<TrackedDependencies>;
 }
 
 type OnceTaskFn = Mutex<Option<Pin<Box<dyn Future<Output = Result<RawVc>> + Send + 'static>>>>;
@@ -50,8 +56,8 @@ type OnceTaskFn = Mutex<Option<Pin<Box<dyn Future<Output = Result<RawVc>> + Send
 enum TaskType {
     /// A root task that will track dependencies and re-execute when
     /// dependencies change. Task will eventually settle to the correct
-    /// execution.
-    Root(NativeTaskFn),
+    /// execution. The function can be replaced, see [Task::replace_root].
+    Root(Mutex<NativeTaskFn>),
 
     // TODO implement these strongly consistency
     /// A single root task execution. It won't track dependencies.
@@ -109,14 +115,46 @@ pub struct Task {
     inputs: Vec<TaskInput>,
     /// The type of the task
     ty: TaskType,
+    /// A human readable label set by the task itself. It's kept separate from
+    /// the state, as descriptions are also needed while the state is locked.
+    label: Mutex<Option<String>>,
+    /// Logs the changes of the task, see [MemoryBackend::trace_task].
+    traced: AtomicBool,
+    /// Set by the running execution with [Task::mark_final]. It takes effect
+    /// when that execution completes.
+    final_requested: AtomicBool,
+    /// The output and cells of the task won't change anymore, so reads of
+    /// them are not tracked. See [turbo_tasks::mark_final]. Reset when the
+    /// task executes again, which has to mark it again.
+    is_final: AtomicBool,
+    /// The task dropped its dependencies and the readers of its output and
+    /// cells, and ignores invalidations. See [MemoryBackend::freeze_subtree].
+    is_frozen: AtomicBool,
+    /// The last changes of the task, only recorded for diagnostics bundles,
+    /// see [MemoryBackend::with_panic_dumps].
+    recent_transitions: Option<Box<Mutex<VecDeque<String>>>>,
     /// The mutable state of the task
     state: RwLock<TaskState>,
+    /// The cells of the task. They are locked separately from the state, so
+    /// reading and updating cells doesn't contend with scope and state
+    /// changes of the task.
+    cells: RwLock<AutoMap<ValueTypeId, Vec<Cell>>>,
+    /// The stats of the task. They are locked separately from the state, so
+    /// recording them doesn't extend the critical sections of state changes.
+    stats: Mutex<TaskStats>,
+    /// The tasks that have this task as child. They are counted, as an
+    /// execution of a parent disconnecting its children might interleave with
+    /// the next one connecting them again.
+    parents: Mutex<CountHashSet<TaskId, BuildHasherDefault<FxHasher>>>,
 }
 
 impl Debug for Task {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let mut result = f.debug_struct("Task");
         result.field("type", &self.ty);
+        if let Some(label) = self.get_label() {
+            result.field("label", &label);
+        }
         if let Some(state) = self.state.try_read() {
             result.field("state", &Task::state_string(&state));
         }
@@ -124,6 +162,28 @@ impl Debug for Task {
     }
 }
 
+/// An insertion ordered set with the hasher used for task ids.
+pub(crate) type FxIndexSet<T> = IndexSet<T, BuildHasherDefault<FxHasher>>;
+
+/// The number of children a task stores without allocating. Most tasks have
+/// fewer children.
+const INLINE_CHILDREN: usize = 4;
+
+/// The children of a task, in the order they were connected.
+pub(crate) type TaskChildren = AutoIndexSet<TaskId, BuildHasherDefault<FxHasher>, INLINE_CHILDREN>;
+
+/// The number of dependencies a task keeps in a list before switching to a
+/// hash set. Tasks read more cells than they have children, and the
+/// dependencies are mostly iterated as a whole, so the list is longer.
+const LISTED_DEPENDENCIES: usize = 32;
+
+/// The cells, outputs and scopes read by the last execution of a task.
+pub(crate) type TaskDependencies =
+    AutoSet<TaskDependency, BuildHasherDefault<FxHasher>, LISTED_DEPENDENCIES>;
+
+/// The number of changes kept per task when transitions are recorded.
+const RECENT_TRANSITIONS: usize = 16;
+
 /// The state of a [Task]
 struct TaskState {
     scopes: TaskScopes,
@@ -133,22 +193,19 @@ struct TaskState {
     /// dirty, scheduled, in progress
     state_type: TaskStateType,
 
-    /// Children are only modified from execution
-    children: HashSet<TaskId>,
+    /// Children are only modified from execution. They are kept in the order
+    /// they were connected, so scope changes and scheduling follow the same
+    /// order in every run.
+    children: TaskChildren,
 
     /// Collectibles are only modified from execution
     collectibles: MaybeCollectibles,
 
     output: Output,
-    // TODO use AutoMap here
-    cells: HashMap<ValueTypeId, Vec<Cell>>,
-
-    // Stats:
-    stats: TaskStats,
 }
 
 impl TaskState {
-    fn new(id: TaskId, stats_type: StatsType) -> Self {
+    fn new(id: TaskId) -> Self {
         Self {
             scopes: Default::default(),
             state_type: Dirty {
@@ -157,24 +214,20 @@ impl TaskState {
             children: Default::default(),
             collectibles: Default::default(),
             output: Default::default(),
-            cells: Default::default(),
-            stats: TaskStats::new(stats_type),
             #[cfg(feature = "track_wait_dependencies")]
             last_waiting_task: Default::default(),
         }
     }
 
-    fn new_scheduled_in_scope(id: TaskId, scope: TaskScopeId, stats_type: StatsType) -> Self {
+    fn new_scheduled_in_scope(id: TaskId, scope: TaskScopeId) -> Self {
         Self {
-            scopes: TaskScopes::Inner(CountHashSet::from([scope]), 0),
+            scopes: TaskScopes::Inner(TaskScopeList::from([scope]), 0),
             state_type: Scheduled {
                 event: Event::new(move || format!("TaskState({id})::event")),
             },
             children: Default::default(),
             collectibles: Default::default(),
             output: Default::default(),
-            cells: Default::default(),
-            stats: TaskStats::new(stats_type),
             #[cfg(feature = "track_wait_dependencies")]
             last_waiting_task: Default::default(),
         }
@@ -238,7 +291,7 @@ enum TaskStateType {
         /// there might affect this task.
         ///
         /// This back-edge is [Cell] `dependent_tasks`, which is a weak edge.
-        dependencies: HashSet<TaskDependency>,
+        dependencies: TaskDependencies,
     },
 
     /// Execution is invalid, but not yet scheduled
@@ -267,14 +320,16 @@ enum TaskStateType {
 use TaskStateType::*;
 
 use crate::{
-    cell::Cell,
+    cell::{Cell, CellNotFound},
     count_hash_set::CountHashSet,
     memory_backend::Job,
-    output::Output,
-    scope::{ScopeChildChangeEffect, TaskScopeId, TaskScopes},
+    output::{Output, OutputContent, OutputNotComputed},
+    scope::{ScopeChildChangeEffect, TaskScopeId, TaskScopeList, TaskScopes},
+    slow_ops::SlowOperation,
     stats::{self, StatsReferences},
     task_stats::TaskStats,
-    MemoryBackend,
+    tracked_dependencies::TrackedDependencies,
+    DurationHistogram, MemoryBackend,
 };
 
 impl Task {
@@ -289,7 +344,16 @@ impl Task {
             id,
             inputs,
             ty: TaskType::Native(native_fn, bound_fn),
-            state: RwLock::new(TaskState::new(id, stats_type)),
+            label: Default::default(),
+            traced: Default::default(),
+            final_requested: Default::default(),
+            is_final: Default::default(),
+            is_frozen: Default::default(),
+            recent_transitions: None,
+            state: RwLock::new(TaskState::new(id)),
+            cells: Default::default(),
+            stats: Mutex::new(TaskStats::new(stats_type)),
+            parents: Default::default(),
         }
     }
 
@@ -303,7 +367,16 @@ impl Task {
             id,
             inputs,
             ty: TaskType::ResolveNative(native_fn),
-            state: RwLock::new(TaskState::new(id, stats_type)),
+            label: Default::default(),
+            traced: Default::default(),
+            final_requested: Default::default(),
+            is_final: Default::default(),
+            is_frozen: Default::default(),
+            recent_transitions: None,
+            state: RwLock::new(TaskState::new(id)),
+            cells: Default::default(),
+            stats: Mutex::new(TaskStats::new(stats_type)),
+            parents: Default::default(),
         }
     }
 
@@ -318,7 +391,16 @@ impl Task {
             id,
             inputs,
             ty: TaskType::ResolveTrait(trait_type, trait_fn_name),
-            state: RwLock::new(TaskState::new(id, stats_type)),
+            label: Default::default(),
+            traced: Default::default(),
+            final_requested: Default::default(),
+            is_final: Default::default(),
+            is_frozen: Default::default(),
+            recent_transitions: None,
+            state: RwLock::new(TaskState::new(id)),
+            cells: Default::default(),
+            stats: Mutex::new(TaskStats::new(stats_type)),
+            parents: Default::default(),
         }
     }
 
@@ -331,8 +413,17 @@ impl Task {
         Self {
             id,
             inputs: Vec::new(),
-            ty: TaskType::Root(Box::new(functor)),
-            state: RwLock::new(TaskState::new_scheduled_in_scope(id, scope, stats_type)),
+            ty: TaskType::Root(Mutex::new(Box::new(functor))),
+            label: Default::default(),
+            traced: Default::default(),
+            final_requested: Default::default(),
+            is_final: Default::default(),
+            is_frozen: Default::default(),
+            recent_transitions: None,
+            state: RwLock::new(TaskState::new_scheduled_in_scope(id, scope)),
+            cells: Default::default(),
+            stats: Mutex::new(TaskStats::new(stats_type)),
+            parents: Default::default(),
         }
     }
 
@@ -346,12 +437,163 @@ impl Task {
             id,
             inputs: Vec::new(),
             ty: TaskType::Once(Mutex::new(Some(Box::pin(functor)))),
-            state: RwLock::new(TaskState::new_scheduled_in_scope(id, scope, stats_type)),
+            label: Default::default(),
+            traced: Default::default(),
+            final_requested: Default::default(),
+            is_final: Default::default(),
+            is_frozen: Default::default(),
+            recent_transitions: None,
+            state: RwLock::new(TaskState::new_scheduled_in_scope(id, scope)),
+            cells: Default::default(),
+            stats: Mutex::new(TaskStats::new(stats_type)),
+            parents: Default::default(),
+        }
+    }
+
+    pub(crate) fn set_label(&self, label: String) {
+        *self.label.lock() = Some(label);
+    }
+
+    pub(crate) fn get_label(&self) -> Option<String> {
+        self.label.lock().clone()
+    }
+
+    pub(crate) fn set_traced(&self, enabled: bool) {
+        self.traced.store(enabled, atomic::Ordering::Relaxed);
+    }
+
+    /// Marks the output and cells of the task as final once the running
+    /// execution completes.
+    pub(crate) fn mark_final(&self) {
+        self.final_requested.store(true, atomic::Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_final(&self) -> bool {
+        self.is_final.load(atomic::Ordering::Acquire)
+    }
+
+    pub(crate) fn is_frozen(&self) -> bool {
+        self.is_frozen.load(atomic::Ordering::Acquire)
+    }
+
+    /// Freezes the task when it's done, see [MemoryBackend::freeze_subtree].
+    /// Returns the children of the task, or `None` when it's not done or
+    /// already frozen.
+    pub(crate) fn freeze(&self, backend: &MemoryBackend) -> Option<Vec<TaskId>> {
+        let mut state = self.state.write();
+        let Done { ref mut dependencies } = state.state_type else {
+            return None;
+        };
+        if self.is_frozen.swap(true, atomic::Ordering::AcqRel) {
+            return None;
+        }
+        let dependencies = take(dependencies);
+        state.output.clear_dependent_tasks();
+        let children = state.children.iter().copied().collect();
+        drop(state);
+        for list in self.cells.write().values_mut() {
+            for cell in list {
+                cell.dependent_tasks = Default::default();
+            }
+        }
+        self.trace(|| "frozen".to_string());
+        if !dependencies.is_empty() {
+            self.clear_dependencies(dependencies, backend);
+        }
+        Some(children)
+    }
+
+    /// Unfreezes the task, see [MemoryBackend::unfreeze_subtree]. Returns the
+    /// children of the task, or `None` when it's not frozen.
+    pub(crate) fn unfreeze(&self) -> Option<Vec<TaskId>> {
+        if !self.is_frozen.swap(false, atomic::Ordering::AcqRel) {
+            return None;
+        }
+        self.trace(|| "unfrozen".to_string());
+        Some(self.children())
+    }
+
+    /// Keeps the last changes of the task for its diagnostics bundle.
+    pub(crate) fn record_transitions(&mut self) {
+        self.recent_transitions = Some(Default::default());
+    }
+
+    /// Logs a change of the task when it's traced, and records it when
+    /// transitions are recorded.
+    fn trace(&self, change: impl FnOnce() -> String) {
+        let traced = self.traced.load(atomic::Ordering::Relaxed);
+        if !traced && self.recent_transitions.is_none() {
+            return;
+        }
+        let change = change();
+        if traced {
+            tracing::info!(task = %self.id, "{}", change);
+        }
+        if let Some(recent_transitions) = &self.recent_transitions {
+            let mut recent_transitions = recent_transitions.lock();
+            if recent_transitions.len() == RECENT_TRANSITIONS {
+                recent_transitions.pop_front();
+            }
+            recent_transitions.push_back(change);
         }
     }
 
+    pub(crate) fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Describes the task, its scopes, parents, children and last changes for
+    /// a diagnostics bundle. Locks are only tried, so this also works while
+    /// the backend is in a broken state.
+    pub(crate) fn diagnostics(&self, backend: &MemoryBackend) -> String {
+        let mut out = String::new();
+        writeln!(out, "{}", self.get_description()).unwrap();
+        writeln!(out, "{:#?}", self).unwrap();
+        let Some(state) = self.state.try_read() else {
+            writeln!(out, "\nstate is locked").unwrap();
+            return out;
+        };
+        writeln!(out, "\nstate: {}", Task::state_string(&state)).unwrap();
+        writeln!(out, "\nscopes:").unwrap();
+        for scope in state.scopes.iter() {
+            let scope_state = backend.with_scope(scope, |scope| scope.diagnostics());
+            writeln!(out, "  {scope}: {scope_state}").unwrap();
+        }
+        writeln!(out, "\nchildren:").unwrap();
+        let children = state.children.iter().copied().collect::<Vec<_>>();
+        drop(state);
+        for child in children {
+            let description = backend.with_task(child, |task| task.get_description());
+            writeln!(out, "  {description}").unwrap();
+        }
+        writeln!(out, "\nparents:").unwrap();
+        let parents = self
+            .parents
+            .try_lock()
+            .map(|parents| parents.iter().copied().collect::<Vec<_>>());
+        match parents {
+            Some(parents) => {
+                for parent in parents {
+                    let description = backend.with_task(parent, |task| task.get_description());
+                    writeln!(out, "  {description}").unwrap();
+                }
+            }
+            None => writeln!(out, "  parents are locked").unwrap(),
+        }
+        writeln!(out, "\nrecent transitions:").unwrap();
+        match &self.recent_transitions {
+            Some(recent_transitions) => {
+                for change in recent_transitions.lock().iter() {
+                    writeln!(out, "  {change}").unwrap();
+                }
+            }
+            None => writeln!(out, "  not recorded").unwrap(),
+        }
+        out
+    }
+
     pub(crate) fn get_description(&self) -> String {
-        match &self.ty {
+        let description = match &self.ty {
             TaskType::Root(..) => format!("[{}] root", self.id),
             TaskType::Once(..) => format!("[{}] once", self.id),
             TaskType::Native(native_fn, _) => {
@@ -372,6 +614,10 @@ impl Task {
                     registry::get_trait(*trait_type).name
                 )
             }
+        };
+        match self.get_label() {
+            Some(label) => format!("{description} ({label})"),
+            None => description,
         }
     }
 
@@ -379,9 +625,7 @@ impl Task {
         match dep {
             TaskDependency::TaskOutput(task) => {
                 backend.with_task(task, |task| {
-                    task.with_output_mut(|output| {
-                        output.dependent_tasks.remove(&reader);
-                    });
+                    task.with_output(|output| output.remove_dependent_task(reader));
                 });
             }
             TaskDependency::TaskCell(task, index) => {
@@ -402,34 +646,14 @@ impl Task {
         }
     }
 
-    #[cfg(not(feature = "report_expensive"))]
-    fn clear_dependencies(&self, dependencies: HashSet<TaskDependency>, backend: &MemoryBackend) {
-        for dep in dependencies.into_iter() {
-            Task::remove_dependency(dep, self.id, backend);
-        }
-    }
-
-    #[cfg(feature = "report_expensive")]
-    fn clear_dependencies(&self, dependencies: HashSet<TaskDependency>, backend: &MemoryBackend) {
-        use std::time::Instant;
-
-        use turbo_tasks::util::FormatDuration;
-        let start = Instant::now();
-
+    fn clear_dependencies(&self, dependencies: TaskDependencies, backend: &MemoryBackend) {
         let count = dependencies.len();
-
-        for dep in dependencies.into_iter() {
-            Task::remove_dependency(dep, self.id, backend);
-        }
-        let elapsed = start.elapsed();
-        if elapsed.as_millis() >= 10 || count > 10000 {
-            println!(
-                "clear_dependencies({}) took {}: {:?}",
-                count,
-                FormatDuration(elapsed),
-                self
-            );
-        }
+        backend.measure_slow_op(SlowOperation::ClearDependencies { count }, self.id, || {
+            for dep in dependencies.into_iter() {
+                self.trace(|| format!("removed dependency {dep:?}"));
+                Task::remove_dependency(dep, self.id, backend);
+            }
+        });
     }
 
     pub(crate) fn execution_started(
@@ -447,7 +671,11 @@ impl Task {
                 state.state_type = InProgress {
                     event: event.take(),
                 };
-                state.stats.increment_executions();
+                self.trace(|| "scheduled -> in progress".to_string());
+                self.stats.lock().increment_executions();
+                if self.is_final.swap(false, atomic::Ordering::AcqRel) {
+                    self.trace(|| "no longer final".to_string());
+                }
                 // TODO we need to reconsider the approach of doing scope changes in background
                 // since they affect collectibles and need to be computed eagerly to allow
                 // strongly_consistent to work properly.
@@ -455,22 +683,15 @@ impl Task {
                 // finished.
                 if !state.children.is_empty() {
                     let set = take(&mut state.children);
-                    let state_scopes = &state.scopes;
-                    match state_scopes {
-                        TaskScopes::Root(scope) => {
-                            turbo_tasks.schedule_backend_foreground_job(
-                                backend.create_backend_job(Job::RemoveFromScope(set, *scope)),
-                            );
-                        }
-                        TaskScopes::Inner(ref scopes, _) => {
-                            turbo_tasks.schedule_backend_foreground_job(
-                                backend.create_backend_job(Job::RemoveFromScopes(
-                                    set,
-                                    scopes.iter().copied().collect(),
-                                )),
-                            );
-                        }
+                    for child in set.iter() {
+                        backend.with_task(*child, |child| child.parents.lock().remove(self.id));
                     }
+                    let scopes = state.scopes.iter().collect::<Vec<_>>();
+                    turbo_tasks.schedule_backend_foreground_job(
+                        backend.create_backend_job(Job::UpdateScopes(ScopeUpdate::remove(
+                            set, scopes,
+                        ))),
+                    );
                 }
                 if let Some(collectibles) = state.collectibles.take() {
                     let emitted = collectibles.emitted;
@@ -502,10 +723,30 @@ impl Task {
             Dirty { .. } => {
                 let state_type = Task::state_string(&state);
                 drop(state);
-                panic!(
-                    "{:?} execution started in unexpected state {}",
-                    self, state_type
-                )
+                backend.invariant_violation(
+                    self,
+                    format!(
+                        "{:?} execution started in unexpected state {}",
+                        self, state_type
+                    ),
+                );
+                // The task is not executed now. It's scheduled properly instead,
+                // so readers that wait for it are notified.
+                let mut state = self.state.write();
+                if let Dirty { ref mut event } = state.state_type {
+                    state.state_type = Scheduled {
+                        event: event.take(),
+                    };
+                    self.trace(|| "dirty -> scheduled after invariant violation".to_string());
+                    for scope in state.scopes.iter() {
+                        backend.with_scope(scope, |scope| {
+                            scope.state.lock().remove_dirty_task(self.id);
+                        });
+                    }
+                    drop(state);
+                    turbo_tasks.schedule(self.id);
+                }
+                return false;
             }
         };
         true
@@ -514,29 +755,126 @@ impl Task {
     pub(crate) fn execution_result(
         &self,
         result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        backend: &MemoryBackend,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) {
         let mut state = self.state.write();
         match state.state_type {
-            InProgress { .. } => match result {
-                Ok(Ok(result)) => state.output.link(result, turbo_tasks),
-                Ok(Err(err)) => state.output.error(err, turbo_tasks),
-                Err(message) => state.output.panic(message, turbo_tasks),
-            },
+            InProgress { .. } => {
+                let generation = state.output.generation();
+                // The comparison happens under the same lock as the update, so the output
+                // can't be linked elsewhere in between
+                let link_equal = match &result {
+                    Ok(Ok(target)) => {
+                        Self::is_linked_to_equal_cell(&state.output, *target, backend)
+                    }
+                    _ => false,
+                };
+                let mut moved_readers = Vec::new();
+                match result {
+                    Ok(Ok(result)) if link_equal => {
+                        moved_readers = state.output.link_equal(result);
+                    }
+                    Ok(Ok(result)) => state.output.link(result, turbo_tasks),
+                    Ok(Err(err)) => state.output.error(err, turbo_tasks),
+                    Err(message) => state.output.panic(message, turbo_tasks),
+                }
+                if state.output.generation() == generation {
+                    return;
+                }
+                let linked_cell = state.output.linked_cell();
+                let max_history = backend.output_history_len();
+                if max_history > 0 {
+                    state.output.record_history(max_history, turbo_tasks);
+                }
+                let memoized_by = take(&mut state.output.memoized_by);
+                let resolved_generation = state.output.resolved_generation();
+                drop(state);
+                if !memoized_by.is_empty() {
+                    // Readers of an equal cell don't need to be notified
+                    backend.clear_memoized_links(
+                        self.id,
+                        resolved_generation,
+                        memoized_by,
+                        !link_equal,
+                        turbo_tasks,
+                    );
+                }
+                if let (false, Some((task, index))) = (moved_readers.is_empty(), linked_cell) {
+                    Self::move_readers_to_cell(moved_readers, task, index, backend);
+                }
+            }
             InProgressDirty { .. } => {
                 // We don't want to assign the output cell here
                 // as we want to avoid unnecessary updates
                 // TODO maybe this should be controlled by a heuristic
             }
             Dirty { .. } | Scheduled { .. } | Done { .. } => {
-                panic!(
-                    "Task execution completed in unexpected state {}",
-                    Task::state_string(&state)
-                )
+                let state_type = Task::state_string(&state);
+                drop(state);
+                // The result is ignored
+                backend.invariant_violation(
+                    self,
+                    format!("Task execution completed in unexpected state {state_type}"),
+                );
             }
         };
     }
 
+    /// Returns true when the output is linked to a different cell than
+    /// `target` with an equal value, for value types that opted into
+    /// [turbo_tasks::ValueType::link_eq].
+    fn is_linked_to_equal_cell(output: &Output, target: RawVc, backend: &MemoryBackend) -> bool {
+        let RawVc::TaskCell(task, index) = target else {
+            return false;
+        };
+        let Some(old) = output.linked_cell() else {
+            return false;
+        };
+        if old == (task, index) || old.1.type_id != index.type_id {
+            return false;
+        }
+        let read = |(task, index): (TaskId, CellId)| {
+            backend.with_task(task, |task| {
+                task.with_cell(index, |cell| cell.read_content_untracked())
+            })
+        };
+        let (CellContent(Some(old)), CellContent(Some(new))) = (read(old), read((task, index))) else {
+            return false;
+        };
+        registry::get_value_type(index.type_id)
+            .link_eq(&*old.1, &*new.1)
+            .unwrap_or(false)
+    }
+
+    /// Makes the readers of an output that has been linked to an equal cell
+    /// depend on that cell, as they only depend on the previous one, which
+    /// might not change anymore.
+    fn move_readers_to_cell(
+        readers: Vec<TaskId>,
+        task: TaskId,
+        index: CellId,
+        backend: &MemoryBackend,
+    ) {
+        backend.with_task(task, |task| {
+            task.with_cell_mut(index, |cell| {
+                for reader in readers.iter() {
+                    cell.track_read(*reader);
+                }
+            })
+        });
+        for reader in readers {
+            backend.with_task(reader, |reader| {
+                if let Done {
+                    ref mut dependencies,
+                } = reader.state.write().state_type
+                {
+                    dependencies.insert(TaskDependency::TaskCell(task, index));
+                }
+            });
+        }
+    }
+
     #[must_use]
     pub(crate) fn execution_completed(
         &self,
@@ -546,18 +884,26 @@ impl Task {
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> bool {
         let mut schedule_task = false;
-        let mut dependencies = DEPENDENCIES_TO_TRACK.with(|deps| deps.take());
+        let mut dependencies = DEPENDENCIES_TO_TRACK.with(|deps| deps.take().into_set());
+        self.stats
+            .lock()
+            .register_execution(duration, turbo_tasks.program_duration_until(instant));
         {
             let mut state = self.state.write();
-            state
-                .stats
-                .register_execution(duration, turbo_tasks.program_duration_until(instant));
             match state.state_type {
                 InProgress { ref mut event } => {
                     let event = event.take();
+                    self.trace(|| "in progress -> done".to_string());
+                    for dep in dependencies.iter() {
+                        self.trace(|| format!("added dependency {dep:?}"));
+                    }
                     state.state_type = Done {
                         dependencies: take(&mut dependencies),
                     };
+                    if self.final_requested.swap(false, atomic::Ordering::Relaxed) {
+                        self.trace(|| "marked final".to_string());
+                        self.is_final.store(true, atomic::Ordering::Release);
+                    }
                     for scope in state.scopes.iter() {
                         backend.with_scope(scope, |scope| {
                             scope.decrement_unfinished_tasks(backend);
@@ -567,6 +913,9 @@ impl Task {
                 }
                 InProgressDirty { ref mut event } => {
                     let event = event.take();
+                    // The execution is outdated, the next one needs to mark it
+                    // again
+                    self.final_requested.store(false, atomic::Ordering::Relaxed);
                     let mut active = false;
                     for scope in state.scopes.iter() {
                         if backend.with_scope(scope, |scope| scope.state.lock().is_active()) {
@@ -577,15 +926,35 @@ impl Task {
                     if active {
                         state.state_type = Scheduled { event };
                         schedule_task = true;
+                        self.trace(|| "in progress (dirty) -> scheduled".to_string());
+                    } else if state.output.is_empty() {
+                        // The first execution has been aborted and nobody needs the task
+                        // anymore. It's done without output, so reads fail with
+                        // OutputNotComputed instead of waiting for an execution that nobody
+                        // schedules.
+                        state.state_type = Done {
+                            dependencies: Default::default(),
+                        };
+                        for scope in state.scopes.iter() {
+                            backend.with_scope(scope, |scope| {
+                                scope.decrement_unfinished_tasks(backend);
+                            })
+                        }
+                        event.notify(usize::MAX);
+                        self.trace(|| "in progress (dirty) -> done without output".to_string());
                     } else {
                         state.state_type = Dirty { event };
+                        self.trace(|| "in progress (dirty) -> dirty".to_string());
                     }
                 }
                 Dirty { .. } | Scheduled { .. } | Done { .. } => {
-                    panic!(
-                        "Task execution completed in unexpected state {}",
-                        Task::state_string(&state)
-                    )
+                    let state_type = Task::state_string(&state);
+                    drop(state);
+                    // The dependencies of the execution are dropped below
+                    backend.invariant_violation(
+                        self,
+                        format!("Task execution completed in unexpected state {state_type}"),
+                    );
                 }
             };
         }
@@ -600,16 +969,33 @@ impl Task {
         schedule_task
     }
 
-    fn make_dirty(&self, backend: &MemoryBackend, turbo_tasks: &dyn TurboTasksBackendApi) {
+    /// Returns false when the invalidation has been ignored, as once tasks and
+    /// frozen tasks don't become dirty.
+    fn make_dirty(&self, backend: &MemoryBackend, turbo_tasks: &dyn TurboTasksBackendApi) -> bool {
         if let TaskType::Once(_) = self.ty {
             // once task won't become dirty
-            return;
+            return false;
+        }
+        if self.is_frozen() {
+            self.trace(|| "invalidation ignored while frozen".to_string());
+            return false;
+        }
+
+        if matches!(
+            self.state.read().state_type,
+            Dirty { .. } | Scheduled { .. } | InProgressDirty { .. }
+        ) {
+            // Invalidations of tasks that are already dirty are common under
+            // fan-out, they don't need to wait for the write lock
+            self.trace(|| "invalidated while already dirty".to_string());
+            return true;
         }
 
         let id = self.id;
-        let mut clear_dependencies = HashSet::new();
+        let mut clear_dependencies = TaskDependencies::default();
         {
             let mut state = self.state.write();
+            self.trace(|| format!("invalidated while {}", Task::state_string(&state)));
             match state.state_type {
                 Dirty { .. } | Scheduled { .. } | InProgressDirty { .. } => {
                     // already dirty
@@ -621,11 +1007,15 @@ impl Task {
                     clear_dependencies = take(dependencies);
                     // add to dirty lists and potentially schedule
                     let mut active = false;
+                    let mut priority = false;
                     for scope in state.scopes.iter() {
                         backend.with_scope(scope, |scope| {
                             scope.increment_unfinished_tasks(backend);
                             log_scope_update!("add unfinished task: {} -> {}", *scope.id, *self.id);
                             let mut scope = scope.state.lock();
+                            if scope.has_strongly_consistent_readers() {
+                                priority = true;
+                            }
                             if scope.is_active() {
                                 active = true;
                             } else {
@@ -638,12 +1028,14 @@ impl Task {
                             event: Event::new(move || format!("TaskState({id})::event")),
                         };
                         drop(state);
-                        turbo_tasks.schedule(self.id);
+                        self.trace(|| "done -> scheduled".to_string());
+                        turbo_tasks.schedule_invalidated(self.id, priority);
                     } else {
                         state.state_type = Dirty {
                             event: Event::new(move || format!("TaskState({id})::event")),
                         };
                         drop(state);
+                        self.trace(|| "done -> dirty".to_string());
                     }
                 }
                 InProgress { ref mut event } => {
@@ -651,6 +1043,7 @@ impl Task {
                         event: event.take(),
                     };
                     drop(state);
+                    self.trace(|| "in progress -> in progress (dirty)".to_string());
                 }
             }
         }
@@ -658,6 +1051,7 @@ impl Task {
         if !clear_dependencies.is_empty() {
             self.clear_dependencies(clear_dependencies, backend);
         }
+        true
     }
 
     pub(crate) fn schedule_when_dirty(&self, turbo_tasks: &dyn TurboTasksBackendApi) {
@@ -667,6 +1061,7 @@ impl Task {
                 event: event.take(),
             };
             drop(state);
+            self.trace(|| "dirty -> scheduled".to_string());
             turbo_tasks.schedule(self.id);
         }
     }
@@ -690,6 +1085,7 @@ impl Task {
                     // The task is already in the root scope we're trying to add it to.
                     return;
                 }
+                self.trace(|| format!("added {root} as child of {id}"));
 
                 if let Some(ScopeChildChangeEffect {
                     notify,
@@ -705,17 +1101,24 @@ impl Task {
                         backend.increase_scope_active(root, turbo_tasks);
                     }
                     if parent {
-                        backend.with_scope(root, |child| {
+                        let acquired = backend.with_scope(root, |child| {
+                            let acquired = child.parents().is_empty();
                             child.add_parent(id, backend);
-                        })
+                            acquired
+                        });
+                        if acquired {
+                            self.acquired(Some(root), backend);
+                        }
                     }
                 }
             }
             TaskScopes::Inner(ref mut list, ref mut optimization_counter) => {
+                let acquired = list.is_empty();
                 if !list.add(id) {
                     // The task is already in the scope we're trying to add it to.
                     return;
                 }
+                self.trace(|| format!("added to {id}"));
 
                 if depth < usize::BITS as usize {
                     if is_optimization_scope {
@@ -725,6 +1128,11 @@ impl Task {
                         *optimization_counter += children.len() >> depth;
                         if *optimization_counter >= 0x10000 {
                             list.remove(id);
+                            // Only root scopes from the optimization are profiled, strongly
+                            // consistent reads make tasks root scoped anyway
+                            if let TaskType::Native(fn_id, _) = self.ty {
+                                backend.record_root_scoped(fn_id);
+                            }
                             self.make_root_scoped_internal(state, backend, turbo_tasks);
                             return self.add_to_scope_internal_shallow(
                                 id,
@@ -745,6 +1153,9 @@ impl Task {
                     self.add_self_to_new_scope(&mut state, id, backend, turbo_tasks);
                 drop(state);
 
+                if acquired {
+                    self.acquired(None, backend);
+                }
                 if schedule_self {
                     turbo_tasks.schedule(self.id);
                 }
@@ -792,6 +1203,7 @@ impl Task {
                             event: event.take(),
                         };
                         schedule_self = true;
+                        self.trace(|| format!("dirty -> scheduled by active {id}"));
                     } else {
                         scope.add_dirty_task(self.id);
                     }
@@ -879,6 +1291,7 @@ impl Task {
         match state.scopes {
             TaskScopes::Root(root) => {
                 if root != id {
+                    self.trace(|| format!("removed {root} as child of {id}"));
                     if let Some(ScopeChildChangeEffect {
                         notify,
                         active,
@@ -893,23 +1306,50 @@ impl Task {
                             backend.decrease_scope_active(root, turbo_tasks);
                         }
                         if parent {
-                            backend.with_scope(root, |child| {
+                            let released = backend.with_scope(root, |child| {
                                 child.remove_parent(id, backend);
-                            })
+                                child.parents().is_empty()
+                            });
+                            if released {
+                                self.released(Some(root), backend, turbo_tasks);
+                            }
                         }
                     }
                 }
             }
             TaskScopes::Inner(ref mut set, _) => {
                 if set.remove(id) {
+                    self.trace(|| format!("removed from {id}"));
+                    let released = set.is_empty();
                     self.remove_self_from_scope(&mut state, id, backend, turbo_tasks);
                     queue.extend(state.children.iter().copied());
                     drop(state);
+                    if released {
+                        self.released(None, backend, turbo_tasks);
+                    }
                 }
             }
         }
     }
 
+    /// Called when the task has been added to a scope while it wasn't in any.
+    /// `root_scope` is the root scope of the task, if it has one.
+    fn acquired(&self, root_scope: Option<TaskScopeId>, backend: &MemoryBackend) {
+        backend.task_acquired(self.id, root_scope);
+    }
+
+    /// Called when the task has left all scopes. `root_scope` is the root
+    /// scope the task had, if any.
+    fn released(
+        &self,
+        root_scope: Option<TaskScopeId>,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        backend.task_released(self.id, root_scope);
+        turbo_tasks.task_released(self.id);
+    }
+
     fn remove_from_scope_internal(
         &self,
         id: TaskScopeId,
@@ -930,17 +1370,6 @@ impl Task {
         self.remove_from_scope_internal(id, backend, turbo_tasks)
     }
 
-    pub(crate) fn remove_from_scopes(
-        &self,
-        scopes: impl Iterator<Item = TaskScopeId>,
-        backend: &MemoryBackend,
-        turbo_tasks: &dyn TurboTasksBackendApi,
-    ) {
-        for id in scopes {
-            self.remove_from_scope_internal(id, backend, turbo_tasks)
-        }
-    }
-
     pub(crate) fn remove_root_or_initial_scope(
         &self,
         backend: &MemoryBackend,
@@ -950,19 +1379,27 @@ impl Task {
         match state.scopes {
             TaskScopes::Root(root) => {
                 log_scope_update!("removing root scope {root}");
+                self.trace(|| format!("removed root scope {root}"));
                 state.scopes = TaskScopes::default();
 
-                turbo_tasks.schedule_backend_foreground_job(
-                    backend.create_backend_job(Job::RemoveFromScope(state.children.clone(), root)),
-                );
+                turbo_tasks.schedule_backend_foreground_job(backend.create_backend_job(
+                    Job::UpdateScopes(ScopeUpdate::remove(state.children.clone(), vec![root])),
+                ));
+                drop(state);
+                self.released(Some(root), backend, turbo_tasks);
             }
             TaskScopes::Inner(ref mut set, _) => {
                 log_scope_update!("removing initial scope");
                 let initial = backend.initial_scope;
                 if set.remove(initial) {
+                    self.trace(|| "removed from the initial scope".to_string());
+                    let released = set.is_empty();
                     self.remove_self_from_scope(&mut state, initial, backend, turbo_tasks);
                     let children = state.children.iter().copied().collect::<VecDeque<_>>();
                     drop(state);
+                    if released {
+                        self.released(None, backend, turbo_tasks);
+                    }
 
                     if !children.is_empty() {
                         run_remove_from_scope_queue(children, initial, backend, turbo_tasks);
@@ -972,6 +1409,105 @@ impl Task {
         }
     }
 
+    /// Reverts [Task::make_root_scoped_internal]: the task becomes an inner
+    /// task of the scopes that contain its root scope, and its subtree is
+    /// moved into them. The root scope is left empty and unlinked. Returns
+    /// false when the task doesn't own `root_scope`, or when the root scope
+    /// has no parents it could be merged into.
+    pub(crate) fn merge_root_scope(
+        &self,
+        root_scope: TaskScopeId,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> bool {
+        let mut state = self.state.write();
+        if !matches!(state.scopes, TaskScopes::Root(root) if root == root_scope) {
+            return false;
+        }
+        // The number of parents of the task in each scope was moved to the
+        // child counter of the root scope in that scope
+        let scopes = backend
+            .with_scope(root_scope, |scope| scope.parents())
+            .into_iter()
+            .filter_map(|parent| {
+                let count =
+                    backend.with_scope(parent, |scope| scope.state.lock().child_count(root_scope));
+                (count > 0).then_some((parent, count as usize))
+            })
+            .collect::<Vec<_>>();
+        if scopes.is_empty() {
+            return false;
+        }
+        self.trace(|| format!("merged root scope {root_scope} into its parents"));
+        log_scope_update!("merge {root_scope} of {:?} into {scopes:?}", self.ty);
+        let mut list = TaskScopeList::default();
+        for (scope, count) in scopes.iter() {
+            for _ in 0..*count {
+                list.add(*scope);
+            }
+        }
+        state.scopes = TaskScopes::Inner(list, 0);
+
+        // add self to the parent scopes and remove it from the root scope
+        let mut schedule_self = false;
+        for (scope, _) in scopes.iter() {
+            schedule_self |= self.add_self_to_new_scope(&mut state, *scope, backend, turbo_tasks);
+        }
+        self.remove_self_from_scope(&mut state, root_scope, backend, turbo_tasks);
+        let children = state.children.clone();
+        drop(state);
+
+        // Move children from the root scope to the parent scopes, while they are
+        // still reachable through the root scope
+        let parents = scopes.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        let queue = children
+            .into_iter()
+            .map(|child| ScopeUpdate {
+                task: child,
+                depth: 0,
+                add: parents.clone(),
+                remove: vec![root_scope],
+            })
+            .collect();
+        run_update_scopes_queue(queue, backend, turbo_tasks);
+
+        // Unlink the now empty root scope from the parent scopes
+        let mut tasks = HashSet::new();
+        let mut active_counter = 0;
+        for (scope_id, count) in scopes {
+            let effect = backend.with_scope(scope_id, |scope| {
+                scope.state.lock().remove_child_count(root_scope, count)
+            });
+            if let Some(ScopeChildChangeEffect {
+                notify,
+                active,
+                parent,
+            }) = effect
+            {
+                tasks.extend(notify);
+                if active {
+                    active_counter += 1;
+                }
+                if parent {
+                    backend.with_scope(root_scope, |root_scope| {
+                        root_scope.remove_parent(scope_id, backend)
+                    });
+                }
+            }
+        }
+        if !tasks.is_empty() {
+            turbo_tasks.schedule_notify_tasks_set(&tasks);
+        }
+        if active_counter > 0 {
+            backend.decrease_scope_active_by(root_scope, active_counter, turbo_tasks);
+        }
+
+        if schedule_self {
+            turbo_tasks.schedule(self.id);
+        }
+        true
+    }
+
     fn make_root_scoped_internal<'a>(
         &self,
         mut state: RwLockWriteGuard<'a, TaskState>,
@@ -981,11 +1517,13 @@ impl Task {
         if matches!(state.scopes, TaskScopes::Root(_)) {
             return Some(state);
         }
-        let root_scope = backend.create_new_scope(0);
+        let root_scope = backend.create_root_scope(self.id);
+        backend.record_root_scope(root_scope, self.id, turbo_tasks);
+        self.trace(|| format!("became root scoped with {root_scope}"));
         // Set the root scope of the current task
         if let TaskScopes::Inner(set, _) = replace(&mut state.scopes, TaskScopes::Root(root_scope))
         {
-            let scopes = set.into_counts().collect::<Vec<_>>();
+            let scopes = set.into_counts();
             log_scope_update!(
                 "new {root_scope} for {:?} as internal root scope (replacing {scopes:?})",
                 self.ty
@@ -1083,12 +1621,19 @@ impl Task {
 
                 drop(state);
 
-                // Add children to new root scope
-                for child in children.iter() {
-                    backend.with_task(*child, |child| {
-                        child.add_to_scope_internal(root_scope, true, backend, turbo_tasks);
+                // Move children from the old scopes to the new root scope, with a single
+                // traversal of their subtrees
+                let old_scopes = scopes.into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+                let queue = children
+                    .into_iter()
+                    .map(|child| ScopeUpdate {
+                        task: child,
+                        depth: 0,
+                        add: vec![root_scope],
+                        remove: old_scopes.clone(),
                     })
-                }
+                    .collect();
+                run_update_scopes_queue(queue, backend, turbo_tasks);
 
                 // Potentially schedule itself, when root scope is active and task is dirty
                 // I think that will never happen since it should already be scheduled by the
@@ -1096,11 +1641,6 @@ impl Task {
                 if schedule_self {
                     turbo_tasks.schedule(self.id);
                 }
-
-                // Remove children from old scopes
-                turbo_tasks.schedule_backend_foreground_job(backend.create_backend_job(
-                    Job::RemoveFromScopes(children, scopes.into_iter().map(|(id, _)| id).collect()),
-                ));
                 None
             } else {
                 Some(state)
@@ -1117,15 +1657,29 @@ impl Task {
         })
     }
 
+    pub(crate) fn add_dependencies_to_current(deps: impl IntoIterator<Item = TaskDependency>) {
+        DEPENDENCIES_TO_TRACK.with(|list| {
+            let mut list = list.borrow_mut();
+            list.extend(deps);
+        })
+    }
+
     pub(crate) fn execute(&self, tt: &dyn TurboTasksBackendApi) -> NativeTaskFuture {
-        match &self.ty {
-            TaskType::Root(bound_fn) => bound_fn(),
+        let future = match &self.ty {
+            TaskType::Root(bound_fn) => bound_fn.lock()(),
             TaskType::Once(mutex) => {
                 let future = mutex.lock().take().expect("Task can only be executed once");
                 // let task = self.clone();
                 Box::pin(future)
             }
-            TaskType::Native(_, bound_fn) => bound_fn(),
+            TaskType::Native(native_fn, bound_fn) => {
+                if registry::is_function_replaced(*native_fn) {
+                    // The function was bound to the previous implementation
+                    registry::get_function(*native_fn).bind(&self.inputs)()
+                } else {
+                    bound_fn()
+                }
+            }
             TaskType::ResolveNative(ref native_fn) => {
                 let native_fn = *native_fn;
                 let inputs = self.inputs.clone();
@@ -1143,7 +1697,52 @@ impl Task {
                     trait_type, name, inputs, tt,
                 ))
             }
+        };
+        Box::pin(future.instrument(self.execution_span()))
+    }
+
+    /// Returns true when the task executes a blocking function.
+    pub(crate) fn is_blocking(&self) -> bool {
+        match &self.ty {
+            TaskType::Native(native_fn, _) => registry::get_function(*native_fn).blocking,
+            _ => false,
+        }
+    }
+
+    /// Creates the span the execution of the task runs in. Executions of tasks
+    /// that are scheduled by another task are nested in the span of that task.
+    fn execution_span(&self) -> Span {
+        if !tracing::enabled!(Level::INFO) {
+            return Span::none();
+        }
+        let span = tracing::info_span!(
+            "execute",
+            task = tracing::field::Empty,
+            function = tracing::field::Empty,
+            task_id = *self.id,
+            scopes = tracing::field::Empty,
+        );
+        if span.is_disabled() {
+            return span;
         }
+        // Only collect these when the span is recorded, as this runs for every
+        // execution
+        let function = match &self.ty {
+            TaskType::Root(..) => Cow::Borrowed("root"),
+            TaskType::Once(..) => Cow::Borrowed("once"),
+            TaskType::Native(native_fn, _) | TaskType::ResolveNative(native_fn) => {
+                Cow::Borrowed(&*registry::get_function(*native_fn).name)
+            }
+            TaskType::ResolveTrait(trait_type, fn_name) => Cow::Owned(format!(
+                "{}::{fn_name}",
+                registry::get_trait(*trait_type).name
+            )),
+        };
+        let scopes = self.state.read().scopes.iter().collect::<Vec<_>>();
+        span.record("task", tracing::field::display(self.get_description()));
+        span.record("function", tracing::field::display(function));
+        span.record("scopes", tracing::field::debug(scopes));
+        span
     }
 
     /// Get an [Invalidator] that can be used to invalidate the current [Task]
@@ -1152,6 +1751,18 @@ impl Task {
         get_invalidator()
     }
 
+    /// Replaces the function of a root task, which is used from its next
+    /// execution on. Returns false when the task is not a root task.
+    pub(crate) fn replace_root(&self, functor: NativeTaskFn) -> bool {
+        match &self.ty {
+            TaskType::Root(bound_fn) => {
+                *bound_fn.lock() = functor;
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Called by the [Invalidator]. Invalidate the [Task]. When the task is
     /// active it will be scheduled for execution.
     pub(crate) fn invalidate(
@@ -1159,7 +1770,20 @@ impl Task {
         backend: &MemoryBackend,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) {
-        self.make_dirty(backend, turbo_tasks)
+        self.make_dirty(backend, turbo_tasks);
+    }
+
+    /// Returns `true` when the task has finished an execution before.
+    pub(crate) fn has_executed(&self) -> bool {
+        let state = self.state.read();
+        !matches!(state.output.content, OutputContent::Empty)
+    }
+
+    /// Read access to the output cell. Reads of the output can be tracked
+    /// with a read lock.
+    pub(crate) fn with_output<T>(&self, func: impl FnOnce(&Output) -> T) -> T {
+        let state = self.state.read();
+        func(&state.output)
     }
 
     /// Access to the output cell.
@@ -1170,8 +1794,8 @@ impl Task {
 
     /// Access to a cell.
     pub(crate) fn with_cell_mut<T>(&self, index: CellId, func: impl FnOnce(&mut Cell) -> T) -> T {
-        let mut state = self.state.write();
-        let list = state.cells.entry(index.type_id).or_default();
+        let mut cells = self.cells.write();
+        let list = cells.entry(index.type_id).or_default();
         let i = index.index as usize;
         if list.len() <= i {
             list.resize_with(i + 1, Default::default);
@@ -1179,10 +1803,53 @@ impl Task {
         func(&mut list[i])
     }
 
+    /// Assigns multiple cells while holding the lock, so readers see either
+    /// none or all of the new contents.
+    pub(crate) fn assign_cells(
+        &self,
+        updates: Vec<(CellId, CellContent)>,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        let mut cells = self.cells.write();
+        for (index, content) in updates {
+            let list = cells.entry(index.type_id).or_default();
+            let i = index.index as usize;
+            if list.len() <= i {
+                list.resize_with(i + 1, Default::default);
+            }
+            list[i].assign(content, turbo_tasks);
+        }
+    }
+
+    /// Removes the cells after the ones the last execution created, as
+    /// executions reuse the cells of previous executions by index. Readers of
+    /// removed cells are notified and will read an empty cell if they still
+    /// refer to it.
+    pub(crate) fn compact_cells(
+        &self,
+        cell_counts: &CellCounts,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        let mut dependent_tasks = HashSet::new();
+        self.cells.write().retain(|type_id, list| {
+            let count = cell_counts.get(type_id).copied().unwrap_or_default() as usize;
+            if list.len() > count {
+                for cell in list.drain(count..) {
+                    dependent_tasks.extend(cell.dependent_tasks);
+                }
+                list.shrink_to_fit();
+            }
+            !list.is_empty()
+        });
+        if !dependent_tasks.is_empty() {
+            turbo_tasks.schedule_notify_tasks_set(&dependent_tasks);
+        }
+    }
+
     /// Access to a cell.
     pub(crate) fn with_cell<T>(&self, index: CellId, func: impl FnOnce(&Cell) -> T) -> T {
-        let state = self.state.read();
-        if let Some(list) = state.cells.get(&index.type_id) {
+        let cells = self.cells.read();
+        if let Some(list) = cells.get(&index.type_id) {
             if let Some(cell) = list.get(index.index as usize) {
                 return func(cell);
             }
@@ -1190,10 +1857,47 @@ impl Task {
         func(&Default::default())
     }
 
+    /// Reads a cell that an execution of the task has assigned. The `reader`
+    /// is tracked even when the cell doesn't exist, so it's notified when the
+    /// cell is created.
+    pub(crate) fn read_cell(
+        &self,
+        index: CellId,
+        reader: Option<TaskId>,
+    ) -> Result<CellContent, CellNotFound> {
+        let not_found = |list: Option<&Vec<Cell>>| CellNotFound {
+            task: self.id,
+            index,
+            created: list.map_or(0, |list| {
+                list.iter().filter(|cell| cell.is_assigned()).count()
+            }),
+        };
+        let Some(reader) = reader else {
+            let cells = self.cells.read();
+            let list = cells.get(&index.type_id);
+            return match list.and_then(|list| list.get(index.index as usize)) {
+                Some(cell) if cell.is_assigned() => Ok(cell.read_content_untracked()),
+                _ => Err(not_found(list)),
+            };
+        };
+        let mut cells = self.cells.write();
+        let list = cells.entry(index.type_id).or_default();
+        let i = index.index as usize;
+        if list.len() <= i {
+            list.resize_with(i + 1, Default::default);
+        }
+        let cell = &mut list[i];
+        if cell.is_assigned() {
+            Ok(cell.read_content(reader))
+        } else {
+            cell.track_read(reader);
+            Err(not_found(Some(list)))
+        }
+    }
+
     /// For testing purposes
     pub fn reset_executions(&self) {
-        let mut state = self.state.write();
-        state.stats.reset_executions()
+        self.stats.lock().reset_executions()
     }
 
     pub fn is_pending(&self) -> bool {
@@ -1201,20 +1905,100 @@ impl Task {
         !matches!(state.state_type, TaskStateType::Done { .. })
     }
 
+    /// Returns the Vc the output links to, if it has been assigned one.
+    pub(crate) fn output_link(&self) -> Option<RawVc> {
+        match self.state.read().output.content {
+            OutputContent::Link(vc) => Some(vc),
+            _ => None,
+        }
+    }
+
+    /// Returns true when the last completed execution read the dependency.
+    /// Always false while the task is not done.
+    pub(crate) fn has_dependency(&self, dependency: &TaskDependency) -> bool {
+        match &self.state.read().state_type {
+            Done { dependencies } => dependencies.contains(dependency),
+            _ => false,
+        }
+    }
+
+    /// Returns the scopes the task is in.
+    pub(crate) fn scopes(&self) -> Vec<TaskScopeId> {
+        self.state.read().scopes.iter().collect()
+    }
+
+    /// Returns true when the task is directly in the scope, not only through
+    /// nested scopes.
+    pub fn is_in_scope(&self, scope: TaskScopeId) -> bool {
+        self.state.read().scopes.contains(scope)
+    }
+
+    /// Returns the root scope of the task, when it's root scoped.
+    pub(crate) fn own_root_scope(&self) -> Option<TaskScopeId> {
+        match self.state.read().scopes {
+            TaskScopes::Root(root) => Some(root),
+            TaskScopes::Inner(..) => None,
+        }
+    }
+
+    /// Returns the scopes of the task with their counts.
+    pub(crate) fn scope_counts(&self) -> Vec<(TaskScopeId, isize)> {
+        self.state.read().scopes.counts()
+    }
+
+    /// Returns the tasks called by the last execution.
+    pub(crate) fn children(&self) -> Vec<TaskId> {
+        self.state.read().children.iter().copied().collect()
+    }
+
+    /// Returns the tasks whose last execution called this task.
+    pub fn parents(&self) -> Vec<TaskId> {
+        self.parents.lock().iter().copied().collect()
+    }
+
+    /// Returns the function of a native task.
+    pub fn function_id(&self) -> Option<FunctionId> {
+        match self.ty {
+            TaskType::Native(function, _) => Some(function),
+            _ => None,
+        }
+    }
+
+    /// Returns true when the task is done and in an active scope, so it's
+    /// scheduled as soon as it's invalidated.
+    pub(crate) fn is_active_and_done(&self, backend: &MemoryBackend) -> bool {
+        let state = self.state.read();
+        matches!(state.state_type, Done { .. })
+            && state
+                .scopes
+                .iter()
+                .any(|scope| backend.with_scope(scope, |scope| scope.state.lock().is_active()))
+    }
+
+    /// Returns true when the task needs to execute (again), including when
+    /// it's scheduled or has been invalidated while in progress.
+    pub fn is_dirty(&self) -> bool {
+        let state = self.state.read();
+        matches!(
+            state.state_type,
+            Dirty { .. } | Scheduled { .. } | InProgressDirty { .. }
+        )
+    }
+
     pub fn reset_stats(&self) {
-        let mut state = self.state.write();
-        state.stats.reset();
+        self.stats.lock().reset();
     }
 
     pub fn get_stats_info(&self, backend: &MemoryBackend) -> TaskStatsInfo {
         let state = self.state.read();
 
-        let (total_duration, last_duration, executions) = match &state.stats {
-            TaskStats::Essential(stats) => (None, stats.last_duration(), None),
+        let (total_duration, last_duration, executions, durations) = match &*self.stats.lock() {
+            TaskStats::Essential(stats) => (None, stats.last_duration(), None, None),
             TaskStats::Full(stats) => (
                 Some(stats.total_duration()),
                 stats.last_duration(),
                 Some(stats.executions()),
+                Some(stats.durations().clone()),
             ),
         };
 
@@ -1222,6 +2006,9 @@ impl Task {
             total_duration,
             last_duration,
             executions,
+            durations,
+            output_updates: state.output.generation(),
+            output_reads: state.output.reads(),
             root_scoped: matches!(state.scopes, TaskScopes::Root(_)),
             child_scopes: match state.scopes {
                 TaskScopes::Root(_) => 1,
@@ -1235,12 +2022,16 @@ impl Task {
     }
 
     pub fn get_stats_type(self: &Task) -> stats::TaskType {
-        match &self.ty {
+        let ty = match &self.ty {
             TaskType::Root(_) => stats::TaskType::Root(self.id),
             TaskType::Once(_) => stats::TaskType::Once(self.id),
             TaskType::Native(f, _) => stats::TaskType::Native(*f),
             TaskType::ResolveNative(f) => stats::TaskType::ResolveNative(*f),
             TaskType::ResolveTrait(t, n) => stats::TaskType::ResolveTrait(*t, n.to_string()),
+        };
+        match self.get_label() {
+            Some(label) => stats::TaskType::Labeled(Box::new(ty), label),
+            None => ty,
         }
     }
 
@@ -1276,6 +2067,7 @@ impl Task {
         StatsReferences {
             tasks: refs,
             scopes: scope_refs,
+            parents: self.parents(),
         }
     }
 
@@ -1313,39 +2105,75 @@ impl Task {
         backend: &MemoryBackend,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) {
+        self.connect_children(&[child_id], backend, turbo_tasks)
+    }
+
+    /// Connects many children with a single lock of the state. Children that
+    /// are already connected are skipped, the new ones are added to each scope
+    /// of the task with a single queue.
+    pub(crate) fn connect_children(
+        &self,
+        children: &[TaskId],
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        // Re-executions mostly connect the same children again, which only
+        // needs the read lock
+        {
+            let state = self.state.read();
+            if children.iter().all(|child| state.children.contains(child)) {
+                return;
+            }
+        }
         let mut state = self.state.write();
-        if state.children.insert(child_id) {
-            let scopes = state.scopes.clone();
-            drop(state);
+        let new_children = children
+            .iter()
+            .copied()
+            .filter(|child| state.children.insert(*child))
+            .collect::<Vec<_>>();
+        if new_children.is_empty() {
+            return;
+        }
+        let scopes = state.scopes.clone();
+        drop(state);
+        for child in new_children.iter() {
+            backend.with_task(*child, |child| child.parents.lock().add(self.id));
+        }
 
-            backend.with_task(child_id, |child| {
-                for scope in scopes.iter() {
-                    #[cfg(not(feature = "report_expensive"))]
-                    {
-                        child.add_to_scope_internal(scope, false, backend, turbo_tasks);
-                    }
-                    #[cfg(feature = "report_expensive")]
-                    {
-                        use std::time::Instant;
-
-                        use turbo_tasks::util::FormatDuration;
-
-                        let start = Instant::now();
-                        child.add_to_scope_internal(scope, false, backend, turbo_tasks);
-                        let elapsed = start.elapsed();
-                        if elapsed.as_millis() >= 10 {
-                            println!(
-                                "add_to_scope {scope} took {}: {:?}",
-                                FormatDuration(elapsed),
-                                child
-                            );
-                        }
-                    }
-                }
+        for scope in scopes.iter() {
+            let queue = new_children.iter().map(|child| (*child, 0)).collect();
+            backend.measure_slow_op(SlowOperation::AddToScope, self.id, || {
+                run_add_to_scope_queue(queue, scope, false, backend, turbo_tasks)
             });
         }
     }
 
+    /// Gives the task its own root scope, unless it already has one.
+    pub(crate) fn make_root_scoped(
+        &self,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        let state = self.state.write();
+        drop(self.ensure_root_scoped(state, backend, turbo_tasks));
+    }
+
+    /// Returns the root scope of the task, after giving the task its own root
+    /// scope if needed.
+    pub(crate) fn root_scope(
+        &self,
+        backend: &MemoryBackend,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> TaskScopeId {
+        let state = self.state.write();
+        let state = self.ensure_root_scoped(state, backend, turbo_tasks);
+        if let TaskScopes::Root(root) = state.scopes {
+            root
+        } else {
+            unreachable!()
+        }
+    }
+
     fn ensure_root_scoped<'a>(
         &'a self,
         mut state: RwLockWriteGuard<'a, TaskState>,
@@ -1353,26 +2181,9 @@ impl Task {
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> RwLockWriteGuard<'a, TaskState> {
         while !state.scopes.is_root() {
-            #[cfg(not(feature = "report_expensive"))]
-            let result = self.make_root_scoped_internal(state, backend, turbo_tasks);
-            #[cfg(feature = "report_expensive")]
-            let result = {
-                use std::time::Instant;
-
-                use turbo_tasks::util::FormatDuration;
-
-                let start = Instant::now();
-                let result = self.make_root_scoped_internal(state, backend, turbo_tasks);
-                let elapsed = start.elapsed();
-                if elapsed.as_millis() >= 10 {
-                    println!(
-                        "make_root_scoped took {}: {:?}",
-                        FormatDuration(elapsed),
-                        self
-                    );
-                }
-                result
-            };
+            let result = backend.measure_slow_op(SlowOperation::MakeRootScoped, self.id, || {
+                self.make_root_scoped_internal(state, backend, turbo_tasks)
+            });
             if let Some(s) = result {
                 state = s;
                 break;
@@ -1385,7 +2196,7 @@ impl Task {
         state
     }
 
-    pub(crate) fn get_or_wait_output<T, F: FnOnce(&mut Output) -> Result<T>>(
+    pub(crate) fn get_or_wait_output<T, F: FnOnce(&Output) -> Result<T>>(
         &self,
         strongly_consistent: bool,
         func: F,
@@ -1393,6 +2204,14 @@ impl Task {
         backend: &MemoryBackend,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> Result<Result<T, EventListener>> {
+        if !strongly_consistent {
+            // Fast path for the common case, concurrent readers of a done task
+            // only share the lock
+            let state = self.state.read();
+            if matches!(state.state_type, Done { .. }) && !state.output.is_empty() {
+                return Ok(Ok(func(&state.output)?));
+            }
+        }
         let mut state = self.state.write();
         if strongly_consistent {
             state = self.ensure_root_scoped(state, backend, turbo_tasks);
@@ -1403,8 +2222,11 @@ impl Task {
                 return Ok(Err(listener));
             }
             if let TaskScopes::Root(root) = state.scopes {
+                if let Some(error) = backend.scope_failure(root) {
+                    return Err(error.into());
+                }
                 if let Some(listener) = backend.with_scope(root, |scope| {
-                    if let Some(listener) = scope.has_unfinished_tasks() {
+                    if let Some(listener) = scope.wait_for_unfinished_tasks() {
                         return Some(listener);
                     }
                     None
@@ -1416,16 +2238,43 @@ impl Task {
             }
         }
         match state.state_type {
+            Done { .. } if state.output.is_empty() => {
+                let waits = self.function_id().map_or(false, |function| {
+                    backend.waits_for_first_execution(function)
+                });
+                drop(state);
+                // Executes the task again and waits for it like a dirty task. Frozen tasks
+                // ignore that, so they fail like tasks that don't wait.
+                if !waits || !self.make_dirty(backend, turbo_tasks) {
+                    return Err(OutputNotComputed { task: self.id }.into());
+                }
+                self.get_or_wait_output(strongly_consistent, func, note, backend, turbo_tasks)
+            }
             Done { .. } => {
-                let result = func(&mut state.output)?;
+                let result = func(&state.output)?;
                 drop(state);
 
                 Ok(Ok(result))
             }
-            Dirty { ref event }
-            | Scheduled { ref event }
-            | InProgress { ref event }
-            | InProgressDirty { ref event } => {
+            Dirty { ref mut event } => {
+                // The task is dirty in inactive scopes only, so nobody would schedule it. The
+                // reader need the fresh value, so we schedule it now instead of waiting for
+                // the scopes to become active.
+                let listener = event.listen_with_note(note);
+                state.state_type = Scheduled {
+                    event: event.take(),
+                };
+                self.trace(|| "dirty -> scheduled by a read".to_string());
+                for scope in state.scopes.iter() {
+                    backend.with_scope(scope, |scope| {
+                        scope.state.lock().remove_dirty_task(self.id);
+                    });
+                }
+                drop(state);
+                turbo_tasks.schedule(self.id);
+                Ok(Err(listener))
+            }
+            Scheduled { ref event } | InProgress { ref event } | InProgressDirty { ref event } => {
                 let listener = event.listen_with_note(note);
                 drop(state);
                 Ok(Err(listener))
@@ -1450,7 +2299,7 @@ impl Task {
         }
         if let TaskScopes::Root(scope_id) = state.scopes {
             backend.with_scope(scope_id, |scope| {
-                if let Some(l) = scope.has_unfinished_tasks() {
+                if let Some(l) = scope.wait_for_unfinished_tasks() {
                     return Ok(Err(l));
                 }
                 let set = scope.read_collectibles(scope_id, trait_id, reader, backend);
@@ -1513,9 +2362,15 @@ impl Task {
 }
 
 /// Heuristic to decide when to split off work in `run_add_to_scope_queue` and
-/// `run_remove_from_scope_queue`.
+/// `run_remove_from_scope_queue`. Work is only split off when there are idle
+/// workers to pick it up, otherwise the overhead of the additional jobs isn't
+/// worth it.
 const SPLIT_OFF_QUEUE_AT: usize = 100;
 
+fn should_split_off_queue(len: usize, turbo_tasks: &dyn TurboTasksBackendApi) -> bool {
+    len > SPLIT_OFF_QUEUE_AT && turbo_tasks.idle_workers() > 0
+}
+
 /// Adds a list of tasks and their children to a scope, recursively.
 pub fn run_add_to_scope_queue(
     mut queue: VecDeque<(TaskId, usize)>,
@@ -1535,7 +2390,7 @@ pub fn run_add_to_scope_queue(
                 &mut queue,
             );
         });
-        if queue.len() > SPLIT_OFF_QUEUE_AT {
+        if should_split_off_queue(queue.len(), turbo_tasks) {
             let split_off_queue = queue.split_off(SPLIT_OFF_QUEUE_AT);
             turbo_tasks.schedule_backend_foreground_job(backend.create_backend_job(
                 Job::AddToScopeQueue(split_off_queue, id, is_optimization_scope),
@@ -1555,7 +2410,7 @@ pub fn run_remove_from_scope_queue(
         backend.with_task(child, |child| {
             child.remove_from_scope_internal_shallow(id, backend, turbo_tasks, &mut queue);
         });
-        if queue.len() > SPLIT_OFF_QUEUE_AT {
+        if should_split_off_queue(queue.len(), turbo_tasks) {
             let split_off_queue = queue.split_off(SPLIT_OFF_QUEUE_AT);
 
             turbo_tasks.schedule_backend_foreground_job(
@@ -1565,6 +2420,99 @@ pub fn run_remove_from_scope_queue(
     }
 }
 
+/// A task that is added to and removed from scopes by
+/// [run_update_scopes_queue].
+pub struct ScopeUpdate {
+    task: TaskId,
+    depth: usize,
+    add: Vec<TaskScopeId>,
+    remove: Vec<TaskScopeId>,
+}
+
+impl ScopeUpdate {
+    /// Removes the tasks and their children from the scopes.
+    fn remove(tasks: TaskChildren, scopes: Vec<TaskScopeId>) -> VecDeque<Self> {
+        tasks
+            .into_iter()
+            .map(|task| ScopeUpdate {
+                task,
+                depth: 0,
+                add: Vec::new(),
+                remove: scopes.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Moves a list of tasks and their children between scopes, recursively.
+/// Each subtree is traversed once for all scopes, instead of once per scope
+/// that is added or removed. Scopes are added as optimization scopes, and
+/// before the old ones are removed.
+pub fn run_update_scopes_queue(
+    mut queue: VecDeque<ScopeUpdate>,
+    backend: &MemoryBackend,
+    turbo_tasks: &dyn TurboTasksBackendApi,
+) {
+    let mut added = VecDeque::new();
+    let mut removed = VecDeque::new();
+    while let Some(ScopeUpdate {
+        task,
+        depth,
+        add,
+        remove,
+    }) = queue.pop_front()
+    {
+        backend.scope_update_visited();
+        // The scopes that changed for the task, grouped by the children they
+        // need to be propagated to. There is a single group, unless the
+        // children changed in between.
+        let mut groups: Vec<(Vec<TaskId>, Vec<TaskScopeId>, Vec<TaskScopeId>)> = Vec::new();
+        backend.with_task(task, |task| {
+            for id in add {
+                task.add_to_scope_internal_shallow(
+                    id,
+                    true,
+                    depth,
+                    backend,
+                    turbo_tasks,
+                    &mut added,
+                );
+                if !added.is_empty() {
+                    let children = added.drain(..).map(|(child, _)| child).collect();
+                    match groups.iter_mut().find(|(list, ..)| *list == children) {
+                        Some((_, add, _)) => add.push(id),
+                        None => groups.push((children, vec![id], Vec::new())),
+                    }
+                }
+            }
+            for id in remove {
+                task.remove_from_scope_internal_shallow(id, backend, turbo_tasks, &mut removed);
+                if !removed.is_empty() {
+                    let children = removed.drain(..).collect();
+                    match groups.iter_mut().find(|(list, ..)| *list == children) {
+                        Some((_, _, remove)) => remove.push(id),
+                        None => groups.push((children, Vec::new(), vec![id])),
+                    }
+                }
+            }
+        });
+        for (children, add, remove) in groups {
+            queue.extend(children.into_iter().map(|child| ScopeUpdate {
+                task: child,
+                depth: depth + 1,
+                add: add.clone(),
+                remove: remove.clone(),
+            }));
+        }
+        if should_split_off_queue(queue.len(), turbo_tasks) {
+            let split_off_queue = queue.split_off(SPLIT_OFF_QUEUE_AT);
+            turbo_tasks.schedule_backend_foreground_job(
+                backend.create_backend_job(Job::UpdateScopes(split_off_queue)),
+            );
+        }
+    }
+}
+
 impl Display for Task {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let state = self.state.read();
@@ -1595,6 +2543,12 @@ pub struct TaskStatsInfo {
     pub total_duration: Option<Duration>,
     pub last_duration: Duration,
     pub executions: Option<u32>,
+    /// Only recorded with full stats.
+    pub durations: Option<DurationHistogram>,
+    /// The number of updates of the output, including the first one.
+    pub output_updates: u32,
+    /// The number of tracked reads of the output by other tasks.
+    pub output_reads: u32,
     pub root_scoped: bool,
     pub child_scopes: usize,
     pub active: bool,
@@ -2,6 +2,8 @@ use std::time::Duration;
 
 use turbo_tasks::{small_duration::SmallDuration, StatsType};
 
+use crate::DurationHistogram;
+
 /// Keeps track of the number of times a task has been executed, and its
 /// duration.
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -41,6 +43,7 @@ impl TaskStats {
             Self::Full(stats) => {
                 stats.total_duration += duration.into();
                 stats.last_duration = duration.into();
+                stats.durations.record(duration);
             }
             Self::Essential(stats) => {
                 stats.last_duration = duration.into();
@@ -56,6 +59,7 @@ impl TaskStats {
                 stats.executions = 0;
                 stats.total_duration = Duration::ZERO;
                 stats.last_duration = Duration::ZERO;
+                stats.durations = DurationHistogram::default();
             }
             Self::Essential(stats) => {
                 stats.last_duration = SmallDuration::MIN;
@@ -96,6 +100,8 @@ pub struct TaskStatsFull {
     last_duration: Duration,
     /// The total duration of the task.
     total_duration: Duration,
+    /// The durations of all executions of the task.
+    durations: DurationHistogram,
     /// The last execution of the task relative to the start of the program,
     /// with a precision of 1 millisecond.
     last_execution_relative_to_start: SmallDuration<1_000_000>,
@@ -117,6 +123,11 @@ impl TaskStatsFull {
         self.total_duration
     }
 
+    /// Returns the histogram of the durations of all executions of the task.
+    pub fn durations(&self) -> &DurationHistogram {
+        &self.durations
+    }
+
     /// Returns the last execution of the task relative to the start of the
     /// program.
     #[allow(dead_code)] // NOTE(alexkirsz) This will be useful for GC.
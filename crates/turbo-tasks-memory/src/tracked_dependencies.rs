@@ -0,0 +1,75 @@
+use std::{cell::RefCell, collections::HashSet, hash::BuildHasherDefault};
+
+use rustc_hash::FxHasher;
+use smallvec::SmallVec;
+
+use crate::task::{TaskDependencies, TaskDependency};
+
+/// The number of dependencies an execution tracks without allocating.
+const INLINE_DEPENDENCIES: usize = 4;
+
+/// Sets that grew larger than this are dropped instead of being reused, so
+/// a single huge execution doesn't keep its memory alive.
+const MAX_SPARE_CAPACITY: usize = 1024;
+
+type DependencySet = HashSet<TaskDependency, BuildHasherDefault<FxHasher>>;
+
+thread_local! {
+    /// A cleared set of an earlier execution on this thread.
+    static SPARE_SET: RefCell<Option<DependencySet>> = RefCell::new(None);
+}
+
+/// The Vcs and scopes read by a task execution. Executions that read a few of
+/// them don't allocate, larger ones reuse the set of an earlier execution on
+/// the same thread.
+#[derive(Default)]
+pub struct TrackedDependencies {
+    inline: SmallVec<[TaskDependency; INLINE_DEPENDENCIES]>,
+    set: Option<DependencySet>,
+}
+
+impl TrackedDependencies {
+    pub fn insert(&mut self, dep: TaskDependency) {
+        if let Some(set) = &mut self.set {
+            set.insert(dep);
+            return;
+        }
+        if self.inline.contains(&dep) {
+            return;
+        }
+        if self.inline.len() < INLINE_DEPENDENCIES {
+            self.inline.push(dep);
+            return;
+        }
+        let mut set = SPARE_SET
+            .with(|spare| spare.borrow_mut().take())
+            .unwrap_or_default();
+        set.extend(self.inline.drain(..));
+        set.insert(dep);
+        self.set = Some(set);
+    }
+
+    /// Moves the dependencies into a set of the exact size, to be stored in
+    /// the task. A reused set is handed back to the thread.
+    pub fn into_set(self) -> TaskDependencies {
+        let Some(mut set) = self.set else {
+            let mut deps = TaskDependencies::with_capacity(self.inline.len());
+            deps.extend(self.inline);
+            return deps;
+        };
+        let mut deps = TaskDependencies::with_capacity(set.len());
+        deps.extend(set.drain());
+        if set.capacity() <= MAX_SPARE_CAPACITY {
+            SPARE_SET.with(|spare| *spare.borrow_mut() = Some(set));
+        }
+        deps
+    }
+}
+
+impl Extend<TaskDependency> for TrackedDependencies {
+    fn extend<T: IntoIterator<Item = TaskDependency>>(&mut self, iter: T) {
+        for dep in iter {
+            self.insert(dep);
+        }
+    }
+}
@@ -1,4 +1,6 @@
 pub mod graph;
+#[cfg(feature = "viz_server")]
+pub mod server;
 pub mod table;
 
 use std::{
@@ -0,0 +1,231 @@
+use std::{
+    convert::Infallible, fmt::Write, future::Future, net::SocketAddr, pin::Pin, sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode, Uri,
+};
+use turbo_tasks::TurboTasks;
+
+use super::{escape_html, graph, table};
+use crate::{
+    stats::{ReferenceType, Stats},
+    MemoryBackend,
+};
+
+/// The number of functions with the most queued tasks shown on the overview.
+const TOP_QUEUED_FUNCTIONS: usize = 10;
+
+/// An HTTP server with a live view of the task graph, the scopes, the
+/// scheduler and the stats per function. Pages reload themselves, so the
+/// view follows the incremental build. Everything is rendered from the
+/// introspection APIs of the [MemoryBackend] on every request, on a blocking
+/// thread, as it walks all tasks.
+pub struct VizServer {
+    pub addr: SocketAddr,
+    pub future: Pin<Box<dyn Future<Output = Result<()>> + Send + 'static>>,
+}
+
+impl VizServer {
+    /// Binds the server to `addr`. Pages reload every `refresh` interval.
+    /// The server runs while `future` is polled.
+    pub fn listen(
+        turbo_tasks: Arc<TurboTasks<MemoryBackend>>,
+        addr: SocketAddr,
+        refresh: Duration,
+    ) -> Result<Self> {
+        let make_svc = make_service_fn(move |_| {
+            let tt = turbo_tasks.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let tt = tt.clone();
+                    let uri = request.uri().clone();
+                    async move {
+                        let response =
+                            tokio::task::spawn_blocking(move || handle(&tt, &uri, refresh))
+                                .await
+                                .unwrap_or_else(|err| {
+                                    let mut response = Response::new(Body::from(err.to_string()));
+                                    *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+                                    response
+                                });
+                        Ok::<_, Infallible>(response)
+                    }
+                }))
+            }
+        });
+        let server = Server::try_bind(&addr)
+            .context("Not able to start viz server")?
+            .serve(make_svc);
+        Ok(Self {
+            addr: server.local_addr(),
+            future: Box::pin(async move {
+                server.await?;
+                Ok(())
+            }),
+        })
+    }
+}
+
+fn handle(tt: &TurboTasks<MemoryBackend>, uri: &Uri, refresh: Duration) -> Response<Body> {
+    let active_only = uri.query().map_or(false, |query| {
+        query.split('&').any(|param| param == "active")
+    });
+    let html = match uri.path() {
+        "/" => page("overview", refresh, &overview(tt)),
+        "/graph" => with_refresh(
+            graph::wrap_html(&graph::visualize_stats_tree(
+                stats(tt, false).treeify(ReferenceType::Dependency),
+                ReferenceType::Dependency,
+                tt.stats_type(),
+            )),
+            refresh,
+        ),
+        "/call-graph" => with_refresh(
+            graph::wrap_html(&graph::visualize_stats_tree(
+                stats(tt, false).treeify(ReferenceType::Child),
+                ReferenceType::Child,
+                tt.stats_type(),
+            )),
+            refresh,
+        ),
+        "/table" => with_refresh(
+            table::wrap_html(&table::create_table(
+                stats(tt, active_only).treeify(ReferenceType::Dependency),
+                tt.stats_type(),
+            )),
+            refresh,
+        ),
+        "/scopes" => page("scopes", refresh, &scopes(tt)),
+        _ => {
+            let mut response = Response::new(Body::from("not found"));
+            *response.status_mut() = StatusCode::NOT_FOUND;
+            return response;
+        }
+    };
+    let mut response = Response::new(Body::from(html));
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, "text/html; charset=utf-8".parse().unwrap());
+    response
+}
+
+/// Collects the stats of all tasks, or of the active ones only.
+fn stats(tt: &TurboTasks<MemoryBackend>, active_only: bool) -> Stats {
+    let mut stats = Stats::new();
+    let backend = tt.backend();
+    backend.with_all_cached_tasks(|task| {
+        stats.add_id_conditional(backend, task, |_, info| !active_only || info.active);
+    });
+    stats
+}
+
+/// Makes a page reload itself after the interval.
+fn with_refresh(html: String, refresh: Duration) -> String {
+    html.replacen(
+        "<head>",
+        &format!(
+            r#"<head><meta http-equiv="refresh" content="{}">"#,
+            refresh.as_secs().max(1)
+        ),
+        1,
+    )
+}
+
+fn page(title: &str, refresh: Duration, body: &str) -> String {
+    with_refresh(
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>turbo-tasks {title}</title>
+<style>body{{font-family:monospace;}}td,th{{padding:2px 10px;text-align:left;}}</style>
+</head>
+<body>
+<p><a href="/">overview</a> | <a href="/graph">graph</a> | <a href="/call-graph">call graph</a> | <a href="/table">functions</a> | <a href="/table?active">active functions</a> | <a href="/scopes">scopes</a></p>
+{body}
+</body>
+</html>"#
+        ),
+        refresh,
+    )
+}
+
+fn overview(tt: &TurboTasks<MemoryBackend>) -> String {
+    let backend = tt.backend();
+    let mut tasks = 0;
+    backend.with_all_cached_tasks(|_| tasks += 1);
+    let memo = backend.memo_table_stats();
+    let mut out = String::new();
+    writeln!(out, "<h2>backend</h2><pre>").unwrap();
+    writeln!(out, "cached tasks: {tasks}").unwrap();
+    writeln!(out, "scopes: {}", backend.scope_count()).unwrap();
+    writeln!(out, "root scopes: {}", backend.root_scopes().len()).unwrap();
+    writeln!(
+        out,
+        "pruned dependencies: {}",
+        backend.pruned_dependencies()
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "memo table: {} entries, {} hits, {} misses, {} evictions",
+        memo.entries, memo.hits, memo.misses, memo.evictions
+    )
+    .unwrap();
+    writeln!(out, "</pre><h2>scheduler</h2><pre>").unwrap();
+    write!(
+        out,
+        "{}",
+        escape_html(&tt.scheduler_snapshot(TOP_QUEUED_FUNCTIONS).to_string())
+    )
+    .unwrap();
+    writeln!(out, "</pre>").unwrap();
+    out
+}
+
+fn scopes(tt: &TurboTasks<MemoryBackend>) -> String {
+    let backend = tt.backend();
+    let mut root_scopes = backend.root_scopes();
+    root_scopes.sort_by_key(|(scope, _)| **scope);
+    let mut out = String::new();
+    out += "<table><thead><tr>";
+    for title in [
+        "scope",
+        "task",
+        "tasks",
+        "unfinished tasks",
+        "active",
+        "child scopes",
+        "parent scopes",
+    ] {
+        write!(out, "<th>{title}</th>").unwrap();
+    }
+    out += "</tr></thead><tbody>";
+    for (scope, task) in root_scopes {
+        let description = backend.with_task(task, |task| task.get_description());
+        let (counts, active) = backend.with_scope(scope, |scope| {
+            (scope.audit_counts(), scope.state.lock().is_active())
+        });
+        out += "<tr>";
+        for cell in [
+            (*scope).to_string(),
+            escape_html(&description),
+            counts.tasks.to_string(),
+            counts.unfinished_tasks.max(0).to_string(),
+            active.to_string(),
+            counts.children.len().to_string(),
+            counts.parents.len().to_string(),
+        ] {
+            write!(out, "<td>{cell}</td>").unwrap();
+        }
+        out += "</tr>";
+    }
+    out += "</tbody></table>";
+    out
+}
@@ -0,0 +1,225 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt::{self, Display},
+    fs,
+    io::ErrorKind,
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use turbo_tasks::{backend::PersistentTaskType, TaskId, TaskInput, TurboTasksBackendApi};
+
+use crate::{
+    execution_recording::StableIdMapping, memory_backend::Job, stable_task_ids::StableTaskIds,
+    task::TaskStatsInfo, MemoryBackend,
+};
+
+/// The number of tasks of a [WarmupProfile] that are created and scheduled
+/// per background job. Background jobs only start when no tasks are
+/// scheduled, so the warmup yields to other work between batches.
+const WARMUP_BATCH_SIZE: usize = 16;
+
+/// How the tasks of a [WarmupProfile] are chosen, see
+/// [crate::MemoryBackend::warmup_profile].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WarmupOrder {
+    /// The tasks that have been executed most often. Executions are only
+    /// counted with [turbo_tasks::StatsType::Full], otherwise every executed
+    /// task counts once.
+    #[default]
+    Executions,
+    /// The tasks with the longest total execution time. Without
+    /// [turbo_tasks::StatsType::Full] only the last execution counts.
+    TotalDuration,
+}
+
+impl WarmupOrder {
+    /// Returns how hot the task is, zero when it hasn't been executed.
+    pub(crate) fn score(self, info: &TaskStatsInfo) -> u128 {
+        match self {
+            WarmupOrder::Executions => match info.executions {
+                Some(executions) => executions as u128,
+                None => (!info.last_duration.is_zero()) as u128,
+            },
+            WarmupOrder::TotalDuration => info
+                .total_duration
+                .unwrap_or(info.last_duration)
+                .as_micros(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct WarmupTask {
+    /// The description of the task, to make profiles readable.
+    description: String,
+    /// The [PersistentTaskType] of the task. Tasks in the inputs are referred
+    /// to by their [crate::StableTaskId]s.
+    task: serde_json::Value,
+}
+
+/// A profile of the tasks that have been hottest in a run.
+///
+/// It can be stored between runs and loaded with
+/// [crate::MemoryBackend::with_warmup_profile], which creates these tasks on
+/// startup and schedules them at background priority, so the first request
+/// finds them executed already.
+///
+/// The profile is stored as one JSON line per task with its function and
+/// inputs. Tasks that are inputs of other tasks come before them.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WarmupProfile {
+    tasks: Vec<WarmupTask>,
+}
+
+impl WarmupProfile {
+    /// Creates a profile of the `hot` tasks and the tasks in their inputs.
+    /// Tasks that can't be serialized, e.g. because they have transient
+    /// inputs, are skipped.
+    pub(crate) fn from_tasks(
+        hot: impl IntoIterator<Item = TaskId>,
+        task_types: &HashMap<TaskId, PersistentTaskType>,
+        description: impl Fn(TaskId) -> String,
+        ids: &StableTaskIds,
+    ) -> Self {
+        let mapping = StableIdMapping::new(ids);
+        let mut tasks = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack: Vec<(TaskId, bool)> = hot.into_iter().map(|task| (task, false)).collect();
+        stack.reverse();
+        while let Some((task, inputs_added)) = stack.pop() {
+            let Some(task_type) = task_types.get(&task) else {
+                continue;
+            };
+            if inputs_added {
+                let description = description(task);
+                match mapping.serialize(task_type) {
+                    Ok(task) => tasks.push(WarmupTask { description, task }),
+                    Err(err) => {
+                        tracing::debug!("{description} is not added to the warmup profile: {err:#}")
+                    }
+                }
+                continue;
+            }
+            if !visited.insert(task) {
+                continue;
+            }
+            stack.push((task, true));
+            let mut input_tasks = Vec::new();
+            collect_input_tasks(task_inputs(task_type), &mut input_tasks);
+            for input in input_tasks.into_iter().rev() {
+                if !visited.contains(&input) {
+                    stack.push((input, false));
+                }
+            }
+        }
+        Self { tasks }
+    }
+
+    /// Returns the descriptions of the tasks in the profile.
+    pub fn tasks(&self) -> impl Iterator<Item = &str> {
+        self.tasks.iter().map(|task| task.description.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    pub(crate) fn into_tasks(self) -> VecDeque<WarmupTask> {
+        self.tasks.into()
+    }
+
+    /// Reads a profile from a file. A missing file results in an empty
+    /// profile.
+    pub fn read(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => content
+                .parse()
+                .with_context(|| format!("invalid warmup profile {}", path.display())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err)
+                .with_context(|| format!("unable to read warmup profile {}", path.display())),
+        }
+    }
+
+    /// Writes the profile to a file.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_string())
+            .with_context(|| format!("unable to write warmup profile {}", path.display()))
+    }
+}
+
+impl Display for WarmupProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for task in self.tasks.iter() {
+            let line = serde_json::to_string(task).map_err(|_| fmt::Error)?;
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for WarmupProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(Self {
+            tasks: s
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .enumerate()
+                .map(|(number, line)| {
+                    serde_json::from_str(line)
+                        .with_context(|| format!("invalid task in line {}", number + 1))
+                })
+                .collect::<Result<_>>()?,
+        })
+    }
+}
+
+/// Creates and schedules the next batch of tasks of a [WarmupProfile] and
+/// schedules the rest in another background job. Tasks that don't exist in
+/// this build anymore, or that refer to tasks that couldn't be created, are
+/// skipped.
+pub(crate) fn run_warmup(
+    mut tasks: VecDeque<WarmupTask>,
+    ids: &StableTaskIds,
+    backend: &MemoryBackend,
+    turbo_tasks: &dyn TurboTasksBackendApi,
+) {
+    let mapping = StableIdMapping::new(ids);
+    for WarmupTask { description, task } in tasks.drain(..tasks.len().min(WARMUP_BATCH_SIZE)) {
+        match mapping.deserialize::<PersistentTaskType>(&task) {
+            Ok(task_type) => backend.warm_up_task(task_type, turbo_tasks),
+            Err(err) => tracing::debug!("{description} is not warmed up: {err:#}"),
+        }
+    }
+    if !tasks.is_empty() {
+        turbo_tasks.schedule_backend_background_job(backend.create_backend_job(Job::Warmup(tasks)));
+    }
+}
+
+fn task_inputs(task_type: &PersistentTaskType) -> &[TaskInput] {
+    match task_type {
+        PersistentTaskType::Native(_, inputs)
+        | PersistentTaskType::ResolveNative(_, inputs)
+        | PersistentTaskType::ResolveTrait(_, _, inputs) => inputs,
+    }
+}
+
+fn collect_input_tasks(inputs: &[TaskInput], tasks: &mut Vec<TaskId>) {
+    for input in inputs {
+        match input {
+            TaskInput::TaskOutput(task) | TaskInput::TaskCell(task, _) => tasks.push(*task),
+            TaskInput::List(list) => collect_input_tasks(list, tasks),
+            _ => {}
+        }
+    }
+}
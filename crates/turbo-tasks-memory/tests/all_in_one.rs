@@ -1,7 +1,10 @@
 #![feature(min_specialization)]
 
 use anyhow::{anyhow, Result};
-use turbo_tasks::{primitives::StringVc, Value, ValueToString, ValueToStringVc};
+use turbo_tasks::{
+    primitives::StringVc, registry, RawVc, Typed, Value, ValueToString, ValueToStringVc,
+    ValueTraitVc,
+};
 use turbo_tasks_testing::{register, run};
 
 register!();
@@ -29,6 +32,82 @@ async fn all_in_one() {
     }
 }
 
+#[tokio::test]
+async fn trait_implementors() {
+    run! {
+        let implementors =
+            registry::get_trait_implementors(ValueToStringVc::get_trait_type_id());
+        assert!(implementors.contains(&MyEnumValue::get_value_type_id()));
+        assert!(implementors.contains(&MyStructValue::get_value_type_id()));
+        assert!(!implementors.contains(&MyTransparentValue::get_value_type_id()));
+    }
+}
+
+#[tokio::test]
+async fn trait_casts() {
+    run! {
+        let traits = registry::get_value_type_traits(MyStructValue::get_value_type_id());
+        assert!(traits.contains(&ValueToStringVc::get_trait_type_id()));
+        assert!(traits.contains(&MyTraitVc::get_trait_type_id()));
+        assert!(registry::is_supertrait(
+            MyTraitVc::get_trait_type_id(),
+            ValueToStringVc::get_trait_type_id()
+        ));
+        assert!(!registry::is_supertrait(
+            ValueToStringVc::get_trait_type_id(),
+            MyTraitVc::get_trait_type_id()
+        ));
+
+        let value = MyStructValueVc::cell(MyStructValue { value: 1, next: None });
+        let my_trait = value.try_cast::<MyTraitVc>().unwrap();
+        let to_string = my_trait.try_cast::<ValueToStringVc>().await?.unwrap();
+        assert_eq!(*to_string.to_string().await?, "1");
+        assert!(to_string.try_cast::<MyTraitVc>().await?.is_some());
+
+        let transparent = MyTransparentValueVc::cell(1);
+        assert!(transparent.try_cast::<ValueToStringVc>().is_none());
+        let enum_value: ValueToStringVc = MyEnumValueVc::cell(MyEnumValue::Nah).into();
+        assert!(enum_value.try_cast::<MyTraitVc>().await?.is_none());
+    }
+}
+
+#[tokio::test]
+async fn trait_resolution_error() {
+    run! {
+        // Skips the checked cast, so the trait method is resolved on a value
+        // that doesn't implement the trait
+        let transparent = MyTransparentValueVc::cell(1);
+        let to_string = ValueToStringVc::from(RawVc::from(transparent));
+        let error = to_string.to_string().await.err().unwrap();
+        let message = format!("{error:#}");
+        assert!(message.contains("doesn't implement"), "{message}");
+        assert!(message.contains("implementors are "), "{message}");
+        for implementor in [
+            MyEnumValue::get_value_type_id(),
+            MyStructValue::get_value_type_id(),
+        ] {
+            let name = &registry::get_value_type(implementor).name;
+            assert!(message.contains(name.as_str()), "{name} is missing in {message}");
+        }
+    }
+}
+
+#[tokio::test]
+async fn trait_method_cache() {
+    run! {
+        let first = MyStructValueVc::cell(MyStructValue { value: 1, next: None });
+        let first: ValueToStringVc = first.into();
+        assert_eq!(*first.to_string().await?, "1");
+        let before = registry::trait_method_cache_stats();
+        // Another value of the same type resolves the method from the cache
+        let second = MyStructValueVc::cell(MyStructValue { value: 2, next: None });
+        let second: ValueToStringVc = second.into();
+        assert_eq!(*second.to_string().await?, "2");
+        let after = registry::trait_method_cache_stats();
+        assert!(after.hits > before.hits);
+    }
+}
+
 #[turbo_tasks::value(transparent, serialization = "auto_for_input")]
 #[derive(Debug, Clone, PartialOrd, Ord, Hash)]
 struct MyTransparentValue(u32);
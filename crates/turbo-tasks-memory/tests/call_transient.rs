@@ -0,0 +1,57 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::Ordering;
+
+use turbo_tasks::{call_transient, primitives::U64Vc, registry, TaskInput, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn executes_without_task() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let function = registry::get_function(*DOUBLE_FUNCTION_ID);
+    let cached_calls = function.cached_call_count.load(Ordering::SeqCst);
+    let tt_inner = tt.clone();
+    tt.run_once(async move {
+        let raw = call_transient(*DOUBLE_FUNCTION_ID, vec![TaskInput::U64(2)]).await?;
+        assert_eq!(*U64Vc::from(raw).await?, 4);
+        // Tasks are only listed while they are in use, so this checks during
+        // the run
+        assert!(tt_inner
+            .backend()
+            .tasks_for_function(*DOUBLE_FUNCTION_ID)
+            .is_empty());
+        Ok(())
+    })
+    .await
+    .unwrap();
+    assert!(function.transient_call_count.load(Ordering::SeqCst) >= 1);
+    assert_eq!(
+        function.cached_call_count.load(Ordering::SeqCst),
+        cached_calls
+    );
+
+    let tt_inner = tt.clone();
+    tt.run_once(async move {
+        assert_eq!(*double(3).await?, 6);
+        assert_eq!(
+            tt_inner
+                .backend()
+                .tasks_for_function(*DOUBLE_FUNCTION_ID)
+                .len(),
+            1
+        );
+        Ok(())
+    })
+    .await
+    .unwrap();
+    assert!(function.cached_call_count.load(Ordering::SeqCst) > cached_calls);
+}
+
+#[turbo_tasks::function]
+fn double(value: u64) -> U64Vc {
+    U64Vc::cell(value * 2)
+}
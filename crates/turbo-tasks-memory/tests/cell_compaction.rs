@@ -0,0 +1,58 @@
+#![feature(min_specialization)]
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+use turbo_tasks::{get_invalidator, primitives::UsizeVc, Invalidator, RawVc, TurboTasks};
+use turbo_tasks_memory::{CellNotFound, MemoryBackend};
+use turbo_tasks_testing::register;
+
+register!();
+
+static COUNT: AtomicUsize = AtomicUsize::new(3);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+#[tokio::test]
+async fn unused_cells_are_removed() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let last = tt
+        .run_once(async {
+            let cells = numbers().strongly_consistent().await?;
+            assert_eq!(cells.len(), 3);
+            Ok(cells[2])
+        })
+        .await
+        .unwrap();
+
+    COUNT.store(1, Ordering::SeqCst);
+    INVALIDATOR.lock().unwrap().take().unwrap().invalidate();
+    tt.run_once(async move {
+        assert_eq!(numbers().strongly_consistent().await?.len(), 1);
+        let RawVc::TaskCell(_, index) = last.into() else {
+            panic!("the number should be a cell");
+        };
+        let err = last.await.unwrap_err();
+        let not_found = err
+            .chain()
+            .find_map(|err| err.downcast_ref::<CellNotFound>())
+            .unwrap();
+        assert_eq!(not_found.index, index);
+        assert_eq!(not_found.created, 1);
+        Ok(())
+    })
+    .await
+    .unwrap();
+}
+
+#[turbo_tasks::value(transparent)]
+struct Numbers(Vec<UsizeVc>);
+
+#[turbo_tasks::function]
+fn numbers() -> NumbersVc {
+    *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    let count = COUNT.load(Ordering::SeqCst);
+    NumbersVc::cell((0..count).map(UsizeVc::cell).collect())
+}
@@ -0,0 +1,104 @@
+#![feature(min_specialization)]
+
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Mutex,
+};
+
+use turbo_tasks::{get_invalidator, Invalidator, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn cell_conflicts() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async {
+        assert_eq!(*nondeterministic().strongly_consistent().await?, 1);
+        INVALIDATOR.lock().unwrap().take().unwrap().invalidate();
+        assert_eq!(*nondeterministic().strongly_consistent().await?, 2);
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    let conflicts = tt.cell_conflicts();
+    if cfg!(debug_assertions) {
+        assert_eq!(conflicts.len(), 1);
+        assert!(conflicts[0].task.ends_with("nondeterministic"));
+        assert!(conflicts[0].value_type.ends_with("Number"));
+        assert_eq!((conflicts[0].previous, conflicts[0].current), (1, 2));
+        assert!(!conflicts[0].reordered);
+    } else {
+        assert!(conflicts.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn reordered_cells() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async {
+        assert_eq!(*reordering().strongly_consistent().await?, 1);
+        REORDERING_INVALIDATOR
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap()
+            .invalidate();
+        assert_eq!(*reordering().strongly_consistent().await?, 2);
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    let conflicts = tt.cell_conflicts();
+    if cfg!(debug_assertions) {
+        // Both types have the same number of cells, but swapped positions
+        assert_eq!(conflicts.len(), 2);
+        assert!(conflicts
+            .iter()
+            .all(|conflict| conflict.reordered && (conflict.previous, conflict.current) == (1, 1)));
+    } else {
+        assert!(conflicts.is_empty());
+    }
+}
+
+static EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+static REORDERINGS: AtomicU32 = AtomicU32::new(0);
+static REORDERING_INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+#[turbo_tasks::value(transparent)]
+struct Number(u32);
+
+/// Creates one more cell in every execution.
+#[turbo_tasks::function]
+fn nondeterministic() -> NumberVc {
+    *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    let executions = EXECUTIONS.fetch_add(1, Ordering::SeqCst) + 1;
+    for i in 1..executions {
+        NumberVc::cell(i);
+    }
+    NumberVc::cell(executions)
+}
+
+#[turbo_tasks::value(transparent)]
+struct Label(String);
+
+/// Creates the same cells in a different order in every execution.
+#[turbo_tasks::function]
+fn reordering() -> NumberVc {
+    *REORDERING_INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    let executions = REORDERINGS.fetch_add(1, Ordering::SeqCst) + 1;
+    if executions % 2 == 1 {
+        LabelVc::cell("before".to_string());
+        NumberVc::cell(executions)
+    } else {
+        let number = NumberVc::cell(executions);
+        LabelVc::cell("after".to_string());
+        number
+    }
+}
@@ -0,0 +1,33 @@
+#![feature(min_specialization)]
+
+use turbo_tasks::{RawVc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn cells_are_described_by_type_name() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let cell = tt
+        .run_once(async { Ok(RawVc::from(value()).resolve().await?) })
+        .await
+        .unwrap();
+    let RawVc::TaskCell(_, index) = cell else {
+        panic!("{cell} is not a cell");
+    };
+    assert_eq!(index.to_string(), "cell #0 (cell_names::Value)");
+    assert!(cell
+        .to_string()
+        .starts_with("cell #0 (cell_names::Value) of "));
+    assert!(format!("{index:?}").contains("cell_names::Value"));
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u32);
+
+#[turbo_tasks::function]
+fn value() -> ValueVc {
+    ValueVc::cell(42)
+}
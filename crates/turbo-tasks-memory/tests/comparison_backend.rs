@@ -0,0 +1,60 @@
+#![feature(min_specialization)]
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+use anyhow::Result;
+use turbo_tasks::{get_invalidator, Invalidator, TurboTasks};
+use turbo_tasks_memory::{ComparisonBackend, MemoryBackend, MemoryBackendWithPersistedGraph};
+use turbo_tasks_testing::register;
+
+register!();
+
+static SOURCE: AtomicU64 = AtomicU64::new(1);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+#[tokio::test]
+async fn backends_agree() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(ComparisonBackend::new(
+        MemoryBackend::new(),
+        MemoryBackendWithPersistedGraph::new(()),
+    ));
+    let value = tt
+        .run_once(async { Ok(*sum(2).strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 4);
+
+    SOURCE.store(5, Ordering::SeqCst);
+    INVALIDATOR.lock().unwrap().take().unwrap().invalidate();
+    let value = tt
+        .run_once(async { Ok(*sum(2).strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 12);
+
+    let mismatches = tt.backend().mismatches();
+    assert!(mismatches.is_empty(), "{mismatches:#?}");
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u64);
+
+#[turbo_tasks::function]
+fn source() -> ValueVc {
+    *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    ValueVc::cell(SOURCE.load(Ordering::SeqCst))
+}
+
+#[turbo_tasks::function]
+async fn double(value: ValueVc) -> Result<ValueVc> {
+    Ok(ValueVc::cell(*value.await? * 2))
+}
+
+#[turbo_tasks::function]
+async fn sum(offset: u64) -> Result<ValueVc> {
+    Ok(ValueVc::cell(*double(source()).await? + offset))
+}
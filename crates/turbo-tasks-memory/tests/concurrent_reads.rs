@@ -0,0 +1,70 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::Result;
+use turbo_tasks::{join_all, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static SOURCE_EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+static READER_EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+
+#[tokio::test]
+async fn concurrent_readers_are_all_notified() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .worker_threads(4)
+        .build()
+        .unwrap();
+    let (readers, values) = tt
+        .run_once(async {
+            let readers = (0..16).map(reader).collect::<Vec<_>>();
+            let values = join_all(readers.iter().copied()).await?;
+            Ok((
+                readers,
+                values.iter().map(|value| **value).collect::<Vec<_>>(),
+            ))
+        })
+        .await
+        .unwrap();
+    assert_eq!(values, (1..17).collect::<Vec<_>>());
+    assert_eq!(READER_EXECUTIONS.load(Ordering::SeqCst), 16);
+
+    let _guards = readers
+        .iter()
+        .map(|reader| tt.keep_alive((*reader).into()))
+        .collect::<Vec<_>>();
+    tt.invalidate_function(*SOURCE_FUNCTION_ID);
+    let values = tt
+        .run_once(async move {
+            let mut values = Vec::new();
+            for reader in readers {
+                values.push(*reader.strongly_consistent().await?);
+            }
+            Ok(values)
+        })
+        .await
+        .unwrap();
+    assert_eq!(values, (2..18).collect::<Vec<_>>());
+    // Every reader has been tracked as dependent of the source
+    assert_eq!(SOURCE_EXECUTIONS.load(Ordering::SeqCst), 2);
+    assert_eq!(READER_EXECUTIONS.load(Ordering::SeqCst), 32);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u64);
+
+#[turbo_tasks::function]
+fn source() -> ValueVc {
+    let executions = SOURCE_EXECUTIONS.fetch_add(1, Ordering::SeqCst) as u64;
+    ValueVc::cell(executions)
+}
+
+#[turbo_tasks::function]
+async fn reader(offset: u64) -> Result<ValueVc> {
+    READER_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    Ok(ValueVc::cell(*source().await? + offset + 1))
+}
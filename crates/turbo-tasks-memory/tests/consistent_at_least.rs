@@ -0,0 +1,110 @@
+#![feature(min_specialization)]
+
+use std::time::Duration;
+
+use anyhow::Result;
+use turbo_tasks::{State, TurboTasks, TurboTasksBackendApi};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn reflects_invalidations_up_to_generation() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (config, length, config_ref) = tt
+        .run_once(async {
+            let config = ConfigVc::cell(Config {
+                mode: State::new("dev".to_string()),
+            });
+            let length = mode_length(config);
+            assert_eq!(*length.await?, 3);
+            Ok((config, length, config.await?))
+        })
+        .await
+        .unwrap();
+    let _guard = tt.keep_alive(length.into());
+
+    config_ref.mode.set("production".to_string());
+    let generation = tt.update_generation();
+    let length = tt
+        .run_once(async move {
+            Ok(*mode_length(config)
+                .read_consistent_at_least(generation)
+                .await?)
+        })
+        .await
+        .unwrap();
+    assert_eq!(length, 10);
+}
+
+#[tokio::test]
+async fn skips_strongly_consistent_read_when_settled() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let tt2 = tt.clone();
+    let (config, length, generation) = tt
+        .run_once(async move {
+            let config = ConfigVc::cell(Config {
+                mode: State::new("dev".to_string()),
+            });
+            let length = mode_length(config);
+            length.await?;
+            // The generation is increased once this work has been finished
+            Ok((config, length, tt2.update_generation()))
+        })
+        .await
+        .unwrap();
+    let _guard = tt.keep_alive(length.into());
+    wait_for_generation(&tt, generation).await;
+
+    let length = tt
+        .run_once(async move {
+            Ok(*mode_length(config)
+                .read_consistent_at_least(generation)
+                .await?)
+        })
+        .await
+        .unwrap();
+    assert_eq!(length, 3);
+    assert!(tt.backend().root_scopes().is_empty());
+
+    // A generation that has not been reached requires a strongly consistent
+    // read, which makes the task root scoped
+    let length = tt
+        .run_once(async move {
+            Ok(*mode_length(config)
+                .read_consistent_at_least(usize::MAX)
+                .await?)
+        })
+        .await
+        .unwrap();
+    assert_eq!(length, 3);
+    assert_eq!(tt.backend().root_scopes().len(), 1);
+}
+
+async fn wait_for_generation(tt: &TurboTasks<MemoryBackend>, generation: usize) {
+    for _ in 0..1000 {
+        if tt.update_generation() > generation {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    panic!("generation {generation} has not been passed");
+}
+
+#[turbo_tasks::value(serialization = "none", cell = "new", eq = "manual")]
+struct Config {
+    mode: State<String>,
+}
+
+#[turbo_tasks::value(transparent)]
+struct Length(usize);
+
+#[turbo_tasks::function]
+async fn mode_length(config: ConfigVc) -> Result<LengthVc> {
+    let config = config.await?;
+    let length = config.mode.get().len();
+    Ok(LengthVc::cell(length))
+}
@@ -0,0 +1,98 @@
+#![feature(min_specialization)]
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use turbo_tasks::{MemoryContentStore, RawVc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn content_store() {
+    lazy_static::initialize(&REGISTER);
+    let store = Arc::new(MemoryContentStore::new());
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .content_store(store.clone(), 100)
+        .build()
+        .unwrap();
+    let (large, small): (RawVc, RawVc) = tt
+        .run_once(async {
+            let large = text(1_000).resolve().await?;
+            let small = text(10).resolve().await?;
+            Ok((large.into(), small.into()))
+        })
+        .await
+        .unwrap();
+    // Values are pushed in the background
+    tt.wait_background_done().await;
+    assert_eq!(store.len(), 1);
+    assert_eq!(tt.content_hash(small), None);
+    let hash = tt.content_hash(large).unwrap();
+
+    // Another instance shares the value without computing it
+    let other = TurboTasks::builder(MemoryBackend::new())
+        .content_store(store, 100)
+        .build()
+        .unwrap();
+    let value = other.fetch_content::<Text>(&hash).unwrap().unwrap();
+    assert_eq!(value.0, "a".repeat(1_000));
+    assert!(other.fetch_content::<String>(&hash).is_err());
+}
+
+#[tokio::test]
+async fn unused_cells_are_forgotten() {
+    lazy_static::initialize(&REGISTER);
+    let store = Arc::new(MemoryContentStore::new());
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .content_store(store, 100)
+        .build()
+        .unwrap();
+    LARGE_TEXTS.store(2, Ordering::SeqCst);
+    let last = tt
+        .run_once(async {
+            let texts = large_texts().strongly_consistent().await?;
+            Ok(RawVc::from(texts[1]))
+        })
+        .await
+        .unwrap();
+    tt.wait_background_done().await;
+    assert!(tt.content_hash(last).is_some());
+
+    // The next execution doesn't create the cell anymore
+    LARGE_TEXTS.store(1, Ordering::SeqCst);
+    tt.invalidate_function(*LARGE_TEXTS_FUNCTION_ID);
+    tt.run_once(async {
+        assert_eq!(large_texts().strongly_consistent().await?.len(), 1);
+        Ok(())
+    })
+    .await
+    .unwrap();
+    tt.wait_background_done().await;
+    assert_eq!(tt.content_hash(last), None);
+}
+
+static LARGE_TEXTS: AtomicUsize = AtomicUsize::new(0);
+
+#[turbo_tasks::value(transparent)]
+struct Text(String);
+
+#[turbo_tasks::value(transparent)]
+struct Texts(Vec<TextVc>);
+
+#[turbo_tasks::function]
+fn large_texts() -> TextsVc {
+    TextsVc::cell(
+        (0..LARGE_TEXTS.load(Ordering::SeqCst))
+            .map(|i| TextVc::cell(i.to_string().repeat(1_000)))
+            .collect(),
+    )
+}
+
+#[turbo_tasks::function]
+fn text(length: usize) -> TextVc {
+    TextVc::cell("a".repeat(length))
+}
@@ -0,0 +1,44 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{get_context, primitives::StringVc, with_context, RawVc};
+use turbo_tasks_testing::{register, run};
+
+register!();
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct Env(&'static str);
+
+#[tokio::test]
+async fn context_is_visible_to_callees() {
+    run! {
+        let task = |vc: StringVc| RawVc::from(vc).get_task_id();
+
+        let dev = with_context(Env("dev"), async { describe() }).await;
+        assert_eq!(*dev.await?, "env: dev");
+        let dev_again = with_context(Env("dev"), async { describe() }).await;
+        assert_eq!(*dev_again.await?, "env: dev");
+
+        // The context is not part of the inputs, so the task is the same and
+        // executes again with the new context
+        let prod = with_context(Env("prod"), async { describe() }).await;
+        assert_eq!(*prod.await?, "env: prod");
+        assert_eq!(task(dev), task(prod));
+
+        let none = describe();
+        assert_eq!(*none.await?, "env: none");
+        assert_eq!(task(dev), task(none));
+    }
+}
+
+/// Reads the context in a nested call.
+#[turbo_tasks::function]
+async fn describe() -> Result<StringVc> {
+    Ok(StringVc::cell(format!("env: {}", env_name().await?)))
+}
+
+#[turbo_tasks::function]
+fn env_name() -> StringVc {
+    let env = get_context::<Env>();
+    StringVc::cell(env.map_or("none", |env| env.0).to_string())
+}
@@ -0,0 +1,77 @@
+#![feature(min_specialization)]
+
+use anyhow::{bail, Result};
+use turbo_tasks::{join_all, primitives::U64Vc, RawVc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn tracks_all_links() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (reader, outer) = tt
+        .run_once(async {
+            let reader = read_outer();
+            reader.await?;
+            Ok((reader, outer()))
+        })
+        .await
+        .unwrap();
+
+    let reader = RawVc::from(reader).get_task_id();
+    let chain = tt.backend().dependency_chain(reader, outer.into());
+    assert_eq!(chain.len(), 3);
+    assert!(matches!(chain[0].vc, RawVc::TaskOutput(_)));
+    assert!(matches!(chain[1].vc, RawVc::TaskOutput(_)));
+    assert!(matches!(chain[2].vc, RawVc::TaskCell(..)));
+    assert!(chain.iter().all(|step| step.tracked));
+}
+
+#[tokio::test]
+async fn tracks_failed_outputs_read_together() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (reader, failing) = tt
+        .run_once(async {
+            let reader = read_all();
+            reader.await?;
+            Ok((reader, failing()))
+        })
+        .await
+        .unwrap();
+
+    let reader = RawVc::from(reader).get_task_id();
+    let chain = tt.backend().dependency_chain(reader, failing.into());
+    assert_eq!(chain.len(), 1);
+    assert!(chain[0].tracked);
+}
+
+#[turbo_tasks::function]
+fn inner() -> U64Vc {
+    U64Vc::cell(42)
+}
+
+#[turbo_tasks::function]
+fn outer() -> U64Vc {
+    inner()
+}
+
+#[turbo_tasks::function]
+fn failing() -> Result<U64Vc> {
+    bail!("failing")
+}
+
+#[turbo_tasks::function]
+async fn read_outer() -> Result<U64Vc> {
+    Ok(U64Vc::cell(*outer().await? + 1))
+}
+
+#[turbo_tasks::function]
+async fn read_all() -> Result<U64Vc> {
+    let count = join_all([failing(), inner()])
+        .await
+        .map_or(0, |values| values.len());
+    Ok(U64Vc::cell(count as u64))
+}
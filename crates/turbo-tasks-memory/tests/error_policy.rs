@@ -0,0 +1,59 @@
+#![feature(min_specialization)]
+
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use turbo_tasks::TurboTasks;
+use turbo_tasks_memory::{ErrorPolicy, MemoryBackend};
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn store_surfaces_error_on_read() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let result = tokio::time::timeout(
+        Duration::from_secs(10),
+        tt.run_once(async { Ok(*build(200).strongly_consistent().await?) }),
+    )
+    .await
+    .unwrap();
+    let error = format!("{:#}", result.unwrap_err());
+    assert!(error.contains("broken input"), "{error}");
+}
+
+#[tokio::test]
+async fn fail_fast_returns_first_error() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new().with_error_policy(ErrorPolicy::FailFast));
+    let result = tokio::time::timeout(
+        Duration::from_secs(10),
+        tt.run_once(async { Ok(*build(60000).strongly_consistent().await?) }),
+    )
+    .await
+    .expect("the error should not wait for the slow task");
+    let error = format!("{:#}", result.unwrap_err());
+    assert!(error.contains("broken input"), "{error}");
+}
+
+#[turbo_tasks::value(transparent)]
+struct Number(u32);
+
+#[turbo_tasks::function]
+async fn build(slow_millis: u64) -> Result<NumberVc> {
+    let slow = slow(slow_millis);
+    let failing = failing();
+    Ok(NumberVc::cell(*slow.await? + *failing.await?))
+}
+
+#[turbo_tasks::function]
+async fn slow(millis: u64) -> Result<NumberVc> {
+    tokio::time::sleep(Duration::from_millis(millis)).await;
+    Ok(NumberVc::cell(1))
+}
+
+#[turbo_tasks::function]
+async fn failing() -> Result<NumberVc> {
+    bail!("broken input")
+}
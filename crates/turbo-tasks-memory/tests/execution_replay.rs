@@ -0,0 +1,77 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::Result;
+use turbo_tasks::TurboTasks;
+use turbo_tasks_memory::{ExecutionRecording, MemoryBackend};
+use turbo_tasks_testing::register;
+
+register!();
+
+static EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+
+#[tokio::test]
+async fn replays_recorded_executions() {
+    lazy_static::initialize(&REGISTER);
+    let path = std::env::temp_dir().join(format!("execution-replay-{}.jsonl", std::process::id()));
+
+    let tt = TurboTasks::new(MemoryBackend::new().with_execution_recording(&path));
+    let value = tt
+        .run_once(async { Ok(*sum(1, 2).strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 6);
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 3);
+    assert_eq!(tt.backend().recorded_executions(), (3, 0));
+
+    let recording = ExecutionRecording::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(recording.len(), 3);
+    let tt = TurboTasks::new(MemoryBackend::new().with_execution_replay(recording));
+    let tt_inner = tt.clone();
+    let value = tt
+        .run_once(async move {
+            let value = *sum(1, 2).strongly_consistent().await?;
+            // The replayed calls are connected as children, checked while the
+            // run uses the tasks
+            let backend = tt_inner.backend();
+            let sum_task = backend.tasks_for_function(*SUM_FUNCTION_ID)[0];
+            let double_tasks = backend.tasks_for_function(*DOUBLE_FUNCTION_ID);
+            assert_eq!(double_tasks.len(), 2);
+            for task in double_tasks {
+                let parents = backend.with_task(task, |task| task.parents());
+                assert_eq!(parents, vec![sum_task]);
+            }
+            Ok(value)
+        })
+        .await
+        .unwrap();
+    assert_eq!(value, 6);
+    // No function has been executed, the calls of `sum` have been repeated
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 3);
+    assert_eq!(tt.backend().replayed_executions(), 3);
+
+    // Tasks that are not in the recording are executed, `double(2)` is reused
+    let value = tt
+        .run_once(async { Ok(*sum(2, 3).strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 10);
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 5);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u64);
+
+#[turbo_tasks::function]
+async fn sum(a: u64, b: u64) -> Result<ValueVc> {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    Ok(ValueVc::cell(*double(a).await? + *double(b).await?))
+}
+
+#[turbo_tasks::function]
+fn double(value: u64) -> ValueVc {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    ValueVc::cell(value * 2)
+}
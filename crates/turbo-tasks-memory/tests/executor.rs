@@ -0,0 +1,123 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use turbo_tasks::{
+    primitives::{BoolVc, StringVc},
+    TurboTasks,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn dedicated_runtime() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .worker_threads(2)
+        .thread_name("turbo-tasks-worker")
+        .max_background_jobs(1)
+        .build()
+        .unwrap();
+    let name = tt
+        .run_once(async { Ok(thread_name().await?.clone_value()) })
+        .await
+        .unwrap();
+    assert_eq!(name, "turbo-tasks-worker");
+}
+
+#[tokio::test]
+async fn idle_workers() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .worker_threads(2)
+        .build()
+        .unwrap();
+    let tt2 = tt.clone();
+    let idle = tt
+        .run_once(async move { Ok(tt2.idle_workers()) })
+        .await
+        .unwrap();
+    // The worker that executes the task is busy
+    assert!(idle < 2);
+}
+
+#[tokio::test]
+async fn blocking_function() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .worker_threads(1)
+        .build()
+        .unwrap();
+    let flag_seen = tt
+        .run_once(async {
+            let flag_seen = wait_for_flag();
+            set_flag().await?;
+            Ok(*flag_seen.await?)
+        })
+        .await
+        .unwrap();
+    // The only worker would be blocked if the function was executed on it
+    assert!(flag_seen);
+    // The task might not have returned from the blocking pool yet
+    let stats = tt.blocking_pool_stats();
+    assert_eq!(stats.executions.max(stats.running), 1);
+}
+
+#[test]
+fn run_once_blocking_without_runtime() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let name = tt
+        .run_once_blocking(async { Ok(thread_name().await?.clone_value()) })
+        .unwrap();
+    assert_eq!(name, "tokio-runtime-worker");
+}
+
+#[test]
+fn run_once_blocking_on_dedicated_runtime() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .worker_threads(1)
+        .thread_name("turbo-tasks-worker")
+        .build()
+        .unwrap();
+    let name = tt
+        .run_once_blocking(async { Ok(thread_name().await?.clone_value()) })
+        .unwrap();
+    assert_eq!(name, "turbo-tasks-worker");
+}
+
+static FLAG: AtomicBool = AtomicBool::new(false);
+
+#[turbo_tasks::function(blocking)]
+fn wait_for_flag() -> BoolVc {
+    let start = Instant::now();
+    while !FLAG.load(Ordering::SeqCst) {
+        if start.elapsed() > Duration::from_secs(10) {
+            return BoolVc::cell(false);
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    BoolVc::cell(true)
+}
+
+#[turbo_tasks::function]
+fn set_flag() -> BoolVc {
+    FLAG.store(true, Ordering::SeqCst);
+    BoolVc::cell(true)
+}
+
+#[turbo_tasks::function]
+fn thread_name() -> StringVc {
+    StringVc::cell(
+        std::thread::current()
+            .name()
+            .unwrap_or_default()
+            .to_string(),
+    )
+}
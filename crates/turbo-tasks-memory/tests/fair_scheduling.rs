@@ -0,0 +1,60 @@
+#![feature(min_specialization)]
+
+use std::{sync::Mutex, time::Duration};
+
+use anyhow::Result;
+use turbo_tasks::{
+    primitives::{StringVc, UsizeVc},
+    FairScheduling, TryJoinIterExt, TurboTasks,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static EXECUTIONS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+#[tokio::test]
+async fn interleaves_root_tasks() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .fair_scheduling(FairScheduling {
+            max_tasks_per_tick: 1,
+            tick: Duration::from_millis(5),
+        })
+        .build()
+        .unwrap();
+    let (a, b) = tokio::join!(
+        tt.run_once(async { Ok(*page("a".to_string()).await?) }),
+        tt.run_once(async { Ok(*page("b".to_string()).await?) }),
+    );
+    assert_eq!(a.unwrap(), 6);
+    assert_eq!(b.unwrap(), 6);
+    assert!(tt.waiting_tasks_by_root().is_empty());
+    // Finished tasks are forgotten
+    tt.stop_and_wait().await;
+    assert_eq!(tt.fair_scheduling_tracked_tasks(), 0);
+
+    // The leaves of both pages take turns instead of one page starving the
+    // other
+    let executions = EXECUTIONS.lock().unwrap();
+    let last_a = executions.iter().rposition(|page| page == "a").unwrap();
+    let first_b = executions.iter().position(|page| page == "b").unwrap();
+    assert!(first_b < last_a, "{executions:?}");
+}
+
+#[turbo_tasks::function]
+fn leaf(page: String, _index: usize) -> StringVc {
+    EXECUTIONS.lock().unwrap().push(page.clone());
+    StringVc::cell(page)
+}
+
+#[turbo_tasks::function]
+async fn page(name: String) -> Result<UsizeVc> {
+    let leaves = (0..6)
+        .map(|i| leaf(name.clone(), i))
+        .map(|leaf| async move { leaf.await })
+        .try_join()
+        .await?;
+    Ok(UsizeVc::cell(leaves.len()))
+}
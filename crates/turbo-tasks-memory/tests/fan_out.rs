@@ -0,0 +1,79 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::Result;
+use turbo_tasks::{join_all, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+const FAN_OUT: u64 = 32;
+
+static VERSION: AtomicU64 = AtomicU64::new(0);
+
+#[tokio::test]
+async fn concurrent_invalidations_of_a_fan_out() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .worker_threads(4)
+        .build()
+        .unwrap();
+    for version in 1..=5 {
+        VERSION.store(version, Ordering::SeqCst);
+        // Invalidate the shared source from several threads while the readers
+        // of the fan-out are executing and being invalidated again
+        let invalidations = (0..4)
+            .map(|_| {
+                let tt = tt.clone();
+                tokio::spawn(async move {
+                    for _ in 0..16 {
+                        tt.invalidate_function(*SOURCE_FUNCTION_ID);
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        let reads = (0..2)
+            .map(|_| {
+                let tt = tt.clone();
+                tokio::spawn(async move {
+                    tt.run_once(async { Ok(*fan_out().strongly_consistent().await?) })
+                        .await
+                })
+            })
+            .collect::<Vec<_>>();
+        for invalidation in invalidations {
+            invalidation.await.unwrap();
+        }
+        for read in reads {
+            read.await.unwrap().unwrap();
+        }
+
+        let sum = tt
+            .run_once(async { Ok(*fan_out().strongly_consistent().await?) })
+            .await
+            .unwrap();
+        assert_eq!(sum, FAN_OUT * version + FAN_OUT * (FAN_OUT - 1) / 2);
+    }
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u64);
+
+#[turbo_tasks::function]
+fn source() -> ValueVc {
+    ValueVc::cell(VERSION.load(Ordering::SeqCst))
+}
+
+#[turbo_tasks::function]
+async fn reader(offset: u64) -> Result<ValueVc> {
+    Ok(ValueVc::cell(*source().await? + offset))
+}
+
+#[turbo_tasks::function]
+async fn fan_out() -> Result<ValueVc> {
+    let values = join_all((0..FAN_OUT).map(reader)).await?;
+    Ok(ValueVc::cell(values.iter().map(|value| **value).sum()))
+}
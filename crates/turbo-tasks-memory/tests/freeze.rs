@@ -0,0 +1,66 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::Result;
+use turbo_tasks::{RawVc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static SOURCE_EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+
+#[tokio::test]
+async fn frozen_subtrees_ignore_invalidations() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let package = tt
+        .run_once(async {
+            let package = package();
+            assert_eq!(*package.strongly_consistent().await?, 11);
+            Ok(package)
+        })
+        .await
+        .unwrap();
+    let _guard = tt.keep_alive(package.into());
+    let task = RawVc::from(package).get_task_id();
+    let backend = tt.backend();
+
+    // The package and the source it reads
+    assert_eq!(backend.freeze_subtree(task), 2);
+    assert!(backend.is_task_frozen(task));
+    assert_eq!(backend.freeze_subtree(task), 0);
+
+    tt.invalidate_function(*SOURCE_FUNCTION_ID);
+    let pruned = backend.pruned_dependencies();
+    let value = tt
+        .run_once(async move { Ok(*package.strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 11);
+    assert_eq!(SOURCE_EXECUTIONS.load(Ordering::SeqCst), 1);
+    assert!(backend.pruned_dependencies() > pruned);
+
+    assert_eq!(backend.unfreeze_subtree(task, &*tt), 2);
+    assert!(!backend.is_task_frozen(task));
+    let value = tt
+        .run_once(async move { Ok(*package.strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 12);
+    assert_eq!(SOURCE_EXECUTIONS.load(Ordering::SeqCst), 2);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u32);
+
+#[turbo_tasks::function]
+fn source() -> ValueVc {
+    ValueVc::cell(SOURCE_EXECUTIONS.fetch_add(1, Ordering::SeqCst) + 1)
+}
+
+#[turbo_tasks::function]
+async fn package() -> Result<ValueVc> {
+    Ok(ValueVc::cell(*source().await? + 10))
+}
@@ -0,0 +1,231 @@
+#![feature(min_specialization)]
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, Result};
+use turbo_tasks::{
+    backend::PersistentTaskType,
+    persisted_graph::{
+        ActivateResult, DeactivateResult, PersistResult, PersistTaskState, PersistedGraph,
+        PersistedGraphApi, ReadTaskState, TaskData,
+    },
+    primitives::U64Vc,
+    registry, FunctionId, NativeFunction, RawVc, TaskId, TurboTasks,
+};
+use turbo_tasks_memory::MemoryBackendWithPersistedGraph;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn invalidates_changed_functions() {
+    lazy_static::initialize(&REGISTER);
+    let changed = *DOUBLE_FUNCTION_ID;
+    let unchanged = *TRIPLE_FUNCTION_ID;
+    let graph = FingerprintGraph::default();
+    {
+        let mut fingerprints = graph.fingerprints.lock().unwrap();
+        fingerprints.insert(changed, registry::get_function(changed).fingerprint ^ 1);
+        fingerprints.insert(unchanged, registry::get_function(unchanged).fingerprint);
+    }
+    let tt = TurboTasks::new(MemoryBackendWithPersistedGraph::new(graph.clone()));
+
+    assert_eq!(*graph.dirty_functions.lock().unwrap(), vec![changed]);
+    {
+        let fingerprints = graph.fingerprints.lock().unwrap();
+        assert_eq!(
+            fingerprints[&changed],
+            registry::get_function(changed).fingerprint
+        );
+        // Functions without a stored fingerprint get one
+        assert_eq!(fingerprints.len(), registry::get_function_ids().len());
+    }
+    tt.stop_and_wait().await;
+}
+
+#[test]
+fn fingerprints_differ_by_implementation() {
+    lazy_static::initialize(&REGISTER);
+    let double = registry::get_function(*DOUBLE_FUNCTION_ID).fingerprint;
+    let triple = registry::get_function(*TRIPLE_FUNCTION_ID).fingerprint;
+    assert_ne!(double, 0);
+    assert_ne!(double, triple);
+}
+
+#[test]
+fn fingerprints_differ_by_crate_version() {
+    let fingerprint = |crate_version| {
+        NativeFunction::new("f".to_string(), |_| bail!("not called"))
+            .with_fingerprint(1, crate_version)
+            .fingerprint
+    };
+    assert_ne!(fingerprint("crate@0.1.0"), fingerprint("crate@0.1.1"));
+}
+
+#[tokio::test]
+async fn fails_startup_when_fingerprints_cant_be_loaded() {
+    lazy_static::initialize(&REGISTER);
+    let graph = FingerprintGraph {
+        fail_reads: true,
+        ..Default::default()
+    };
+    let result = TurboTasks::builder(MemoryBackendWithPersistedGraph::new(graph.clone())).build();
+    let err = result.err().expect("startup should fail");
+    assert!(
+        format!("{err:#}").contains("loading the fingerprint of"),
+        "{err:#}"
+    );
+    assert!(graph.dirty_functions.lock().unwrap().is_empty());
+}
+
+#[turbo_tasks::function]
+async fn double(value: U64Vc) -> Result<U64Vc> {
+    Ok(U64Vc::cell(*value.await? * 2))
+}
+
+#[turbo_tasks::function]
+async fn triple(value: U64Vc) -> Result<U64Vc> {
+    Ok(U64Vc::cell(*value.await? * 3))
+}
+
+/// A persisted graph that only stores function fingerprints.
+#[derive(Clone, Default)]
+struct FingerprintGraph {
+    fingerprints: Arc<Mutex<HashMap<FunctionId, u64>>>,
+    dirty_functions: Arc<Mutex<Vec<FunctionId>>>,
+    fail_reads: bool,
+}
+
+impl PersistedGraph for FingerprintGraph {
+    fn read_function_fingerprint(
+        &self,
+        function: FunctionId,
+        _api: &dyn PersistedGraphApi,
+    ) -> Result<Option<u64>> {
+        if self.fail_reads {
+            bail!("storage unavailable");
+        }
+        Ok(self.fingerprints.lock().unwrap().get(&function).copied())
+    }
+
+    fn store_function_fingerprint(
+        &self,
+        function: FunctionId,
+        fingerprint: u64,
+        _api: &dyn PersistedGraphApi,
+    ) -> Result<()> {
+        self.fingerprints
+            .lock()
+            .unwrap()
+            .insert(function, fingerprint);
+        Ok(())
+    }
+
+    fn make_function_tasks_dirty(
+        &self,
+        function: FunctionId,
+        _api: &dyn PersistedGraphApi,
+    ) -> Result<()> {
+        self.dirty_functions.lock().unwrap().push(function);
+        Ok(())
+    }
+
+    fn read(
+        &self,
+        task: TaskId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<(TaskData, ReadTaskState)>> {
+        ().read(task, api)
+    }
+
+    fn lookup(
+        &self,
+        partial_task_type: &PersistentTaskType,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<bool> {
+        ().lookup(partial_task_type, api)
+    }
+
+    fn lookup_one(
+        &self,
+        task_type: &PersistentTaskType,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<TaskId>> {
+        ().lookup_one(task_type, api)
+    }
+
+    fn is_persisted(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        ().is_persisted(task, api)
+    }
+
+    fn persist(
+        &self,
+        task: TaskId,
+        data: TaskData,
+        state: PersistTaskState,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<PersistResult>> {
+        ().persist(task, data, state, api)
+    }
+
+    fn activate_when_needed(
+        &self,
+        task: TaskId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<ActivateResult>> {
+        ().activate_when_needed(task, api)
+    }
+
+    fn deactivate_when_needed(
+        &self,
+        task: TaskId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<DeactivateResult>> {
+        ().deactivate_when_needed(task, api)
+    }
+
+    fn set_externally_active(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        ().set_externally_active(task, api)
+    }
+
+    fn unset_externally_active(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        ().unset_externally_active(task, api)
+    }
+
+    fn remove_outdated_externally_active(
+        &self,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Vec<TaskId>> {
+        ().remove_outdated_externally_active(api)
+    }
+
+    fn make_dirty(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<bool> {
+        ().make_dirty(task, api)
+    }
+
+    fn make_clean(&self, task: TaskId, api: &dyn PersistedGraphApi) -> Result<()> {
+        ().make_clean(task, api)
+    }
+
+    fn make_dependent_dirty(&self, vc: RawVc, api: &dyn PersistedGraphApi) -> Result<Vec<TaskId>> {
+        ().make_dependent_dirty(vc, api)
+    }
+
+    fn get_active_external_tasks(&self, api: &dyn PersistedGraphApi) -> Result<Vec<TaskId>> {
+        ().get_active_external_tasks(api)
+    }
+
+    fn get_dirty_active_tasks(&self, api: &dyn PersistedGraphApi) -> Result<Vec<TaskId>> {
+        ().get_dirty_active_tasks(api)
+    }
+
+    fn get_pending_active_update(
+        &self,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<(Vec<TaskId>, Vec<TaskId>)> {
+        ().get_pending_active_update(api)
+    }
+}
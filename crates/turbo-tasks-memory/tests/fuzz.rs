@@ -0,0 +1,261 @@
+#![feature(min_specialization)]
+
+//! Generates random task graphs and random sequences of input changes, and
+//! checks that strongly consistent reads match a recomputation from scratch.
+//! Failing cases are shrunk to a minimal reproduction before reporting.
+//!
+//! The number of cases and the first seed can be set with
+//! `TURBO_TASKS_FUZZ_CASES` and `TURBO_TASKS_FUZZ_SEED`, e.g. to replay a
+//! reported failure.
+
+use std::{env, fmt::Display, time::Duration};
+
+use anyhow::Result;
+use turbo_tasks::{State, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[tokio::test]
+async fn fuzz_incremental_computation() {
+    lazy_static::initialize(&REGISTER);
+    let cases = env_or("TURBO_TASKS_FUZZ_CASES", 100);
+    let first_seed = env_or("TURBO_TASKS_FUZZ_SEED", 1);
+    for seed in first_seed..first_seed + cases {
+        let case = Case::generate(&mut Rng::new(seed));
+        if let Err(error) = run_case(&case).await {
+            let (case, error) = shrink(case, error).await;
+            panic!("seed {seed} failed: {error}\nminimal case:\n{case}");
+        }
+    }
+}
+
+fn env_or(name: &str, default: u64) -> u64 {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// A xorshift generator, so cases are reproducible from their seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // Spreads consecutive seeds, the state must not be zero
+        Self(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+#[derive(Clone, Debug)]
+struct NodeSpec {
+    /// Nodes with a higher index, so the graph is acyclic.
+    deps: Vec<usize>,
+    /// Reads only one dependency, chosen by the input, instead of all of
+    /// them. This changes the dependencies of the task between executions.
+    select: bool,
+    input: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+    Set(usize, u32),
+    Read(usize),
+}
+
+#[derive(Clone, Debug)]
+struct Case {
+    nodes: Vec<NodeSpec>,
+    ops: Vec<Op>,
+}
+
+impl Case {
+    fn generate(rng: &mut Rng) -> Self {
+        let len = 1 + rng.below(8) as usize;
+        let nodes = (0..len)
+            .map(|index| NodeSpec {
+                deps: (index + 1..len).filter(|_| rng.below(3) == 0).collect(),
+                select: rng.below(4) == 0,
+                // Small values make equal results likely, which skips updates
+                input: rng.below(4) as u32,
+            })
+            .collect();
+        let ops = (0..1 + rng.below(12))
+            .map(|_| {
+                let node = rng.below(len as u64) as usize;
+                if rng.below(3) == 0 {
+                    Op::Read(node)
+                } else {
+                    Op::Set(node, rng.below(4) as u32)
+                }
+            })
+            .collect();
+        Self { nodes, ops }
+    }
+
+    /// Smaller variants of the case, used for shrinking.
+    fn candidates(&self) -> Vec<Case> {
+        let mut candidates = Vec::new();
+        for index in 0..self.ops.len() {
+            let mut case = self.clone();
+            case.ops.remove(index);
+            candidates.push(case);
+        }
+        for (node, spec) in self.nodes.iter().enumerate() {
+            for dep in 0..spec.deps.len() {
+                let mut case = self.clone();
+                case.nodes[node].deps.remove(dep);
+                candidates.push(case);
+            }
+            if spec.select {
+                let mut case = self.clone();
+                case.nodes[node].select = false;
+                candidates.push(case);
+            }
+        }
+        let last = self.nodes.len() - 1;
+        let referenced = self.nodes.iter().any(|node| node.deps.contains(&last))
+            || self
+                .ops
+                .iter()
+                .any(|op| matches!(op, Op::Set(node, _) | Op::Read(node) if *node == last));
+        if last > 0 && !referenced {
+            let mut case = self.clone();
+            case.nodes.pop();
+            candidates.push(case);
+        }
+        candidates
+    }
+}
+
+impl Display for Case {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (index, node) in self.nodes.iter().enumerate() {
+            let mode = if node.select { "select" } else { "sum" };
+            writeln!(f, "  node {index} = {} {mode} {:?}", node.input, node.deps)?;
+        }
+        for op in self.ops.iter() {
+            writeln!(f, "  {op:?}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The value of a node computed from scratch.
+fn expected(nodes: &[NodeSpec], inputs: &[u32], index: usize) -> u32 {
+    let input = inputs[index];
+    let deps = &nodes[index].deps;
+    if nodes[index].select && !deps.is_empty() {
+        let dep = deps[input as usize % deps.len()];
+        input.wrapping_add(expected(nodes, inputs, dep))
+    } else {
+        deps.iter().fold(input, |value, &dep| {
+            value.wrapping_add(expected(nodes, inputs, dep))
+        })
+    }
+}
+
+async fn run_case(case: &Case) -> Result<(), String> {
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let nodes = case.nodes.clone();
+    let (graph, root, graph_ref) = tt
+        .run_once(async move {
+            let graph = GraphVc::cell(Graph {
+                deps: nodes.iter().map(|node| node.deps.clone()).collect(),
+                select: nodes.iter().map(|node| node.select).collect(),
+                inputs: nodes.iter().map(|node| State::new(node.input)).collect(),
+            });
+            let root = compute(graph, 0);
+            root.await?;
+            Ok((graph, root, graph.await?))
+        })
+        .await
+        .map_err(|err| format!("initial execution failed: {err}"))?;
+    let _guard = tt.keep_alive(root.into());
+
+    let mut inputs = case.nodes.iter().map(|node| node.input).collect::<Vec<_>>();
+    let final_read = Op::Read(0);
+    for op in case.ops.iter().chain([&final_read]) {
+        match *op {
+            Op::Set(node, value) => {
+                graph_ref.inputs[node].set(value);
+                inputs[node] = value;
+            }
+            Op::Read(node) => {
+                let read =
+                    tt.run_once(
+                        async move { Ok(*compute(graph, node).strongly_consistent().await?) },
+                    );
+                let value = match tokio::time::timeout(READ_TIMEOUT, read).await {
+                    Ok(Ok(value)) => value,
+                    Ok(Err(err)) => return Err(format!("read of node {node} failed: {err}")),
+                    Err(_) => return Err(format!("read of node {node} timed out")),
+                };
+                let expected = expected(&case.nodes, &inputs, node);
+                if value != expected {
+                    return Err(format!("node {node} is {value}, expected {expected}"));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Greedily applies the first smaller variant that still fails, until no
+/// variant fails anymore.
+async fn shrink(mut case: Case, mut error: String) -> (Case, String) {
+    'outer: loop {
+        for candidate in case.candidates() {
+            if let Err(candidate_error) = run_case(&candidate).await {
+                case = candidate;
+                error = candidate_error;
+                continue 'outer;
+            }
+        }
+        return (case, error);
+    }
+}
+
+#[turbo_tasks::value(serialization = "none", cell = "new", eq = "manual")]
+struct Graph {
+    deps: Vec<Vec<usize>>,
+    select: Vec<bool>,
+    inputs: Vec<State<u32>>,
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u32);
+
+#[turbo_tasks::function]
+async fn compute(graph: GraphVc, index: usize) -> Result<ValueVc> {
+    let graph_ref = graph.await?;
+    let input = *graph_ref.inputs[index].get();
+    let deps = &graph_ref.deps[index];
+    let value = if graph_ref.select[index] && !deps.is_empty() {
+        let dep = deps[input as usize % deps.len()];
+        input.wrapping_add(*compute(graph, dep).await?)
+    } else {
+        let mut value = input;
+        for &dep in deps.iter() {
+            value = value.wrapping_add(*compute(graph, dep).await?);
+        }
+        value
+    };
+    Ok(ValueVc::cell(value))
+}
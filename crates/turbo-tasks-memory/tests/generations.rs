@@ -0,0 +1,64 @@
+#![feature(min_specialization)]
+
+use std::time::Duration;
+
+use turbo_tasks::{backend::Backend, TurboTasks, TurboTasksBackendApi};
+use turbo_tasks_memory::{ExecutionReason, MemoryBackend};
+use turbo_tasks_testing::{
+    counter::{Counter, CounterVc},
+    register,
+};
+
+register!();
+
+#[tokio::test]
+async fn diff_generations() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.backend().track_generations(10);
+
+    let first = tt.update_generation();
+    let counter = tt
+        .run_once(async {
+            let counter = CounterVc::cell(Counter::new(0));
+            assert_eq!(*counter.get_value().strongly_consistent().await?, 0);
+            Ok(counter)
+        })
+        .await
+        .unwrap();
+    tt.get_or_wait_update_info(Duration::ZERO).await;
+
+    let second = tt.update_generation();
+    assert!(second > first);
+    tt.run_once(async move {
+        counter.await?.incr();
+        assert_eq!(*counter.get_value().strongly_consistent().await?, 1);
+        Ok(())
+    })
+    .await
+    .unwrap();
+    tt.get_or_wait_update_info(Duration::ZERO).await;
+
+    let backend = tt.backend();
+    let is_get_value = |task| backend.get_task_description(task).contains("get_value");
+    let first_record = backend.generation_record(first).unwrap();
+    let second_record = backend.generation_record(second).unwrap();
+    let (&get_value, _) = first_record
+        .executed
+        .iter()
+        .find(|(task, reason)| is_get_value(**task) && **reason == ExecutionReason::Initial)
+        .unwrap();
+    assert_eq!(
+        second_record.executed.get(&get_value),
+        Some(&ExecutionReason::Invalidated)
+    );
+
+    let diff = backend.diff_generations(first, second);
+    assert!(diff.invalidated.contains(&get_value));
+    assert!(diff
+        .newly_executed
+        .iter()
+        .all(|(task, _)| *task != get_value));
+    assert!(!diff.skipped.is_empty());
+    assert!(!diff.skipped.contains(&get_value));
+}
@@ -0,0 +1,45 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{InputSizeLimits, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn input_size_limits() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .input_size_limits(InputSizeLimits {
+            warn: Some(1_000),
+            error: Some(100_000),
+            track_largest: 2,
+        })
+        .build()
+        .unwrap();
+    tt.run_once(async {
+        assert_eq!(*length("a".repeat(10)).await?, 10);
+        assert_eq!(*length("a".repeat(1_000)).await?, 1_000);
+        assert_eq!(*length("a".repeat(10_000)).await?, 10_000);
+        let err = length("a".repeat(1_000_000)).await.unwrap_err();
+        assert!(err.to_string().contains("exceeds the limit"));
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    let largest = tt.largest_task_inputs();
+    assert_eq!(largest.len(), 2);
+    assert!(largest[0].size > 10_000);
+    assert!(largest[0].function.ends_with("length"));
+    assert!(largest[1].size > 1_000 && largest[1].size < 10_000);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Length(usize);
+
+#[turbo_tasks::function]
+fn length(value: String) -> LengthVc {
+    LengthVc::cell(value.len())
+}
@@ -0,0 +1,70 @@
+#![feature(min_specialization)]
+
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Mutex,
+};
+
+use anyhow::{anyhow, Context, Result};
+use turbo_tasks::{get_invalidator, Invalidator, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static SOURCE: AtomicU64 = AtomicU64::new(0);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+#[tokio::test]
+async fn falls_back_on_error() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let value = tt
+        .run_once(async { Ok(*config_or_default().strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 42);
+
+    let error = tt
+        .run_once(async {
+            Ok(parse_config()
+                .into_result()
+                .await?
+                .expect_err("parsing should fail"))
+        })
+        .await
+        .unwrap();
+    assert_eq!(error.message(), "parsing config");
+    assert_eq!(error.causes(), ["config is empty"]);
+    assert_eq!(format!("{error:#}"), "parsing config: config is empty");
+
+    // The fallback is reexecuted once parsing succeeds
+    SOURCE.store(7, Ordering::SeqCst);
+    INVALIDATOR.lock().unwrap().take().unwrap().invalidate();
+    let value = tt
+        .run_once(async { Ok(*config_or_default().strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 7);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Config(u64);
+
+#[turbo_tasks::function]
+async fn parse_config() -> Result<ConfigVc> {
+    *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    let source = SOURCE.load(Ordering::SeqCst);
+    if source == 0 {
+        return Err(anyhow!("config is empty")).context("parsing config");
+    }
+    Ok(ConfigVc::cell(source))
+}
+
+#[turbo_tasks::function]
+async fn config_or_default() -> Result<ConfigVc> {
+    match parse_config().into_result().await? {
+        Ok(config) => Ok(config),
+        Err(_) => Ok(ConfigVc::cell(42)),
+    }
+}
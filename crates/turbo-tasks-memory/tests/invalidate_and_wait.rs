@@ -0,0 +1,76 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{get_invalidator, primitives::UsizeVc, Invalidator, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static VALUE: AtomicUsize = AtomicUsize::new(1);
+static DOUBLED: AtomicUsize = AtomicUsize::new(0);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+#[tokio::test]
+async fn invalidate_and_wait() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let doubled = tt
+        .run_once(async {
+            let doubled = double();
+            doubled.await?;
+            Ok(doubled)
+        })
+        .await
+        .unwrap();
+    let _guard = tt.keep_alive(doubled.into());
+    assert_eq!(DOUBLED.load(Ordering::SeqCst), 2);
+
+    // Unrelated work doesn't delay the wait
+    let slow = tokio::spawn({
+        let tt = tt.clone();
+        async move {
+            tt.run_once(async {
+                slow().await?;
+                Ok(())
+            })
+            .await
+        }
+    });
+
+    // The dependent has executed again without reading it
+    VALUE.store(5, Ordering::SeqCst);
+    let invalidator = INVALIDATOR.lock().unwrap().take().unwrap();
+    tokio::time::timeout(Duration::from_secs(2), invalidator.invalidate_and_wait())
+        .await
+        .unwrap();
+    assert_eq!(DOUBLED.load(Ordering::SeqCst), 10);
+    assert!(!slow.is_finished());
+}
+
+#[turbo_tasks::function]
+async fn slow() -> Result<UsizeVc> {
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    Ok(UsizeVc::cell(0))
+}
+
+#[turbo_tasks::function]
+fn value() -> UsizeVc {
+    *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    UsizeVc::cell(VALUE.load(Ordering::SeqCst))
+}
+
+#[turbo_tasks::function]
+async fn double() -> Result<UsizeVc> {
+    let doubled = *value().await? * 2;
+    DOUBLED.store(doubled, Ordering::SeqCst);
+    Ok(UsizeVc::cell(doubled))
+}
@@ -0,0 +1,59 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::Result;
+use turbo_tasks::TurboTasks;
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static CONFIG_EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+static OTHER_EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+
+#[tokio::test]
+async fn invalidates_only_tasks_of_the_function() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let result = tt
+        .run_once(async {
+            let result = combined();
+            assert_eq!(*result.strongly_consistent().await?, 3);
+            Ok(result)
+        })
+        .await
+        .unwrap();
+    assert_eq!(CONFIG_EXECUTIONS.load(Ordering::SeqCst), 1);
+    assert_eq!(OTHER_EXECUTIONS.load(Ordering::SeqCst), 1);
+
+    let _guard = tt.keep_alive(result.into());
+    tt.invalidate_function(*CONFIG_FUNCTION_ID);
+    let value = tt
+        .run_once(async move { Ok(*result.strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 3);
+    assert_eq!(CONFIG_EXECUTIONS.load(Ordering::SeqCst), 2);
+    assert_eq!(OTHER_EXECUTIONS.load(Ordering::SeqCst), 1);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u32);
+
+#[turbo_tasks::function]
+fn config() -> ValueVc {
+    CONFIG_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    ValueVc::cell(1)
+}
+
+#[turbo_tasks::function]
+fn other() -> ValueVc {
+    OTHER_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    ValueVc::cell(2)
+}
+
+#[turbo_tasks::function]
+async fn combined() -> Result<ValueVc> {
+    Ok(ValueVc::cell(*config().await? + *other().await?))
+}
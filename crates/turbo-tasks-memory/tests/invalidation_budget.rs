@@ -0,0 +1,84 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{InvalidationBudget, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::{
+    counter::{Counter, CounterValueVc, CounterVc},
+    register,
+};
+
+register!();
+
+static ITEM_EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn defers_invalidated_tasks() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .invalidation_budget(InvalidationBudget {
+            max_tasks_per_tick: 2,
+            tick: Duration::from_millis(50),
+        })
+        .build()
+        .unwrap();
+    let (counter, sum) = tt
+        .run_once(async {
+            let counter = CounterVc::cell(Counter::new(1));
+            let sum = sum(counter);
+            assert_eq!(*sum.strongly_consistent().await?, 10);
+            Ok((counter, sum))
+        })
+        .await
+        .unwrap();
+    assert_eq!(ITEM_EXECUTIONS.load(Ordering::SeqCst), 10);
+
+    let guard = tt.keep_alive(sum.into());
+    tt.run_once(async move {
+        counter.await?.incr();
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    // The invalidated items are re-executed over multiple ticks
+    let mut max_deferred = 0;
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while ITEM_EXECUTIONS.load(Ordering::SeqCst) < 20 {
+            max_deferred = max_deferred.max(tt.deferred_invalidations_count());
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await
+    .unwrap();
+    drop(guard);
+    assert!(max_deferred > 0);
+
+    let value = tt
+        .run_once(async move { Ok(*sum.strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 20);
+    assert_eq!(tt.deferred_invalidations_count(), 0);
+    assert_eq!(ITEM_EXECUTIONS.load(Ordering::SeqCst), 20);
+}
+
+#[turbo_tasks::function]
+async fn item(counter: CounterVc, _index: usize) -> Result<CounterValueVc> {
+    ITEM_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    Ok(CounterValueVc::cell(*counter.get_value().await?))
+}
+
+#[turbo_tasks::function]
+async fn sum(counter: CounterVc) -> Result<CounterValueVc> {
+    let mut sum = 0;
+    for i in 0..10 {
+        sum += *item(counter, i).await?;
+    }
+    Ok(CounterValueVc::cell(sum))
+}
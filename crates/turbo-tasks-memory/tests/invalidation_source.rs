@@ -0,0 +1,219 @@
+#![feature(min_specialization)]
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use lazy_static::lazy_static;
+use turbo_tasks::{
+    get_invalidator, primitives::StringVc, test_helpers::current_task_for_testing, turbo_tasks,
+    InvalidationSource, Invalidator, ResourceWatcher, TurboTasks,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[derive(Default)]
+struct Watcher {
+    log: Mutex<Vec<String>>,
+}
+
+impl ResourceWatcher for Watcher {
+    type Key = String;
+
+    fn watch(&self, key: &String) {
+        self.log.lock().unwrap().push(format!("watch {key}"));
+    }
+
+    fn unwatch(&self, key: &String) {
+        self.log.lock().unwrap().push(format!("unwatch {key}"));
+    }
+}
+
+lazy_static! {
+    static ref SOURCE: InvalidationSource<Watcher> = InvalidationSource::new(Watcher::default());
+    static ref VERSIONS: Mutex<HashMap<String, usize>> = Mutex::new(HashMap::new());
+    static ref RELEASE_SOURCE: InvalidationSource<Watcher> =
+        InvalidationSource::new(Watcher::default());
+    static ref HOOK_TOKEN: Arc<()> = Arc::new(());
+}
+
+fn log() -> Vec<String> {
+    SOURCE.watcher().log.lock().unwrap().clone()
+}
+
+#[tokio::test]
+async fn unwatch_released_resources() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (selector, value) = tt
+        .run_once(async {
+            let selector = SelectorVc::cell(Selector {
+                key: Mutex::new(("a".to_string(), None)),
+            });
+            let value = read_resource(selector);
+            assert_eq!(*value.strongly_consistent().await?, "a:0");
+            Ok((selector, value))
+        })
+        .await
+        .unwrap();
+    // Nothing needs the task after the run, so it's released
+    settle(&tt).await;
+    assert_eq!(log(), ["watch a", "unwatch a"]);
+    let _guard = tt.keep_alive(value.into());
+
+    // The resource stays watched while the task executes again
+    *VERSIONS.lock().unwrap().entry("a".to_string()).or_default() += 1;
+    SOURCE.invalidate(&"a".to_string());
+    let read = tt
+        .run_once(async move { Ok(value.strongly_consistent().await?.clone_value()) })
+        .await
+        .unwrap();
+    assert_eq!(read, "a:1");
+    assert_eq!(log(), ["watch a", "unwatch a", "watch a"]);
+
+    // The task doesn't read the resource anymore
+    tt.run_once(async move {
+        selector.await?.select("b");
+        Ok(())
+    })
+    .await
+    .unwrap();
+    let read = tt
+        .run_once(async move { Ok(value.strongly_consistent().await?.clone_value()) })
+        .await
+        .unwrap();
+    assert_eq!(read, "b:0");
+    assert_eq!(
+        log(),
+        ["watch a", "unwatch a", "watch a", "watch b", "unwatch a"]
+    );
+    assert!(!SOURCE.is_watched(&"a".to_string()));
+    assert_eq!(SOURCE.watched_count(), 1);
+}
+
+#[tokio::test]
+async fn unwatch_resources_of_released_tasks() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let value = tt
+        .run_once(async { Ok(read_release_resource("c".to_string())) })
+        .await
+        .unwrap();
+    let guard = tt.keep_alive(value.into());
+    let read = tt
+        .run_once(async move { Ok(value.strongly_consistent().await?.clone_value()) })
+        .await
+        .unwrap();
+    assert_eq!(read, "c");
+    settle(&tt).await;
+    assert!(RELEASE_SOURCE.is_watched(&"c".to_string()));
+
+    // Nothing needs the task anymore
+    drop(guard);
+    settle(&tt).await;
+    assert!(!RELEASE_SOURCE.is_watched(&"c".to_string()));
+
+    // The released task has been invalidated, so it registers again when it's
+    // needed again
+    let _guard = tt.keep_alive(value.into());
+    let read = tt
+        .run_once(async move { Ok(value.strongly_consistent().await?.clone_value()) })
+        .await
+        .unwrap();
+    assert_eq!(read, "c");
+    settle(&tt).await;
+    assert!(RELEASE_SOURCE.is_watched(&"c".to_string()));
+}
+
+#[tokio::test]
+async fn drop_execution_hooks_of_released_tasks() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let read = tt
+        .run_once(async {
+            Ok(read_with_execution_hook("d".to_string())
+                .strongly_consistent()
+                .await?
+                .clone_value())
+        })
+        .await
+        .unwrap();
+    assert_eq!(read, "d");
+    // Nothing needs the task after the run, so it's released and won't
+    // execute again
+    settle(&tt).await;
+    assert_eq!(Arc::strong_count(&HOOK_TOKEN), 1);
+}
+
+/// Waits until no tasks are executing, including the scope updates after
+/// their executions. The update info is reported when the last of them is
+/// done, a stale one from an earlier update is taken and waited past.
+async fn settle(tt: &TurboTasks<MemoryBackend>) {
+    while tt.get_in_progress_count() > 0 {
+        tt.get_or_wait_update_info(Duration::ZERO).await;
+    }
+}
+
+#[turbo_tasks::value(serialization = "none", cell = "new", eq = "manual")]
+struct Selector {
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    key: Mutex<(String, Option<Invalidator>)>,
+}
+
+impl Selector {
+    fn select(&self, key: &str) {
+        let mut lock = self.key.lock().unwrap();
+        lock.0 = key.to_string();
+        if let Some(i) = lock.1.take() {
+            i.invalidate();
+        }
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl SelectorVc {
+    #[turbo_tasks::function]
+    async fn key(self) -> Result<StringVc> {
+        let this = self.await?;
+        let mut lock = this.key.lock().unwrap();
+        lock.1 = Some(get_invalidator());
+        Ok(StringVc::cell(lock.0.clone()))
+    }
+}
+
+#[turbo_tasks::function]
+async fn read_resource(selector: SelectorVc) -> Result<StringVc> {
+    let key = selector.key().await?.clone_value();
+    SOURCE.register(key.clone());
+    let version = VERSIONS
+        .lock()
+        .unwrap()
+        .get(&key)
+        .copied()
+        .unwrap_or_default();
+    Ok(StringVc::cell(format!("{key}:{version}")))
+}
+
+#[turbo_tasks::function]
+fn read_release_resource(key: String) -> StringVc {
+    RELEASE_SOURCE.register(key.clone());
+    StringVc::cell(key)
+}
+
+#[turbo_tasks::function]
+fn read_with_execution_hook(key: String) -> StringVc {
+    let token = HOOK_TOKEN.clone();
+    turbo_tasks().on_next_execution(
+        current_task_for_testing(),
+        Box::new(move || {
+            drop(token);
+            Box::new(|| {})
+        }),
+    );
+    StringVc::cell(key)
+}
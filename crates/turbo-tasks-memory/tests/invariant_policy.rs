@@ -0,0 +1,58 @@
+#![feature(min_specialization)]
+// Violations are only recovered from in release builds
+#![cfg(not(debug_assertions))]
+
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::{Duration, Instant},
+};
+
+use turbo_tasks::{backend::Backend, RawVc, TurboTasks};
+use turbo_tasks_memory::{InvariantPolicy, MemoryBackend};
+use turbo_tasks_testing::register;
+
+register!();
+
+static EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+
+#[tokio::test]
+async fn execution_started_while_dirty_schedules_the_task() {
+    lazy_static::initialize(&REGISTER);
+    let tt =
+        TurboTasks::new(MemoryBackend::new().with_invariant_policy(InvariantPolicy::LogInRelease));
+    let task = tt
+        .run_once(async {
+            let count = counted();
+            count.await?;
+            Ok(RawVc::from(count).get_task_id())
+        })
+        .await
+        .unwrap();
+    // The task is not in an active scope anymore, so it stays dirty
+    tt.invalidate_function(*COUNTED_FUNCTION_ID);
+    assert!(tt.backend().with_task(task, |task| task.is_dirty()));
+
+    assert!(tt.backend().try_start_task_execution(task, &*tt).is_none());
+    assert_eq!(tt.backend().recovered_invariant_violations(), 1);
+
+    // The task is scheduled instead of being left dirty, so it executes without
+    // being read
+    let start = Instant::now();
+    while EXECUTIONS.load(Ordering::SeqCst) < 2 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "the task was not executed again"
+        );
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    let value = tt.run_once(async { Ok(*counted().await?) }).await.unwrap();
+    assert_eq!(value, 2);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Count(u32);
+
+#[turbo_tasks::function]
+fn counted() -> CountVc {
+    CountVc::cell(EXECUTIONS.fetch_add(1, Ordering::SeqCst) + 1)
+}
@@ -0,0 +1,43 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::join_all;
+use turbo_tasks_testing::{
+    counter::{Counter, CounterValueVc, CounterVc},
+    register, run,
+};
+
+register!();
+
+#[tokio::test]
+async fn read_all() {
+    run! {
+        let values = join_all((0..10).map(double)).await?;
+        let values: Vec<usize> = values.iter().map(|value| **value).collect();
+        assert_eq!(values, (0..10).map(|i| i * 2).collect::<Vec<_>>());
+    }
+}
+
+#[tokio::test]
+async fn tracks_dependencies() {
+    run! {
+        let counter = CounterVc::cell(Counter::new(1));
+        let sum = sum(counter);
+        assert_eq!(*sum.await?, 5);
+        counter.await?.incr();
+        assert_eq!(*sum.strongly_consistent().await?, 6);
+    }
+}
+
+#[turbo_tasks::function]
+fn double(value: usize) -> CounterValueVc {
+    CounterValueVc::cell(value * 2)
+}
+
+#[turbo_tasks::function]
+async fn sum(counter: CounterVc) -> Result<CounterValueVc> {
+    let values = join_all([counter.get_value(), double(2)]).await?;
+    Ok(CounterValueVc::cell(
+        values.iter().map(|value| **value).sum(),
+    ))
+}
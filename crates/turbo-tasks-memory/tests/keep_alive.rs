@@ -0,0 +1,65 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::TurboTasks;
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::{
+    counter::{Counter, CounterValueVc, CounterVc},
+    register,
+};
+
+register!();
+
+static EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn keep_alive() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (counter, doubled) = tt
+        .run_once(async {
+            let counter = CounterVc::cell(Counter::new(1));
+            let doubled = double(counter);
+            assert_eq!(*doubled.strongly_consistent().await?, 2);
+            Ok((counter, doubled))
+        })
+        .await
+        .unwrap();
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 1);
+
+    let guard = tt.keep_alive(doubled.into());
+    tt.run_once(async move {
+        counter.await?.incr();
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    // The task is recomputed without being read
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while EXECUTIONS.load(Ordering::SeqCst) < 2 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+    drop(guard);
+
+    let value = tt
+        .run_once(async move { Ok(*doubled.strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 4);
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 2);
+}
+
+#[turbo_tasks::function]
+async fn double(counter: CounterVc) -> Result<CounterValueVc> {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    Ok(CounterValueVc::cell(*counter.get_value().await? * 2))
+}
@@ -0,0 +1,113 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use turbo_tasks_testing::{
+    counter::{Counter, CounterVc},
+    register, run,
+};
+
+register!();
+
+#[tokio::test]
+async fn equal_cell_does_not_notify() {
+    run! {
+        let counter = CounterVc::cell(Counter::new(0));
+        assert_eq!(*read_number(counter).strongly_consistent().await?, 1);
+        counter.await?.incr();
+        assert_eq!(*read_number(counter).strongly_consistent().await?, 1);
+        assert_eq!(NUMBER_READS.load(Ordering::SeqCst), 1);
+    }
+}
+
+#[tokio::test]
+async fn different_cell_notifies() {
+    run! {
+        let counter = CounterVc::cell(Counter::new(0));
+        assert_eq!(*read_plain(counter).strongly_consistent().await?, 1);
+        counter.await?.incr();
+        assert_eq!(*read_plain(counter).strongly_consistent().await?, 1);
+        assert_eq!(PLAIN_READS.load(Ordering::SeqCst), 2);
+    }
+}
+
+#[tokio::test]
+async fn equal_cell_notifies_on_later_changes() {
+    run! {
+        let counter = CounterVc::cell(Counter::new(0));
+        let source = CounterVc::cell(Counter::new(0));
+        assert_eq!(*read_tracked(counter, source).strongly_consistent().await?, 1);
+        counter.await?.incr();
+        assert_eq!(*read_tracked(counter, source).strongly_consistent().await?, 1);
+        assert_eq!(TRACKED_READS.load(Ordering::SeqCst), 1);
+        // The reader depends on the equal cell the output has been linked to
+        source.await?.incr();
+        assert_eq!(*read_tracked(counter, source).strongly_consistent().await?, 2);
+        assert_eq!(TRACKED_READS.load(Ordering::SeqCst), 2);
+    }
+}
+
+static NUMBER_READS: AtomicUsize = AtomicUsize::new(0);
+static PLAIN_READS: AtomicUsize = AtomicUsize::new(0);
+static TRACKED_READS: AtomicUsize = AtomicUsize::new(0);
+
+#[turbo_tasks::value(transparent, link_eq)]
+struct Number(u32);
+
+#[turbo_tasks::value(transparent)]
+struct Plain(u32);
+
+#[turbo_tasks::function]
+fn number(_variant: u32) -> NumberVc {
+    NumberVc::cell(1)
+}
+
+#[turbo_tasks::function]
+fn plain(_variant: u32) -> PlainVc {
+    PlainVc::cell(1)
+}
+
+/// Returns an equal cell of a different task whenever the counter changes.
+#[turbo_tasks::function]
+async fn pick_number(counter: CounterVc) -> Result<NumberVc> {
+    let variant = *counter.get_value().await? as u32;
+    number(variant).resolve().await
+}
+
+#[turbo_tasks::function]
+async fn pick_plain(counter: CounterVc) -> Result<PlainVc> {
+    let variant = *counter.get_value().await? as u32;
+    plain(variant).resolve().await
+}
+
+/// The value depends on the source only, so the cells of all variants are
+/// equal.
+#[turbo_tasks::function]
+async fn tracked(_variant: u32, source: CounterVc) -> Result<NumberVc> {
+    Ok(NumberVc::cell(*source.get_value().await? as u32 + 1))
+}
+
+#[turbo_tasks::function]
+async fn pick_tracked(counter: CounterVc, source: CounterVc) -> Result<NumberVc> {
+    let variant = *counter.get_value().await? as u32;
+    tracked(variant, source).resolve().await
+}
+
+#[turbo_tasks::function]
+async fn read_tracked(counter: CounterVc, source: CounterVc) -> Result<NumberVc> {
+    TRACKED_READS.fetch_add(1, Ordering::SeqCst);
+    Ok(NumberVc::cell(*pick_tracked(counter, source).await?))
+}
+
+#[turbo_tasks::function]
+async fn read_number(counter: CounterVc) -> Result<NumberVc> {
+    NUMBER_READS.fetch_add(1, Ordering::SeqCst);
+    Ok(NumberVc::cell(*pick_number(counter).await?))
+}
+
+#[turbo_tasks::function]
+async fn read_plain(counter: CounterVc) -> Result<PlainVc> {
+    PLAIN_READS.fetch_add(1, Ordering::SeqCst);
+    Ok(PlainVc::cell(*pick_plain(counter).await?))
+}
@@ -0,0 +1,92 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::Result;
+use turbo_tasks::{mark_final, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static CONSTANT_EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+static READER_EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+static FIRST_FINAL_EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+
+#[tokio::test]
+async fn reads_of_final_tasks_are_not_tracked() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async {
+        assert_eq!(*constant().strongly_consistent().await?, 42);
+        Ok(())
+    })
+    .await
+    .unwrap();
+    let pruned = tt.backend().pruned_dependencies();
+
+    let result = tt
+        .run_once(async {
+            let result = reader();
+            assert_eq!(*result.strongly_consistent().await?, 43);
+            Ok(result)
+        })
+        .await
+        .unwrap();
+    // The output and the cell of the final task
+    assert_eq!(tt.backend().pruned_dependencies() - pruned, 2);
+
+    let _guard = tt.keep_alive(result.into());
+    tt.invalidate_function(*CONSTANT_FUNCTION_ID);
+    let value = tt
+        .run_once(async move { Ok(*result.strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 43);
+    assert_eq!(CONSTANT_EXECUTIONS.load(Ordering::SeqCst), 2);
+    assert_eq!(READER_EXECUTIONS.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn executing_again_resets_final() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let read = || tt.run_once(async { Ok(*first_final().strongly_consistent().await?) });
+    assert_eq!(read().await.unwrap(), 1);
+    let pruned = tt.backend().pruned_dependencies();
+    assert_eq!(read().await.unwrap(), 1);
+    assert!(tt.backend().pruned_dependencies() > pruned);
+
+    // The second execution doesn't mark the task final, so it's read tracked
+    tt.invalidate_function(*FIRST_FINAL_FUNCTION_ID);
+    assert_eq!(read().await.unwrap(), 2);
+    let pruned = tt.backend().pruned_dependencies();
+    assert_eq!(read().await.unwrap(), 2);
+    assert_eq!(tt.backend().pruned_dependencies(), pruned);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u32);
+
+#[turbo_tasks::function]
+fn constant() -> ValueVc {
+    CONSTANT_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    mark_final();
+    ValueVc::cell(42)
+}
+
+#[turbo_tasks::function]
+async fn reader() -> Result<ValueVc> {
+    READER_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    Ok(ValueVc::cell(*constant().await? + 1))
+}
+
+/// Only the first execution marks the task final.
+#[turbo_tasks::function]
+fn first_final() -> ValueVc {
+    let execution = FIRST_FINAL_EXECUTIONS.fetch_add(1, Ordering::SeqCst) + 1;
+    if execution == 1 {
+        mark_final();
+    }
+    ValueVc::cell(execution)
+}
@@ -0,0 +1,106 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{join_all, primitives::U64Vc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+static MAX_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+static RECURSIVE_RUNNING: AtomicUsize = AtomicUsize::new(0);
+static RECURSIVE_MAX_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+static BLOCKING_RUNNING: AtomicUsize = AtomicUsize::new(0);
+static BLOCKING_MAX_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn limits_concurrent_executions() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let values = tt
+        .run_once(async {
+            let values = join_all((0..8).map(limited)).await?;
+            Ok(values.iter().map(|value| **value).collect::<Vec<_>>())
+        })
+        .await
+        .unwrap();
+    assert_eq!(values, (0..8).collect::<Vec<_>>());
+    assert_eq!(MAX_RUNNING.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn limits_concurrent_blocking_executions() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .worker_threads(2)
+        .build()
+        .unwrap();
+    let values = tt
+        .run_once(async {
+            let values = join_all((0..4).map(limited_blocking)).await?;
+            Ok(values.iter().map(|value| **value).collect::<Vec<_>>())
+        })
+        .await
+        .unwrap();
+    assert_eq!(values, (0..4).collect::<Vec<_>>());
+    assert_eq!(BLOCKING_MAX_RUNNING.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn recursion_beyond_the_limit() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let value = tokio::time::timeout(
+        Duration::from_secs(10),
+        tt.run_once(async { Ok(*recursive(8).await?) }),
+    )
+    .await
+    .expect("waiting for the recursive calls should release the permit")
+    .unwrap();
+    assert_eq!(value, 36);
+    assert_eq!(RECURSIVE_MAX_RUNNING.load(Ordering::SeqCst), 1);
+}
+
+#[turbo_tasks::function(max_concurrency = 2)]
+async fn limited(value: u64) -> Result<U64Vc> {
+    let running = RUNNING.fetch_add(1, Ordering::SeqCst) + 1;
+    MAX_RUNNING.fetch_max(running, Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    RUNNING.fetch_sub(1, Ordering::SeqCst);
+    Ok(U64Vc::cell(value))
+}
+
+#[turbo_tasks::function(blocking, max_concurrency = 1)]
+fn limited_blocking(value: u64) -> U64Vc {
+    let running = BLOCKING_RUNNING.fetch_add(1, Ordering::SeqCst) + 1;
+    BLOCKING_MAX_RUNNING.fetch_max(running, Ordering::SeqCst);
+    std::thread::sleep(Duration::from_millis(20));
+    BLOCKING_RUNNING.fetch_sub(1, Ordering::SeqCst);
+    U64Vc::cell(value)
+}
+
+/// Sums up the numbers up to `value`, with a call for every number, which is
+/// much deeper than the limit.
+#[turbo_tasks::function(max_concurrency = 1)]
+async fn recursive(value: u64) -> Result<U64Vc> {
+    let running = RECURSIVE_RUNNING.fetch_add(1, Ordering::SeqCst) + 1;
+    RECURSIVE_MAX_RUNNING.fetch_max(running, Ordering::SeqCst);
+    let sum = if value == 0 {
+        0
+    } else {
+        RECURSIVE_RUNNING.fetch_sub(1, Ordering::SeqCst);
+        let sum = *recursive(value - 1).await?;
+        RECURSIVE_RUNNING.fetch_add(1, Ordering::SeqCst);
+        sum + value
+    };
+    RECURSIVE_RUNNING.fetch_sub(1, Ordering::SeqCst);
+    Ok(U64Vc::cell(sum))
+}
@@ -0,0 +1,66 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use turbo_tasks::{memoize, primitives::U64Vc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static SQUARES: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(PartialEq, Eq, Hash)]
+struct Square(u64);
+
+fn square(value: u64) -> u64 {
+    memoize(Square(value), |Square(value)| {
+        SQUARES.fetch_add(1, Ordering::SeqCst);
+        value * value
+    })
+}
+
+#[tokio::test]
+async fn shares_results_across_tasks() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async {
+        assert_eq!(*square_plus(1).strongly_consistent().await?, 50);
+        assert_eq!(*square_plus(2).strongly_consistent().await?, 51);
+        Ok(())
+    })
+    .await
+    .unwrap();
+    assert_eq!(SQUARES.load(Ordering::SeqCst), 1);
+    let stats = tt.backend().memo_table_stats();
+    assert_eq!(stats.misses, 1);
+    assert_eq!(stats.hits, 1);
+}
+
+#[derive(PartialEq, Eq, Hash)]
+struct Length(&'static str);
+
+#[tokio::test]
+async fn evicts_unused_results() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new().with_memo_eviction(0));
+    tt.run_once(async {
+        assert_eq!(memoize(Length("abc"), |Length(s)| s.len()), 3);
+        Ok(())
+    })
+    .await
+    .unwrap();
+    tt.get_or_wait_update_info(Duration::ZERO).await;
+    tt.wait_background_done().await;
+    let stats = tt.backend().memo_table_stats();
+    assert_eq!(stats.entries, 0);
+    assert_eq!(stats.evictions, 1);
+}
+
+#[turbo_tasks::function]
+fn square_plus(value: u64) -> U64Vc {
+    U64Vc::cell(square(7) + value)
+}
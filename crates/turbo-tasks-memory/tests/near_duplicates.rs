@@ -0,0 +1,44 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{TaskInput, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn reports_tasks_differing_in_noisy_inputs() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async {
+        load("a".to_string(), 1).await?;
+        load("a".to_string(), 2).await?;
+        load("a".to_string(), 3).await?;
+        load("b".to_string(), 1).await?;
+        load("c".to_string(), 1).await?;
+        load("c".to_string(), 2).await?;
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    let backend = tt.backend();
+    let clusters = backend.near_duplicate_tasks(|input| matches!(input, TaskInput::U64(_)));
+    assert_eq!(clusters.len(), 2);
+    assert!(clusters
+        .iter()
+        .all(|cluster| cluster.function == *LOAD_FUNCTION_ID));
+    assert_eq!(clusters[0].tasks.len(), 3);
+    assert_eq!(clusters[1].tasks.len(), 2);
+
+    assert!(backend.near_duplicate_tasks(|_| false).is_empty());
+}
+
+#[turbo_tasks::value(transparent)]
+struct Content(String);
+
+#[turbo_tasks::function]
+fn load(path: String, timestamp: u64) -> ContentVc {
+    ContentVc::cell(format!("{path}@{timestamp}"))
+}
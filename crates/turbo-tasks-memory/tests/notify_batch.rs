@@ -0,0 +1,48 @@
+#![feature(min_specialization)]
+
+use std::collections::HashSet;
+
+use turbo_tasks::{primitives::U64Vc, registry, RawVc, TurboTasks, TurboTasksBackendApi};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn notifies_batched_tasks_once() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let value = tt
+        .run_once(async {
+            let value = value();
+            value.strongly_consistent().await?;
+            Ok(value)
+        })
+        .await
+        .unwrap();
+    let task = RawVc::from(value).get_task_id();
+    let function = registry::get_function_id(&VALUE_FUNCTION);
+    let backend = tt.backend();
+
+    {
+        let _batch = tt.batch_notify_tasks();
+        tt.schedule_notify_tasks(&[task]);
+        {
+            let _nested = tt.batch_notify_tasks();
+            tt.schedule_notify_tasks_set(&HashSet::from([task]));
+        }
+        assert_eq!(backend.invalidations_for_function(function), 0);
+    }
+    assert_eq!(backend.invalidations_for_function(function), 1);
+
+    let value = tt
+        .run_once(async move { Ok(*value.strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 42);
+}
+
+#[turbo_tasks::function]
+fn value() -> U64Vc {
+    U64Vc::cell(42)
+}
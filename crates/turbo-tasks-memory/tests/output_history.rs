@@ -0,0 +1,64 @@
+#![feature(min_specialization)]
+
+use anyhow::{bail, Result};
+use turbo_tasks::{RawVc, TurboTasks};
+use turbo_tasks_memory::{MemoryBackend, OutputContent};
+use turbo_tasks_testing::{
+    counter::{Counter, CounterValueVc, CounterVc},
+    register,
+};
+
+register!();
+
+#[tokio::test]
+async fn output_history() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new().with_output_history(2));
+    let even = tt
+        .run_once(async {
+            let counter = CounterVc::cell(Counter::new(0));
+            let even = even_value(counter);
+            assert_eq!(*even.strongly_consistent().await?, 0);
+            counter.await?.incr();
+            assert!(even.strongly_consistent().await.is_err());
+            counter.await?.incr();
+            assert_eq!(*even.strongly_consistent().await?, 2);
+            Ok(even)
+        })
+        .await
+        .unwrap();
+
+    let task = RawVc::from(even).get_task_id();
+    let history = tt.backend().output_history(task);
+    assert_eq!(history.len(), 2);
+    assert!(matches!(history[0].content, OutputContent::Error(_)));
+    assert!(matches!(history[1].content, OutputContent::Link(_)));
+    assert!(history[0].time <= history[1].time);
+}
+
+#[tokio::test]
+async fn output_history_disabled() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let even = tt
+        .run_once(async {
+            let counter = CounterVc::cell(Counter::new(0));
+            let even = even_value(counter);
+            even.await?;
+            Ok(even)
+        })
+        .await
+        .unwrap();
+
+    let task = RawVc::from(even).get_task_id();
+    assert!(tt.backend().output_history(task).is_empty());
+}
+
+#[turbo_tasks::function]
+async fn even_value(counter: CounterVc) -> Result<CounterValueVc> {
+    let value = *counter.get_value().await?;
+    if value % 2 == 1 {
+        bail!("{value} is odd");
+    }
+    Ok(CounterValueVc::cell(value))
+}
@@ -0,0 +1,89 @@
+#![feature(min_specialization)]
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use anyhow::Result;
+use turbo_tasks::{
+    get_invalidator,
+    primitives::{BoolVc, UsizeVc},
+    Invalidator, RawVc, TurboTasks,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static SELECT_A: AtomicBool = AtomicBool::new(true);
+static SELECT_INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+static READ_INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+#[tokio::test]
+async fn memoized_output_link() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    assert_eq!(read(&tt).await, 1);
+    let outer: RawVc = tt.run_once(async { Ok(outer().into()) }).await.unwrap();
+    let outer = outer.get_task_id();
+
+    // Reading the outer output again memoizes the cell it resolves to
+    READ_INVALIDATOR
+        .lock()
+        .unwrap()
+        .take()
+        .unwrap()
+        .invalidate();
+    assert_eq!(read(&tt).await, 1);
+    assert!(tt.backend().memoized_output_cell(outer).is_some());
+
+    // The reader only depends on the outer output now, but is notified when
+    // the inner output links elsewhere
+    SELECT_A.store(false, Ordering::SeqCst);
+    SELECT_INVALIDATOR
+        .lock()
+        .unwrap()
+        .take()
+        .unwrap()
+        .invalidate();
+    assert_eq!(read(&tt).await, 2);
+}
+
+async fn read(tt: &TurboTasks<MemoryBackend>) -> usize {
+    tt.run_once(async { Ok(*read_outer().strongly_consistent().await?) })
+        .await
+        .unwrap()
+}
+
+#[turbo_tasks::function]
+fn select() -> BoolVc {
+    *SELECT_INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    BoolVc::cell(SELECT_A.load(Ordering::SeqCst))
+}
+
+#[turbo_tasks::function]
+fn a() -> UsizeVc {
+    UsizeVc::cell(1)
+}
+
+#[turbo_tasks::function]
+fn b() -> UsizeVc {
+    UsizeVc::cell(2)
+}
+
+#[turbo_tasks::function]
+async fn inner() -> Result<UsizeVc> {
+    Ok(if *select().await? { a() } else { b() })
+}
+
+#[turbo_tasks::function]
+fn outer() -> UsizeVc {
+    inner()
+}
+
+#[turbo_tasks::function]
+async fn read_outer() -> Result<UsizeVc> {
+    *READ_INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    Ok(UsizeVc::cell(*outer().await?))
+}
@@ -0,0 +1,119 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::atomic::{AtomicU32, Ordering},
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{FunctionId, RawVc, TaskId, TurboTasks};
+use turbo_tasks_memory::{MemoryBackend, OutputNotComputed};
+use turbo_tasks_testing::register;
+
+register!();
+
+static ABORTED_EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+static WAITED_EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+static FROZEN_EXECUTIONS: AtomicU32 = AtomicU32::new(0);
+
+#[tokio::test]
+async fn read_of_aborted_first_execution_fails() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    abort_first_execution(&tt, *ABORTED_FUNCTION_ID, aborted).await;
+
+    let not_computed = tt
+        .run_once(async {
+            let error = aborted().await.err().unwrap();
+            Ok(error.downcast_ref::<OutputNotComputed>().is_some())
+        })
+        .await
+        .unwrap();
+    assert!(not_computed);
+    assert_eq!(ABORTED_EXECUTIONS.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn read_of_aborted_first_execution_waits() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.backend().wait_for_first_execution(*WAITED_FUNCTION_ID);
+    abort_first_execution(&tt, *WAITED_FUNCTION_ID, waited).await;
+
+    let value = tt.run_once(async { Ok(*waited().await?) }).await.unwrap();
+    // The read executed the task again
+    assert_eq!(value, 1);
+    assert_eq!(WAITED_EXECUTIONS.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn read_of_frozen_aborted_task_fails() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.backend().wait_for_first_execution(*FROZEN_FUNCTION_ID);
+    let task = abort_first_execution(&tt, *FROZEN_FUNCTION_ID, frozen).await;
+    assert_eq!(tt.backend().freeze_subtree(task), 1);
+
+    // Frozen tasks don't execute again, so the read can't wait for them
+    let not_computed = tt
+        .run_once(async {
+            let error = frozen().await.err().unwrap();
+            Ok(error.downcast_ref::<OutputNotComputed>().is_some())
+        })
+        .await
+        .unwrap();
+    assert!(not_computed);
+    assert_eq!(FROZEN_EXECUTIONS.load(Ordering::SeqCst), 1);
+}
+
+/// Starts the first execution of the task of the function, which is only
+/// needed by a once task, and invalidates it while it's executing, after the
+/// once task has completed. Returns the task.
+async fn abort_first_execution(
+    tt: &TurboTasks<MemoryBackend>,
+    function: FunctionId,
+    call: fn() -> NumberVc,
+) -> TaskId {
+    let task = tt
+        .run_once(async move { Ok(RawVc::from(call()).get_task_id()) })
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    tt.invalidate_function(function);
+    while tt.get_in_progress_count() > 0 {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    task
+}
+
+#[turbo_tasks::value(transparent)]
+struct Number(u32);
+
+async fn slow_first_execution(executions: &AtomicU32) -> u32 {
+    let execution = executions.fetch_add(1, Ordering::SeqCst);
+    if execution == 0 {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }
+    execution
+}
+
+#[turbo_tasks::function]
+async fn aborted() -> Result<NumberVc> {
+    Ok(NumberVc::cell(
+        slow_first_execution(&ABORTED_EXECUTIONS).await,
+    ))
+}
+
+#[turbo_tasks::function]
+async fn waited() -> Result<NumberVc> {
+    Ok(NumberVc::cell(
+        slow_first_execution(&WAITED_EXECUTIONS).await,
+    ))
+}
+
+#[turbo_tasks::function]
+async fn frozen() -> Result<NumberVc> {
+    Ok(NumberVc::cell(
+        slow_first_execution(&FROZEN_EXECUTIONS).await,
+    ))
+}
@@ -0,0 +1,137 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, Layer};
+use turbo_tasks::{RawVc, State, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+/// Records the messages of the events of traced tasks.
+#[derive(Clone, Default)]
+struct Messages(Arc<Mutex<Vec<String>>>);
+
+impl Messages {
+    fn invalidations(&self) -> usize {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|message| message.starts_with("invalidated while"))
+            .count()
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{value:?}");
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for Messages {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        self.0.lock().unwrap().push(visitor.0);
+    }
+}
+
+#[tokio::test]
+async fn output_updates_notify_dependents_once_until_read_again() {
+    lazy_static::initialize(&REGISTER);
+    let messages = Messages::default();
+    tracing_subscriber::registry().with(messages.clone()).init();
+
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (input, source, input_ref, task) = tt
+        .run_once(async {
+            let input = InputVc::cell(Input {
+                value: State::new(1),
+            });
+            let dependent = dependent(input);
+            assert_eq!(*dependent.strongly_consistent().await?, 2);
+            Ok((
+                input,
+                source(input),
+                input.await?,
+                RawVc::from(dependent).get_task_id(),
+            ))
+        })
+        .await
+        .unwrap();
+    // Only the source stays active, so the notified dependent doesn't execute
+    // between the updates of the output of the source
+    let _guard = tt.keep_alive(source.into());
+    settle(&tt).await;
+    tt.backend().trace_task(task, true);
+
+    input_ref.value.set(2);
+    settle(&tt).await;
+    input_ref.value.set(3);
+    settle(&tt).await;
+    assert_eq!(messages.invalidations(), 1);
+
+    // After reading the output again, the dependent is notified again
+    let value = tt
+        .run_once(async move { Ok(*dependent(input).await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 4);
+    settle(&tt).await;
+    input_ref.value.set(4);
+    settle(&tt).await;
+    assert_eq!(messages.invalidations(), 2);
+}
+
+/// Waits until no tasks are executing, including the notifications of their
+/// dependents. The update info is reported when the last of them is done, a
+/// stale one from an earlier update is taken and waited past.
+async fn settle(tt: &TurboTasks<MemoryBackend>) {
+    while tt.get_in_progress_count() > 0 {
+        tt.get_or_wait_update_info(Duration::ZERO).await;
+    }
+}
+
+#[turbo_tasks::value(serialization = "none", cell = "new", eq = "manual")]
+struct Input {
+    value: State<u32>,
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u32);
+
+#[turbo_tasks::function]
+fn constant(value: u32) -> ValueVc {
+    ValueVc::cell(value)
+}
+
+/// The output is linked to a different task for every value, so every change
+/// of the value updates the output.
+#[turbo_tasks::function]
+async fn source(input: InputVc) -> Result<ValueVc> {
+    Ok(constant(*input.await?.value.get()))
+}
+
+#[turbo_tasks::function]
+async fn dependent(input: InputVc) -> Result<ValueVc> {
+    // Strongly consistent reads don't memoize the linked cell, so the output
+    // is the only way the dependent is notified
+    Ok(ValueVc::cell(
+        *source(input).strongly_consistent().await? + 1,
+    ))
+}
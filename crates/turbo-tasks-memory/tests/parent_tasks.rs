@@ -0,0 +1,71 @@
+#![feature(min_specialization)]
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use anyhow::Result;
+use turbo_tasks::{get_invalidator, primitives::U64Vc, Invalidator, RawVc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static CALL_SHARED: AtomicBool = AtomicBool::new(true);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+#[tokio::test]
+async fn tracks_parents_of_children() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (a, b, shared) = tt
+        .run_once(async {
+            let a = parent_a();
+            let b = parent_b();
+            a.strongly_consistent().await?;
+            b.strongly_consistent().await?;
+            Ok((a, b, shared()))
+        })
+        .await
+        .unwrap();
+    let a = RawVc::from(a).get_task_id();
+    let b = RawVc::from(b).get_task_id();
+    let shared = RawVc::from(shared).get_task_id();
+    let backend = tt.backend();
+    let parents = backend.parent_tasks(shared);
+    assert!(parents.contains(&a), "{parents:?}");
+    assert!(parents.contains(&b), "{parents:?}");
+
+    // Parents are removed when they execute again without calling the task
+    CALL_SHARED.store(false, Ordering::SeqCst);
+    INVALIDATOR.lock().unwrap().take().unwrap().invalidate();
+    tt.run_once(async move {
+        assert_eq!(*parent_b().strongly_consistent().await?, 0);
+        Ok(())
+    })
+    .await
+    .unwrap();
+    let parents = backend.parent_tasks(shared);
+    assert!(parents.contains(&a), "{parents:?}");
+    assert!(!parents.contains(&b), "{parents:?}");
+}
+
+#[turbo_tasks::function]
+fn shared() -> U64Vc {
+    U64Vc::cell(42)
+}
+
+#[turbo_tasks::function]
+async fn parent_a() -> Result<U64Vc> {
+    Ok(U64Vc::cell(*shared().await? + 1))
+}
+
+#[turbo_tasks::function]
+async fn parent_b() -> Result<U64Vc> {
+    *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    if !CALL_SHARED.load(Ordering::SeqCst) {
+        return Ok(U64Vc::cell(0));
+    }
+    Ok(U64Vc::cell(*shared().await? + 2))
+}
@@ -0,0 +1,64 @@
+#![feature(min_specialization)]
+
+use std::time::Duration;
+
+use anyhow::Result;
+use turbo_tasks::{primitives::U64Vc, NothingVc, RootTaskState, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn awaits_all_roots() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let roots = (0..3)
+        .map(|page| {
+            tt.spawn_root_task(move || {
+                Box::pin(async move {
+                    slow_page(page).await?;
+                    Ok(NothingVc::new().into())
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tt.pending_roots(),
+        roots
+            .iter()
+            .map(|root| (*root, RootTaskState::Executing))
+            .collect::<Vec<_>>()
+    );
+
+    tt.await_all_roots().await;
+    assert!(tt.pending_roots().is_empty());
+}
+
+#[tokio::test]
+async fn reports_settling_roots() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let root = tt.spawn_root_task(|| {
+        Box::pin(async {
+            // The root is done before the page it calls
+            let _ = slow_page(100);
+            Ok(NothingVc::new().into())
+        })
+    });
+    let mut pending = tt.pending_roots();
+    while pending == [(root, RootTaskState::Executing)] {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        pending = tt.pending_roots();
+    }
+    assert_eq!(pending, [(root, RootTaskState::Settling)]);
+
+    tt.await_all_roots().await;
+    assert!(tt.pending_roots().is_empty());
+}
+
+#[turbo_tasks::function]
+async fn slow_page(page: u64) -> Result<U64Vc> {
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    Ok(U64Vc::cell(page))
+}
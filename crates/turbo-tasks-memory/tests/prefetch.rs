@@ -0,0 +1,112 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::TurboTasks;
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::{
+    counter::{Counter, CounterValueVc, CounterVc},
+    register,
+};
+
+register!();
+
+static DOUBLE_EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+static TRIPLE_EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn prefetch() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (counter, doubled) = tt
+        .run_once(async {
+            let counter = new_counter();
+            let doubled = double(counter);
+            assert_eq!(*doubled.strongly_consistent().await?, 2);
+            Ok((counter, doubled))
+        })
+        .await
+        .unwrap();
+    assert_eq!(DOUBLE_EXECUTIONS.load(Ordering::SeqCst), 1);
+
+    // The task is not active anymore, so it's not recomputed on change
+    tt.run_once(async move {
+        counter.await?.incr();
+        doubled.prefetch();
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    wait_for(&DOUBLE_EXECUTIONS, 2).await;
+    let value = tt
+        .run_once(async move { Ok(*doubled.strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 4);
+    assert_eq!(DOUBLE_EXECUTIONS.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn prefetch_with_children() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (counter, parent) = tt
+        .run_once(async {
+            let counter = new_counter();
+            let parent = call_triple(counter);
+            parent.strongly_consistent().await?;
+            Ok((counter, parent))
+        })
+        .await
+        .unwrap();
+    wait_for(&TRIPLE_EXECUTIONS, 1).await;
+
+    // Only the child depends on the counter
+    tt.run_once(async move {
+        counter.await?.incr();
+        parent.prefetch_with_children();
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    wait_for(&TRIPLE_EXECUTIONS, 2).await;
+}
+
+async fn wait_for(executions: &AtomicUsize, count: usize) {
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while executions.load(Ordering::SeqCst) < count {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+fn new_counter() -> CounterVc {
+    CounterVc::cell(Counter::new(1))
+}
+
+#[turbo_tasks::function]
+async fn double(counter: CounterVc) -> Result<CounterValueVc> {
+    DOUBLE_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    Ok(CounterValueVc::cell(counter.await?.get() * 2))
+}
+
+#[turbo_tasks::function]
+async fn triple(counter: CounterVc) -> Result<CounterValueVc> {
+    TRIPLE_EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    Ok(CounterValueVc::cell(counter.await?.get() * 3))
+}
+
+#[turbo_tasks::function]
+fn call_triple(counter: CounterVc) -> CounterValueVc {
+    // The result is not read, so the parent doesn't depend on it
+    triple(counter);
+    CounterValueVc::cell(0)
+}
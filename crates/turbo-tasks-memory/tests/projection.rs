@@ -0,0 +1,73 @@
+#![feature(min_specialization)]
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+use anyhow::Result;
+use turbo_tasks::{get_invalidator, Invalidator, Projection, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static SOURCE: Mutex<(&str, u32)> = Mutex::new(("a", 1));
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+static EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+static EXPORTS: Projection<Module, Exports> = Projection::new(|module| Exports(module.exports));
+
+#[tokio::test]
+async fn invalidates_on_projection_change() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let read = || tt.run_once(async { Ok(*exports_count(parse()).strongly_consistent().await?) });
+    assert_eq!(read().await.unwrap(), 1);
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 1);
+
+    // The code changes, but the exports stay the same
+    update(("b", 1));
+    assert_eq!(read().await.unwrap(), 1);
+    let code = tt
+        .run_once(async { Ok(parse().await?.code.clone()) })
+        .await
+        .unwrap();
+    assert_eq!(code, "b");
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 1);
+
+    update(("b", 2));
+    assert_eq!(read().await.unwrap(), 2);
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 2);
+}
+
+fn update(source: (&'static str, u32)) {
+    *SOURCE.lock().unwrap() = source;
+    INVALIDATOR.lock().unwrap().take().unwrap().invalidate();
+}
+
+#[turbo_tasks::value]
+struct Module {
+    code: String,
+    exports: u32,
+}
+
+#[turbo_tasks::value(transparent)]
+struct Exports(u32);
+
+#[turbo_tasks::function]
+fn parse() -> ModuleVc {
+    *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    let (code, exports) = *SOURCE.lock().unwrap();
+    Module {
+        code: code.to_string(),
+        exports,
+    }
+    .cell()
+}
+
+#[turbo_tasks::function]
+async fn exports_count(module: ModuleVc) -> Result<ExportsVc> {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    Ok(ExportsVc::cell(*module.project(&EXPORTS).await?))
+}
@@ -0,0 +1,113 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{primitives::UsizeVc, ReadConsistency, State, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static DELAY_MS: AtomicU64 = AtomicU64::new(0);
+static HANG: AtomicBool = AtomicBool::new(false);
+
+#[tokio::test]
+async fn returns_stale_value_after_timeout() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (config, config_ref) = tt
+        .run_once(async {
+            let config = ConfigVc::cell(Config {
+                value: State::new(1),
+            });
+            let read = double(config).read_with(ReadConsistency::Strong).await?;
+            assert_eq!(*read.value, 2);
+            assert!(!read.stale);
+            Ok((config, config.await?))
+        })
+        .await
+        .unwrap();
+
+    DELAY_MS.store(500, Ordering::SeqCst);
+    config_ref.value.set(2);
+    let timeout = ReadConsistency::StrongWithTimeout(Duration::from_millis(50));
+    let read = tt
+        .run_once(async move {
+            let read = double(config).read_with(timeout).await?;
+            Ok((*read.value, read.stale))
+        })
+        .await
+        .unwrap();
+    assert_eq!(read, (2, true));
+
+    let read = tt
+        .run_once(async move {
+            let read = double(config).read_with(ReadConsistency::Strong).await?;
+            Ok((*read.value, read.stale))
+        })
+        .await
+        .unwrap();
+    assert_eq!(read, (4, false));
+}
+
+#[tokio::test]
+async fn returns_last_value_of_task_that_never_finishes() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (config, config_ref) = tt
+        .run_once(async {
+            let config = ConfigVc::cell(Config {
+                value: State::new(1),
+            });
+            assert_eq!(*hanging_value(config).strongly_consistent().await?, 1);
+            Ok((config, config.await?))
+        })
+        .await
+        .unwrap();
+
+    HANG.store(true, Ordering::SeqCst);
+    config_ref.value.set(2);
+    let timeout = ReadConsistency::StrongWithTimeout(Duration::from_millis(50));
+    let read = tokio::time::timeout(
+        Duration::from_secs(5),
+        tt.run_once(async move {
+            let read = hanging_value(config).read_with(timeout).await?;
+            Ok((*read.value, read.stale))
+        }),
+    )
+    .await
+    .expect("the read should not wait for the execution")
+    .unwrap();
+    assert_eq!(read, (1, true));
+}
+
+#[turbo_tasks::value(serialization = "none", cell = "new", eq = "manual")]
+struct Config {
+    value: State<usize>,
+}
+
+#[turbo_tasks::function]
+async fn slow_value(config: ConfigVc) -> Result<UsizeVc> {
+    let value = *config.await?.value.get();
+    tokio::time::sleep(Duration::from_millis(DELAY_MS.load(Ordering::SeqCst))).await;
+    Ok(UsizeVc::cell(value))
+}
+
+#[turbo_tasks::function]
+async fn double(config: ConfigVc) -> Result<UsizeVc> {
+    Ok(UsizeVc::cell(*slow_value(config).await? * 2))
+}
+
+/// Never finishes once [HANG] is set.
+#[turbo_tasks::function]
+async fn hanging_value(config: ConfigVc) -> Result<UsizeVc> {
+    let value = *config.await?.value.get();
+    if HANG.load(Ordering::SeqCst) {
+        std::future::pending::<()>().await;
+    }
+    Ok(UsizeVc::cell(value))
+}
@@ -0,0 +1,56 @@
+#![feature(min_specialization)]
+
+use std::time::Duration;
+
+use turbo_tasks::TurboTasks;
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::{
+    counter::{Counter, CounterVc},
+    register, run,
+};
+
+register!();
+
+#[tokio::test]
+async fn read_after_invalidation() {
+    run! {
+        let counter = CounterVc::cell(Counter::new(1));
+        let value = counter.get_value();
+        assert_eq!(*value.await?, 1);
+        counter.await?.incr();
+        assert_eq!(*value.await?, 2);
+    }
+}
+
+#[tokio::test]
+async fn read_dirty_task_from_inactive_scope() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (counter, value) = tt
+        .run_once(async {
+            let counter = CounterVc::cell(Counter::new(1));
+            let value = counter.get_value();
+            assert_eq!(*value.await?, 1);
+            Ok((counter, value))
+        })
+        .await
+        .unwrap();
+
+    // Nothing keeps the task active, so it only becomes dirty
+    tt.run_once(async move {
+        counter.await?.incr();
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    // Reading the dirty task schedules it
+    let value = tokio::time::timeout(
+        Duration::from_secs(10),
+        tt.run_once(async move { Ok(*value.await?) }),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(value, 2);
+}
@@ -0,0 +1,61 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use lazy_static::lazy_static;
+use turbo_tasks::{registry, NativeFunction, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+lazy_static! {
+    /// A function that is only registered as replacement, like the function of
+    /// a reloaded plugin.
+    static ref REPLACEMENT: NativeFunction =
+        NativeFunction::new("replacement".to_string(), |_| {
+            Ok(Box::new(|| Box::pin(async { Ok(ValueVc::cell(2).into()) })))
+        });
+}
+
+#[tokio::test]
+async fn replaced_function_is_reexecuted() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let result = tt
+        .run_once(async {
+            let result = caller();
+            assert_eq!(*result.strongly_consistent().await?, 1);
+            Ok(result)
+        })
+        .await
+        .unwrap();
+
+    let id = registry::get_function_id(&ORIGINAL_FUNCTION);
+    let global_name = registry::get_function_global_name(id);
+    let _guard = tt.keep_alive(result.into());
+    tt.reload_functions(|| registry::register_function(global_name, &REPLACEMENT));
+    assert_eq!(
+        registry::get_function_id_by_global_name(global_name),
+        Some(id)
+    );
+    assert_eq!(registry::get_function_id(&REPLACEMENT), id);
+
+    let value = tt
+        .run_once(async move { Ok(*result.strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 2);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u32);
+
+#[turbo_tasks::function]
+fn original() -> ValueVc {
+    ValueVc::cell(1)
+}
+
+#[turbo_tasks::function]
+async fn caller() -> Result<ValueVc> {
+    Ok(ValueVc::cell(*original().await?))
+}
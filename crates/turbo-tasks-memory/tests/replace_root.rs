@@ -0,0 +1,50 @@
+#![feature(min_specialization)]
+
+use std::{future::Future, pin::Pin, sync::Mutex};
+
+use anyhow::Result;
+use turbo_tasks::{primitives::U64Vc, NothingVc, RawVc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static RENDERED: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+#[tokio::test]
+async fn replaces_root_function() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let root = tt.spawn_root_task(render_root(1));
+    tt.await_all_roots().await;
+    assert_eq!(*RENDERED.lock().unwrap(), vec![1]);
+
+    tt.replace_root(root, render_root(2)).unwrap();
+    tt.await_all_roots().await;
+    assert_eq!(*RENDERED.lock().unwrap(), vec![1, 2]);
+
+    // The page rendered by the first function is still cached
+    tt.replace_root(root, render_root(1)).unwrap();
+    tt.await_all_roots().await;
+    assert_eq!(*RENDERED.lock().unwrap(), vec![1, 2]);
+
+    let once = tt.spawn_once_task(async { Ok(NothingVc::new().into()) });
+    assert!(tt.replace_root(once, render_root(3)).is_err());
+}
+
+fn render_root(
+    page: u64,
+) -> impl Fn() -> Pin<Box<dyn Future<Output = Result<RawVc>> + Send>> + Send + Sync + 'static {
+    move || {
+        Box::pin(async move {
+            render(page).await?;
+            Ok(NothingVc::new().into())
+        })
+    }
+}
+
+#[turbo_tasks::function]
+fn render(page: u64) -> U64Vc {
+    RENDERED.lock().unwrap().push(page);
+    U64Vc::cell(page)
+}
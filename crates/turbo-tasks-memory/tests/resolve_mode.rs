@@ -0,0 +1,72 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{primitives::U64Vc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn deep_resolves_all_arguments() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let tt_inner = tt.clone();
+    tt.run_once(async move {
+        let a = deep_sum(value(), value());
+        let b = deep_sum(forward(), forward());
+        assert_eq!(*a.strongly_consistent().await?, 84);
+        assert_eq!(*b.strongly_consistent().await?, 84);
+        // Only tasks in use are listed, so this checks while the run uses them
+        let backend = tt_inner.backend();
+        assert_eq!(backend.tasks_for_function(*DEEP_SUM_FUNCTION_ID).len(), 1);
+        Ok(())
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn shallow_resolves_first_argument() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let tt_inner = tt.clone();
+    tt.run_once(async move {
+        // Same first argument after resolving, so they share a task
+        let a = shallow_sum(value(), value());
+        let b = shallow_sum(forward(), value());
+        // The second argument is not resolved, so this gets its own task
+        let c = shallow_sum(value(), forward());
+        assert_eq!(*a.strongly_consistent().await?, 84);
+        assert_eq!(*b.strongly_consistent().await?, 84);
+        assert_eq!(*c.strongly_consistent().await?, 84);
+        let backend = tt_inner.backend();
+        assert_eq!(
+            backend.tasks_for_function(*SHALLOW_SUM_FUNCTION_ID).len(),
+            2
+        );
+        Ok(())
+    })
+    .await
+    .unwrap();
+}
+
+#[turbo_tasks::function]
+fn value() -> U64Vc {
+    U64Vc::cell(42)
+}
+
+#[turbo_tasks::function]
+fn forward() -> U64Vc {
+    value()
+}
+
+#[turbo_tasks::function]
+async fn deep_sum(a: U64Vc, b: U64Vc) -> Result<U64Vc> {
+    Ok(U64Vc::cell(*a.await? + *b.await?))
+}
+
+#[turbo_tasks::function(shallow_resolve)]
+async fn shallow_sum(a: U64Vc, b: U64Vc) -> Result<U64Vc> {
+    Ok(U64Vc::cell(*a.await? + *b.await?))
+}
@@ -0,0 +1,46 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{bail, Result};
+use turbo_tasks::TurboTasks;
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+async fn setup(fail: bool) -> Result<u32> {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    if fail {
+        bail!("setup failed");
+    }
+    Ok(42)
+}
+
+#[tokio::test]
+async fn run_once_keyed() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+
+    let (a, b) = tokio::join!(
+        tt.run_once_keyed("setup", setup(false)),
+        tt.run_once_keyed("setup", setup(false))
+    );
+    assert_eq!(a.unwrap(), 42);
+    assert_eq!(b.unwrap(), 42);
+    assert_eq!(tt.run_once_keyed("setup", setup(false)).await.unwrap(), 42);
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 1);
+
+    // Failures are not cached
+    assert!(tt.run_once_keyed(1, setup(true)).await.is_err());
+    assert_eq!(tt.run_once_keyed(1, setup(false)).await.unwrap(), 42);
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 3);
+
+    // Using the same key with a different result type is an error
+    assert!(tt
+        .run_once_keyed("setup", async { Ok(String::new()) })
+        .await
+        .is_err());
+}
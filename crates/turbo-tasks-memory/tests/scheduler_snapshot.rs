@@ -0,0 +1,67 @@
+#![feature(min_specialization)]
+
+use std::time::Duration;
+
+use turbo_tasks::{primitives::U64Vc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn scheduler_snapshot() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .track_queued_tasks()
+        .build()
+        .unwrap();
+    let tt2 = tt.clone();
+    tt.run_once(async move {
+        let snapshot = tt2.scheduler_snapshot(10);
+        // The once task itself is executing
+        assert!(snapshot.in_progress >= 1, "{snapshot}");
+        let queued = snapshot
+            .top_functions
+            .iter()
+            .map(|(_, count)| count)
+            .sum::<usize>();
+        assert_eq!(queued, snapshot.queued_tasks, "{snapshot}");
+        assert_eq!(*value().strongly_consistent().await?, 42);
+        Ok(())
+    })
+    .await
+    .unwrap();
+    // Wait until the once task has finished
+    tt.get_or_wait_update_info(Duration::ZERO).await;
+
+    let snapshot = tt.scheduler_snapshot(10);
+    assert_eq!(snapshot.queued_tasks, 0);
+    assert_eq!(snapshot.in_progress, 0);
+    assert_eq!(snapshot.foreground_jobs, 0);
+    assert_eq!(snapshot.oldest_queued, None);
+    assert!(snapshot.top_functions.is_empty());
+    let dump = snapshot.to_string();
+    assert!(dump.contains("queued tasks: 0\n"), "{dump}");
+    assert!(!dump.contains("top queued functions"), "{dump}");
+}
+
+#[tokio::test]
+async fn queued_tasks_are_not_tracked_by_default() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let tt2 = tt.clone();
+    tt.run_once(async move {
+        let snapshot = tt2.scheduler_snapshot(10);
+        assert!(snapshot.in_progress >= 1, "{snapshot}");
+        assert_eq!(snapshot.queued_tasks, 0, "{snapshot}");
+        assert!(snapshot.top_functions.is_empty(), "{snapshot}");
+        Ok(())
+    })
+    .await
+    .unwrap();
+}
+
+#[turbo_tasks::function]
+fn value() -> U64Vc {
+    U64Vc::cell(42)
+}
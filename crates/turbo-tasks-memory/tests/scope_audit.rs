@@ -0,0 +1,93 @@
+#![feature(min_specialization)]
+
+use std::time::Duration;
+
+use anyhow::Result;
+use turbo_tasks::{State, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn consistent_after_recomputation() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new().with_scope_audit(Duration::ZERO));
+    let (total, input_ref) = tt
+        .run_once(async {
+            let input = InputVc::cell(Input {
+                value: State::new(1),
+            });
+            let total = total(input);
+            assert_eq!(*total.strongly_consistent().await?, 3);
+            Ok((total, input.await?))
+        })
+        .await
+        .unwrap();
+    let _guard = tt.keep_alive(total.into());
+    tt.wait_background_done().await;
+    let report = tt.backend().audit_scopes();
+    assert!(report.is_empty(), "{report}");
+
+    input_ref.value.set(2);
+    let value = tt
+        .run_once(async move { Ok(*total.strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 6);
+    tt.wait_background_done().await;
+    let report = tt.backend().audit_scopes();
+    assert!(report.is_empty(), "{report}");
+}
+
+#[tokio::test]
+async fn consistent_after_moving_scopes() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let backend_tt = tt.clone();
+    let (value, visits) = tt
+        .run_once(async move {
+            let input = InputVc::cell(Input {
+                value: State::new(1),
+            });
+            assert_eq!(*outer(input).await?, 3);
+            let visits = backend_tt.backend().scope_update_visits();
+            // Gives the inner task a root scope, which moves its subtree out of the
+            // scopes of the outer task
+            let value = *total(input).strongly_consistent().await?;
+            Ok((value, backend_tt.backend().scope_update_visits() - visits))
+        })
+        .await
+        .unwrap();
+    assert_eq!(value, 3);
+    // The child is visited once to be added to the new scope and removed from
+    // the old one
+    assert_eq!(visits, 1);
+    tt.wait_background_done().await;
+    let report = tt.backend().audit_scopes();
+    assert!(report.is_empty(), "{report}");
+}
+
+#[turbo_tasks::value(serialization = "none", cell = "new", eq = "manual")]
+struct Input {
+    value: State<u32>,
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u32);
+
+#[turbo_tasks::function]
+async fn double(input: InputVc) -> Result<ValueVc> {
+    Ok(ValueVc::cell(*input.await?.value.get() * 2))
+}
+
+#[turbo_tasks::function]
+async fn total(input: InputVc) -> Result<ValueVc> {
+    let value = *input.await?.value.get();
+    Ok(ValueVc::cell(value + *double(input).await?))
+}
+
+#[turbo_tasks::function]
+async fn outer(input: InputVc) -> Result<ValueVc> {
+    Ok(ValueVc::cell(*total(input).await?))
+}
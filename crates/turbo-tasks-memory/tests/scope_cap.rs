@@ -0,0 +1,97 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::TurboTasks;
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn merges_root_scopes_over_the_cap() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new().with_max_scopes(3));
+    let value = tt
+        .run_once(async {
+            let mut total = 0;
+            for count in 1..=8 {
+                // Gives each task a root scope
+                total += *sum(count).strongly_consistent().await?;
+            }
+            Ok(total)
+        })
+        .await
+        .unwrap();
+    assert_eq!(value, 168);
+    tt.wait_background_done().await;
+    let backend = tt.backend();
+    assert!(
+        backend.scope_count() <= 3,
+        "{} scopes",
+        backend.scope_count()
+    );
+    assert!(backend.merged_scope_count() > 0);
+    let report = backend.audit_scopes();
+    assert!(report.is_empty(), "{report}");
+
+    // Merged tasks are still read consistently
+    let value = tt
+        .run_once(async { Ok(*sum(8).strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 56);
+}
+
+#[tokio::test]
+async fn reuses_merged_scopes() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new().with_max_scopes(3));
+    let read_all = || {
+        tt.run_once(async {
+            let mut total = 0;
+            for count in 1..=8 {
+                total += *sum(count).strongly_consistent().await?;
+            }
+            Ok(total)
+        })
+    };
+    assert_eq!(read_all().await.unwrap(), 168);
+    tt.wait_background_done().await;
+    let allocated = tt.backend().allocated_scope_count();
+
+    // Reading merged tasks strongly consistent makes them root scoped again,
+    // which takes their merged scopes instead of allocating new ones. These
+    // are not merged again, so the repeated reads settle.
+    let mut merged = Vec::new();
+    for _ in 0..5 {
+        assert_eq!(read_all().await.unwrap(), 168);
+        tt.wait_background_done().await;
+        let backend = tt.backend();
+        merged.push(backend.merged_scope_count());
+        let report = backend.audit_scopes();
+        assert!(report.is_empty(), "{report}");
+    }
+    assert_eq!(
+        merged[2..],
+        [0, 0, 0],
+        "merged scopes after each read: {merged:?}"
+    );
+    assert_eq!(tt.backend().allocated_scope_count(), allocated);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u32);
+
+#[turbo_tasks::function]
+fn leaf(value: u32) -> ValueVc {
+    ValueVc::cell(value * 2)
+}
+
+#[turbo_tasks::function]
+async fn sum(count: u32) -> Result<ValueVc> {
+    let mut sum = 0;
+    for value in 0..count {
+        sum += *leaf(value).await?;
+    }
+    Ok(ValueVc::cell(sum))
+}
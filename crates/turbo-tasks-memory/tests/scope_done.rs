@@ -0,0 +1,63 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use turbo_tasks::{CompletionVc, RawVc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static FINISHED: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn wait_for_scope() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let vc = tt
+        .run_once(async { Ok(RawVc::from(spawn_slow())) })
+        .await
+        .unwrap();
+    let RawVc::TaskOutput(task) = vc else {
+        panic!("expected a task output");
+    };
+    let _guard = tt.keep_alive(vc);
+
+    let scope = tt.backend().root_scope(task, &*tt);
+    tokio::time::timeout(Duration::from_secs(10), async {
+        tt.wait_foreground_done().await;
+        if let Some(listener) = tt
+            .backend()
+            .with_scope(scope, |scope| scope.done_listener())
+        {
+            listener.await;
+        }
+    })
+    .await
+    .unwrap();
+    // All tasks called by `spawn_slow` are finished, even though it doesn't wait
+    // for them
+    assert_eq!(FINISHED.load(Ordering::SeqCst), 5);
+    assert!(tt
+        .backend()
+        .with_scope(scope, |scope| scope.done_listener())
+        .is_none());
+}
+
+#[turbo_tasks::function]
+async fn slow(index: u32) -> CompletionVc {
+    tokio::time::sleep(Duration::from_millis(10 * index as u64)).await;
+    FINISHED.fetch_add(1, Ordering::SeqCst);
+    CompletionVc::new()
+}
+
+#[turbo_tasks::function]
+fn spawn_slow() -> CompletionVc {
+    for i in 0..5 {
+        let _ = slow(i);
+    }
+    CompletionVc::new()
+}
@@ -0,0 +1,64 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{primitives::U64Vc, RawVc, TurboTasks};
+use turbo_tasks_memory::{MemoryBackend, ScopeMembershipStep};
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn explains_chain_of_children() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (outer, middle, inner) = tt
+        .run_once(async {
+            let outer = outer();
+            outer.await?;
+            Ok((outer, middle(), inner()))
+        })
+        .await
+        .unwrap();
+    let outer = RawVc::from(outer).get_task_id();
+    let middle = RawVc::from(middle).get_task_id();
+    let inner = RawVc::from(inner).get_task_id();
+    let backend = tt.backend();
+    let outer_scope = backend.root_scope(outer, &*tt);
+    let middle_scope = backend.root_scope(middle, &*tt);
+
+    assert_eq!(
+        backend.explain_scope_membership(inner, outer_scope),
+        Some(vec![
+            ScopeMembershipStep {
+                parent: outer,
+                child: middle,
+                child_root_scope: Some(middle_scope),
+            },
+            ScopeMembershipStep {
+                parent: middle,
+                child: inner,
+                child_root_scope: None,
+            },
+        ])
+    );
+    assert_eq!(
+        backend.explain_scope_membership(outer, outer_scope),
+        Some(vec![])
+    );
+    assert_eq!(backend.explain_scope_membership(outer, middle_scope), None);
+}
+
+#[turbo_tasks::function]
+fn inner() -> U64Vc {
+    U64Vc::cell(42)
+}
+
+#[turbo_tasks::function]
+async fn middle() -> Result<U64Vc> {
+    Ok(U64Vc::cell(*inner().await? + 1))
+}
+
+#[turbo_tasks::function]
+async fn outer() -> Result<U64Vc> {
+    Ok(U64Vc::cell(*middle().await? + 1))
+}
@@ -0,0 +1,58 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{registry, TurboTasks};
+use turbo_tasks_memory::{MemoryBackend, ScopeProfile};
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn scope_profile() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async {
+        // Strongly consistent reads give the task a root scope, but that is not
+        // profiled
+        assert_eq!(*double(21).strongly_consistent().await?, 42);
+        Ok(())
+    })
+    .await
+    .unwrap();
+    assert!(tt.backend().scope_profile().is_empty());
+
+    let profile: ScopeProfile = format!(
+        "{}\n",
+        registry::get_function_global_name(*DOUBLE_FUNCTION_ID)
+    )
+    .parse()
+    .unwrap();
+    let tt = TurboTasks::new(MemoryBackend::new().with_scope_profile(&profile));
+    tt.run_once(async {
+        assert_eq!(*double_all(vec![1, 2, 3]).await?, vec![2, 4, 6]);
+        Ok(())
+    })
+    .await
+    .unwrap();
+    assert_eq!(tt.backend().scope_profile(), profile);
+}
+
+#[turbo_tasks::value(transparent)]
+struct Number(u32);
+
+#[turbo_tasks::value(transparent)]
+struct Numbers(Vec<u32>);
+
+#[turbo_tasks::function]
+fn double(value: u32) -> NumberVc {
+    NumberVc::cell(value * 2)
+}
+
+#[turbo_tasks::function]
+async fn double_all(values: Vec<u32>) -> Result<NumbersVc> {
+    let mut result = Vec::new();
+    for value in values {
+        result.push(*double(value).await?);
+    }
+    Ok(NumbersVc::cell(result))
+}
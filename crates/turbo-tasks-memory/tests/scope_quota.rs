@@ -0,0 +1,121 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{get_invalidator, primitives::UsizeVc, Invalidator, RawVc, TurboTasks};
+use turbo_tasks_memory::{MemoryBackend, ScopeQuota};
+use turbo_tasks_testing::register;
+
+register!();
+
+static COUNT: AtomicUsize = AtomicUsize::new(2);
+static INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+static OFFSET: AtomicUsize = AtomicUsize::new(0);
+static SHIFTED_INVALIDATOR: Mutex<Option<Invalidator>> = Mutex::new(None);
+
+#[tokio::test]
+async fn rejects_tasks_over_quota() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let sum: RawVc = tt
+        .run_once(async {
+            let sum = sum();
+            assert_eq!(*sum.strongly_consistent().await?, 1);
+            Ok(sum.into())
+        })
+        .await
+        .unwrap();
+    tt.backend().set_scope_quota(
+        sum.get_task_id(),
+        ScopeQuota {
+            max_tasks: Some(3),
+            max_bytes: None,
+        },
+        &*tt,
+    );
+
+    // Existing tasks are still called, but only 3 more tasks are created
+    COUNT.store(10, Ordering::SeqCst);
+    INVALIDATOR.lock().unwrap().take().unwrap().invalidate();
+    let err = tt
+        .run_once(async move { Ok(*UsizeVc::from(sum).strongly_consistent().await?) })
+        .await
+        .unwrap_err();
+    let message = format!("{err:#}");
+    assert!(message.contains("exceeds the quota"), "{message}");
+    assert!(message.contains("(3 tasks,"), "{message}");
+    assert!(message.contains("number (3 tasks,"), "{message}");
+}
+
+#[tokio::test]
+async fn released_tasks_are_not_counted() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let sum: RawVc = tt
+        .run_once(async {
+            let sum = shifted_sum();
+            assert_eq!(*sum.strongly_consistent().await?, 1);
+            Ok(sum.into())
+        })
+        .await
+        .unwrap();
+    tt.backend().set_scope_quota(
+        sum.get_task_id(),
+        ScopeQuota {
+            max_tasks: Some(2),
+            max_bytes: None,
+        },
+        &*tt,
+    );
+
+    // Every update replaces both number tasks, which releases the previous ones
+    for offset in [100, 200, 300] {
+        OFFSET.store(offset, Ordering::SeqCst);
+        SHIFTED_INVALIDATOR
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap()
+            .invalidate();
+        let value = tt
+            .run_once(async move { Ok(*UsizeVc::from(sum).strongly_consistent().await?) })
+            .await
+            .unwrap();
+        assert_eq!(value, offset * 2 + 1);
+    }
+}
+
+#[turbo_tasks::function]
+fn number(value: usize) -> UsizeVc {
+    UsizeVc::cell(value)
+}
+
+#[turbo_tasks::function]
+async fn sum() -> Result<UsizeVc> {
+    *INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    let mut sum = 0;
+    for i in 0..COUNT.load(Ordering::SeqCst) {
+        sum += *number(i).await?;
+    }
+    Ok(UsizeVc::cell(sum))
+}
+
+#[turbo_tasks::function]
+async fn shifted_sum() -> Result<UsizeVc> {
+    *SHIFTED_INVALIDATOR.lock().unwrap() = Some(get_invalidator());
+    let offset = OFFSET.load(Ordering::SeqCst);
+    if offset > 0 {
+        // Gives the previous number tasks time to leave the scope
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    Ok(UsizeVc::cell(
+        *number(offset).await? + *number(offset + 1).await?,
+    ))
+}
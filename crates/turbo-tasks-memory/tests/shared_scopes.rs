@@ -0,0 +1,71 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{join_all, RawVc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::{
+    counter::{Counter, CounterValueVc, CounterVc},
+    register,
+};
+
+register!();
+
+const READERS: usize = 6;
+
+#[tokio::test]
+async fn task_shared_by_root_scopes() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (counter, shared, readers) = tt
+        .run_once(async {
+            let counter = CounterVc::cell(Counter::new(1));
+            let readers: Vec<_> = (0..READERS).map(|i| read_shared(counter, i)).collect();
+            join_all(readers.clone()).await?;
+            Ok((counter, shared(counter), readers))
+        })
+        .await
+        .unwrap();
+    let backend = tt.backend();
+    let guards: Vec<_> = readers
+        .iter()
+        .map(|reader| {
+            let reader = RawVc::from(*reader);
+            backend.root_scope(reader.get_task_id(), &*tt);
+            tt.keep_alive(reader)
+        })
+        .collect();
+    let shared = RawVc::from(shared).get_task_id();
+    let scopes = backend.with_task(shared, |task| task.get_stats_info(backend).child_scopes);
+    // More scopes than are stored inline
+    assert!(scopes >= READERS, "{scopes} scopes");
+    for reader in &readers {
+        let scope = backend.root_scope(RawVc::from(*reader).get_task_id(), &*tt);
+        assert!(backend.with_task(shared, |task| task.is_in_scope(scope)));
+    }
+    let unrelated = backend.create_new_scope(0);
+    assert!(!backend.with_task(shared, |task| task.is_in_scope(unrelated)));
+
+    let values = tt
+        .run_once(async move {
+            counter.await?.incr();
+            let mut values = Vec::new();
+            for reader in readers {
+                values.push(*reader.strongly_consistent().await?);
+            }
+            Ok(values)
+        })
+        .await
+        .unwrap();
+    assert_eq!(values, (0..READERS).map(|i| 2 + i).collect::<Vec<_>>());
+    drop(guards);
+}
+
+#[turbo_tasks::function]
+async fn shared(counter: CounterVc) -> Result<CounterValueVc> {
+    Ok(CounterValueVc::cell(counter.await?.get()))
+}
+
+#[turbo_tasks::function]
+async fn read_shared(counter: CounterVc, offset: usize) -> Result<CounterValueVc> {
+    Ok(CounterValueVc::cell(*shared(counter).await? + offset))
+}
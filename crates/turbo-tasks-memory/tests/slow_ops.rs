@@ -0,0 +1,57 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{TaskId, TurboTasks};
+use turbo_tasks_memory::{MemoryBackend, SlowOpReporter, SlowOpThresholds, SlowOperation};
+use turbo_tasks_testing::register;
+
+register!();
+
+#[derive(Clone, Default)]
+struct RecordingReporter(Arc<Mutex<Vec<SlowOperation>>>);
+
+impl SlowOpReporter for RecordingReporter {
+    fn report(&self, operation: SlowOperation, _task: TaskId, _duration: Duration) {
+        self.0.lock().unwrap().push(operation);
+    }
+}
+
+#[tokio::test]
+async fn slow_ops() {
+    lazy_static::initialize(&REGISTER);
+    let reporter = RecordingReporter::default();
+    let tt = TurboTasks::new(MemoryBackend::new().with_slow_op_reporter(
+        reporter.clone(),
+        SlowOpThresholds {
+            duration: Duration::ZERO,
+            dependencies: 0,
+        },
+    ));
+    tt.run_once(async {
+        assert_eq!(*outer().strongly_consistent().await?, 42);
+        Ok(())
+    })
+    .await
+    .unwrap();
+    let operations = reporter.0.lock().unwrap();
+    assert!(operations.contains(&SlowOperation::AddToScope));
+    assert!(operations.contains(&SlowOperation::MakeRootScoped));
+}
+
+#[turbo_tasks::value(transparent)]
+struct Number(u32);
+
+#[turbo_tasks::function]
+async fn outer() -> Result<NumberVc> {
+    Ok(NumberVc::cell(*inner().await? * 2))
+}
+
+#[turbo_tasks::function]
+fn inner() -> NumberVc {
+    NumberVc::cell(21)
+}
@@ -0,0 +1,58 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{primitives::U64Vc, RawVc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn ids_are_stable_across_instances() {
+    lazy_static::initialize(&REGISTER);
+    let first = TurboTasks::new(MemoryBackend::new().with_stable_task_ids());
+    let (first_a, first_b) = first
+        .run_once(async {
+            assert_eq!(*add_one(double(1)).await?, 3);
+            assert_eq!(*double(2).await?, 4);
+            Ok((double(2), add_one(double(1))))
+        })
+        .await
+        .unwrap();
+
+    // Creates the tasks in a different order, so they get different task ids
+    let second = TurboTasks::new(MemoryBackend::new().with_stable_task_ids());
+    let (second_a, second_b) = second
+        .run_once(async {
+            assert_eq!(*double(2).await?, 4);
+            assert_eq!(*add_one(double(1)).await?, 3);
+            Ok((double(2), add_one(double(1))))
+        })
+        .await
+        .unwrap();
+
+    for (first_vc, second_vc) in [(first_a, second_a), (first_b, second_b)] {
+        let first_task = RawVc::from(first_vc).get_task_id();
+        let second_task = RawVc::from(second_vc).get_task_id();
+        let id = first.backend().stable_task_id(first_task).unwrap();
+        assert_eq!(second.backend().stable_task_id(second_task), Some(id));
+        assert_eq!(second.backend().task_by_stable_id(id), Some(second_task));
+    }
+    let first_a = first
+        .backend()
+        .stable_task_id(RawVc::from(first_a).get_task_id());
+    let first_b = first
+        .backend()
+        .stable_task_id(RawVc::from(first_b).get_task_id());
+    assert_ne!(first_a, first_b);
+}
+
+#[turbo_tasks::function]
+fn double(value: u64) -> U64Vc {
+    U64Vc::cell(value * 2)
+}
+
+#[turbo_tasks::function]
+async fn add_one(value: U64Vc) -> Result<U64Vc> {
+    Ok(U64Vc::cell(*value.await? + 1))
+}
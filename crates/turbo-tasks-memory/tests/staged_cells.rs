@@ -0,0 +1,77 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{flush_cell_updates, stage_cell_updates};
+use turbo_tasks_testing::{register, run};
+
+register!();
+
+#[tokio::test]
+async fn staged_cells() {
+    run! {
+        let pair = pair(21).await?;
+        assert_eq!(*pair.first.await?, 21);
+        assert_eq!(*pair.second.await?, 42);
+    }
+}
+
+static STREAMED: Mutex<Option<(NumberVc, NumberVc)>> = Mutex::new(None);
+static RELEASED: AtomicBool = AtomicBool::new(false);
+
+#[tokio::test]
+async fn flushed_cells() {
+    run! {
+        let result = streamed();
+        let (flushed, staged) = loop {
+            if let Some(cells) = *STREAMED.lock().unwrap() {
+                break cells;
+            }
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        };
+        // The producer is still running
+        assert_eq!(*flushed.await?, 1);
+        assert!(staged.await.is_err());
+        RELEASED.store(true, Ordering::SeqCst);
+        assert_eq!(*result.await?, 3);
+        assert_eq!(*staged.await?, 2);
+    }
+}
+
+#[turbo_tasks::value(transparent)]
+struct Number(u32);
+
+#[turbo_tasks::value]
+struct Pair {
+    first: NumberVc,
+    second: NumberVc,
+}
+
+#[turbo_tasks::function]
+async fn pair(value: u32) -> Result<PairVc> {
+    stage_cell_updates();
+    let first = NumberVc::cell(value);
+    // Staged values are visible to the task itself
+    let second = NumberVc::cell(*first.await? * 2);
+    Ok(PairVc::cell(Pair { first, second }))
+}
+
+#[turbo_tasks::function]
+async fn streamed() -> Result<NumberVc> {
+    stage_cell_updates();
+    let flushed = NumberVc::cell(1);
+    flush_cell_updates();
+    let staged = NumberVc::cell(2);
+    *STREAMED.lock().unwrap() = Some((flushed, staged));
+    while !RELEASED.load(Ordering::SeqCst) {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+    Ok(NumberVc::cell(3))
+}
@@ -0,0 +1,57 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use turbo_tasks::{State, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn set_invalidates_readers() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (config, config_ref) = tt
+        .run_once(async {
+            let config = ConfigVc::cell(Config {
+                mode: State::new("dev".to_string()),
+            });
+            assert_eq!(*mode_length(config).strongly_consistent().await?, 3);
+            Ok((config, config.await?))
+        })
+        .await
+        .unwrap();
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 1);
+
+    // Outside of task execution
+    assert!(!config_ref.mode.set("dev".to_string()));
+    assert!(config_ref.mode.set("production".to_string()));
+    assert_eq!(*config_ref.mode.get_untracked(), "production");
+
+    let length = tt
+        .run_once(async move { Ok(*mode_length(config).strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(length, 10);
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 2);
+}
+
+static EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[turbo_tasks::value(serialization = "none", cell = "new", eq = "manual")]
+struct Config {
+    mode: State<String>,
+}
+
+#[turbo_tasks::value(transparent)]
+struct Length(usize);
+
+#[turbo_tasks::function]
+async fn mode_length(config: ConfigVc) -> Result<LengthVc> {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    let config = config.await?;
+    let length = config.mode.get().len();
+    Ok(LengthVc::cell(length))
+}
@@ -0,0 +1,206 @@
+#![feature(min_specialization)]
+
+use std::time::Duration;
+
+use anyhow::Result;
+use turbo_tasks::{StatsType, TurboTasks, TurboTasksBackendApi};
+use turbo_tasks_memory::{
+    stats::{Report, ReportEntry, Stats},
+    DurationHistogram, MemoryBackend,
+};
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn report_roundtrip() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async {
+        assert_eq!(*sum(3).await?, 6);
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    let mut stats = Stats::new();
+    let b = tt.backend();
+    b.with_all_cached_tasks(|task| {
+        stats.add_id(b, task);
+    });
+    let report = stats.report();
+
+    let double = report
+        .functions
+        .iter()
+        .find(|entry| entry.name.ends_with("double"))
+        .unwrap();
+    assert_eq!(double.count, 3);
+    assert_eq!(double.output_updates, 3);
+    assert!(double.output_reads >= 3);
+    let sum = report
+        .functions
+        .iter()
+        .find(|entry| entry.name.ends_with("sum"))
+        .unwrap();
+    assert!(sum
+        .references
+        .iter()
+        .any(|r| r.target.ends_with("double") && r.count == 3));
+
+    let json = report.to_json().unwrap();
+    assert_eq!(Report::from_json(&json).unwrap(), report);
+
+    let csv = report.to_csv();
+    let mut lines = csv.lines();
+    assert!(lines.next().unwrap().starts_with("kind,name,count,"));
+    assert_eq!(lines.count(), report.tasks.len() + report.functions.len());
+}
+
+#[tokio::test]
+async fn duration_percentiles() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.set_stats_type(StatsType::Full);
+    tt.run_once(async {
+        assert_eq!(*sum(4).await?, 12);
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    let mut stats = Stats::new();
+    let b = tt.backend();
+    b.with_all_cached_tasks(|task| {
+        stats.add_id(b, task);
+    });
+    let report = stats.report();
+    let double = report
+        .functions
+        .iter()
+        .find(|entry| entry.name.ends_with("double"))
+        .unwrap();
+    assert_eq!(double.durations.as_ref().unwrap().count(), 4);
+    let p50 = double.duration_percentile_us(0.5).unwrap();
+    let p99 = double.duration_percentile_us(0.99).unwrap();
+    assert!(p50 <= p99);
+    assert!(p99 >= double.max_duration_us);
+
+    let json = report.to_json().unwrap();
+    assert_eq!(Report::from_json(&json).unwrap(), report);
+}
+
+#[test]
+fn histogram_percentiles() {
+    let mut histogram = DurationHistogram::new();
+    assert_eq!(histogram.percentile(0.5), None);
+    for _ in 0..90 {
+        histogram.record(Duration::from_micros(100));
+    }
+    for _ in 0..10 {
+        histogram.record(Duration::from_millis(5));
+    }
+    assert_eq!(histogram.count(), 100);
+    assert_eq!(histogram.percentile(0.5), Some(Duration::from_micros(128)));
+    assert_eq!(histogram.percentile(0.9), Some(Duration::from_micros(128)));
+    assert_eq!(
+        histogram.percentile(0.95),
+        Some(Duration::from_micros(8192))
+    );
+
+    let mut merged = DurationHistogram::new();
+    merged.merge(&histogram);
+    merged.merge(&histogram);
+    assert_eq!(merged.count(), 200);
+    assert_eq!(merged.percentile(0.99), Some(Duration::from_micros(8192)));
+}
+
+#[test]
+fn trends_against_previous_run() {
+    let path = std::env::temp_dir().join(format!("stats-report-{}.json", std::process::id()));
+    assert_eq!(Report::read(&path).unwrap(), None);
+    let previous = report_of(&[("parse", 1000, 10), ("resolve", 500, 5), ("emit", 100, 1)]);
+    previous.write(&path).unwrap();
+    let previous = Report::read(&path).unwrap().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let current = report_of(&[("parse", 1350, 10), ("resolve", 250, 5), ("emit", 105, 1)]);
+    let trends = current.trends(&previous, 0.1);
+    assert_eq!(
+        trends
+            .iter()
+            .map(|trend| trend.name.as_str())
+            .collect::<Vec<_>>(),
+        ["resolve", "parse"]
+    );
+    assert_eq!(
+        trends[1].to_string(),
+        "parse average duration +35% vs last run (100us -> 135us)"
+    );
+}
+
+#[test]
+fn high_churn_outputs() {
+    let mut report = report_of(&[("parse", 0, 1), ("resolve", 0, 1), ("emit", 0, 1)]);
+    for (entry, (updates, reads)) in report
+        .functions
+        .iter_mut()
+        .zip([(50, 2), (100, 100), (5, 0)])
+    {
+        entry.output_updates = updates;
+        entry.output_reads = reads;
+    }
+    assert_eq!(report.functions[0].output_churn(), 25.0);
+    assert_eq!(report.functions[2].output_churn(), 5.0);
+    assert_eq!(
+        report
+            .high_churn(2, 4.0)
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect::<Vec<_>>(),
+        ["parse", "emit"]
+    );
+    assert_eq!(report.high_churn(10, 4.0).len(), 1);
+}
+
+fn report_of(functions: &[(&str, u64, u32)]) -> Report {
+    Report {
+        tasks: Vec::new(),
+        functions: functions
+            .iter()
+            .map(|&(name, total_duration_us, executions)| ReportEntry {
+                name: name.to_string(),
+                count: 1,
+                active_count: 0,
+                executions: Some(executions),
+                roots: 0,
+                scopes: 0,
+                total_duration_us: Some(total_duration_us),
+                total_current_duration_us: 0,
+                total_update_duration_us: 0,
+                max_duration_us: 0,
+                durations: None,
+                output_updates: 0,
+                output_reads: 0,
+                references: Vec::new(),
+            })
+            .collect(),
+    }
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u32);
+
+#[turbo_tasks::function]
+fn double(value: u32) -> ValueVc {
+    ValueVc::cell(value * 2)
+}
+
+#[turbo_tasks::function]
+async fn sum(count: u32) -> Result<ValueVc> {
+    let mut sum = 0;
+    for i in 0..count {
+        sum += *double(i).await?;
+    }
+    Ok(ValueVc::cell(sum))
+}
@@ -0,0 +1,66 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use anyhow::Result;
+use turbo_tasks::{primitives::U64Vc, TaskBarrier, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static DONE: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn waits_for_all_attached() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async {
+        let barrier = TaskBarrier::new();
+        for i in 0..50 {
+            barrier.attach(outer(i));
+        }
+        assert_eq!(barrier.len(), 50);
+        barrier.wait().await?;
+        // The inner tasks have settled as well
+        assert_eq!(DONE.load(Ordering::SeqCst), 50);
+        Ok(())
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn fails_with_task_error() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async {
+        let barrier = TaskBarrier::new();
+        barrier.attach(failing());
+        let err = barrier.wait().await.unwrap_err();
+        assert!(format!("{err:?}").contains("failed on purpose"), "{err:?}");
+        Ok(())
+    })
+    .await
+    .unwrap();
+}
+
+#[turbo_tasks::function]
+fn outer(value: u64) -> U64Vc {
+    inner(value)
+}
+
+#[turbo_tasks::function]
+async fn inner(value: u64) -> U64Vc {
+    tokio::time::sleep(Duration::from_millis(value % 5)).await;
+    DONE.fetch_add(1, Ordering::SeqCst);
+    U64Vc::cell(value)
+}
+
+#[turbo_tasks::function]
+async fn failing() -> Result<U64Vc> {
+    anyhow::bail!("failed on purpose")
+}
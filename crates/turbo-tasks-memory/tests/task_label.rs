@@ -0,0 +1,34 @@
+#![feature(min_specialization)]
+
+use anyhow::Result;
+use turbo_tasks::{backend::Backend, set_task_label, RawVc, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn task_label() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let processed = tt
+        .run_once(async {
+            let processed = process("src/index.js".to_string());
+            processed.await?;
+            Ok(processed)
+        })
+        .await
+        .unwrap();
+    let task = RawVc::from(processed).get_task_id();
+    let description = tt.backend().get_task_description(task);
+    assert!(description.ends_with("process (src/index.js)"));
+}
+
+#[turbo_tasks::value(transparent)]
+struct Length(usize);
+
+#[turbo_tasks::function]
+async fn process(path: String) -> Result<LengthVc> {
+    set_task_label(&path);
+    Ok(LengthVc::cell(path.len()))
+}
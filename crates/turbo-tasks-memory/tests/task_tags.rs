@@ -0,0 +1,99 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use turbo_tasks::{primitives::StringVc, tag_task, RawVc, TurboTasks};
+use turbo_tasks_memory::{stats::Stats, MemoryBackend};
+use turbo_tasks_testing::register;
+
+register!();
+
+static EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+static ABOUT_EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn task_tags() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (about, index) = tt
+        .run_once(async {
+            let about = page("about".to_string());
+            let index = page("index".to_string());
+            about.await?;
+            index.await?;
+            Ok((about, index))
+        })
+        .await
+        .unwrap();
+    tt.tag_vc(index.into(), "entry").await.unwrap();
+
+    assert_eq!(tt.tasks_with_tag("page:/about").len(), 1);
+    assert_eq!(tt.tasks_with_tag("page:/index").len(), 1);
+    assert_eq!(
+        tt.tasks_with_tag("entry"),
+        [tt.tasks_with_tag("page:/index")[0]]
+    );
+    assert!(tt.tasks_with_tag("page:/missing").is_empty());
+
+    let mut stats = Stats::new();
+    stats.add_tagged(tt.backend(), "page:/about");
+    let report = stats.report();
+    assert_eq!(report.tasks.len(), 1);
+    assert_eq!(report.tasks[0].count, 1);
+
+    let executions = EXECUTIONS.load(Ordering::SeqCst);
+    tt.invalidate_tag("page:/about");
+    tt.run_once(async move {
+        about.strongly_consistent().await?;
+        index.strongly_consistent().await?;
+        Ok(())
+    })
+    .await
+    .unwrap();
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), executions + 1);
+    // The execution set its tags again, tags of earlier executions are gone
+    assert_eq!(tt.tasks_with_tag("page:/about").len(), 1);
+    assert!(tt.tasks_with_tag("about:1").is_empty());
+    assert_eq!(tt.tasks_with_tag("about:2").len(), 1);
+}
+
+#[tokio::test]
+async fn tags_the_resolved_task() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (vc, resolved) = tt
+        .run_once(async {
+            // The input is not resolved, so the call goes through a task that
+            // resolves it first
+            let vc: RawVc = title(name()).into();
+            Ok((vc, vc.resolve().await?))
+        })
+        .await
+        .unwrap();
+    tt.tag_vc(vc, "contact").await.unwrap();
+    assert_eq!(tt.tasks_with_tag("contact"), [resolved.get_task_id()]);
+    assert_eq!(tt.tasks_with_tag("contact"), tt.tasks_with_tag("title"));
+}
+
+#[turbo_tasks::function]
+fn page(name: String) -> StringVc {
+    tag_task(format!("page:/{name}"));
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    if name == "about" {
+        let execution = ABOUT_EXECUTIONS.fetch_add(1, Ordering::SeqCst) + 1;
+        tag_task(format!("about:{execution}"));
+    }
+    StringVc::cell(name)
+}
+
+#[turbo_tasks::function]
+fn name() -> StringVc {
+    StringVc::cell("contact".to_string())
+}
+
+#[turbo_tasks::function]
+async fn title(name: StringVc) -> Result<StringVc> {
+    tag_task("title");
+    Ok(StringVc::cell(name.await?.to_uppercase()))
+}
@@ -0,0 +1,101 @@
+#![feature(min_specialization)]
+
+use std::time::Duration;
+
+use anyhow::Result;
+use turbo_tasks::{RawVc, TaskId, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn tasks_for_function() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let tt_inner = tt.clone();
+    tt.run_once(async move {
+        let a = parse_module("a.js".to_string());
+        let b = parse_module("b.js".to_string());
+        a.await?;
+        b.await?;
+        parse_module("a.js".to_string()).await?;
+        // The tasks are used by this task while it's executing
+        let tasks = vec![RawVc::from(a).get_task_id(), RawVc::from(b).get_task_id()];
+        let backend = tt_inner.backend();
+        assert_eq!(backend.tasks_for_function(*PARSE_MODULE_FUNCTION_ID), tasks);
+        let dirty = tasks
+            .iter()
+            .filter(|task| backend.with_task(**task, |task| task.is_dirty()))
+            .count();
+        assert_eq!(dirty, 0);
+        assert!(backend.tasks_for_function(*UNUSED_FUNCTION_ID).is_empty());
+        Ok(())
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn released_tasks_are_not_listed() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let task = tt
+        .run_once(async { Ok(RawVc::from(compile_module("c.js".to_string())).get_task_id()) })
+        .await
+        .unwrap();
+    // Nothing needs the task after the run, so it's released
+    wait_until_unlisted(&tt, task).await;
+
+    // Calling the function again uses the cached task
+    let tt_inner = tt.clone();
+    tt.run_once(async move {
+        let called = compile_module("c.js".to_string());
+        assert_eq!(RawVc::from(called).get_task_id(), task);
+        called.await?;
+        assert!(tt_inner
+            .backend()
+            .tasks_for_function(*COMPILE_MODULE_FUNCTION_ID)
+            .contains(&task));
+        Ok(())
+    })
+    .await
+    .unwrap();
+    assert_eq!(
+        tt.backend()
+            .created_for_function(*COMPILE_MODULE_FUNCTION_ID),
+        1
+    );
+}
+
+async fn wait_until_unlisted(tt: &TurboTasks<MemoryBackend>, task: TaskId) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while tt
+            .backend()
+            .tasks_for_function(*COMPILE_MODULE_FUNCTION_ID)
+            .contains(&task)
+        {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+}
+
+#[turbo_tasks::value(transparent)]
+struct Module(String);
+
+#[turbo_tasks::function]
+fn parse_module(path: String) -> ModuleVc {
+    ModuleVc::cell(path)
+}
+
+#[turbo_tasks::function]
+fn compile_module(path: String) -> ModuleVc {
+    ModuleVc::cell(path)
+}
+
+#[turbo_tasks::function]
+fn unused() -> ModuleVc {
+    ModuleVc::cell(String::new())
+}
@@ -0,0 +1,111 @@
+#![feature(min_specialization)]
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use tracing::{
+    field::{Field, Visit},
+    Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, Layer};
+use turbo_tasks::{RawVc, State, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+/// Records the task and the message of every event with a task field.
+#[derive(Clone, Default)]
+struct TaskEvents(Arc<Mutex<Vec<(String, String)>>>);
+
+#[derive(Default)]
+struct TaskEventVisitor {
+    task: Option<String>,
+    message: String,
+}
+
+impl Visit for TaskEventVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            "task" => self.task = Some(format!("{value:?}")),
+            "message" => self.message = format!("{value:?}"),
+            _ => {}
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for TaskEvents {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = TaskEventVisitor::default();
+        event.record(&mut visitor);
+        if let Some(task) = visitor.task {
+            self.0.lock().unwrap().push((task, visitor.message));
+        }
+    }
+}
+
+#[tokio::test]
+async fn logs_changes_of_traced_task() {
+    lazy_static::initialize(&REGISTER);
+    let events = TaskEvents::default();
+    tracing_subscriber::registry().with(events.clone()).init();
+
+    let tt = TurboTasks::new(MemoryBackend::new());
+    let (total, input_ref) = tt
+        .run_once(async {
+            let input = InputVc::cell(Input {
+                value: State::new(1),
+            });
+            let total = total(input);
+            assert_eq!(*total.strongly_consistent().await?, 3);
+            Ok((total, input.await?))
+        })
+        .await
+        .unwrap();
+    let _guard = tt.keep_alive(total.into());
+    let task = RawVc::from(total).get_task_id();
+    tt.backend().trace_task(task, true);
+
+    input_ref.value.set(2);
+    let value = tt
+        .run_once(async move { Ok(*total.strongly_consistent().await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 6);
+
+    let events = events.0.lock().unwrap();
+    assert!(events.iter().all(|(t, _)| *t == task.to_string()));
+    let messages = events.iter().map(|(_, m)| m.as_str()).collect::<Vec<_>>();
+    assert!(messages[0].starts_with("tracing "));
+    for expected in [
+        "invalidated while done",
+        "removed dependency",
+        "scheduled -> in progress",
+        "in progress -> done",
+        "added dependency",
+    ] {
+        assert!(
+            messages.iter().any(|m| m.starts_with(expected)),
+            "{expected:?} is missing in {messages:#?}"
+        );
+    }
+}
+
+#[turbo_tasks::value(serialization = "none", cell = "new", eq = "manual")]
+struct Input {
+    value: State<u32>,
+}
+
+#[turbo_tasks::value(transparent)]
+struct Value(u32);
+
+#[turbo_tasks::function]
+async fn double(input: InputVc) -> Result<ValueVc> {
+    Ok(ValueVc::cell(*input.await?.value.get() * 2))
+}
+
+#[turbo_tasks::function]
+async fn total(input: InputVc) -> Result<ValueVc> {
+    let value = *input.await?.value.get();
+    Ok(ValueVc::cell(value + *double(input).await?))
+}
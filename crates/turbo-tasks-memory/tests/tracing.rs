@@ -0,0 +1,112 @@
+#![feature(min_specialization)]
+
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+use turbo_tasks::TurboTasks;
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+struct Execution {
+    function: String,
+    parent: Option<String>,
+}
+
+/// Records the function and the parent function of every task execution span.
+/// The function is recorded after the span has been created.
+#[derive(Clone, Default)]
+struct ExecutionSpans(Arc<Mutex<Vec<Execution>>>);
+
+#[derive(Default)]
+struct FunctionVisitor(String);
+
+impl Visit for FunctionVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "function" {
+            self.0 = format!("{value:?}");
+        }
+    }
+}
+
+/// The function of an execution span and the index of its [Execution].
+struct Function(String, usize);
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for ExecutionSpans {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != "execute" {
+            return;
+        }
+        let mut visitor = FunctionVisitor::default();
+        attrs.record(&mut visitor);
+        let span = ctx.span(id).unwrap();
+        let parent = span
+            .scope()
+            .skip(1)
+            .find_map(|span| span.extensions().get::<Function>().map(|f| f.0.clone()));
+        let mut executions = self.0.lock().unwrap();
+        span.extensions_mut()
+            .insert(Function(visitor.0.clone(), executions.len()));
+        executions.push(Execution {
+            function: visitor.0,
+            parent,
+        });
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let mut visitor = FunctionVisitor::default();
+        values.record(&mut visitor);
+        if visitor.0.is_empty() {
+            return;
+        }
+        let span = ctx.span(id).unwrap();
+        let mut extensions = span.extensions_mut();
+        let Some(function) = extensions.get_mut::<Function>() else {
+            return;
+        };
+        function.0 = visitor.0.clone();
+        self.0.lock().unwrap()[function.1].function = visitor.0;
+    }
+}
+
+#[tokio::test]
+async fn execution_spans() {
+    lazy_static::initialize(&REGISTER);
+    let spans = ExecutionSpans::default();
+    tracing_subscriber::registry().with(spans.clone()).init();
+
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async {
+        assert_eq!(*outer().await?, 42);
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    let spans = spans.0.lock().unwrap();
+    let inner = spans
+        .iter()
+        .find(|execution| execution.function.ends_with("inner"))
+        .unwrap();
+    assert!(inner.parent.as_ref().unwrap().ends_with("outer"));
+}
+
+#[turbo_tasks::value(transparent)]
+struct Number(u32);
+
+#[turbo_tasks::function]
+async fn outer() -> Result<NumberVc> {
+    Ok(NumberVc::cell(*inner().await? * 2))
+}
+
+#[turbo_tasks::function]
+fn inner() -> NumberVc {
+    NumberVc::cell(21)
+}
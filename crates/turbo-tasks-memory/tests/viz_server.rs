@@ -0,0 +1,58 @@
+#![feature(min_specialization)]
+#![cfg(feature = "viz_server")]
+
+use std::time::Duration;
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+};
+use turbo_tasks::{primitives::U64Vc, TurboTasks};
+use turbo_tasks_memory::{viz::server::VizServer, MemoryBackend};
+use turbo_tasks_testing::register;
+
+register!();
+
+#[tokio::test]
+async fn serves_the_overview() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async { Ok(*value().strongly_consistent().await?) })
+        .await
+        .unwrap();
+    let server = VizServer::listen(
+        tt.clone(),
+        ([127, 0, 0, 1], 0).into(),
+        Duration::from_secs(1),
+    )
+    .unwrap();
+    let addr = server.addr;
+    tokio::spawn(server.future);
+
+    let response = get(addr, "/").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    assert!(response.contains("cached tasks"), "{response}");
+    let response = get(addr, "/table?active").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{response}");
+    let response = get(addr, "/missing").await;
+    assert!(response.starts_with("HTTP/1.1 404"), "{response}");
+}
+
+async fn get(addr: std::net::SocketAddr, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream
+        .write_all(
+            format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                .as_bytes(),
+        )
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[turbo_tasks::function]
+fn value() -> U64Vc {
+    U64Vc::cell(42)
+}
@@ -0,0 +1,62 @@
+#![feature(min_specialization)]
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use turbo_tasks::{primitives::U64Vc, RawVc, StatsType, TurboTasks, TurboTasksBackendApi};
+use turbo_tasks_memory::{MemoryBackend, WarmupOrder, WarmupProfile};
+use turbo_tasks_testing::register;
+
+register!();
+
+static EXECUTIONS: AtomicUsize = AtomicUsize::new(0);
+
+#[tokio::test]
+async fn warms_up_hot_tasks() {
+    lazy_static::initialize(&REGISTER);
+    let first = TurboTasks::new(MemoryBackend::new().with_stable_task_ids());
+    first.set_stats_type(StatsType::Full);
+    let task = first
+        .run_once(async {
+            let value = add_one(double(20).resolve().await?);
+            assert_eq!(*value.await?, 41);
+            Ok(RawVc::from(value).get_task_id())
+        })
+        .await
+        .unwrap();
+    let stable_id = first.backend().stable_task_id(task).unwrap();
+    let profile = first.backend().warmup_profile(WarmupOrder::Executions, 10);
+    let descriptions = profile.tasks().collect::<Vec<_>>();
+    let double_index = descriptions.iter().position(|d| d.contains("double"));
+    let add_one_index = descriptions.iter().position(|d| d.contains("add_one"));
+    // Inputs come before the tasks that use them
+    assert!(double_index.unwrap() < add_one_index.unwrap());
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 1);
+
+    let profile: WarmupProfile = profile.to_string().parse().unwrap();
+    let second = TurboTasks::new(MemoryBackend::new().with_warmup_profile(profile.clone()));
+    second.wait_background_done().await;
+    assert_eq!(second.backend().warmed_up_tasks(), profile.len());
+    let task = second.backend().task_by_stable_id(stable_id).unwrap();
+    second.wait_task_completion(task, false).await.unwrap();
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 2);
+
+    // The request finds the task executed already
+    let value = second
+        .run_once(async { Ok(*add_one(double(20).resolve().await?).await?) })
+        .await
+        .unwrap();
+    assert_eq!(value, 41);
+    assert_eq!(EXECUTIONS.load(Ordering::SeqCst), 2);
+}
+
+#[turbo_tasks::function]
+fn double(value: u64) -> U64Vc {
+    U64Vc::cell(value * 2)
+}
+
+#[turbo_tasks::function]
+async fn add_one(value: U64Vc) -> Result<U64Vc> {
+    EXECUTIONS.fetch_add(1, Ordering::SeqCst);
+    Ok(U64Vc::cell(*value.await? + 1))
+}
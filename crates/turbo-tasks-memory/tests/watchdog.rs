@@ -0,0 +1,95 @@
+#![feature(min_specialization)]
+
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use turbo_tasks::{
+    primitives::{BoolVc, StringVc},
+    TurboTasks, WatchdogOptions,
+};
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_testing::register;
+
+register!();
+
+static RELEASED: AtomicBool = AtomicBool::new(false);
+
+#[tokio::test]
+async fn added_worker_unblocks_progress() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::builder(MemoryBackend::new())
+        .worker_threads(1)
+        .watchdog(WatchdogOptions {
+            stall_timeout: Duration::from_millis(100),
+            add_worker: true,
+        })
+        .build()
+        .unwrap();
+    let blocked = tokio::spawn({
+        let tt = tt.clone();
+        async move { tt.run_once(async { Ok(*block_worker().await?) }).await }
+    });
+
+    let start = Instant::now();
+    while tt.stall_count() == 0 {
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "no stall detected"
+        );
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    // The only worker is blocked, so this runs on the added worker
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        tt.run_once(async {
+            release().await?;
+            Ok(())
+        }),
+    )
+    .await
+    .expect("the added worker should execute new tasks")
+    .unwrap();
+    assert!(blocked.await.unwrap().unwrap());
+
+    // Once the blocked execution has finished, new work goes to the regular
+    // worker again
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let thread = tt
+        .run_once(async { Ok(worker_name().await?.clone_value()) })
+        .await
+        .unwrap();
+    assert_ne!(thread, "turbo-tasks-overflow");
+}
+
+/// Blocks the worker thread until it's released.
+#[turbo_tasks::function]
+fn block_worker() -> BoolVc {
+    let start = Instant::now();
+    while !RELEASED.load(Ordering::SeqCst) {
+        if start.elapsed() > Duration::from_secs(10) {
+            return BoolVc::cell(false);
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    BoolVc::cell(true)
+}
+
+#[turbo_tasks::function]
+fn worker_name() -> StringVc {
+    StringVc::cell(
+        std::thread::current()
+            .name()
+            .unwrap_or_default()
+            .to_string(),
+    )
+}
+
+#[turbo_tasks::function]
+async fn release() -> Result<BoolVc> {
+    RELEASED.store(true, Ordering::SeqCst);
+    Ok(BoolVc::cell(true))
+}
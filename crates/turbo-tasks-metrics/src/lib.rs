@@ -0,0 +1,209 @@
+//! Exposes the state of a [TurboTasks] instance with a [MemoryBackend] as
+//! metrics in the Prometheus text exposition format. The same format can be
+//! scraped by the OpenTelemetry collector with its Prometheus receiver.
+//!
+//! A [MetricsSnapshot] is collected on demand, e.g. when the metrics endpoint
+//! of a server is requested, so nothing is recorded when metrics are not
+//! used.
+
+use std::{
+    fmt::{Display, Write},
+    sync::atomic::Ordering,
+};
+
+use turbo_tasks::{backend::Backend, registry, TurboTasks};
+use turbo_tasks_memory::MemoryBackend;
+
+/// The metrics of the tasks of one function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionMetrics {
+    /// The name of the function.
+    pub name: String,
+    /// The number of tasks that have been created for the function.
+    pub tasks: usize,
+    /// The number of executions of tasks of the function.
+    pub executions: usize,
+    /// The number of invalidations of tasks of the function.
+    pub invalidations: usize,
+    /// The number of calls that looked up or created a task of the function,
+    /// including calls in other [TurboTasks] instances of the process.
+    pub cached_calls: usize,
+    /// The number of calls with [turbo_tasks::call_transient], which don't
+    /// create tasks, including calls in other [TurboTasks] instances of the
+    /// process.
+    pub transient_calls: usize,
+}
+
+/// The metrics of a root scope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RootScopeMetrics {
+    /// The description of the task that owns the scope.
+    pub task: String,
+    /// The number of tasks in the scope that are not done.
+    pub unfinished_tasks: usize,
+}
+
+/// The metrics of a [TurboTasks] instance at a point in time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Metrics by function, only for functions with tasks or transient calls.
+    pub functions: Vec<FunctionMetrics>,
+    /// The number of tasks that are scheduled or executing.
+    pub scheduled_tasks: usize,
+    /// The number of background jobs that are scheduled or running.
+    pub background_jobs: usize,
+    /// The number of task scopes.
+    pub scopes: usize,
+    /// Metrics by root scope of tasks in use.
+    pub root_scopes: Vec<RootScopeMetrics>,
+}
+
+impl MetricsSnapshot {
+    pub fn collect(turbo_tasks: &TurboTasks<MemoryBackend>) -> Self {
+        let backend = turbo_tasks.backend();
+        let mut functions = registry::get_function_ids()
+            .into_iter()
+            .filter_map(|id| {
+                let tasks = backend.created_for_function(id);
+                let function = registry::get_function(id);
+                let transient_calls = function.transient_call_count.load(Ordering::Relaxed);
+                if tasks == 0 && transient_calls == 0 {
+                    return None;
+                }
+                Some(FunctionMetrics {
+                    name: function.name.clone(),
+                    tasks,
+                    executions: backend.executions_for_function(id),
+                    invalidations: backend.invalidations_for_function(id),
+                    cached_calls: function.cached_call_count.load(Ordering::Relaxed),
+                    transient_calls,
+                })
+            })
+            .collect::<Vec<_>>();
+        functions.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut root_scopes = backend
+            .root_scopes()
+            .into_iter()
+            .map(|(scope, task)| RootScopeMetrics {
+                task: backend.get_task_description(task),
+                unfinished_tasks: backend.with_scope(scope, |scope| scope.unfinished_tasks()),
+            })
+            .collect::<Vec<_>>();
+        root_scopes.sort_by(|a, b| a.task.cmp(&b.task));
+        Self {
+            functions,
+            scheduled_tasks: turbo_tasks.get_in_progress_count(),
+            background_jobs: turbo_tasks.background_jobs_count(),
+            scopes: backend.scope_count(),
+            root_scopes,
+        }
+    }
+
+    /// Encodes the metrics in the Prometheus text exposition format.
+    pub fn to_prometheus(&self) -> String {
+        let mut out = String::new();
+        metric(
+            &mut out,
+            "turbo_tasks_tasks_created_total",
+            "counter",
+            "Tasks created by function.",
+            self.functions
+                .iter()
+                .map(|f| (Some(("function", f.name.as_str())), f.tasks)),
+        );
+        metric(
+            &mut out,
+            "turbo_tasks_executions_total",
+            "counter",
+            "Task executions by function.",
+            self.functions
+                .iter()
+                .map(|f| (Some(("function", f.name.as_str())), f.executions)),
+        );
+        metric(
+            &mut out,
+            "turbo_tasks_invalidations_total",
+            "counter",
+            "Task invalidations by function.",
+            self.functions
+                .iter()
+                .map(|f| (Some(("function", f.name.as_str())), f.invalidations)),
+        );
+        metric(
+            &mut out,
+            "turbo_tasks_cached_calls_total",
+            "counter",
+            "Calls that looked up or created a task by function.",
+            self.functions
+                .iter()
+                .map(|f| (Some(("function", f.name.as_str())), f.cached_calls)),
+        );
+        metric(
+            &mut out,
+            "turbo_tasks_transient_calls_total",
+            "counter",
+            "Calls that executed without a task by function.",
+            self.functions
+                .iter()
+                .map(|f| (Some(("function", f.name.as_str())), f.transient_calls)),
+        );
+        metric(
+            &mut out,
+            "turbo_tasks_scheduled_tasks",
+            "gauge",
+            "Tasks that are scheduled or executing.",
+            [(None, self.scheduled_tasks)],
+        );
+        metric(
+            &mut out,
+            "turbo_tasks_background_jobs",
+            "gauge",
+            "Background jobs that are scheduled or running.",
+            [(None, self.background_jobs)],
+        );
+        metric(
+            &mut out,
+            "turbo_tasks_scopes",
+            "gauge",
+            "Task scopes.",
+            [(None, self.scopes)],
+        );
+        metric(
+            &mut out,
+            "turbo_tasks_root_scope_unfinished_tasks",
+            "gauge",
+            "Unfinished tasks by root scope.",
+            self.root_scopes
+                .iter()
+                .map(|s| (Some(("task", s.task.as_str())), s.unfinished_tasks)),
+        );
+        out
+    }
+}
+
+/// Writes a metric family with one sample per optional label.
+fn metric<'a>(
+    out: &mut String,
+    name: &str,
+    ty: &str,
+    help: &str,
+    samples: impl IntoIterator<Item = (Option<(&'a str, &'a str)>, impl Display)>,
+) {
+    writeln!(out, "# HELP {name} {help}").unwrap();
+    writeln!(out, "# TYPE {name} {ty}").unwrap();
+    for (label, value) in samples {
+        match label {
+            Some((key, label)) => {
+                writeln!(out, "{name}{{{key}=\"{}\"}} {value}", escape_label(label)).unwrap()
+            }
+            None => writeln!(out, "{name} {value}").unwrap(),
+        }
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
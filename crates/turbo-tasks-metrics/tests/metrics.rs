@@ -0,0 +1,110 @@
+#![feature(min_specialization)]
+
+use std::time::Duration;
+
+use anyhow::Result;
+use turbo_tasks::TurboTasks;
+use turbo_tasks_memory::MemoryBackend;
+use turbo_tasks_metrics::MetricsSnapshot;
+use turbo_tasks_testing::{
+    counter::{Counter, CounterValueVc, CounterVc},
+    register,
+};
+
+register!();
+
+#[tokio::test]
+async fn metrics() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async {
+        let counter = CounterVc::cell(Counter::new(0));
+        let double = double_value(counter);
+        assert_eq!(*double.strongly_consistent().await?, 0);
+        counter.await?.incr();
+        assert_eq!(*double.strongly_consistent().await?, 2);
+        Ok(())
+    })
+    .await
+    .unwrap();
+
+    let snapshot = MetricsSnapshot::collect(&tt);
+    let double = snapshot
+        .functions
+        .iter()
+        .find(|f| f.name == "double_value")
+        .unwrap();
+    assert_eq!(double.tasks, 1);
+    assert_eq!(double.executions, 2);
+    // Invalidated because the value it read has changed
+    assert_eq!(double.invalidations, 1);
+    assert_eq!(double.cached_calls, 1);
+    assert_eq!(double.transient_calls, 0);
+    let get_value = snapshot
+        .functions
+        .iter()
+        .find(|f| f.name.ends_with("get_value"))
+        .unwrap();
+    assert_eq!(get_value.invalidations, 1);
+    assert_eq!(snapshot.scheduled_tasks, 0);
+    assert!(snapshot.scopes >= 1);
+
+    let text = snapshot.to_prometheus();
+    assert!(text.contains("# TYPE turbo_tasks_executions_total counter\n"));
+    assert!(text.contains("turbo_tasks_executions_total{function=\"double_value\"} 2\n"));
+    assert!(text.contains("turbo_tasks_scheduled_tasks 0\n"));
+}
+
+#[tokio::test]
+async fn counts_executions_per_instance() {
+    lazy_static::initialize(&REGISTER);
+    for _ in 0..2 {
+        let tt = TurboTasks::new(MemoryBackend::new());
+        tt.run_once(async { Ok(*triple_value(1).await?) })
+            .await
+            .unwrap();
+        let snapshot = MetricsSnapshot::collect(&tt);
+        let triple = snapshot
+            .functions
+            .iter()
+            .find(|f| f.name == "triple_value")
+            .unwrap();
+        assert_eq!(triple.executions, 1);
+    }
+}
+
+#[tokio::test]
+async fn drops_root_scopes_of_released_tasks() {
+    lazy_static::initialize(&REGISTER);
+    let tt = TurboTasks::new(MemoryBackend::new());
+    tt.run_once(async { Ok(*triple_value(2).strongly_consistent().await?) })
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while !MetricsSnapshot::collect(&tt).root_scopes.is_empty() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    // The root scope is listed again when the task is used again
+    let tt_inner = tt.clone();
+    tt.run_once(async move {
+        triple_value(2).strongly_consistent().await?;
+        assert_eq!(MetricsSnapshot::collect(&tt_inner).root_scopes.len(), 1);
+        Ok(())
+    })
+    .await
+    .unwrap();
+}
+
+#[turbo_tasks::function]
+fn triple_value(value: usize) -> CounterValueVc {
+    CounterValueVc::cell(value * 3)
+}
+
+#[turbo_tasks::function]
+async fn double_value(counter: CounterVc) -> Result<CounterValueVc> {
+    Ok(CounterValueVc::cell(*counter.get_value().await? * 2))
+}
@@ -655,10 +655,8 @@ impl Backend for RocksDbBackend {
         self.try_initialize(task_id_provider).unwrap();
     }
 
-    fn startup(&self, turbo_tasks: &dyn TurboTasksBackendApi) {
-        self.with_task_id_mapping(turbo_tasks, || {
-            self.try_startup(turbo_tasks).unwrap();
-        })
+    fn startup(&self, turbo_tasks: &dyn TurboTasksBackendApi) -> Result<()> {
+        self.with_task_id_mapping(turbo_tasks, || self.try_startup(turbo_tasks))
     }
 
     fn invalidate_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {
@@ -1114,7 +1112,7 @@ mod tests {
         std::fs::remove_dir_all(&db).unwrap();
         turbo_tasks::register();
         let b = RocksDbBackend::new(db, "hello").unwrap();
-        b.startup(&*turbo_tasks);
+        b.startup(&*turbo_tasks).unwrap();
         let content = CellContent(Some(SharedReference(
             Some(Completion::get_value_type_id()),
             Arc::new(Completion),
@@ -0,0 +1,50 @@
+//! A value that is changed from outside of tasks, which invalidates the tasks
+//! that have read it.
+
+use std::sync::Mutex;
+
+use anyhow::Result;
+use turbo_tasks::{get_invalidator, Invalidator};
+
+#[turbo_tasks::value(transparent)]
+pub struct CounterValue(usize);
+
+#[turbo_tasks::value(serialization = "none", cell = "new", eq = "manual")]
+pub struct Counter {
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    value: Mutex<(usize, Option<Invalidator>)>,
+}
+
+impl Counter {
+    pub fn new(value: usize) -> Self {
+        Self {
+            value: Mutex::new((value, None)),
+        }
+    }
+
+    /// Increments the value and invalidates the task that has read it last.
+    pub fn incr(&self) {
+        let mut lock = self.value.lock().unwrap();
+        lock.0 += 1;
+        if let Some(i) = lock.1.take() {
+            i.invalidate();
+        }
+    }
+
+    /// Reads the value in the current task, so it depends on the counter
+    /// without a task in between.
+    pub fn get(&self) -> usize {
+        let mut lock = self.value.lock().unwrap();
+        lock.1 = Some(get_invalidator());
+        lock.0
+    }
+}
+
+#[turbo_tasks::value_impl]
+impl CounterVc {
+    /// Reads the value in a task of its own.
+    #[turbo_tasks::function]
+    pub async fn get_value(self) -> Result<CounterValueVc> {
+        Ok(CounterValueVc::cell(self.await?.get()))
+    }
+}
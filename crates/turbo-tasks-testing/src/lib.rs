@@ -1,7 +1,9 @@
 //! Testing utilities and macros for turbo-tasks and applications based on it.
 
 #![feature(box_syntax)]
+#![feature(min_specialization)]
 
+pub mod counter;
 mod macros;
 pub mod retry;
 
@@ -19,7 +21,8 @@ use turbo_tasks::{
     event::{Event, EventListener},
     registry,
     test_helpers::{current_task_for_testing, with_turbo_tasks_for_testing},
-    CellId, RawVc, TaskId, TraitTypeId, TurboTasksApi, TurboTasksCallApi,
+    CellId, ExecutionHook, MemoTable, RawVc, ReleaseHook, TaskId, TraitTypeId, TurboTasksApi,
+    TurboTasksCallApi,
 };
 
 enum Task {
@@ -103,6 +106,49 @@ impl TurboTasksApi for VcStorage {
         unreachable!()
     }
 
+    fn invalidate_and_wait(
+        &self,
+        _task: TaskId,
+    ) -> std::pin::Pin<Box<dyn Future<Output = ()> + Send>> {
+        unreachable!()
+    }
+
+    fn flush_current_task_cell_updates(&self) {
+        // ignore
+    }
+
+    fn mark_current_task_final(&self) {
+        // ignore
+    }
+
+    fn set_current_task_label(&self, _label: String) {
+        // ignore
+    }
+
+    fn add_current_task_tag(&self, _tag: String) {
+        // ignore
+    }
+
+    fn on_next_execution(&self, _task: TaskId, _hook: ExecutionHook) {
+        // tasks are never executed again
+    }
+
+    fn on_task_released(&self, _task: TaskId, _hook: ReleaseHook) {
+        // tasks are never released
+    }
+
+    fn prefetch(&self, _task: TaskId, _with_children: bool) {
+        // tasks are executed when they are called
+    }
+
+    fn is_task_consistent_at_least(&self, _task: TaskId, _generation: usize) -> bool {
+        false
+    }
+
+    fn memo_table(&self) -> Option<&MemoTable> {
+        None
+    }
+
     fn notify_scheduled_tasks(&self) {
         // ignore
     }
@@ -120,6 +166,17 @@ impl TurboTasksApi for VcStorage {
         }
     }
 
+    fn try_read_task_outputs(&self, tasks: &[TaskId]) -> Vec<Result<Result<RawVc, EventListener>>> {
+        tasks
+            .iter()
+            .map(|&task| self.try_read_task_output(task, false))
+            .collect()
+    }
+
+    fn try_read_task_output_stale(&self, task: TaskId) -> Result<Result<RawVc, EventListener>> {
+        self.try_read_task_output(task, false)
+    }
+
     fn try_read_task_output_untracked(
         &self,
         task: TaskId,
@@ -216,3 +273,8 @@ impl VcStorage {
         )
     }
 }
+
+pub fn register() {
+    turbo_tasks::register();
+    include!(concat!(env!("OUT_DIR"), "/register.rs"));
+}
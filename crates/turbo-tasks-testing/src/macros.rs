@@ -3,7 +3,7 @@ macro_rules! register {
     () => {
         lazy_static::lazy_static! {
             static ref REGISTER: () = {
-                turbo_tasks::register();
+                $crate::register();
                 include!(concat!(env!("OUT_DIR"), "/register_test_", module_path!(), ".rs"));
             };
         }
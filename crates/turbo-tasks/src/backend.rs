@@ -1,24 +1,33 @@
 use std::{
     any::Any,
     borrow::Cow,
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fmt::{Debug, Display},
     future::Future,
     pin::Pin,
     sync::Arc,
-    time::{Duration, Instant},
+    time::Duration,
 };
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
+use nohash_hasher::BuildNoHashHasher;
 use serde::{Deserialize, Serialize};
 
 pub use crate::id::BackendJobId;
 use crate::{
-    event::EventListener, manager::TurboTasksBackendApi, raw_vc::CellId, registry,
-    task_input::SharedReference, FunctionId, RawVc, ReadRef, TaskId, TaskIdProvider, TaskInput,
-    TraitTypeId,
+    event::EventListener,
+    manager::{RootTaskState, TurboTasksBackendApi},
+    memo_table::MemoTable,
+    platform::Instant,
+    raw_vc::CellId,
+    registry,
+    task_input::SharedReference,
+    FunctionId, RawVc, ReadRef, TaskId, TaskIdProvider, TaskInput, TraitTypeId, ValueTypeId,
 };
 
+/// The number of cells of each value type that an execution created.
+pub type CellCounts = HashMap<ValueTypeId, u32, BuildNoHashHasher<ValueTypeId>>;
+
 /// Different Task types
 pub enum TaskType {
     /// Tasks that only exist for a certain operation and
@@ -30,7 +39,9 @@ pub enum TaskType {
     Persistent(PersistentTaskType),
 }
 
-type TransientTaskRoot =
+/// The function of a [TransientTaskType::Root] task, which is called for
+/// every execution.
+pub type TransientTaskRoot =
     Box<dyn Fn() -> Pin<Box<dyn Future<Output = Result<RawVc>> + Send>> + Send + Sync>;
 
 pub enum TransientTaskType {
@@ -153,8 +164,12 @@ pub trait Backend: Sync + Send {
     #[allow(unused_variables)]
     fn initialize(&mut self, task_id_provider: &dyn TaskIdProvider) {}
 
+    /// Called once when the [crate::TurboTasks] instance has been created.
+    /// Errors, e. g. when persisted state can't be loaded, fail the creation.
     #[allow(unused_variables)]
-    fn startup(&self, turbo_tasks: &dyn TurboTasksBackendApi) {}
+    fn startup(&self, turbo_tasks: &dyn TurboTasksBackendApi) -> Result<()> {
+        Ok(())
+    }
 
     #[allow(unused_variables)]
     fn stop(&self, turbo_tasks: &dyn TurboTasksBackendApi) {}
@@ -163,8 +178,88 @@ pub trait Backend: Sync + Send {
 
     fn invalidate_tasks(&self, tasks: Vec<TaskId>, turbo_tasks: &dyn TurboTasksBackendApi);
 
+    /// Invalidates all tasks of the function, e.g. after its implementation
+    /// has been replaced.
+    #[allow(unused_variables)]
+    fn invalidate_function_tasks(
+        &self,
+        function: FunctionId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+    }
+
+    /// Returns a listener that is notified when the active tasks around the
+    /// task have finished, i.e. the task itself, the tasks it calls and its
+    /// active dependents. Returns `None` when they are finished already.
+    /// Backends without that information return `None`. See
+    /// [crate::Invalidator::invalidate_and_wait].
+    #[allow(unused_variables)]
+    fn task_done_listener(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Option<EventListener> {
+        None
+    }
+
+    /// Returns the state of a root task that has not settled yet, or `None`
+    /// when it and the tasks in its scope are finished. Unlike a strongly
+    /// consistent read of the output, this doesn't affect the scheduling of
+    /// the scope. Backends without that information return `None`. See
+    /// [crate::TurboTasks::pending_roots].
+    #[allow(unused_variables)]
+    fn pending_root_state(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Option<RootTaskState> {
+        None
+    }
+
+    /// Returns true when the task is executed on the blocking thread pool
+    /// instead of the async executor. See [crate::NativeFunction::blocking].
+    #[allow(unused_variables)]
+    fn is_blocking_task(&self, task: TaskId) -> bool {
+        false
+    }
+
     fn get_task_description(&self, task: TaskId) -> String;
 
+    /// Returns the table for [crate::memoize]. Results are computed on every
+    /// call without one.
+    fn memo_table(&self) -> Option<&MemoTable> {
+        None
+    }
+
+    /// Returns the function of the task, if it's a task of a function.
+    #[allow(unused_variables)]
+    fn get_task_function(&self, task: TaskId) -> Option<FunctionId> {
+        None
+    }
+
+    /// Marks the output and cells of the task as final once its running
+    /// execution completes. Reads of them don't need to make the reader depend
+    /// on them, as they won't change anymore.
+    #[allow(unused_variables)]
+    fn mark_task_final(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {}
+
+    /// Attaches a human readable label to the task, which is included in its
+    /// description.
+    #[allow(unused_variables)]
+    fn set_task_label(&self, task: TaskId, label: String, turbo_tasks: &dyn TurboTasksBackendApi) {}
+
+    /// Attaches a tag to the task, to select it for bulk operations with
+    /// [Backend::tasks_with_tag]. A task can have multiple tags. Tags are
+    /// removed when the task executes again.
+    #[allow(unused_variables)]
+    fn add_task_tag(&self, task: TaskId, tag: String, turbo_tasks: &dyn TurboTasksBackendApi) {}
+
+    /// Returns the tasks that have the tag, see [Backend::add_task_tag].
+    #[allow(unused_variables)]
+    fn tasks_with_tag(&self, tag: &str) -> Vec<TaskId> {
+        Vec::new()
+    }
+
     type ExecutionScopeFuture<T: Future<Output = Result<()>> + Send + 'static>: Future<Output = Result<()>>
         + Send
         + 'static;
@@ -181,6 +276,18 @@ pub trait Backend: Sync + Send {
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> Option<TaskExecutionSpec>;
 
+    /// Called after a successful execution with the number of cells it
+    /// created of each type. Cells are indexed per type in creation order, so
+    /// the cells of the task with higher indices are not used anymore.
+    #[allow(unused_variables)]
+    fn task_execution_cell_counts(
+        &self,
+        task: TaskId,
+        cell_counts: &CellCounts,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+    }
+
     fn task_execution_result(
         &self,
         task: TaskId,
@@ -210,6 +317,35 @@ pub trait Backend: Sync + Send {
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> Result<Result<RawVc, EventListener>>;
 
+    /// Reads the outputs of multiple tasks at once, like
+    /// [Backend::try_read_task_output] without strong consistency. Backends
+    /// can override it to register all dependencies in one pass.
+    fn try_read_task_outputs(
+        &self,
+        tasks: &[TaskId],
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Vec<Result<Result<RawVc, EventListener>>> {
+        tasks
+            .iter()
+            .map(|&task| self.try_read_task_output(task, reader, false, turbo_tasks))
+            .collect()
+    }
+
+    /// Reads the output of a task like [Backend::try_read_task_output] without
+    /// strong consistency, but returns the output of the last execution
+    /// instead of waiting while the task is dirty or executing. It only waits
+    /// when the task has no output yet. Backends that don't keep outputs
+    /// during executions wait like [Backend::try_read_task_output].
+    fn try_read_task_output_stale(
+        &self,
+        task: TaskId,
+        reader: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<Result<RawVc, EventListener>> {
+        self.try_read_task_output(task, reader, false, turbo_tasks)
+    }
+
     /// INVALIDATION: Be careful with this, it will not track dependencies, so
     /// using it could break cache invalidation.
     fn try_read_task_output_untracked(
@@ -297,6 +433,31 @@ pub trait Backend: Sync + Send {
         turbo_tasks: &dyn TurboTasksBackendApi,
     );
 
+    /// Updates multiple cells of a task. Backends should apply them atomically,
+    /// so readers observe either none or all of the updates.
+    fn update_task_cells(
+        &self,
+        task: TaskId,
+        updates: Vec<(CellId, CellContent)>,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+        for (index, content) in updates {
+            self.update_task_cell(task, index, content, turbo_tasks);
+        }
+    }
+
+    /// Checks if a call from `parent_task` may create a task of the type,
+    /// before [Backend::get_or_create_persistent_task] is called. An error
+    /// fails the call instead, e.g. when a quota would be exceeded.
+    fn check_task_creation(
+        &self,
+        _task_type: &PersistentTaskType,
+        _parent_task: TaskId,
+        _turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     fn get_or_create_persistent_task(
         &self,
         task_type: PersistentTaskType,
@@ -309,6 +470,53 @@ pub trait Backend: Sync + Send {
         task_type: TransientTaskType,
         turbo_tasks: &dyn TurboTasksBackendApi,
     ) -> TaskId;
+
+    /// Replaces the function of a [TransientTaskType::Root] task and
+    /// invalidates the task, so it's executed with the new function. Its
+    /// scope and children are kept, so the next execution reuses the unchanged
+    /// children. Fails when the task is not a root task.
+    #[allow(unused_variables)]
+    fn replace_root_task(
+        &self,
+        task: TaskId,
+        functor: TransientTaskRoot,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> Result<()> {
+        bail!("replacing root tasks is not supported by the backend")
+    }
+
+    /// Keeps the task and all its child tasks active until
+    /// `release_task_keep_alive` is called. Calls are counted.
+    #[allow(unused_variables)]
+    fn keep_task_alive(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {}
+
+    /// Reverts a previous `keep_task_alive` call.
+    #[allow(unused_variables)]
+    fn release_task_keep_alive(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi) {}
+
+    /// Schedules the task when it's dirty, e.g. because it's not in an active
+    /// scope. With `with_children` the same is done for the children of its
+    /// last execution. Prefetching is only a hint and can be ignored.
+    #[allow(unused_variables)]
+    fn prefetch_task(
+        &self,
+        task: TaskId,
+        with_children: bool,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) {
+    }
+
+    /// Returns true when the task is done and active, so changes of its
+    /// dependencies are propagated to it without reading it. Returning false
+    /// is always correct, reads will be strongly consistent then.
+    #[allow(unused_variables)]
+    fn is_task_active_and_done(
+        &self,
+        task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi,
+    ) -> bool {
+        false
+    }
 }
 
 impl PersistentTaskType {
@@ -317,10 +525,10 @@ impl PersistentTaskType {
         inputs: Vec<TaskInput>,
         turbo_tasks: Arc<dyn TurboTasksBackendApi>,
     ) -> Result<RawVc> {
-        let mut resolved_inputs = Vec::with_capacity(inputs.len());
-        for input in inputs.into_iter() {
-            resolved_inputs.push(input.resolve().await?)
-        }
+        let resolved_inputs = registry::get_function(fn_id)
+            .resolve_mode
+            .resolve(inputs)
+            .await?;
         Ok(turbo_tasks.native_call(fn_id, resolved_inputs))
     }
 
@@ -350,11 +558,21 @@ impl PersistentTaskType {
                             .iter()
                             .map(|t| format!(" {}", t))
                             .collect::<String>();
+                        let implementors = registry::get_trait_implementors(trait_type)
+                            .into_iter()
+                            .map(|ty| registry::get_value_type(ty).name.as_str())
+                            .collect::<Vec<_>>();
+                        let implementors = if implementors.is_empty() {
+                            "there are no implementors".to_string()
+                        } else {
+                            format!("implementors are {}", implementors.join(", "))
+                        };
                         Err(anyhow!(
-                            "{} doesn't implement {} (only{})",
+                            "{} doesn't implement {} (only{}); {}",
                             this_value,
                             registry::get_trait(trait_type),
                             traits,
+                            implementors,
                         ))
                     } else {
                         Err(anyhow!(
@@ -0,0 +1,61 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tokio::runtime::Handle;
+
+use crate::platform::{self, Instant};
+
+/// Utilization of the blocking thread pool by tasks of blocking functions.
+/// See [crate::NativeFunction::blocking].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct BlockingPoolStats {
+    /// The number of tasks that are executing on the blocking pool at the
+    /// moment.
+    pub running: usize,
+    /// The number of times tasks have been run on the blocking pool.
+    pub executions: usize,
+    /// The total time the blocking pool spent executing tasks.
+    pub busy_time: Duration,
+}
+
+#[derive(Default)]
+pub(crate) struct BlockingPool {
+    running: AtomicUsize,
+    executions: AtomicUsize,
+    busy_time_micros: AtomicU64,
+}
+
+impl BlockingPool {
+    /// Drives the future to completion on a thread of the blocking pool, so
+    /// it doesn't occupy a worker of the async executor.
+    pub fn spawn<F>(self: &Arc<Self>, handle: Handle, future: F)
+    where
+        F: Future + Send + 'static,
+    {
+        let this = self.clone();
+        platform::spawn_blocking_future(handle, async move {
+            this.running.fetch_add(1, Ordering::Relaxed);
+            let start = Instant::now();
+            future.await;
+            let busy_time = start.elapsed();
+            this.busy_time_micros
+                .fetch_add(busy_time.as_micros() as u64, Ordering::Relaxed);
+            this.executions.fetch_add(1, Ordering::Relaxed);
+            this.running.fetch_sub(1, Ordering::Relaxed);
+        });
+    }
+
+    pub fn stats(&self) -> BlockingPoolStats {
+        BlockingPoolStats {
+            running: self.running.load(Ordering::Relaxed),
+            executions: self.executions.load(Ordering::Relaxed),
+            busy_time: Duration::from_micros(self.busy_time_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+
+pin_project! {
+    /// Counts the future in `busy` while it's polled, so `busy` is the number
+    /// of workers that are executing one of these futures at the moment.
+    pub struct BusyFuture<F> {
+        busy: Arc<AtomicUsize>,
+        #[pin]
+        future: F,
+    }
+}
+
+impl<F: Future> BusyFuture<F> {
+    pub fn new(busy: Arc<AtomicUsize>, future: F) -> Self {
+        Self { busy, future }
+    }
+}
+
+impl<F: Future> Future for BusyFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        this.busy.fetch_add(1, Ordering::Relaxed);
+        let result = this.future.poll(cx);
+        this.busy.fetch_sub(1, Ordering::Relaxed);
+        result
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::Mutex,
+};
+
+use dashmap::DashMap;
+use nohash_hasher::BuildNoHashHasher;
+
+use crate::{registry, TaskId, ValueTypeId};
+
+/// The number of tasks whose cells are remembered. Executions of other tasks
+/// are not compared.
+const MAX_TRACKED_TASKS: usize = 100_000;
+
+/// The number of conflicts that are kept. Later conflicts are only reported
+/// as `tracing` warning.
+const MAX_CONFLICTS: usize = 1000;
+
+/// Two executions of a task created the cells of a type differently. Cells
+/// are identified by their index per type, so the cells after the difference
+/// now hold different values than before. This is usually caused by a task
+/// body that is not deterministic.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellConflict {
+    /// The description of the task.
+    pub task: String,
+    /// The name of the value type of the cells.
+    pub value_type: String,
+    /// The number of cells of the type in the previous execution.
+    pub previous: u32,
+    /// The number of cells of the type in the current execution.
+    pub current: u32,
+    /// The number of cells is the same, but they were created in a different
+    /// order relative to the cells of other types.
+    pub reordered: bool,
+}
+
+/// The order in which the current execution created its cells, by type.
+#[derive(Default)]
+pub(crate) struct CellOrder(Vec<ValueTypeId>);
+
+impl CellOrder {
+    pub fn push(&mut self, ty: ValueTypeId) {
+        self.0.push(ty);
+    }
+
+    /// The number of cells of each type, and a hash of their positions among
+    /// all cells.
+    fn summarize(&self) -> CellSummary {
+        let mut hashers: HashMap<ValueTypeId, (u32, DefaultHasher), BuildNoHashHasher<_>> =
+            HashMap::default();
+        for (position, ty) in self.0.iter().enumerate() {
+            let (count, hasher) = hashers.entry(*ty).or_default();
+            *count += 1;
+            position.hash(hasher);
+        }
+        hashers
+            .into_iter()
+            .map(|(ty, (count, hasher))| (ty, (count, hasher.finish())))
+            .collect()
+    }
+}
+
+type CellSummary = HashMap<ValueTypeId, (u32, u64), BuildNoHashHasher<ValueTypeId>>;
+
+/// Compares the cells created by successful executions of a task. Only used
+/// in debug builds.
+#[derive(Default)]
+pub(crate) struct CellConflictCheck {
+    cells: DashMap<TaskId, CellSummary, BuildNoHashHasher<TaskId>>,
+    conflicts: Mutex<Vec<CellConflict>>,
+}
+
+impl CellConflictCheck {
+    /// Compares the cells of the current execution with the previous one of
+    /// the task. Conflicts are reported as `tracing` warning. The description
+    /// is only computed when needed.
+    pub fn check(&self, task: TaskId, order: CellOrder, description: impl Fn() -> String) {
+        let current = order.summarize();
+        let previous = if self.cells.len() < MAX_TRACKED_TASKS || self.cells.contains_key(&task) {
+            self.cells.insert(task, current.clone())
+        } else {
+            None
+        };
+        let Some(previous) = previous else {
+            return;
+        };
+        let mut types: Vec<_> = previous.keys().chain(current.keys()).copied().collect();
+        types.sort();
+        types.dedup();
+        let changes = types
+            .into_iter()
+            .map(|ty| {
+                let previous = previous.get(&ty).copied().unwrap_or_default();
+                let current = current.get(&ty).copied().unwrap_or_default();
+                (ty, previous, current)
+            })
+            .filter(|(_, previous, current)| previous != current)
+            .collect::<Vec<_>>();
+        // A different number of cells of one type also moves the cells of
+        // other types, so the order is only compared when all counts match
+        let reordered = changes
+            .iter()
+            .all(|(_, (previous, _), (current, _))| previous == current);
+        for (ty, (previous, _), (current, _)) in changes {
+            if !reordered && previous == current {
+                continue;
+            }
+            let conflict = CellConflict {
+                task: description(),
+                value_type: registry::get_value_type(ty).name.clone(),
+                previous,
+                current,
+                reordered,
+            };
+            if reordered {
+                tracing::warn!(
+                    "{} created its cells of {} in a different order than in the previous \
+                     execution, which swaps the cells that readers refer to",
+                    conflict.task,
+                    conflict.value_type,
+                );
+            } else {
+                tracing::warn!(
+                    "{} created {} cells of {} instead of {} in the previous execution, which \
+                     shifts the cells that readers refer to",
+                    conflict.task,
+                    conflict.current,
+                    conflict.value_type,
+                    conflict.previous
+                );
+            }
+            let mut conflicts = self.conflicts.lock().unwrap();
+            if conflicts.len() < MAX_CONFLICTS {
+                conflicts.push(conflict);
+            }
+        }
+    }
+
+    /// Forgets the cells of a task that is no longer used.
+    pub fn task_released(&self, task: TaskId) {
+        self.cells.remove(&task);
+    }
+
+    pub fn conflicts(&self) -> Vec<CellConflict> {
+        self.conflicts.lock().unwrap().clone()
+    }
+}
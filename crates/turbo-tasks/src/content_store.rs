@@ -0,0 +1,248 @@
+use std::{
+    any::Any,
+    cell::Cell,
+    collections::HashMap,
+    fmt::{self, Display},
+    mem::take,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, Context, Result};
+use dashmap::{mapref::entry::Entry, DashMap};
+use serde::{Deserialize, Serialize};
+use turbo_tasks_hash::{encode_base16, hash_md4};
+
+use crate::{
+    backend::{CellContent, CellCounts},
+    id::with_task_id_mapping,
+    task_input::SharedReference,
+    CellId, IdMapping, TaskId,
+};
+
+/// The hash of the serialized content of a cell, which addresses it in a
+/// [ContentStore].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ContentHash(pub [u8; 16]);
+
+impl ContentHash {
+    pub fn of(data: &[u8]) -> Self {
+        Self(hash_md4(data))
+    }
+}
+
+impl Display for ContentHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&encode_base16(&self.0))
+    }
+}
+
+/// A content-addressable store for serialized cell values, usually shared
+/// between machines via HTTP or gRPC. Values are only pushed and fetched by
+/// their [ContentHash], so machines share computed values without sharing
+/// the task graph.
+///
+/// Values are pushed by a background job on a blocking thread, after the tasks
+/// that updated them have finished.
+pub trait ContentStore: Send + Sync {
+    /// Returns the data that has been pushed under the hash, if any.
+    fn fetch(&self, hash: &ContentHash) -> Result<Option<Vec<u8>>>;
+
+    /// Stores the data under its hash.
+    fn push(&self, hash: ContentHash, data: Vec<u8>) -> Result<()>;
+
+    /// Returns true when the store has the data, to avoid pushing it again.
+    fn contains(&self, hash: &ContentHash) -> Result<bool> {
+        Ok(self.fetch(hash)?.is_some())
+    }
+}
+
+/// A [ContentStore] that keeps the data in memory, e.g. to share values
+/// between [crate::TurboTasks] instances in the same process.
+#[derive(Default)]
+pub struct MemoryContentStore {
+    data: Mutex<HashMap<ContentHash, Vec<u8>>>,
+}
+
+impl MemoryContentStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ContentStore for MemoryContentStore {
+    fn fetch(&self, hash: &ContentHash) -> Result<Option<Vec<u8>>> {
+        Ok(self.data.lock().unwrap().get(hash).cloned())
+    }
+
+    fn push(&self, hash: ContentHash, data: Vec<u8>) -> Result<()> {
+        self.data.lock().unwrap().insert(hash, data);
+        Ok(())
+    }
+
+    fn contains(&self, hash: &ContentHash) -> Result<bool> {
+        Ok(self.data.lock().unwrap().contains_key(hash))
+    }
+}
+
+/// Pushes cell values to a [ContentStore] after they have been updated and
+/// remembers their hashes.
+pub(crate) struct ContentCache {
+    store: Arc<dyn ContentStore>,
+    /// Values with a smaller serialized size are not pushed.
+    min_size: usize,
+    /// The hashes of the pushed cells by task. Cells that are not used by the
+    /// last execution of their task are removed, see [ContentCache::prune].
+    hashes: DashMap<TaskId, HashMap<CellId, ContentHash>>,
+    /// The latest content of the cells that have been updated since the last
+    /// push, see [ContentCache::push_pending].
+    pending: Mutex<HashMap<TaskId, HashMap<CellId, SharedReference>>>,
+}
+
+impl ContentCache {
+    pub fn new(store: Arc<dyn ContentStore>, min_size: usize) -> Self {
+        Self {
+            store,
+            min_size,
+            hashes: DashMap::new(),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records the new content of a cell, which is serialized and pushed
+    /// later by [ContentCache::push_pending], as that's too expensive while
+    /// cells are updated. Returns true when there was no pending update
+    /// before, so the push needs to be scheduled.
+    pub fn cell_updated(&self, task: TaskId, index: CellId, content: &CellContent) -> bool {
+        if let Some(mut hashes) = self.hashes.get_mut(&task) {
+            hashes.remove(&index);
+        }
+        let mut pending = self.pending.lock().unwrap();
+        let was_empty = pending.is_empty();
+        match &content.0 {
+            Some(reference) => {
+                pending
+                    .entry(task)
+                    .or_default()
+                    .insert(index, reference.clone());
+            }
+            None => {
+                if let Some(cells) = pending.get_mut(&task) {
+                    cells.remove(&index);
+                    if cells.is_empty() {
+                        pending.remove(&task);
+                    }
+                }
+            }
+        }
+        was_empty && !pending.is_empty()
+    }
+
+    /// Forgets the cells of the task that its last execution didn't create.
+    pub fn prune(&self, task: TaskId, cell_counts: &CellCounts) {
+        let is_used =
+            |index: &CellId| index.index < cell_counts.get(&index.type_id).copied().unwrap_or(0);
+        if let Entry::Occupied(mut entry) = self.hashes.entry(task) {
+            entry.get_mut().retain(|index, _| is_used(index));
+            if entry.get().is_empty() {
+                entry.remove();
+            }
+        }
+        if let Some(cells) = self.pending.lock().unwrap().get_mut(&task) {
+            cells.retain(|index, _| is_used(index));
+        }
+    }
+
+    /// Pushes the pending cell contents when they are serializable and large
+    /// enough. Values that reference other tasks are skipped, as task ids
+    /// differ between machines. Failures are only logged, as the store is
+    /// just a cache. It blocks on the store.
+    pub fn push_pending(&self) {
+        let pending = take(&mut *self.pending.lock().unwrap());
+        for (task, cells) in pending {
+            for (index, reference) in cells {
+                self.push(task, index, &reference);
+            }
+        }
+    }
+
+    fn push(&self, task: TaskId, index: CellId, reference: &SharedReference) {
+        let mapping = DetectTaskIds::default();
+        let data = with_task_id_mapping(&mapping, || serde_json::to_vec(reference));
+        let Some(data) = data
+            .ok()
+            .filter(|data| !mapping.found.get() && data.len() >= self.min_size)
+        else {
+            return;
+        };
+        let hash = ContentHash::of(&data);
+        let pushed = self.store.contains(&hash).and_then(|contains| {
+            if contains {
+                Ok(())
+            } else {
+                self.store.push(hash, data)
+            }
+        });
+        match pushed {
+            Ok(()) => {
+                self.hashes.entry(task).or_default().insert(index, hash);
+            }
+            Err(err) => {
+                tracing::warn!("failed to push {index} of {task} to the content store: {err:?}");
+            }
+        }
+    }
+
+    pub fn hash(&self, task: TaskId, index: CellId) -> Option<ContentHash> {
+        self.hashes
+            .get(&task)
+            .and_then(|hashes| hashes.get(&index).copied())
+    }
+
+    pub fn fetch(&self, hash: &ContentHash) -> Result<Option<SharedReference>> {
+        let Some(data) = self.store.fetch(hash)? else {
+            return Ok(None);
+        };
+        let reference = serde_json::from_slice(&data)
+            .with_context(|| format!("failed to deserialize content {hash}"))?;
+        Ok(Some(reference))
+    }
+
+    pub fn fetch_typed<T: Any + Send + Sync>(&self, hash: &ContentHash) -> Result<Option<Arc<T>>> {
+        let Some(reference) = self.fetch(hash)? else {
+            return Ok(None);
+        };
+        let reference_description = reference.to_string();
+        match reference.downcast::<T>() {
+            Some(value) => Ok(Some(value)),
+            None => bail!(
+                "content {hash} is a {reference_description}, not a {}",
+                std::any::type_name::<T>()
+            ),
+        }
+    }
+}
+
+/// Records whether a serialized value contains task ids.
+#[derive(Default)]
+struct DetectTaskIds {
+    found: Cell<bool>,
+}
+
+impl IdMapping<TaskId> for DetectTaskIds {
+    fn forward(&self, _id: TaskId) -> usize {
+        self.found.set(true);
+        0
+    }
+
+    fn backward(&self, _id: usize) -> TaskId {
+        unreachable!("only used for serialization")
+    }
+}
@@ -0,0 +1,101 @@
+use std::{any::TypeId, collections::BTreeMap, fmt::Debug, future::Future, hash::Hash, sync::Arc};
+
+use dashmap::{mapref::entry::Entry, DashMap};
+use nohash_hasher::BuildNoHashHasher;
+
+use crate::{magic_any::MagicAny, task_local, TaskId};
+
+/// The context values that are visible to a task execution, by type.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub(crate) struct TaskContext {
+    values: BTreeMap<TypeId, Arc<dyn MagicAny>>,
+}
+
+task_local! {
+    /// The context of the current task execution or [with_context] scope.
+    static CONTEXT: Arc<TaskContext>;
+}
+
+/// Returns the current context, unless there is none or it's empty.
+pub(crate) fn current_context() -> Option<Arc<TaskContext>> {
+    CONTEXT
+        .try_with(|context| context.clone())
+        .ok()
+        .filter(|context| !context.values.is_empty())
+}
+
+/// Runs the future with a context value, e.g. the current environment or
+/// feature flags. It replaces a value of the same type from an outer scope.
+///
+/// Functions called from the future see the value with [get_context], and so
+/// do the functions called by them. The context is not part of the inputs of
+/// these calls: a task remembers the context of its latest call and executes
+/// again when it's called with a different context. So the context should
+/// only change between updates, as calls with the same inputs under
+/// different contexts at the same time share one task.
+pub async fn with_context<T, F>(value: T, future: F) -> F::Output
+where
+    T: Debug + Eq + Ord + Hash + Send + Sync + 'static,
+    F: Future,
+{
+    let mut context = CONTEXT
+        .try_with(|context| (**context).clone())
+        .unwrap_or_default();
+    context.values.insert(TypeId::of::<T>(), Arc::new(value));
+    CONTEXT.scope(Arc::new(context), future).await
+}
+
+/// Returns the context value of the type that has been set with
+/// [with_context] by the current task or one of its callers.
+pub fn get_context<T: Send + Sync + 'static>() -> Option<Arc<T>> {
+    let context = current_context()?;
+    let value = context.values.get(&TypeId::of::<T>())?.clone();
+    value.magic_any_arc().downcast().ok()
+}
+
+/// The context of the latest call of each task, kept next to the task
+/// instead of in its inputs, so the task stays cached and persistable.
+#[derive(Default)]
+pub(crate) struct TaskContexts {
+    contexts: DashMap<TaskId, Arc<TaskContext>, BuildNoHashHasher<TaskId>>,
+}
+
+impl TaskContexts {
+    /// Remembers the context of a call of the task. Returns true when it
+    /// differs from the context of the previous call, so the task need to be
+    /// invalidated.
+    pub fn called(&self, task: TaskId, context: Option<Arc<TaskContext>>) -> bool {
+        let Some(context) = context else {
+            // Most calls have no context, they only need a shared lock
+            return self.contexts.contains_key(&task) && self.contexts.remove(&task).is_some();
+        };
+        match self.contexts.entry(task) {
+            Entry::Occupied(entry) if *entry.get() == context => false,
+            Entry::Occupied(mut entry) => {
+                entry.insert(context);
+                true
+            }
+            Entry::Vacant(entry) => {
+                // The task might already execute without the context
+                entry.insert(context);
+                true
+            }
+        }
+    }
+
+    /// Runs the future of an execution of the task with the context of its
+    /// latest call.
+    pub fn scope<F: Future>(&self, task: TaskId, future: F) -> impl Future<Output = F::Output> {
+        let context = self
+            .contexts
+            .get(&task)
+            .map(|context| context.clone())
+            .unwrap_or_default();
+        CONTEXT.scope(context, future)
+    }
+
+    /// Forgets the context of a task that is no longer used.
+    pub fn task_released(&self, task: TaskId) {
+        self.contexts.remove(&task);
+    }
+}
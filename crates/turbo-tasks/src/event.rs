@@ -8,7 +8,7 @@ use std::{
 use std::{sync::Arc, task::ready, task::Poll, time::Duration};
 
 #[cfg(feature = "hanging_detection")]
-use tokio::time::{timeout, Timeout};
+use crate::platform::{timeout, Timeout};
 
 pub struct Event {
     #[cfg(feature = "hanging_detection")]
@@ -0,0 +1,196 @@
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+use indexmap::IndexMap;
+use nohash_hasher::{BuildNoHashHasher, IntMap};
+
+use crate::{platform::Instant, TaskId};
+
+/// Limits how many tasks start per tick and interleaves them across root
+/// tasks, so a root task with a large graph (e.g. a page compiling) doesn't
+/// starve other root tasks. Tasks above the limit wait for later ticks, where
+/// root tasks take turns by weight, see
+/// [crate::TurboTasks::set_root_task_weight].
+///
+/// A task belongs to the root task whose execution scheduled it, until the
+/// task has finished. Invalidated tasks are only throttled by the
+/// [crate::InvalidationBudget] when one is configured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FairScheduling {
+    /// The maximum number of tasks started per tick.
+    pub max_tasks_per_tick: usize,
+    /// The length of a tick.
+    pub tick: Duration,
+}
+
+impl Default for FairScheduling {
+    fn default() -> Self {
+        Self {
+            max_tasks_per_tick: 1000,
+            tick: Duration::from_millis(10),
+        }
+    }
+}
+
+pub(crate) enum Turn {
+    /// The task can be started right away.
+    Now,
+    /// The task is waiting for its turn. When `start_draining` is true, the
+    /// caller need to start draining the waiting tasks with
+    /// [FairSchedulingState::next_tick].
+    Waiting { start_draining: bool },
+}
+
+pub(crate) struct FairSchedulingState {
+    options: FairScheduling,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    tick_start: Option<Instant>,
+    started_in_tick: usize,
+    /// The root task of every scheduled task that hasn't finished yet, see
+    /// [FairSchedulingState::task_finished].
+    roots: IntMap<TaskId, TaskId>,
+    /// Weights set by the embedder, 1 when not set.
+    weights: IntMap<TaskId, usize>,
+    /// The waiting tasks by root task, in the order the root tasks take
+    /// turns.
+    waiting: IndexMap<TaskId, VecDeque<TaskId>, BuildNoHashHasher<TaskId>>,
+    /// The root task whose turn it is and how many of its tasks have been
+    /// started in this turn.
+    turn: usize,
+    started_in_turn: usize,
+    draining: bool,
+}
+
+impl Inner {
+    fn has_budget(&mut self, options: &FairScheduling) -> bool {
+        let now = Instant::now();
+        match self.tick_start {
+            Some(start) if now - start < options.tick => {}
+            _ => {
+                self.tick_start = Some(now);
+                self.started_in_tick = 0;
+            }
+        }
+        self.started_in_tick < options.max_tasks_per_tick
+    }
+
+    /// Takes the next waiting task, giving every root task as many tasks per
+    /// turn as its weight.
+    fn next_waiting(&mut self) -> Option<TaskId> {
+        if self.waiting.is_empty() {
+            return None;
+        }
+        if self.turn >= self.waiting.len() {
+            self.turn = 0;
+        }
+        let (root, queue) = self.waiting.get_index_mut(self.turn).unwrap();
+        let root = *root;
+        let task = queue.pop_front().unwrap();
+        self.started_in_turn += 1;
+        if queue.is_empty() {
+            // Keeps the order of the other root tasks, the next one moves into
+            // this turn
+            self.waiting.shift_remove_index(self.turn);
+            self.started_in_turn = 0;
+        } else if self.started_in_turn >= self.weights.get(&root).copied().unwrap_or(1) {
+            self.turn += 1;
+            self.started_in_turn = 0;
+        }
+        Some(task)
+    }
+}
+
+impl FairSchedulingState {
+    pub fn new(options: FairScheduling) -> Self {
+        Self {
+            options,
+            inner: Mutex::new(Inner {
+                tick_start: None,
+                started_in_tick: 0,
+                roots: IntMap::default(),
+                weights: IntMap::default(),
+                waiting: IndexMap::default(),
+                turn: 0,
+                started_in_turn: 0,
+                draining: false,
+            }),
+        }
+    }
+
+    pub fn tick(&self) -> Duration {
+        self.options.tick
+    }
+
+    pub fn set_weight(&self, root: TaskId, weight: usize) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.weights.insert(root, weight.max(1));
+    }
+
+    /// Counts the task against the budget of the current tick or lets it wait
+    /// for the turn of its root task. `parent` is the task that schedules it,
+    /// if any. Tasks also wait while older tasks are waiting, so no root task
+    /// can skip the line.
+    pub fn admit(&self, task: TaskId, parent: Option<TaskId>) -> Turn {
+        let mut inner = self.inner.lock().unwrap();
+        let root = match inner.roots.get(&task) {
+            Some(root) => *root,
+            None => {
+                let root = parent
+                    .and_then(|parent| inner.roots.get(&parent).copied())
+                    .unwrap_or(task);
+                inner.roots.insert(task, root);
+                root
+            }
+        };
+        if !inner.draining && inner.has_budget(&self.options) {
+            inner.started_in_tick += 1;
+            return Turn::Now;
+        }
+        inner.waiting.entry(root).or_default().push_back(task);
+        let start_draining = !inner.draining;
+        inner.draining = true;
+        Turn::Waiting { start_draining }
+    }
+
+    /// Forgets the root task of the task. The tasks it scheduled have been
+    /// assigned to the root task already.
+    pub fn task_finished(&self, task: TaskId) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.roots.remove(&task);
+    }
+
+    /// Takes the waiting tasks that fit in the budget of the current tick.
+    /// Returns `false` when no waiting tasks are left and draining has
+    /// stopped.
+    pub fn next_tick(&self) -> (Vec<TaskId>, bool) {
+        let mut inner = self.inner.lock().unwrap();
+        let mut tasks = Vec::new();
+        while inner.has_budget(&self.options) {
+            let Some(task) = inner.next_waiting() else {
+                break;
+            };
+            inner.started_in_tick += 1;
+            tasks.push(task);
+        }
+        let more = !inner.waiting.is_empty();
+        inner.draining = more;
+        (tasks, more)
+    }
+
+    /// The number of tasks waiting to be started, by root task.
+    pub fn waiting_counts(&self) -> Vec<(TaskId, usize)> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .waiting
+            .iter()
+            .map(|(root, queue)| (*root, queue.len()))
+            .collect()
+    }
+
+    /// The number of unfinished tasks with a known root task.
+    pub fn tracked_count(&self) -> usize {
+        self.inner.lock().unwrap().roots.len()
+    }
+}
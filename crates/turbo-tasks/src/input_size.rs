@@ -0,0 +1,117 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+use anyhow::{bail, Result};
+
+use crate::{task_input::TaskInput, util::FormatBytes};
+
+/// Limits for the estimated size of the arguments of a single function call.
+/// See [TaskInput::estimated_size] for how the size is computed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct InputSizeLimits {
+    /// Calls with larger inputs are reported as `tracing` warning.
+    pub warn: Option<usize>,
+    /// Calls with larger inputs fail with an error instead of creating a task.
+    pub error: Option<usize>,
+    /// The number of calls with the largest inputs that are kept for
+    /// [crate::TurboTasks::largest_task_inputs].
+    pub track_largest: usize,
+}
+
+impl InputSizeLimits {
+    fn is_enabled(&self) -> bool {
+        self.warn.is_some() || self.error.is_some() || self.track_largest > 0
+    }
+}
+
+/// A function call with large inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LargeTaskInput {
+    /// The estimated size of all inputs in bytes.
+    pub size: usize,
+    /// The name of the called function.
+    pub function: String,
+    /// The description of the task that made the call.
+    pub caller: String,
+}
+
+pub(crate) struct InputSizeCheck {
+    limits: InputSizeLimits,
+    /// Sorted from largest to smallest.
+    largest: Mutex<Vec<LargeTaskInput>>,
+    /// The smallest size in `largest` once it's full, to avoid locking for
+    /// inputs that won't be tracked anyway.
+    min_tracked_size: AtomicUsize,
+}
+
+impl InputSizeCheck {
+    pub fn new(limits: InputSizeLimits) -> Self {
+        Self {
+            limits,
+            largest: Mutex::new(Vec::new()),
+            min_tracked_size: AtomicUsize::new(0),
+        }
+    }
+
+    /// Checks the inputs of a call against the limits. The names are only
+    /// computed when needed.
+    pub fn check(
+        &self,
+        inputs: &[TaskInput],
+        function: impl Fn() -> String,
+        caller: impl Fn() -> String,
+    ) -> Result<()> {
+        if !self.limits.is_enabled() {
+            return Ok(());
+        }
+        let size = inputs.iter().map(|input| input.estimated_size()).sum();
+        if let Some(error) = self.limits.error {
+            if size > error {
+                bail!(
+                    "inputs of {} called from {} are {}, which exceeds the limit of {}",
+                    function(),
+                    caller(),
+                    FormatBytes(size),
+                    FormatBytes(error)
+                );
+            }
+        }
+        if let Some(warn) = self.limits.warn {
+            if size > warn {
+                tracing::warn!(
+                    "inputs of {} called from {} are {}, which exceeds {}",
+                    function(),
+                    caller(),
+                    FormatBytes(size),
+                    FormatBytes(warn)
+                );
+            }
+        }
+        if self.limits.track_largest > 0 && size > self.min_tracked_size.load(Ordering::Relaxed) {
+            let mut largest = self.largest.lock().unwrap();
+            let index = largest.partition_point(|input| input.size >= size);
+            if index < self.limits.track_largest {
+                largest.insert(
+                    index,
+                    LargeTaskInput {
+                        size,
+                        function: function(),
+                        caller: caller(),
+                    },
+                );
+                largest.truncate(self.limits.track_largest);
+                if largest.len() == self.limits.track_largest {
+                    self.min_tracked_size
+                        .store(largest.last().unwrap().size, Ordering::Relaxed);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn largest(&self) -> Vec<LargeTaskInput> {
+        self.largest.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,131 @@
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+use crate::{platform::Instant, TaskId};
+
+/// Limits how many invalidated tasks are re-executed per tick. Invalidated
+/// tasks above the limit are deferred to later ticks, so a change that
+/// invalidates a large part of the graph doesn't occupy all workers at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidationBudget {
+    /// The maximum number of invalidated tasks scheduled per tick.
+    pub max_tasks_per_tick: usize,
+    /// The length of a tick.
+    pub tick: Duration,
+}
+
+impl Default for InvalidationBudget {
+    fn default() -> Self {
+        Self {
+            max_tasks_per_tick: 1000,
+            tick: Duration::from_millis(10),
+        }
+    }
+}
+
+pub(crate) enum Admission {
+    /// The task can be scheduled right away.
+    Schedule,
+    /// The task has been deferred. When `start_draining` is true, the caller
+    /// need to start draining the deferred tasks with
+    /// [InvalidationBudgetState::next_tick].
+    Deferred { start_draining: bool },
+}
+
+pub(crate) struct InvalidationBudgetState {
+    budget: InvalidationBudget,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    tick_start: Option<Instant>,
+    scheduled_in_tick: usize,
+    /// Deferred tasks that feed strongly consistent reads. They are scheduled
+    /// before `deferred`.
+    deferred_priority: VecDeque<TaskId>,
+    deferred: VecDeque<TaskId>,
+    draining: bool,
+}
+
+impl Inner {
+    fn has_budget(&mut self, budget: &InvalidationBudget) -> bool {
+        let now = Instant::now();
+        match self.tick_start {
+            Some(start) if now - start < budget.tick => {}
+            _ => {
+                self.tick_start = Some(now);
+                self.scheduled_in_tick = 0;
+            }
+        }
+        self.scheduled_in_tick < budget.max_tasks_per_tick
+    }
+}
+
+impl InvalidationBudgetState {
+    pub fn new(budget: InvalidationBudget) -> Self {
+        Self {
+            budget,
+            inner: Mutex::new(Inner {
+                tick_start: None,
+                scheduled_in_tick: 0,
+                deferred_priority: VecDeque::new(),
+                deferred: VecDeque::new(),
+                draining: false,
+            }),
+        }
+    }
+
+    pub fn tick(&self) -> Duration {
+        self.budget.tick
+    }
+
+    /// Counts the task against the budget of the current tick or defers it.
+    /// Tasks are also deferred while older tasks are waiting, so they keep
+    /// their order.
+    pub fn admit(&self, task: TaskId, priority: bool) -> Admission {
+        let mut inner = self.inner.lock().unwrap();
+        let waiting = if priority {
+            !inner.deferred_priority.is_empty()
+        } else {
+            inner.draining
+        };
+        if !waiting && inner.has_budget(&self.budget) {
+            inner.scheduled_in_tick += 1;
+            return Admission::Schedule;
+        }
+        if priority {
+            inner.deferred_priority.push_back(task);
+        } else {
+            inner.deferred.push_back(task);
+        }
+        let start_draining = !inner.draining;
+        inner.draining = true;
+        Admission::Deferred { start_draining }
+    }
+
+    /// Takes the deferred tasks that fit in the budget of the current tick,
+    /// prioritized ones first. Returns `false` when no deferred tasks are left
+    /// and draining has stopped.
+    pub fn next_tick(&self) -> (Vec<TaskId>, bool) {
+        let mut inner = self.inner.lock().unwrap();
+        let mut tasks = Vec::new();
+        while inner.has_budget(&self.budget) {
+            let Some(task) = inner
+                .deferred_priority
+                .pop_front()
+                .or_else(|| inner.deferred.pop_front()) else {
+                break;
+            };
+            inner.scheduled_in_tick += 1;
+            tasks.push(task);
+        }
+        let more = !inner.deferred_priority.is_empty() || !inner.deferred.is_empty();
+        inner.draining = more;
+        (tasks, more)
+    }
+
+    /// The number of invalidated tasks waiting to be scheduled.
+    pub fn deferred_count(&self) -> usize {
+        let inner = self.inner.lock().unwrap();
+        inner.deferred_priority.len() + inner.deferred.len()
+    }
+}
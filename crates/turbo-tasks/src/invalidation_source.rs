@@ -0,0 +1,256 @@
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+    mem::take,
+    sync::{Arc, Mutex},
+};
+
+use futures::future::join_all;
+
+use crate::{
+    manager::{current_task, get_invalidator, turbo_tasks},
+    Invalidator, TaskId,
+};
+
+/// The external system behind an [InvalidationSource], e.g. a file watcher or
+/// a config poller.
+pub trait ResourceWatcher: Send + Sync + 'static {
+    type Key: Clone + Eq + Hash + Send + Sync + 'static;
+
+    /// Called when the first task registers for the resource. The watcher
+    /// should start watching it.
+    fn watch(&self, key: &Self::Key);
+
+    /// Called when no task is interested in the resource anymore. The watcher
+    /// should stop watching it.
+    fn unwatch(&self, key: &Self::Key);
+}
+
+/// Keeps the [Invalidator]s of tasks that depend on external resources, keyed
+/// by resource.
+///
+/// A registration lasts until the registering task has executed again
+/// without registering for the same resource, or until the task has been
+/// removed from all scopes, see [crate::TurboTasksApi::on_task_released]. Then
+/// the task is removed from the resource, and once no task is left the
+/// [ResourceWatcher] is told to stop watching it. A released task is
+/// invalidated, so it registers again when it's needed again.
+///
+/// [ResourceWatcher::watch] and [ResourceWatcher::unwatch] are called while
+/// the source is locked, so they must not call back into the source.
+pub struct InvalidationSource<W: ResourceWatcher> {
+    inner: Arc<Inner<W>>,
+}
+
+impl<W: ResourceWatcher> Clone for InvalidationSource<W> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+struct Inner<W: ResourceWatcher> {
+    watcher: W,
+    state: Mutex<State<W::Key>>,
+}
+
+struct State<K> {
+    /// The registered tasks per resource. The [Invalidator] is `None` when
+    /// the task has been invalidated but hasn't executed again yet.
+    resources: HashMap<K, HashMap<TaskId, Option<Invalidator>>>,
+    tasks: HashMap<TaskId, TaskResources<K>>,
+    /// Tasks that have a release hook registered.
+    release_hooks: HashSet<TaskId>,
+}
+
+struct TaskResources<K> {
+    /// Resources registered in the current or last execution of the task.
+    current: HashSet<K>,
+    /// Resources registered in the execution before. Those that are not
+    /// registered again are released when the current execution completes.
+    previous: HashSet<K>,
+    /// An execution hook is registered for the next execution of the task.
+    hook_pending: bool,
+}
+
+impl<W: ResourceWatcher> InvalidationSource<W> {
+    pub fn new(watcher: W) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                watcher,
+                state: Mutex::new(State {
+                    resources: HashMap::new(),
+                    tasks: HashMap::new(),
+                    release_hooks: HashSet::new(),
+                }),
+            }),
+        }
+    }
+
+    pub fn watcher(&self) -> &W {
+        &self.inner.watcher
+    }
+
+    /// Registers the current task for the resource, so it's invalidated by
+    /// [InvalidationSource::invalidate].
+    pub fn register(&self, key: W::Key) {
+        let task = current_task("InvalidationSource::register()");
+        let invalidator = get_invalidator();
+        let mut state = self.inner.state.lock().unwrap();
+        let resources = state.tasks.entry(task).or_insert_with(|| TaskResources {
+            current: HashSet::new(),
+            previous: HashSet::new(),
+            hook_pending: false,
+        });
+        resources.previous.remove(&key);
+        resources.current.insert(key.clone());
+        let register_hook = !resources.hook_pending;
+        resources.hook_pending = true;
+        let register_release_hook = state.release_hooks.insert(task);
+        let tasks = state.resources.entry(key).or_insert_with_key(|key| {
+            self.inner.watcher.watch(key);
+            HashMap::new()
+        });
+        tasks.insert(task, Some(invalidator));
+        drop(state);
+        if register_release_hook {
+            let inner = self.inner.clone();
+            turbo_tasks().on_task_released(task, Box::new(move || inner.task_released(task)));
+        }
+        if register_hook {
+            let inner = self.inner.clone();
+            turbo_tasks().on_next_execution(
+                task,
+                Box::new(move || {
+                    inner.execution_started(task);
+                    Box::new(move || inner.execution_completed(task))
+                }),
+            );
+        }
+    }
+
+    /// Invalidates all tasks registered for the resource. The resource stays
+    /// watched until the tasks have executed again.
+    pub fn invalidate(&self, key: &W::Key) {
+        let invalidators: Vec<_> = {
+            let mut state = self.inner.state.lock().unwrap();
+            let Some(tasks) = state.resources.get_mut(key) else {
+                return;
+            };
+            tasks.values_mut().filter_map(Option::take).collect()
+        };
+        for invalidator in invalidators {
+            invalidator.invalidate();
+        }
+    }
+
+    /// Invalidates the tasks of all resources, e.g. when the external system
+    /// lost track of changes.
+    pub fn invalidate_all(&self) {
+        let invalidators: Vec<_> = {
+            let mut state = self.inner.state.lock().unwrap();
+            state
+                .resources
+                .values_mut()
+                .flat_map(|tasks| tasks.values_mut().filter_map(Option::take))
+                .collect()
+        };
+        for invalidator in invalidators {
+            invalidator.invalidate();
+        }
+    }
+
+    /// Like [InvalidationSource::invalidate], but waits until the tasks and
+    /// their active dependents have executed again. See
+    /// [Invalidator::invalidate_and_wait].
+    pub async fn invalidate_and_wait(&self, key: &W::Key) {
+        let invalidators: Vec<_> = {
+            let mut state = self.inner.state.lock().unwrap();
+            let Some(tasks) = state.resources.get_mut(key) else {
+                return;
+            };
+            tasks.values_mut().filter_map(Option::take).collect()
+        };
+        join_all(
+            invalidators
+                .into_iter()
+                .map(|invalidator| invalidator.invalidate_and_wait()),
+        )
+        .await;
+    }
+
+    /// Returns true when any task is registered for the resource.
+    pub fn is_watched(&self, key: &W::Key) -> bool {
+        self.inner.state.lock().unwrap().resources.contains_key(key)
+    }
+
+    /// The number of watched resources.
+    pub fn watched_count(&self) -> usize {
+        self.inner.state.lock().unwrap().resources.len()
+    }
+}
+
+impl<W: ResourceWatcher> Inner<W> {
+    fn execution_started(&self, task: TaskId) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(resources) = state.tasks.get_mut(&task) {
+            let current = take(&mut resources.current);
+            resources.previous.extend(current);
+            resources.hook_pending = false;
+        }
+    }
+
+    fn execution_completed(&self, task: TaskId) {
+        let mut state = self.state.lock().unwrap();
+        let Some(resources) = state.tasks.get_mut(&task) else {
+            return;
+        };
+        let released = take(&mut resources.previous);
+        if resources.current.is_empty() && !resources.hook_pending {
+            state.tasks.remove(&task);
+        }
+        for key in released {
+            self.release(&mut state, task, &key);
+        }
+    }
+
+    /// Releases all resources of the task and invalidates it, as it won't be
+    /// invalidated by changes of the resources anymore.
+    fn task_released(&self, task: TaskId) {
+        let mut invalidator = None;
+        {
+            let mut state = self.state.lock().unwrap();
+            state.release_hooks.remove(&task);
+            let Some(resources) = state.tasks.remove(&task) else {
+                return;
+            };
+            for key in resources.current.into_iter().chain(resources.previous) {
+                // All invalidators of the task invalidate the same task
+                invalidator = self.release(&mut state, task, &key).or(invalidator);
+            }
+        }
+        // The task is not part of any scope, so it's only marked as dirty
+        if let Some(invalidator) = invalidator {
+            invalidator.invalidate();
+        }
+    }
+
+    /// Removes the task from the resource, and stops watching the resource
+    /// when no task is left. Returns the invalidator of the task, unless the
+    /// task has been invalidated already.
+    fn release(
+        &self,
+        state: &mut State<W::Key>,
+        task: TaskId,
+        key: &W::Key,
+    ) -> Option<Invalidator> {
+        let tasks = state.resources.get_mut(key)?;
+        let invalidator = tasks.remove(&task).flatten();
+        if tasks.is_empty() {
+            state.resources.remove(key);
+            self.watcher.unwatch(key);
+        }
+        invalidator
+    }
+}
@@ -34,61 +34,98 @@
 #![feature(new_uninit)]
 
 pub mod backend;
+mod blocking_pool;
+mod busy_future;
+mod cell_conflicts;
 mod collectibles;
 mod completion;
+mod content_store;
+mod context;
 pub mod debug;
 mod display;
 pub mod event;
+mod fair_scheduling;
 mod id;
 mod id_factory;
+mod input_size;
+mod invalidation_budget;
+mod invalidation_source;
 mod join_iter_ext;
 mod magic_any;
 mod manager;
+mod memo_table;
 mod native_function;
 mod no_move_vec;
 mod nothing;
 mod once_map;
 pub mod persisted_graph;
+pub mod platform;
 pub mod primitives;
+mod projection;
 mod raw_vc;
+mod read_consistency;
 mod read_ref;
 pub mod registry;
+mod scheduler_snapshot;
 pub mod small_duration;
+mod state;
+mod task_barrier;
+mod task_error;
 mod task_input;
 mod timed_future;
 pub mod trace;
 pub mod util;
 mod value;
 mod value_type;
+mod watchdog;
 
 pub use anyhow::{Error, Result};
+pub use blocking_pool::BlockingPoolStats;
+pub use cell_conflicts::CellConflict;
 pub use collectibles::CollectiblesSource;
 pub use completion::{Completion, CompletionVc, CompletionsVc};
+pub use content_store::{ContentHash, ContentStore, MemoryContentStore};
+pub use context::{get_context, with_context};
 pub use display::{ValueToString, ValueToStringVc};
+pub use fair_scheduling::FairScheduling;
 pub use id::{
     with_task_id_mapping, without_task_id_mapping, FunctionId, IdMapping, TaskId, TraitTypeId,
     ValueTypeId,
 };
+pub use input_size::{InputSizeLimits, LargeTaskInput};
+pub use invalidation_budget::InvalidationBudget;
+pub use invalidation_source::{InvalidationSource, ResourceWatcher};
 pub use join_iter_ext::{JoinIterExt, TryJoinIterExt};
 pub use manager::{
-    dynamic_call, emit, get_invalidator, run_once, spawn_blocking, spawn_thread, trait_call,
-    turbo_tasks, Invalidator, StatsType, TaskIdProvider, TurboTasks, TurboTasksApi,
-    TurboTasksBackendApi, TurboTasksCallApi,
+    call_transient, dynamic_call, emit, flush_cell_updates, get_invalidator, join_all, mark_final,
+    memoize, run_once, set_task_label, spawn_blocking, spawn_thread, stage_cell_updates, tag_task,
+    trait_call, turbo_tasks, ExecutionHook, Invalidator, KeepAliveGuard, NotifyBatch, ReleaseHook,
+    RootTaskState, StatsType, TaskIdProvider, TurboTasks, TurboTasksApi, TurboTasksBackendApi,
+    TurboTasksBuilder, TurboTasksCallApi,
 };
-pub use native_function::{NativeFunction, NativeFunctionVc};
+pub use memo_table::{MemoTable, MemoTableStats};
+pub use native_function::{NativeFunction, NativeFunctionVc, ResolveMode};
 pub use nothing::{Nothing, NothingVc};
+pub use projection::Projection;
 pub use raw_vc::{CellId, CollectiblesFuture, RawVc, ReadRawVcFuture, ResolveTypeError};
+pub use read_consistency::{ConsistentRead, ConsistentReadFuture, ReadConsistency};
 pub use read_ref::ReadRef;
-pub use task_input::{FromTaskInput, SharedReference, SharedValue, TaskInput};
+pub use scheduler_snapshot::SchedulerSnapshot;
+pub use state::{State, StateRef};
+pub use task_barrier::TaskBarrier;
+pub use task_error::TaskError;
+pub use task_input::{FromTaskInput, InputString, SharedReference, SharedValue, TaskInput};
 pub use turbo_tasks_macros::{function, value, value_impl, value_trait};
 pub use value::{TransientInstance, TransientValue, Value};
 pub use value_type::{
     TraitMethod, TraitType, Typed, TypedForInput, ValueTraitVc, ValueType, ValueVc,
 };
+pub use watchdog::WatchdogOptions;
 
 #[doc(hidden)]
 pub mod macro_helpers {
     pub use once_cell::sync::{Lazy, OnceCell};
+    pub use tokio;
 
     pub use super::manager::find_cell_by_type;
 }
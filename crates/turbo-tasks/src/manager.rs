@@ -1,35 +1,63 @@
 use std::{
+    any::Any,
     borrow::Cow,
     cell::RefCell,
-    collections::{HashMap, HashSet},
-    future::Future,
-    hash::Hash,
+    collections::{hash_map::Entry, HashMap, HashSet},
+    future::{Future, IntoFuture},
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    mem::take,
     panic::AssertUnwindSafe,
     pin::Pin,
     sync::{
         atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc, Mutex, Weak,
     },
-    thread,
-    time::{Duration, Instant},
+    task::Poll,
+    time::Duration,
 };
 
-use anyhow::{anyhow, Result};
-use futures::FutureExt;
+use anyhow::{anyhow, Context, Result};
+use dashmap::DashMap;
+use futures::{
+    future::{self, BoxFuture, Shared},
+    FutureExt,
+};
+use indexmap::IndexSet;
 use nohash_hasher::BuildNoHashHasher;
+use pin_project_lite::pin_project;
 use serde::{de::Visitor, Deserialize, Serialize};
-use tokio::{runtime::Handle, select, task_local};
+use tokio::{
+    runtime::Handle,
+    select,
+    sync::{AcquireError, OwnedSemaphorePermit, Semaphore},
+};
+use tracing::{Instrument, Span};
 
 use crate::{
-    backend::{Backend, CellContent, PersistentTaskType, TransientTaskType},
+    backend::{Backend, CellContent, CellCounts, PersistentTaskType, TransientTaskType},
+    blocking_pool::{BlockingPool, BlockingPoolStats},
+    busy_future::BusyFuture,
+    cell_conflicts::{CellConflict, CellConflictCheck, CellOrder},
+    content_store::{ContentCache, ContentHash, ContentStore},
+    context::{current_context, TaskContexts},
     event::{Event, EventListener},
+    fair_scheduling::{FairScheduling, FairSchedulingState, Turn},
     id::{BackendJobId, FunctionId, TraitTypeId},
     id_factory::IdFactory,
+    input_size::{InputSizeCheck, InputSizeLimits, LargeTaskInput},
+    invalidation_budget::{Admission, InvalidationBudget, InvalidationBudgetState},
+    memo_table::MemoTable,
+    platform::{self, Executor, Instant, RuntimeOptions, TaskLocalFuture},
     raw_vc::{CellId, RawVc},
+    registry,
+    scheduler_snapshot::{QueuedTasks, SchedulerSnapshot},
     task_input::{SharedReference, TaskInput},
+    task_local,
     timed_future::{self, TimedFuture},
     trace::TraceRawVcs,
-    util::FormatDuration,
+    util::{FormatDuration, SharedError},
+    watchdog::{Watchdog, WatchdogOptions},
     Nothing, NothingVc, TaskId, ValueTraitVc, ValueTypeId,
 };
 
@@ -56,6 +84,11 @@ pub trait TurboTasksCallApi: Sync + Send {
 pub trait TurboTasksApi: TurboTasksCallApi + Sync + Send {
     fn invalidate(&self, task: TaskId);
 
+    /// Invalidates the task and returns a future that resolves once the
+    /// re-execution of the task and of its active dependents has finished.
+    /// See [Invalidator::invalidate_and_wait].
+    fn invalidate_and_wait(&self, task: TaskId) -> Pin<Box<dyn Future<Output = ()> + Send>>;
+
     /// Eagerly notifies all tasks that were scheduled for notifications via
     /// `schedule_notify_tasks_set()`
     fn notify_scheduled_tasks(&self);
@@ -66,6 +99,13 @@ pub trait TurboTasksApi: TurboTasksCallApi + Sync + Send {
         strongly_consistent: bool,
     ) -> Result<Result<RawVc, EventListener>>;
 
+    /// Reads the outputs of multiple tasks at once. See [join_all].
+    fn try_read_task_outputs(&self, tasks: &[TaskId]) -> Vec<Result<Result<RawVc, EventListener>>>;
+
+    /// Reads the output of the last execution of a task without waiting for
+    /// the current one. See [Backend::try_read_task_output_stale].
+    fn try_read_task_output_stale(&self, task: TaskId) -> Result<Result<RawVc, EventListener>>;
+
     /// INVALIDATION: Be careful with this, it will not track dependencies, so
     /// using it could break cache invalidation.
     fn try_read_task_output_untracked(
@@ -108,8 +148,43 @@ pub trait TurboTasksApi: TurboTasksCallApi + Sync + Send {
 
     fn read_current_task_cell(&self, index: CellId) -> Result<CellContent>;
     fn update_current_task_cell(&self, index: CellId, content: CellContent);
+
+    fn flush_current_task_cell_updates(&self);
+    fn mark_current_task_final(&self);
+    fn set_current_task_label(&self, label: String);
+    fn add_current_task_tag(&self, tag: String);
+
+    /// Calls `hook` when the task starts its next execution. The closure
+    /// returned by the hook is called once that execution has completed. The
+    /// hook is dropped without being called when the task is released before,
+    /// see [TurboTasksApi::on_task_released].
+    fn on_next_execution(&self, task: TaskId, hook: ExecutionHook);
+
+    /// Calls `hook` once the task has been removed from all scopes, i.e. no
+    /// task or keep alive guard needs it anymore. Backends that don't track
+    /// scopes never call it.
+    fn on_task_released(&self, task: TaskId, hook: ReleaseHook);
+
+    /// Schedules the task, and optionally the children of its last
+    /// execution, at background priority without making the current task
+    /// depend on it. See [RawVc::prefetch].
+    fn prefetch(&self, task: TaskId, with_children: bool);
+
+    /// Returns true when the output of the task already reflects all
+    /// invalidations up to the update generation, so reading it doesn't need
+    /// to be strongly consistent. See [RawVc::into_read_consistent_at_least].
+    fn is_task_consistent_at_least(&self, task: TaskId, generation: usize) -> bool;
+
+    /// Returns the table for [memoize], if the backend has one.
+    fn memo_table(&self) -> Option<&MemoTable>;
 }
 
+/// See [TurboTasksApi::on_next_execution].
+pub type ExecutionHook = Box<dyn FnOnce() -> Box<dyn FnOnce() + Send> + Send + Sync>;
+
+/// See [TurboTasksApi::on_task_released].
+pub type ReleaseHook = Box<dyn FnOnce() + Send + Sync>;
+
 /// The type of stats reporting.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StatsType {
@@ -144,8 +219,20 @@ pub trait TurboTasksBackendApi: TaskIdProvider + TurboTasksCallApi + Sync + Send
     fn pin(&self) -> Arc<dyn TurboTasksBackendApi>;
 
     fn schedule(&self, task: TaskId);
+    /// Schedules a task that has been invalidated. With an
+    /// [InvalidationBudget] the task might be deferred to a later tick.
+    /// `priority` tasks are preferred when deferred tasks are scheduled.
+    fn schedule_invalidated(&self, task: TaskId, priority: bool);
     fn schedule_backend_background_job(&self, id: BackendJobId);
     fn schedule_backend_foreground_job(&self, id: BackendJobId);
+    /// Called by the backend when the task has been removed from all scopes.
+    /// Runs the hooks of [TurboTasksApi::on_task_released]. Must not be
+    /// called while the task is locked.
+    fn task_released(&self, task: TaskId);
+    /// Returns an estimate of the number of worker threads that are not
+    /// executing tasks or foreground jobs at the moment. Useful to decide if
+    /// splitting off work into separate jobs is worth it.
+    fn idle_workers(&self) -> usize;
 
     fn try_foreground_done(&self) -> Result<(), EventListener>;
 
@@ -157,12 +244,22 @@ pub trait TurboTasksBackendApi: TaskIdProvider + TurboTasksCallApi + Sync + Send
     /// eventually call `invalidate_tasks()` on all tasks.
     fn schedule_notify_tasks_set(&self, tasks: &HashSet<TaskId>);
 
+    /// Merges the notifications scheduled outside of task executions on the
+    /// current thread until the returned [NotifyBatch] is dropped, which
+    /// notifies each task once. Notifications within task executions are
+    /// already merged until the execution finishes.
+    fn batch_notify_tasks(&self) -> NotifyBatch<'_>;
+
     /// Returns the stats reporting type.
     fn stats_type(&self) -> StatsType;
     /// Sets the stats reporting type.
     fn set_stats_type(&self, stats_type: StatsType);
     /// Returns the duration from the start of the program to the given instant.
     fn program_duration_until(&self, instant: Instant) -> Duration;
+    /// Returns the current update generation. The generation is increased
+    /// every time all scheduled work has been finished, so all executions
+    /// caused by a single change share the same generation.
+    fn update_generation(&self) -> usize;
 }
 
 impl StatsType {
@@ -201,6 +298,12 @@ pub struct TurboTasks<B: Backend + 'static> {
     this: Weak<Self>,
     backend: B,
     task_id_factory: IdFactory<TaskId>,
+    executor: Executor,
+    /// Limits the number of concurrently running background jobs.
+    background_job_permits: Option<Semaphore>,
+    /// Limits the number of concurrently executing tasks of functions with
+    /// [crate::NativeFunction::max_concurrency], by function.
+    function_permits: DashMap<FunctionId, Arc<Semaphore>>,
     stopped: AtomicBool,
     currently_scheduled_tasks: AtomicUsize,
     currently_scheduled_foreground_jobs: AtomicUsize,
@@ -208,6 +311,7 @@ pub struct TurboTasks<B: Backend + 'static> {
     scheduled_tasks: AtomicUsize,
     start: Mutex<Option<Instant>>,
     aggregated_update: Mutex<Option<(Duration, usize)>>,
+    update_generation: AtomicUsize,
     event: Event,
     event_foreground: Event,
     event_background: Event,
@@ -215,6 +319,90 @@ pub struct TurboTasks<B: Backend + 'static> {
     // locking overhead.
     enable_full_stats: AtomicBool,
     program_start: Instant,
+    /// Results of [TurboTasks::run_once_keyed] by key. The values are
+    /// [OnceKeyedFuture]s.
+    once_keyed: Mutex<HashMap<Box<dyn OnceKey>, Box<dyn Any + Send + Sync>>>,
+    input_size: InputSizeCheck,
+    invalidation_budget: Option<InvalidationBudgetState>,
+    /// The number of task executions and foreground jobs that are being polled
+    /// at the moment.
+    busy_workers: Arc<AtomicUsize>,
+    /// Hooks for the next execution of tasks, see
+    /// [TurboTasksApi::on_next_execution].
+    execution_hooks: DashMap<TaskId, Vec<ExecutionHook>>,
+    /// See [TurboTasksApi::on_task_released].
+    release_hooks: DashMap<TaskId, Vec<ReleaseHook>>,
+    /// Executes the tasks of blocking functions.
+    blocking_pool: Arc<BlockingPool>,
+    /// Only used in debug builds.
+    cell_conflicts: CellConflictCheck,
+    /// See [crate::with_context].
+    task_contexts: TaskContexts,
+    /// The number of prefetches that are waiting to be run.
+    pending_prefetches: AtomicUsize,
+    watchdog: Option<Watchdog>,
+    content_cache: Option<ContentCache>,
+    fair_scheduling: Option<FairSchedulingState>,
+    /// The tasks created with [TurboTasks::spawn_root_task], in creation
+    /// order.
+    root_tasks: Mutex<Vec<TaskId>>,
+    /// Tasks that are scheduled but haven't started executing, see
+    /// [TurboTasksBuilder::track_queued_tasks].
+    queued_tasks: Option<QueuedTasks>,
+}
+
+/// The state of a root task that has not settled yet, see
+/// [TurboTasks::pending_roots].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootTaskState {
+    /// The output of the root task is not available yet.
+    Executing,
+    /// The output of the root task is available, but tasks in its graph are
+    /// still executing, so a strongly consistent read would wait.
+    Settling,
+}
+
+/// Prefetches are dropped when more than this number of them are waiting,
+/// as they are only a hint.
+const MAX_PENDING_PREFETCHES: usize = 1024;
+
+type OnceKeyedFuture<T> = Shared<BoxFuture<'static, Result<T, SharedError>>>;
+
+/// An object safe version of `Hash + Eq` to allow keys of different types in
+/// the same map.
+pub(crate) trait OnceKey: Any + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn dyn_eq(&self, other: &dyn OnceKey) -> bool;
+    fn dyn_hash(&self, state: &mut dyn Hasher);
+}
+
+impl<K: Hash + Eq + Send + Sync + 'static> OnceKey for K {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn dyn_eq(&self, other: &dyn OnceKey) -> bool {
+        other.as_any().downcast_ref::<K>() == Some(self)
+    }
+
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        self.type_id().hash(&mut state);
+        self.hash(&mut state);
+    }
+}
+
+impl PartialEq for dyn OnceKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.dyn_eq(other)
+    }
+}
+
+impl Eq for dyn OnceKey {}
+
+impl Hash for dyn OnceKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.dyn_hash(state)
+    }
 }
 
 // TODO implement our own thread pool and make these thread locals instead
@@ -222,29 +410,191 @@ task_local! {
     /// The current TurboTasks instance
     static TURBO_TASKS: Arc<dyn TurboTasksApi>;
 
-    static CELL_COUNTERS: RefCell<HashMap<ValueTypeId, u32, BuildNoHashHasher<ValueTypeId>>>;
+    static CELL_COUNTERS: RefCell<CellCounts>;
+
+    /// The types of the cells of the current execution in the order they were
+    /// created. Only recorded in debug builds, see [CellConflict].
+    static CELL_ORDER: RefCell<CellOrder>;
 
     static CURRENT_TASK_ID: TaskId;
 
+    /// Cell updates of the current execution that are held back until the
+    /// execution has finished. `None` unless [stage_cell_updates] was called.
+    static STAGED_CELL_UPDATES: RefCell<Option<HashMap<CellId, CellContent>>>;
+
     /// Affected [Task]s, that are tracked during task execution
     /// These tasks will be invalidated when the execution finishes
     /// or before reading a cell value
     static TASKS_TO_NOTIFY: RefCell<Vec<TaskId>>;
 }
 
+thread_local! {
+    /// The tasks to notify when the outermost [NotifyBatch] of the thread is
+    /// dropped. `None` when no batch is active.
+    static NOTIFY_BATCH: RefCell<Option<IndexSet<TaskId, BuildNoHashHasher<TaskId>>>> =
+        RefCell::new(None);
+}
+
+/// Adds the tasks to the active [NotifyBatch] of the thread. Returns `false`
+/// when there is none.
+fn add_to_notify_batch(tasks: impl IntoIterator<Item = TaskId>) -> bool {
+    NOTIFY_BATCH.with(|batch| match &mut *batch.borrow_mut() {
+        Some(batch) => {
+            batch.extend(tasks);
+            true
+        }
+        None => false,
+    })
+}
+
+task_local! {
+    /// Set when the execution waits for a read of another task, see
+    /// [WithPermit].
+    static WAITING_FOR_READ: Arc<AtomicBool>;
+}
+
+/// Marks the current execution as waiting for a read of another task, so it
+/// gives up the permit of its function meanwhile.
+pub(crate) fn mark_waiting_for_read() {
+    let _ = WAITING_FOR_READ.try_with(|waiting| waiting.store(true, Ordering::Relaxed));
+}
+
+/// Waits for the listener of a read of another task, see
+/// [mark_waiting_for_read].
+async fn wait_for_read(listener: EventListener) {
+    let mut listener = Box::pin(listener);
+    future::poll_fn(|cx| {
+        let result = listener.as_mut().poll(cx);
+        if result.is_pending() {
+            mark_waiting_for_read();
+        }
+        result
+    })
+    .await
+}
+
+type AcquirePermit =
+    Pin<Box<dyn Future<Output = Result<OwnedSemaphorePermit, AcquireError>> + Send>>;
+
+pin_project! {
+    /// Runs the future while holding a permit of a function with
+    /// [crate::NativeFunction::max_concurrency]. The permit is released while
+    /// the future waits for reads of other tasks and acquired again before it
+    /// continues. Otherwise executions that wait for other executions of
+    /// limited functions, e.g. recursive calls, would deadlock once the limit
+    /// is reached.
+    struct WithPermit<F> {
+        permits: Arc<Semaphore>,
+        permit: Option<OwnedSemaphorePermit>,
+        acquire: Option<AcquirePermit>,
+        waiting_for_read: Arc<AtomicBool>,
+        #[pin]
+        future: TaskLocalFuture<Arc<AtomicBool>, F>,
+    }
+}
+
+impl<F: Future> Future for WithPermit<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<F::Output> {
+        let this = self.project();
+        if this.permit.is_none() {
+            let acquire = this
+                .acquire
+                .get_or_insert_with(|| Box::pin(this.permits.clone().acquire_owned()));
+            match acquire.as_mut().poll(cx) {
+                Poll::Ready(permit) => {
+                    *this.permit = Some(permit.unwrap());
+                    *this.acquire = None;
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        let result = this.future.poll(cx);
+        if result.is_pending() && this.waiting_for_read.swap(false, Ordering::Relaxed) {
+            *this.permit = None;
+        }
+        result
+    }
+}
+
+/// Runs the future once a permit is acquired, when there are permits. See
+/// [WithPermit].
+async fn with_permit<F: Future>(permits: Option<Arc<Semaphore>>, future: F) -> F::Output {
+    let Some(permits) = permits else {
+        return future.await;
+    };
+    let waiting_for_read = Arc::new(AtomicBool::new(false));
+    WithPermit {
+        permits,
+        permit: None,
+        acquire: None,
+        waiting_for_read: waiting_for_read.clone(),
+        future: WAITING_FOR_READ.scope(waiting_for_read, future),
+    }
+    .await
+}
+
 impl<B: Backend> TurboTasks<B> {
     // TODO better lifetime management for turbo tasks
     // consider using unsafe for the task_local turbo tasks
     // that should be safe as long tasks can't outlife turbo task
     // so we probably want to make sure that all tasks are joined
     // when trying to drop turbo tasks
-    pub fn new(mut backend: B) -> Arc<Self> {
+    /// Creates an instance on the ambient tokio runtime. Panics when the
+    /// startup of the backend fails, use [TurboTasks::builder] to handle the
+    /// error instead.
+    pub fn new(backend: B) -> Arc<Self> {
+        Self::new_with(
+            backend,
+            Executor::ambient(),
+            None,
+            InputSizeLimits::default(),
+            None,
+            None,
+            None,
+            None,
+            false,
+        )
+        .expect("startup of the turbo-tasks backend failed")
+    }
+
+    /// Creates a [TurboTasksBuilder] to configure the executor that runs the
+    /// tasks, instead of using the ambient tokio runtime.
+    pub fn builder(backend: B) -> TurboTasksBuilder<B> {
+        TurboTasksBuilder {
+            backend,
+            runtime_options: Default::default(),
+            max_background_jobs: None,
+            input_size_limits: Default::default(),
+            invalidation_budget: None,
+            watchdog: None,
+            content_store: None,
+            fair_scheduling: None,
+            track_queued_tasks: false,
+        }
+    }
+
+    fn new_with(
+        mut backend: B,
+        executor: Executor,
+        max_background_jobs: Option<usize>,
+        input_size_limits: InputSizeLimits,
+        invalidation_budget: Option<InvalidationBudget>,
+        watchdog: Option<WatchdogOptions>,
+        content_store: Option<(Arc<dyn ContentStore>, usize)>,
+        fair_scheduling: Option<FairScheduling>,
+        track_queued_tasks: bool,
+    ) -> Result<Arc<Self>> {
         let task_id_factory = IdFactory::new();
         backend.initialize(&task_id_factory);
         let this = Arc::new_cyclic(|this| Self {
             this: this.clone(),
             backend,
             task_id_factory,
+            executor,
+            background_job_permits: max_background_jobs.map(Semaphore::new),
+            function_permits: DashMap::new(),
             stopped: AtomicBool::new(false),
             currently_scheduled_tasks: AtomicUsize::new(0),
             currently_scheduled_background_jobs: AtomicUsize::new(0),
@@ -252,14 +602,109 @@ impl<B: Backend> TurboTasks<B> {
             scheduled_tasks: AtomicUsize::new(0),
             start: Default::default(),
             aggregated_update: Default::default(),
+            update_generation: AtomicUsize::new(0),
             event: Event::new(|| "TurboTasks::event".to_string()),
             event_foreground: Event::new(|| "TurboTasks::event_foreground".to_string()),
             event_background: Event::new(|| "TurboTasks::event_background".to_string()),
             enable_full_stats: AtomicBool::new(false),
             program_start: Instant::now(),
+            once_keyed: Default::default(),
+            input_size: InputSizeCheck::new(input_size_limits),
+            invalidation_budget: invalidation_budget.map(InvalidationBudgetState::new),
+            busy_workers: Arc::new(AtomicUsize::new(0)),
+            execution_hooks: DashMap::new(),
+            release_hooks: DashMap::new(),
+            blocking_pool: Arc::new(BlockingPool::default()),
+            cell_conflicts: CellConflictCheck::default(),
+            task_contexts: TaskContexts::default(),
+            pending_prefetches: AtomicUsize::new(0),
+            watchdog: watchdog.map(Watchdog::new),
+            content_cache: content_store
+                .map(|(store, min_size)| ContentCache::new(store, min_size)),
+            fair_scheduling: fair_scheduling.map(FairSchedulingState::new),
+            root_tasks: Default::default(),
+            queued_tasks: track_queued_tasks.then(QueuedTasks::default),
         });
-        this.backend.startup(&*this);
-        this
+        this.backend.startup(&*this)?;
+        #[cfg(not(target_arch = "wasm32"))]
+        if this.watchdog.is_some() {
+            this.start_watchdog();
+        }
+        Ok(this)
+    }
+
+    /// Checks for stalls on a dedicated thread, as the workers might be
+    /// blocked. The thread ends when the instance is stopped or dropped.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start_watchdog(&self) {
+        let this = self.this.clone();
+        let stall_timeout = self.watchdog.as_ref().unwrap().options().stall_timeout;
+        let interval = (stall_timeout / 4).max(Duration::from_millis(1));
+        std::thread::Builder::new()
+            .name("turbo-tasks-watchdog".to_string())
+            .spawn(move || {
+                let mut last_progress = None;
+                let mut last_change = Instant::now();
+                let mut reported = false;
+                loop {
+                    std::thread::sleep(interval);
+                    let Some(this) = this.upgrade() else {
+                        return;
+                    };
+                    if this.stopped.load(Ordering::Acquire) {
+                        return;
+                    }
+                    let watchdog = this.watchdog.as_ref().unwrap();
+                    let progress = watchdog.progress();
+                    let idle = this.currently_scheduled_tasks.load(Ordering::Acquire) == 0;
+                    if idle || last_progress != Some(progress) {
+                        last_progress = Some(progress);
+                        last_change = Instant::now();
+                        reported = false;
+                    } else if !reported && last_change.elapsed() >= stall_timeout {
+                        reported = true;
+                        this.report_stall(watchdog, last_change.elapsed());
+                    }
+                    // New work goes to the added worker until work made progress and the
+                    // executions that blocked the workers have finished
+                    if !reported && this.executor.is_stalled() && !watchdog.is_blocked() {
+                        this.executor.stall_ended();
+                    }
+                }
+            })
+            .unwrap();
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn report_stall(&self, watchdog: &Watchdog, duration: Duration) {
+        let tasks = watchdog
+            .stall_detected()
+            .into_iter()
+            .map(|(task, duration)| {
+                format!(
+                    "\n  {} executing for {}",
+                    self.backend.get_task_description(task),
+                    FormatDuration(duration)
+                )
+            })
+            .collect::<String>();
+        tracing::warn!(
+            "no task execution or foreground job made progress for {}, executing tasks:{tasks}",
+            FormatDuration(duration)
+        );
+        if watchdog.options().add_worker {
+            if let Err(err) = self.executor.add_worker() {
+                tracing::error!("failed to add a worker thread: {err}");
+            }
+        }
+    }
+
+    /// Returns the number of stalls detected by the watchdog, see
+    /// [TurboTasksBuilder::watchdog].
+    pub fn stall_count(&self) -> usize {
+        self.watchdog
+            .as_ref()
+            .map_or(0, |watchdog| watchdog.stalls())
     }
 
     pub fn pin(&self) -> Arc<Self> {
@@ -277,10 +722,53 @@ impl<B: Backend> TurboTasks<B> {
         let id = self
             .backend
             .create_transient_task(TransientTaskType::Root(Box::new(functor)), self);
+        self.root_tasks.lock().unwrap().push(id);
         self.schedule(id);
         id
     }
 
+    /// Replaces the function of a root task created with
+    /// [TurboTasks::spawn_root_task] and re-executes it. The scope of the root
+    /// task and its children are kept, so e.g. a dev server can change the
+    /// entry computation without recomputing what both functions share.
+    pub fn replace_root(
+        &self,
+        task: TaskId,
+        functor: impl Fn() -> Pin<Box<dyn Future<Output = Result<RawVc>> + Send>>
+            + Sync
+            + Send
+            + 'static,
+    ) -> Result<()> {
+        self.backend
+            .replace_root_task(task, Box::new(functor), self)
+    }
+
+    /// Returns the root tasks created with [TurboTasks::spawn_root_task] that
+    /// have not settled yet, in creation order. Root tasks that settled with
+    /// an error are not pending.
+    pub fn pending_roots(&self) -> Vec<(TaskId, RootTaskState)> {
+        let roots = self.root_tasks.lock().unwrap().clone();
+        roots
+            .into_iter()
+            .filter_map(|id| Some((id, self.backend.pending_root_state(id, self)?)))
+            .collect()
+    }
+
+    /// Waits until no root task is pending, including root tasks created or
+    /// invalidated while waiting. Errors of root tasks are left to their
+    /// readers.
+    pub async fn await_all_roots(&self) {
+        loop {
+            let pending = self.pending_roots();
+            if pending.is_empty() {
+                return;
+            }
+            for (id, _) in pending {
+                let _ = self.wait_task_completion(id, true).await;
+            }
+        }
+    }
+
     // TODO make sure that all dependencies settle before reading them
     /// Creates a new root task, that is only executed once.
     /// Dependencies will not invalidate the task.
@@ -314,27 +802,134 @@ impl<B: Backend> TurboTasks<B> {
         Ok(rx.await?)
     }
 
+    /// Like [TurboTasks::run_once], but blocks the current thread until the
+    /// result is available, for embedders that don't manage an async runtime.
+    /// When the instance has no dedicated runtime, a temporary one is started.
+    /// Afterwards the instance is stopped with [TurboTasks::stop_and_wait], so
+    /// it can't be used for further evaluations.
+    ///
+    /// Must not be called from within an async context.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn run_once_blocking<T: TraceRawVcs + Send + 'static>(
+        &self,
+        future: impl Future<Output = Result<T>> + Send + 'static,
+    ) -> Result<T> {
+        self.executor.block_on(async {
+            let result = self.run_once(future).await;
+            self.stop_and_wait().await;
+            result
+        })?
+    }
+
+    /// Like [TurboTasks::run_once], but the result is cached under the given
+    /// key. Concurrent and later calls with the same key don't execute their
+    /// future and receive the cached result instead. Failed executions are not
+    /// cached, so a later call will retry.
+    pub async fn run_once_keyed<K, T>(
+        &self,
+        key: K,
+        future: impl Future<Output = Result<T>> + Send + 'static,
+    ) -> Result<T>
+    where
+        K: Hash + Eq + Clone + Send + Sync + 'static,
+        T: TraceRawVcs + Clone + Send + Sync + 'static,
+    {
+        let shared = match self.once_keyed.lock().unwrap().entry(Box::new(key.clone())) {
+            Entry::Occupied(e) => e
+                .get()
+                .downcast_ref::<OnceKeyedFuture<T>>()
+                .context("run_once_keyed was called with different result types for the same key")?
+                .clone(),
+            Entry::Vacant(e) => {
+                let this = self.pin();
+                let shared: OnceKeyedFuture<T> =
+                    async move { this.run_once(future).await.map_err(SharedError::new) }
+                        .boxed()
+                        .shared();
+                e.insert(Box::new(shared.clone()));
+                shared
+            }
+        };
+        match shared.await {
+            Ok(result) => Ok(result),
+            Err(err) => {
+                let mut once_keyed = self.once_keyed.lock().unwrap();
+                let key: Box<dyn OnceKey> = Box::new(key);
+                // Only remove the entry when it's still a failed one, another caller might
+                // already have started a retry.
+                let has_failed = once_keyed
+                    .get(&key)
+                    .and_then(|f| f.downcast_ref::<OnceKeyedFuture<T>>())
+                    .map_or(false, |f| matches!(f.peek(), Some(Err(_))));
+                if has_failed {
+                    once_keyed.remove(&key);
+                }
+                Err(err.into())
+            }
+        }
+    }
+
+    /// Keeps the task of the Vc and all tasks called by it active while the
+    /// returned guard is held. They stay in scope and are recomputed eagerly
+    /// when invalidated, even when nobody reads them.
+    pub fn keep_alive(&self, vc: RawVc) -> KeepAliveGuard {
+        let task = vc.get_task_id();
+        self.backend.keep_task_alive(task, self);
+        let this = self.this.clone();
+        KeepAliveGuard {
+            handle: self.executor.handle(),
+            release: Some(Box::new(move || {
+                if let Some(this) = this.upgrade() {
+                    this.backend.release_task_keep_alive(task, &*this);
+                }
+            })),
+        }
+    }
+
     /// Call a native function with arguments.
-    /// All inputs must be resolved.
+    /// All inputs must be resolved as far as the [crate::ResolveMode] of the
+    /// function requires.
     pub(crate) fn native_call(&self, func: FunctionId, inputs: Vec<TaskInput>) -> RawVc {
-        RawVc::TaskOutput(self.backend.get_or_create_persistent_task(
-            PersistentTaskType::Native(func, inputs),
-            current_task("turbo_function calls"),
-            self,
-        ))
+        registry::get_function(func)
+            .cached_call_count
+            .fetch_add(1, Ordering::Relaxed);
+        self.persistent_call(PersistentTaskType::Native(func, inputs))
+    }
+
+    /// Returns the output of the task of the call, creating the task when
+    /// needed, unless the backend rejects the creation.
+    fn persistent_call(&self, task_type: PersistentTaskType) -> RawVc {
+        let parent_task = current_task("turbo_function calls");
+        if let Err(err) = self
+            .backend
+            .check_task_creation(&task_type, parent_task, self)
+        {
+            return self.failed_call(err);
+        }
+        let task = self
+            .backend
+            .get_or_create_persistent_task(task_type, parent_task, self);
+        if self.task_contexts.called(task, current_context()) {
+            self.backend.invalidate_task(task, self);
+        }
+        RawVc::TaskOutput(task)
     }
 
     /// Calls a native function with arguments. Resolves arguments when needed
-    /// with a wrapper [Task].
+    /// with a wrapper [Task], see [crate::ResolveMode].
     pub fn dynamic_call(&self, func: FunctionId, inputs: Vec<TaskInput>) -> RawVc {
-        if inputs.iter().all(|i| i.is_resolved() && !i.is_nothing()) {
+        if let Err(err) =
+            self.check_input_size(&inputs, || registry::get_function(func).name.clone())
+        {
+            return self.failed_call(err);
+        }
+        if registry::get_function(func)
+            .resolve_mode
+            .is_resolved(&inputs)
+        {
             self.native_call(func, inputs)
         } else {
-            RawVc::TaskOutput(self.backend.get_or_create_persistent_task(
-                PersistentTaskType::ResolveNative(func, inputs),
-                current_task("turbo_function calls"),
-                self,
-            ))
+            self.persistent_call(PersistentTaskType::ResolveNative(func, inputs))
         }
     }
 
@@ -346,37 +941,263 @@ impl<B: Backend> TurboTasks<B> {
         trait_fn_name: Cow<'static, str>,
         inputs: Vec<TaskInput>,
     ) -> RawVc {
-        RawVc::TaskOutput(self.backend.get_or_create_persistent_task(
-            PersistentTaskType::ResolveTrait(trait_type, trait_fn_name, inputs),
-            current_task("turbo_function calls"),
-            self,
+        if let Err(err) = self.check_input_size(&inputs, || {
+            format!("{}::{trait_fn_name}", registry::get_trait(trait_type).name)
+        }) {
+            return self.failed_call(err);
+        }
+        self.persistent_call(PersistentTaskType::ResolveTrait(
+            trait_type,
+            trait_fn_name,
+            inputs,
         ))
     }
 
+    fn check_input_size(&self, inputs: &[TaskInput], function: impl Fn() -> String) -> Result<()> {
+        self.input_size.check(inputs, function, || {
+            match CURRENT_TASK_ID.try_with(|id| *id) {
+                Ok(id) => self.backend.get_task_description(id),
+                Err(_) => "outside of a task".to_string(),
+            }
+        })
+    }
+
+    /// Returns the output of a task that fails with the error. Used for calls
+    /// that are rejected before a task is created.
+    fn failed_call(&self, err: anyhow::Error) -> RawVc {
+        RawVc::TaskOutput(self.spawn_once_task(async move { Err(err) }))
+    }
+
+    /// Runs `register`, e.g. the registration of a dynamically loaded plugin,
+    /// and invalidates all tasks of functions whose implementation has been
+    /// replaced by it. New functions, value types and trait implementations
+    /// can be registered at any time without that.
+    pub fn reload_functions(&self, register: impl FnOnce()) {
+        let start = registry::function_replacements_count();
+        register();
+        for function in registry::replaced_functions_since(start) {
+            self.invalidate_function(function);
+        }
+    }
+
+    /// Invalidates all cached tasks of the function, e.g. when the embedder
+    /// knows that a whole category of computations is stale after a config
+    /// change. Active tasks are executed again right away, the other ones
+    /// when they are read.
+    pub fn invalidate_function(&self, function: FunctionId) {
+        self.backend.invalidate_function_tasks(function, self);
+    }
+
+    /// Tags the task that computed the Vc, e.g. right after calling the
+    /// function, see [tag_task]. The Vc is resolved first, so the task that
+    /// created the cell is tagged instead of a task that resolves the inputs
+    /// of the call.
+    pub async fn tag_vc(&self, vc: RawVc, tag: impl Into<String>) -> Result<()> {
+        let task = self
+            .run_once(async move { Ok(vc.resolve().await?.get_task_id()) })
+            .await?;
+        self.backend.add_task_tag(task, tag.into(), self);
+        Ok(())
+    }
+
+    /// Returns the tasks that have been tagged with the tag.
+    pub fn tasks_with_tag(&self, tag: &str) -> Vec<TaskId> {
+        self.backend.tasks_with_tag(tag)
+    }
+
+    /// Invalidates all tasks that have been tagged with the tag. Active tasks
+    /// are executed again right away, the other ones when they are read.
+    pub fn invalidate_tag(&self, tag: &str) {
+        let tasks = self.backend.tasks_with_tag(tag);
+        self.backend.invalidate_tasks(tasks, self);
+    }
+
+    /// Returns the calls with the largest inputs so far, largest first. Only
+    /// records calls when [TurboTasksBuilder::input_size_limits] has been
+    /// configured to track them.
+    pub fn largest_task_inputs(&self) -> Vec<LargeTaskInput> {
+        self.input_size.largest()
+    }
+
+    /// Returns the tasks that created a different number of cells of a type
+    /// than in their previous execution. Only detected in debug builds.
+    pub fn cell_conflicts(&self) -> Vec<CellConflict> {
+        self.cell_conflicts.conflicts()
+    }
+
+    /// Returns the hash under which the content of the cell has been pushed
+    /// to the [ContentStore] configured with
+    /// [TurboTasksBuilder::content_store]. Returns `None` for outputs and
+    /// cells that have not been pushed, e.g. because they are too small or
+    /// not serializable. Cells are pushed by a background job, see
+    /// [TurboTasks::wait_background_done].
+    pub fn content_hash(&self, vc: RawVc) -> Option<ContentHash> {
+        match (vc, &self.content_cache) {
+            (RawVc::TaskCell(task, index), Some(content_cache)) => content_cache.hash(task, index),
+            _ => None,
+        }
+    }
+
+    /// Fetches a value from the configured [ContentStore] by its hash, e.g. a
+    /// value that has been computed on another machine. Fails when the value
+    /// has a different type or can't be deserialized.
+    pub fn fetch_content<T: Any + Send + Sync>(
+        &self,
+        hash: &ContentHash,
+    ) -> Result<Option<Arc<T>>> {
+        match &self.content_cache {
+            Some(content_cache) => content_cache.fetch_typed(hash),
+            None => Ok(None),
+        }
+    }
+
+    /// Records the update of a cell for the [ContentStore]. The content is
+    /// serialized and pushed by a background job on a blocking thread, as the
+    /// store might block.
+    fn content_cell_updated(&self, task: TaskId, index: CellId, content: &CellContent) {
+        let Some(content_cache) = &self.content_cache else {
+            return;
+        };
+        if !content_cache.cell_updated(task, index, content) {
+            return;
+        }
+        self.schedule_background_job(|this| async move {
+            let (pushed, wait) = futures::channel::oneshot::channel();
+            let handle = this.executor.handle();
+            platform::spawn_blocking_future(handle, async move {
+                if let Some(content_cache) = &this.content_cache {
+                    content_cache.push_pending();
+                }
+                let _ = pushed.send(());
+            });
+            let _ = wait.await;
+        });
+    }
+
     #[track_caller]
     pub(crate) fn schedule(&self, task_id: TaskId) {
+        let Some(fair_scheduling) = &self.fair_scheduling else {
+            self.start_task(task_id);
+            return;
+        };
+        let parent = CURRENT_TASK_ID.try_with(|id| *id).ok();
+        match fair_scheduling.admit(task_id, parent) {
+            Turn::Now => self.start_task(task_id),
+            Turn::Waiting { start_draining } => {
+                if start_draining {
+                    self.drain_ticks(fair_scheduling.tick(), |this| {
+                        this.fair_scheduling.as_ref().unwrap().next_tick()
+                    });
+                }
+            }
+        }
+    }
+
+    /// Starts the tasks that fit in the budget of each tick, taken with
+    /// `next_tick`, tick by tick until none are left. Counts as primary job,
+    /// so the waiting tasks are considered in progress.
+    fn drain_ticks(&self, tick: Duration, next_tick: fn(&Self) -> (Vec<TaskId>, bool)) {
+        self.begin_primary_job();
+        let this = self.pin();
+        self.executor.handle().spawn(async move {
+            loop {
+                platform::sleep(tick).await;
+                if this.stopped.load(Ordering::Acquire) {
+                    break;
+                }
+                let (tasks, more) = next_tick(&this);
+                for task in tasks {
+                    this.start_task(task);
+                }
+                if !more {
+                    break;
+                }
+            }
+            this.finish_primary_job();
+        });
+    }
+
+    /// Sets how many tasks of the root task start per turn with
+    /// [FairScheduling], relative to other root tasks. The default weight is
+    /// 1.
+    pub fn set_root_task_weight(&self, root: TaskId, weight: usize) {
+        if let Some(fair_scheduling) = &self.fair_scheduling {
+            fair_scheduling.set_weight(root, weight);
+        }
+    }
+
+    /// Returns the number of tasks waiting for their turn with
+    /// [FairScheduling], by root task.
+    pub fn waiting_tasks_by_root(&self) -> Vec<(TaskId, usize)> {
+        self.fair_scheduling
+            .as_ref()
+            .map_or_else(Vec::new, |fair_scheduling| fair_scheduling.waiting_counts())
+    }
+
+    /// Returns the number of unfinished tasks [FairScheduling] knows the root
+    /// task of.
+    pub fn fair_scheduling_tracked_tasks(&self) -> usize {
+        self.fair_scheduling
+            .as_ref()
+            .map_or(0, |fair_scheduling| fair_scheduling.tracked_count())
+    }
+
+    fn start_task(&self, task_id: TaskId) {
         self.begin_primary_job();
         self.scheduled_tasks.fetch_add(1, Ordering::AcqRel);
+        if let Some(queued_tasks) = &self.queued_tasks {
+            queued_tasks.queued(task_id);
+        }
 
         #[cfg(feature = "tokio_tracing")]
         let description = self.backend.get_task_description(task_id);
 
         let this = self.pin();
         let future = async move {
+            if let Some(queued_tasks) = &this.queued_tasks {
+                queued_tasks.started(task_id);
+            }
             loop {
                 if this.stopped.load(Ordering::Acquire) {
                     break;
                 }
                 if let Some(execution) = this.backend.try_start_task_execution(task_id, &*this) {
+                    if let Some(watchdog) = &this.watchdog {
+                        watchdog.execution_started(task_id);
+                    }
+                    let completion_hooks = this.run_execution_hooks(task_id);
                     // Setup thread locals
-                    let (result, duration, instant) = CELL_COUNTERS
-                        .scope(Default::default(), async {
-                            let (result, duration, instant) =
-                                TimedFuture::new(AssertUnwindSafe(execution.future).catch_unwind())
-                                    .await;
-                            (result, duration, instant)
-                        })
-                        .await;
+                    let (result, duration, instant, staged_cell_updates, cell_counts, cell_order) =
+                        CELL_COUNTERS
+                            .scope(
+                                Default::default(),
+                                CELL_ORDER.scope(
+                                    Default::default(),
+                                    STAGED_CELL_UPDATES.scope(Default::default(), async {
+                                        let (result, duration, instant) = TimedFuture::new(
+                                            AssertUnwindSafe(
+                                                this.task_contexts.scope(task_id, execution.future),
+                                            )
+                                            .catch_unwind(),
+                                        )
+                                        .await;
+                                        let staged_cell_updates =
+                                            STAGED_CELL_UPDATES.with(|updates| updates.take());
+                                        let cell_counts =
+                                            CELL_COUNTERS.with(|counts| counts.take());
+                                        let cell_order = CELL_ORDER.with(|order| order.take());
+                                        (
+                                            result,
+                                            duration,
+                                            instant,
+                                            staged_cell_updates,
+                                            cell_counts,
+                                            cell_order,
+                                        )
+                                    }),
+                                ),
+                            )
+                            .await;
                     if cfg!(feature = "log_function_stats") && duration.as_millis() > 1000 {
                         println!(
                             "{} took {}",
@@ -391,11 +1212,39 @@ impl<B: Backend> TurboTasks<B> {
                             Err(_) => None,
                         },
                     });
+                    if let (Some(updates), Ok(Ok(_))) = (staged_cell_updates, &result) {
+                        for (index, content) in updates.iter() {
+                            this.content_cell_updated(task_id, *index, content);
+                        }
+                        this.backend.update_task_cells(
+                            task_id,
+                            updates.into_iter().collect(),
+                            &*this,
+                        );
+                    }
+                    if let Ok(Ok(_)) = &result {
+                        this.backend
+                            .task_execution_cell_counts(task_id, &cell_counts, &*this);
+                        if let Some(content_cache) = &this.content_cache {
+                            content_cache.prune(task_id, &cell_counts);
+                        }
+                        if cfg!(debug_assertions) {
+                            this.cell_conflicts.check(task_id, cell_order, || {
+                                this.backend.get_task_description(task_id)
+                            });
+                        }
+                    }
                     this.backend.task_execution_result(task_id, result, &*this);
                     this.notify_scheduled_tasks_internal();
                     let reexecute = this
                         .backend
                         .task_execution_completed(task_id, duration, instant, &*this);
+                    if let Some(watchdog) = &this.watchdog {
+                        watchdog.execution_finished(task_id);
+                    }
+                    for hook in completion_hooks {
+                        hook();
+                    }
                     if !reexecute {
                         break;
                     }
@@ -403,11 +1252,74 @@ impl<B: Backend> TurboTasks<B> {
                     break;
                 }
             }
+            if let Some(fair_scheduling) = &this.fair_scheduling {
+                fair_scheduling.task_finished(task_id);
+            }
             this.finish_primary_job();
             anyhow::Ok(())
         };
 
-        let future = TURBO_TASKS.scope(
+        // Nest the execution in the span of the task that scheduled it
+        let future = future.instrument(Span::current());
+
+        // Executions of functions with a concurrency limit wait for a permit
+        // in the order they have been scheduled and count as queued until then
+        let permits = self.function_permits(task_id);
+
+        if self.backend.is_blocking_task(task_id) {
+            // It doesn't occupy a worker, so it's not counted in `busy_workers`
+            let future = self.task_scope(task_id, future);
+            let Some(permits) = permits else {
+                self.blocking_pool.spawn(self.executor.handle(), future);
+                return;
+            };
+            // Waits for the permit on the executor, so waiting executions
+            // don't occupy threads of the blocking pool
+            let blocking_pool = self.blocking_pool.clone();
+            let handle = self.executor.handle();
+            self.executor.handle().spawn(async move {
+                let permit = permits.acquire_owned().await.unwrap();
+                blocking_pool.spawn(handle, async move {
+                    let result = future.await;
+                    drop(permit);
+                    result
+                });
+            });
+            return;
+        }
+
+        let future = BusyFuture::new(self.busy_workers.clone(), with_permit(permits, future));
+        let future = self.task_scope(task_id, future);
+
+        #[cfg(feature = "tokio_tracing")]
+        tokio::task::Builder::new()
+            .name(&description)
+            .spawn_on(future, &self.executor.handle())
+            .unwrap();
+        #[cfg(not(feature = "tokio_tracing"))]
+        self.executor.handle().spawn(future);
+    }
+
+    /// Returns the permits of the function of the task, when the function has
+    /// a [crate::NativeFunction::max_concurrency].
+    fn function_permits(&self, task_id: TaskId) -> Option<Arc<Semaphore>> {
+        let function = self.backend.get_task_function(task_id)?;
+        let max_concurrency = registry::get_function(function).max_concurrency?;
+        Some(
+            self.function_permits
+                .entry(function)
+                .or_insert_with(|| Arc::new(Semaphore::new(max_concurrency)))
+                .clone(),
+        )
+    }
+
+    /// Sets up the task locals for the execution of a task.
+    fn task_scope<T: Future<Output = Result<()>> + Send + 'static>(
+        &self,
+        task_id: TaskId,
+        future: T,
+    ) -> impl Future<Output = Result<()>> + Send + 'static {
+        TURBO_TASKS.scope(
             self.pin(),
             CURRENT_TASK_ID.scope(
                 task_id,
@@ -416,15 +1328,110 @@ impl<B: Backend> TurboTasks<B> {
                     self.backend.execution_scope(task_id, future),
                 ),
             ),
-        );
+        )
+    }
 
-        #[cfg(feature = "tokio_tracing")]
-        tokio::task::Builder::new()
-            .name(&description)
-            .spawn(future)
-            .unwrap();
-        #[cfg(not(feature = "tokio_tracing"))]
-        tokio::task::spawn(future);
+    /// Runs the hooks registered for the next execution of the task and
+    /// returns the closures to call when the execution has completed.
+    fn run_execution_hooks(&self, task_id: TaskId) -> Vec<Box<dyn FnOnce() + Send>> {
+        match self.execution_hooks.remove(&task_id) {
+            Some((_, hooks)) => hooks.into_iter().map(|hook| hook()).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Invalidated tasks are throttled by the [InvalidationBudget] when it's
+    /// configured, they don't wait for their turn with [FairScheduling] on top
+    /// of that.
+    fn schedule_invalidated(&self, task_id: TaskId, priority: bool) {
+        let Some(budget) = &self.invalidation_budget else {
+            self.schedule(task_id);
+            return;
+        };
+        match budget.admit(task_id, priority) {
+            Admission::Schedule => self.start_task(task_id),
+            Admission::Deferred { start_draining } => {
+                if start_draining {
+                    self.drain_ticks(budget.tick(), |this| {
+                        this.invalidation_budget.as_ref().unwrap().next_tick()
+                    });
+                }
+            }
+        }
+    }
+
+    /// Returns an estimate of the number of worker threads that are not
+    /// executing tasks or foreground jobs at the moment.
+    pub fn idle_workers(&self) -> usize {
+        self.executor
+            .worker_threads()
+            .saturating_sub(self.busy_workers.load(Ordering::Relaxed))
+    }
+
+    /// Returns the utilization of the blocking thread pool by tasks of blocking
+    /// functions.
+    pub fn blocking_pool_stats(&self) -> BlockingPoolStats {
+        self.blocking_pool.stats()
+    }
+
+    /// Returns the number of invalidated tasks that have been deferred by the
+    /// [InvalidationBudget] and are not scheduled yet.
+    pub fn deferred_invalidations_count(&self) -> usize {
+        self.invalidation_budget
+            .as_ref()
+            .map_or(0, |budget| budget.deferred_count())
+    }
+
+    /// Returns the number of background jobs that are scheduled or running.
+    pub fn background_jobs_count(&self) -> usize {
+        self.currently_scheduled_background_jobs
+            .load(Ordering::Acquire)
+    }
+
+    /// Returns the state of the scheduler, with the `top` functions with the
+    /// most queued tasks. The counters are read one after another, so they
+    /// might not be consistent with each other while tasks are executing.
+    /// Queued tasks are only reported with
+    /// [TurboTasksBuilder::track_queued_tasks].
+    pub fn scheduler_snapshot(&self, top: usize) -> SchedulerSnapshot {
+        let queued = self
+            .queued_tasks
+            .as_ref()
+            .map_or_else(Vec::new, |queued_tasks| queued_tasks.snapshot());
+        let mut functions = HashMap::<Option<FunctionId>, usize>::new();
+        for (task, _) in queued.iter() {
+            *functions
+                .entry(self.backend.get_task_function(*task))
+                .or_default() += 1;
+        }
+        let mut top_functions = functions
+            .into_iter()
+            .map(|(function, count)| {
+                let name = match function {
+                    Some(function) => registry::get_function(function).name.clone(),
+                    None => "(other)".to_string(),
+                };
+                (name, count)
+            })
+            .collect::<Vec<_>>();
+        top_functions.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then_with(|| a_name.cmp(b_name)));
+        top_functions.truncate(top);
+        SchedulerSnapshot {
+            queued_tasks: queued.len(),
+            waiting_tasks: self
+                .waiting_tasks_by_root()
+                .into_iter()
+                .map(|(_, count)| count)
+                .sum(),
+            in_progress: self.get_in_progress_count(),
+            foreground_jobs: self
+                .currently_scheduled_foreground_jobs
+                .load(Ordering::Acquire),
+            background_jobs: self.background_jobs_count(),
+            deferred_invalidations: self.deferred_invalidations_count(),
+            oldest_queued: queued.iter().map(|(_, duration)| *duration).max(),
+            top_functions,
+        }
     }
 
     fn begin_primary_job(&self) {
@@ -449,6 +1456,17 @@ impl<B: Backend> TurboTasks<B> {
             .fetch_sub(1, Ordering::AcqRel)
             == 1
         {
+            // The update has finished before it's reported below
+            let generation = self.update_generation.fetch_add(1, Ordering::AcqRel) + 1;
+            if let Some(memo_table) = self.backend.memo_table() {
+                if memo_table.finish_generation(generation) {
+                    self.schedule_background_job(|this| async move {
+                        if let Some(memo_table) = this.backend.memo_table() {
+                            memo_table.collect();
+                        }
+                    });
+                }
+            }
             // That's not super race-condition-safe, but it's only for
             // statistical reasons
             let total = self.scheduled_tasks.load(Ordering::Acquire);
@@ -467,6 +1485,9 @@ impl<B: Backend> TurboTasks<B> {
     }
 
     fn finish_foreground_job(&self) {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.job_finished();
+        }
         if self
             .currently_scheduled_foreground_jobs
             .fetch_sub(1, Ordering::AcqRel)
@@ -521,7 +1542,7 @@ impl<B: Backend> TurboTasks<B> {
             }
             loop {
                 select! {
-                    () = tokio::time::sleep(aggregation) => {
+                    () = platform::sleep(aggregation) => {
                         break;
                     }
                     () = self.event.listen() => {
@@ -577,25 +1598,31 @@ impl<B: Backend> TurboTasks<B> {
         let this = self.pin();
         self.currently_scheduled_background_jobs
             .fetch_add(1, Ordering::AcqRel);
-        tokio::spawn(TURBO_TASKS.scope(this.clone(), async move {
-            while this.currently_scheduled_tasks.load(Ordering::Acquire) != 0 {
-                let listener = this.event.listen();
-                if this.currently_scheduled_tasks.load(Ordering::Acquire) != 0 {
-                    listener.await;
+        self.executor
+            .handle()
+            .spawn(TURBO_TASKS.scope(this.clone(), async move {
+                while this.currently_scheduled_tasks.load(Ordering::Acquire) != 0 {
+                    let listener = this.event.listen();
+                    if this.currently_scheduled_tasks.load(Ordering::Acquire) != 0 {
+                        listener.await;
+                    }
                 }
-            }
-            let this2 = this.clone();
-            if !this.stopped.load(Ordering::Acquire) {
-                func(this).await;
-            }
-            if this2
-                .currently_scheduled_background_jobs
-                .fetch_sub(1, Ordering::AcqRel)
-                == 1
-            {
-                this2.event_background.notify(usize::MAX);
-            }
-        }));
+                let this2 = this.clone();
+                let _permit = match this2.background_job_permits {
+                    Some(ref permits) => Some(permits.acquire().await.unwrap()),
+                    None => None,
+                };
+                if !this.stopped.load(Ordering::Acquire) {
+                    func(this).await;
+                }
+                if this2
+                    .currently_scheduled_background_jobs
+                    .fetch_sub(1, Ordering::AcqRel)
+                    == 1
+                {
+                    this2.event_background.notify(usize::MAX);
+                }
+            }));
     }
 
     #[track_caller]
@@ -608,12 +1635,15 @@ impl<B: Backend> TurboTasks<B> {
     ) {
         let this = self.pin();
         this.begin_foreground_job();
-        tokio::spawn(TURBO_TASKS.scope(this.clone(), async move {
+        let future = TURBO_TASKS.scope(this.clone(), async move {
             if !this.stopped.load(Ordering::Acquire) {
                 func(this.clone()).await;
             }
             this.finish_foreground_job();
-        }));
+        });
+        self.executor
+            .handle()
+            .spawn(BusyFuture::new(self.busy_workers.clone(), future));
     }
 
     fn notify_scheduled_tasks_internal(&self) {
@@ -678,6 +1708,18 @@ impl<B: Backend> TurboTasksApi for TurboTasks<B> {
         self.backend.invalidate_task(task, self);
     }
 
+    fn invalidate_and_wait(&self, task: TaskId) -> Pin<Box<dyn Future<Output = ()> + Send>> {
+        // Invalidation schedules active tasks right away, so they are unfinished
+        // before waiting
+        self.backend.invalidate_task(task, self);
+        let this = self.pin();
+        Box::pin(async move {
+            while let Some(listener) = this.backend.task_done_listener(task, &*this) {
+                listener.await;
+            }
+        })
+    }
+
     fn notify_scheduled_tasks(&self) {
         let _ = TASKS_TO_NOTIFY.try_with(|tasks| {
             let tasks = tasks.take();
@@ -701,6 +1743,16 @@ impl<B: Backend> TurboTasksApi for TurboTasks<B> {
         )
     }
 
+    fn try_read_task_outputs(&self, tasks: &[TaskId]) -> Vec<Result<Result<RawVc, EventListener>>> {
+        self.backend
+            .try_read_task_outputs(tasks, current_task("reading Vcs"), self)
+    }
+
+    fn try_read_task_output_stale(&self, task: TaskId) -> Result<Result<RawVc, EventListener>> {
+        self.backend
+            .try_read_task_output_stale(task, current_task("reading Vcs"), self)
+    }
+
     fn try_read_task_output_untracked(
         &self,
         task: TaskId,
@@ -715,6 +1767,9 @@ impl<B: Backend> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         index: CellId,
     ) -> Result<Result<CellContent, EventListener>> {
+        if let Some(content) = staged_cell_content(task, index) {
+            return Ok(Ok(content));
+        }
         self.backend
             .try_read_task_cell(task, index, current_task("reading Vcs"), self)
     }
@@ -724,6 +1779,9 @@ impl<B: Backend> TurboTasksApi for TurboTasks<B> {
         task: TaskId,
         index: CellId,
     ) -> Result<Result<CellContent, EventListener>> {
+        if let Some(content) = staged_cell_content(task, index) {
+            return Ok(Ok(content));
+        }
         self.backend.try_read_task_cell_untracked(task, index, self)
     }
 
@@ -732,6 +1790,9 @@ impl<B: Backend> TurboTasksApi for TurboTasks<B> {
         current_task: TaskId,
         index: CellId,
     ) -> Result<CellContent> {
+        if let Some(content) = staged_cell_content(current_task, index) {
+            return Ok(content);
+        }
         self.backend
             .try_read_own_task_cell_untracked(current_task, index, self)
     }
@@ -784,12 +1845,200 @@ impl<B: Backend> TurboTasksApi for TurboTasks<B> {
     }
 
     fn update_current_task_cell(&self, index: CellId, content: CellContent) {
-        self.backend.update_task_cell(
-            current_task("cellting turbo_tasks values"),
-            index,
-            content,
-            self,
-        );
+        let is_staging = STAGED_CELL_UPDATES
+            .try_with(|updates| updates.borrow().is_some())
+            .unwrap_or(false);
+        if is_staging {
+            STAGED_CELL_UPDATES.with(|updates| {
+                updates
+                    .borrow_mut()
+                    .as_mut()
+                    .unwrap()
+                    .insert(index, content)
+            });
+        } else {
+            let task = current_task("cellting turbo_tasks values");
+            self.content_cell_updated(task, index, &content);
+            self.backend.update_task_cell(task, index, content, self);
+        }
+    }
+
+    fn flush_current_task_cell_updates(&self) {
+        let updates = STAGED_CELL_UPDATES
+            .try_with(|updates| updates.borrow_mut().as_mut().map(take))
+            .ok()
+            .flatten();
+        let Some(updates) = updates.filter(|updates| !updates.is_empty()) else {
+            return;
+        };
+        let task = current_task("flushing cell updates");
+        for (index, content) in updates.iter() {
+            self.content_cell_updated(task, *index, content);
+        }
+        self.backend
+            .update_task_cells(task, updates.into_iter().collect(), self);
+    }
+
+    fn mark_current_task_final(&self) {
+        self.backend
+            .mark_task_final(current_task("marking a task final"), self);
+    }
+
+    fn set_current_task_label(&self, label: String) {
+        self.backend
+            .set_task_label(current_task("labeling a task"), label, self);
+    }
+
+    fn add_current_task_tag(&self, tag: String) {
+        self.backend
+            .add_task_tag(current_task("tagging a task"), tag, self);
+    }
+
+    fn on_next_execution(&self, task: TaskId, hook: ExecutionHook) {
+        self.execution_hooks.entry(task).or_default().push(hook);
+    }
+
+    fn on_task_released(&self, task: TaskId, hook: ReleaseHook) {
+        self.release_hooks.entry(task).or_default().push(hook);
+    }
+
+    fn prefetch(&self, task: TaskId, with_children: bool) {
+        // Speculative work is droppable, so it's not worth queuing up
+        if self.pending_prefetches.fetch_add(1, Ordering::AcqRel) >= MAX_PENDING_PREFETCHES {
+            self.pending_prefetches.fetch_sub(1, Ordering::AcqRel);
+            return;
+        }
+        // Background jobs wait until no tasks are scheduled, so the prefetch
+        // doesn't delay the work that is needed right now
+        self.schedule_background_job(move |this| async move {
+            this.pending_prefetches.fetch_sub(1, Ordering::AcqRel);
+            this.backend.prefetch_task(task, with_children, &*this);
+        });
+    }
+
+    fn is_task_consistent_at_least(&self, task: TaskId, generation: usize) -> bool {
+        // The generation is increased when all scheduled work has been finished,
+        // so every invalidation up to the generation has reached active tasks.
+        self.update_generation.load(Ordering::Acquire) > generation
+            && self.backend.is_task_active_and_done(task, self)
+    }
+
+    fn memo_table(&self) -> Option<&MemoTable> {
+        self.backend.memo_table()
+    }
+}
+
+/// Configures the executor of a [TurboTasks] instance. Created by
+/// [TurboTasks::builder].
+pub struct TurboTasksBuilder<B: Backend + 'static> {
+    backend: B,
+    runtime_options: RuntimeOptions,
+    max_background_jobs: Option<usize>,
+    input_size_limits: InputSizeLimits,
+    invalidation_budget: Option<InvalidationBudget>,
+    watchdog: Option<WatchdogOptions>,
+    content_store: Option<(Arc<dyn ContentStore>, usize)>,
+    fair_scheduling: Option<FairScheduling>,
+    track_queued_tasks: bool,
+}
+
+impl<B: Backend> TurboTasksBuilder<B> {
+    /// Sets the number of threads that execute tasks. This creates a
+    /// dedicated tokio runtime for the [TurboTasks] instance.
+    pub fn worker_threads(mut self, worker_threads: usize) -> Self {
+        self.runtime_options.worker_threads = Some(worker_threads);
+        self
+    }
+
+    /// Sets the maximum number of threads used by [spawn_blocking].
+    pub fn max_blocking_threads(mut self, max_blocking_threads: usize) -> Self {
+        self.runtime_options.max_blocking_threads = Some(max_blocking_threads);
+        self
+    }
+
+    /// Sets the name of the threads of the dedicated runtime.
+    pub fn thread_name(mut self, thread_name: impl Into<String>) -> Self {
+        self.runtime_options.thread_name = Some(thread_name.into());
+        self
+    }
+
+    /// Sets the stack size in bytes of the threads of the dedicated runtime.
+    pub fn thread_stack_size(mut self, thread_stack_size: usize) -> Self {
+        self.runtime_options.thread_stack_size = Some(thread_stack_size);
+        self
+    }
+
+    /// Limits the number of backend background jobs that run at the same
+    /// time.
+    pub fn max_background_jobs(mut self, max_background_jobs: usize) -> Self {
+        self.max_background_jobs = Some(max_background_jobs);
+        self
+    }
+
+    /// Reports or rejects function calls with large inputs and tracks the
+    /// calls with the largest inputs.
+    pub fn input_size_limits(mut self, input_size_limits: InputSizeLimits) -> Self {
+        self.input_size_limits = input_size_limits;
+        self
+    }
+
+    /// Caps how many invalidated tasks are re-executed per tick. The remaining
+    /// ones are deferred to later ticks, where tasks that feed strongly
+    /// consistent reads are scheduled first.
+    pub fn invalidation_budget(mut self, invalidation_budget: InvalidationBudget) -> Self {
+        self.invalidation_budget = Some(invalidation_budget);
+        self
+    }
+
+    /// Reports when task executions and foreground jobs stop making progress,
+    /// see [WatchdogOptions]. The watchdog is not available on wasm32.
+    pub fn watchdog(mut self, watchdog: WatchdogOptions) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+
+    /// Pushes cell values with a serialized size of at least `min_size` bytes
+    /// to the content-addressable store, see [TurboTasks::content_hash] and
+    /// [TurboTasks::fetch_content]. Values of types without serialization are
+    /// skipped.
+    pub fn content_store(mut self, store: Arc<dyn ContentStore>, min_size: usize) -> Self {
+        self.content_store = Some((store, min_size));
+        self
+    }
+
+    /// Caps how many tasks start per tick and lets root tasks take turns, so
+    /// one root task can't starve the others, see [FairScheduling].
+    pub fn fair_scheduling(mut self, fair_scheduling: FairScheduling) -> Self {
+        self.fair_scheduling = Some(fair_scheduling);
+        self
+    }
+
+    /// Tracks when tasks are scheduled until they start executing, so
+    /// [TurboTasks::scheduler_snapshot] reports the queued tasks. This costs a
+    /// map insert and removal for every scheduled task, so it's off by
+    /// default.
+    pub fn track_queued_tasks(mut self) -> Self {
+        self.track_queued_tasks = true;
+        self
+    }
+
+    /// Creates the [TurboTasks] instance. When any runtime option has been
+    /// set, a dedicated tokio runtime is started, otherwise the ambient
+    /// runtime is used. The runtime options are ignored on wasm32. Fails when
+    /// the runtime can't be started or the startup of the backend fails.
+    pub fn build(self) -> Result<Arc<TurboTasks<B>>> {
+        let executor = Executor::new(self.runtime_options)?;
+        TurboTasks::new_with(
+            self.backend,
+            executor,
+            self.max_background_jobs,
+            self.input_size_limits,
+            self.invalidation_budget,
+            self.watchdog,
+            self.content_store,
+            self.fair_scheduling,
+            self.track_queued_tasks,
+        )
     }
 }
 
@@ -810,6 +2059,21 @@ impl<B: Backend> TurboTasksBackendApi for TurboTasks<B> {
         })
     }
 
+    fn task_released(&self, task: TaskId) {
+        self.cell_conflicts.task_released(task);
+        self.task_contexts.task_released(task);
+        self.execution_hooks.remove(&task);
+        if let Some((_, hooks)) = self.release_hooks.remove(&task) {
+            for hook in hooks {
+                hook();
+            }
+        }
+    }
+
+    fn idle_workers(&self) -> usize {
+        self.idle_workers()
+    }
+
     fn try_foreground_done(&self) -> Result<(), EventListener> {
         if self
             .currently_scheduled_foreground_jobs
@@ -836,7 +2100,7 @@ impl<B: Backend> TurboTasksBackendApi for TurboTasks<B> {
             let mut list = tasks_list.borrow_mut();
             list.extend(tasks.iter());
         });
-        if result.is_err() {
+        if result.is_err() && !add_to_notify_batch(tasks.iter().copied()) {
             self.backend.invalidate_tasks(tasks.to_vec(), self);
         }
     }
@@ -848,17 +2112,25 @@ impl<B: Backend> TurboTasksBackendApi for TurboTasks<B> {
             let mut list = tasks_list.borrow_mut();
             list.extend(tasks.iter());
         });
-        if result.is_err() {
+        if result.is_err() && !add_to_notify_batch(tasks.iter().copied()) {
             self.backend
                 .invalidate_tasks(tasks.iter().copied().collect(), self);
         };
     }
 
+    fn batch_notify_tasks(&self) -> NotifyBatch<'_> {
+        NotifyBatch::new(self)
+    }
+
     #[track_caller]
     fn schedule(&self, task: TaskId) {
         self.schedule(task)
     }
 
+    fn schedule_invalidated(&self, task: TaskId, priority: bool) {
+        self.schedule_invalidated(task, priority)
+    }
+
     fn stats_type(&self) -> StatsType {
         match self.enable_full_stats.load(Ordering::Acquire) {
             true => StatsType::Full,
@@ -876,6 +2148,10 @@ impl<B: Backend> TurboTasksBackendApi for TurboTasks<B> {
     fn program_duration_until(&self, instant: Instant) -> Duration {
         instant - self.program_start
     }
+
+    fn update_generation(&self) -> usize {
+        self.update_generation.load(Ordering::Acquire)
+    }
 }
 
 impl<B: Backend> TaskIdProvider for TurboTasks<B> {
@@ -888,7 +2164,7 @@ impl<B: Backend> TaskIdProvider for TurboTasks<B> {
     }
 }
 
-fn current_task(from: &str) -> TaskId {
+pub(crate) fn current_task(from: &str) -> TaskId {
     match CURRENT_TASK_ID.try_with(|id| *id) {
         Ok(id) => id,
         Err(_) => panic!(
@@ -898,6 +2174,64 @@ fn current_task(from: &str) -> TaskId {
     }
 }
 
+/// Keeps a task active while held. Created by [TurboTasks::keep_alive].
+#[must_use]
+pub struct KeepAliveGuard {
+    handle: Handle,
+    release: Option<Box<dyn FnOnce() + Send + Sync>>,
+}
+
+impl Drop for KeepAliveGuard {
+    fn drop(&mut self) {
+        if let Some(release) = self.release.take() {
+            let _guard = self.handle.enter();
+            release();
+        }
+    }
+}
+
+/// Merges notifications of dependent tasks while held, see
+/// [TurboTasksBackendApi::batch_notify_tasks]. Batches can be nested, the
+/// outermost one notifies the tasks. It must not be held across an await
+/// point, as the batch is tracked per thread.
+#[must_use]
+pub struct NotifyBatch<'a> {
+    turbo_tasks: &'a dyn TurboTasksBackendApi,
+    outermost: bool,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'a> NotifyBatch<'a> {
+    fn new(turbo_tasks: &'a dyn TurboTasksBackendApi) -> Self {
+        let outermost = NOTIFY_BATCH.with(|batch| {
+            let mut batch = batch.borrow_mut();
+            if batch.is_some() {
+                return false;
+            }
+            *batch = Some(IndexSet::default());
+            true
+        });
+        Self {
+            turbo_tasks,
+            outermost,
+            _not_send: PhantomData,
+        }
+    }
+}
+
+impl Drop for NotifyBatch<'_> {
+    fn drop(&mut self) {
+        if !self.outermost {
+            return;
+        }
+        let tasks = NOTIFY_BATCH.with(|batch| batch.borrow_mut().take().unwrap_or_default());
+        if !tasks.is_empty() {
+            self.turbo_tasks
+                .schedule_notify_tasks(&tasks.into_iter().collect::<Vec<_>>());
+        }
+    }
+}
+
 pub struct Invalidator {
     task: TaskId,
     turbo_tasks: Weak<dyn TurboTasksApi>,
@@ -930,6 +2264,30 @@ impl Invalidator {
             turbo_tasks.invalidate(task);
         }
     }
+
+    /// Invalidates the task and waits until it and its active dependents have
+    /// executed again, e.g. so a file watcher can make sure a write is
+    /// reflected before it's read. Resolves right away when the task isn't
+    /// active, as it's only executed again when it's read.
+    ///
+    /// It waits for the scopes of the task, so other tasks in these scopes,
+    /// e.g. below the same root task, delay it too.
+    pub fn invalidate_and_wait(self) -> impl Future<Output = ()> + Send + 'static {
+        let Invalidator {
+            task,
+            turbo_tasks,
+            handle,
+        } = self;
+        let _guard = handle.enter();
+        let wait = turbo_tasks
+            .upgrade()
+            .map(|turbo_tasks| turbo_tasks.invalidate_and_wait(task));
+        async move {
+            if let Some(wait) = wait {
+                wait.await;
+            }
+        }
+    }
 }
 
 impl TraceRawVcs for Invalidator {
@@ -1002,6 +2360,37 @@ pub fn dynamic_call(func: FunctionId, inputs: Vec<TaskInput>) -> RawVc {
     with_turbo_tasks(|tt| tt.dynamic_call(func, inputs))
 }
 
+/// Executes the function as part of the current task, without creating a
+/// task for the call. Nothing is cached, so the function executes on every
+/// call and every execution of the current task. Its reads become
+/// dependencies of the current task and the cells it creates belong to the
+/// current task. Meant for tiny pure helpers where the overhead of a task
+/// dominates, see [crate::NativeFunction::transient_call_count].
+pub async fn call_transient(func: FunctionId, inputs: Vec<TaskInput>) -> Result<RawVc> {
+    let function = registry::get_function(func);
+    function
+        .transient_call_count
+        .fetch_add(1, Ordering::Relaxed);
+    function.bind(&inputs)().await
+}
+
+/// Returns the result of `compute` for the key, computing it only when it's
+/// not cached yet. Unlike a task, it doesn't track dependencies, so `compute`
+/// must be a pure function of the key and the key must identify the
+/// computation, e.g. a dedicated key type per helper. The results are shared
+/// across tasks and evicted when they haven't been used for a while, see
+/// [MemoTable]. Without a memo table in the backend, it always computes.
+pub fn memoize<K, V>(key: K, compute: impl FnOnce(&K) -> V) -> V
+where
+    K: Hash + Eq + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    with_turbo_tasks(|tt| match tt.memo_table() {
+        Some(memo_table) => memo_table.get_or_compute(key, compute),
+        None => compute(&key),
+    })
+}
+
 /// see [TurboTasks] `trait_call`
 pub fn trait_call(
     trait_type: TraitTypeId,
@@ -1049,29 +2438,78 @@ pub fn get_invalidator() -> Invalidator {
     }
 }
 
+/// Holds back all following cell updates of the current task until its
+/// execution has finished successfully. Then they are applied at once, so
+/// other tasks never observe only a part of them. The task itself reads its
+/// staged values. When the execution fails, the staged updates are discarded.
+pub fn stage_cell_updates() {
+    STAGED_CELL_UPDATES.with(|updates| {
+        updates.borrow_mut().get_or_insert_with(HashMap::new);
+    })
+}
+
+/// Applies the cell updates that [stage_cell_updates] has held back so far,
+/// so other tasks can read them before the execution has finished, e.g. to
+/// stream partial results. Following updates are staged again. Tasks that
+/// read a flushed value are invalidated when a later update of the execution
+/// changes the cell. Flushed updates are kept when the execution fails.
+pub fn flush_cell_updates() {
+    with_turbo_tasks(|tt| tt.flush_current_task_cell_updates())
+}
+
+/// Returns the staged content of a cell when it belongs to the current task.
+fn staged_cell_content(task: TaskId, index: CellId) -> Option<CellContent> {
+    STAGED_CELL_UPDATES
+        .try_with(|updates| {
+            let updates = updates.borrow();
+            let updates = updates.as_ref()?;
+            if CURRENT_TASK_ID.try_with(|id| *id).ok()? != task {
+                return None;
+            }
+            updates.get(&index).cloned()
+        })
+        .ok()
+        .flatten()
+}
+
+/// Marks the output and cells of the current task as final: they won't change
+/// anymore once the current execution completes, e.g. because they are
+/// computed from immutable inputs only. Readers skip storing a dependency on
+/// them, which keeps their dependency sets small.
+///
+/// INVALIDATION: Readers are not notified when the task is recomputed anyway,
+/// e.g. after [TurboTasks::invalidate_function], and keep the old values.
+pub fn mark_final() {
+    with_turbo_tasks(|tt| tt.mark_current_task_final())
+}
+
+/// Attaches a human readable label to the current task, e.g. the file that is
+/// processed. It's shown in task descriptions, stats and graph visualizations
+/// next to the function name.
+pub fn set_task_label(label: impl Into<String>) {
+    with_turbo_tasks(|tt| tt.set_current_task_label(label.into()))
+}
+
+/// Tags the current task, e.g. with the page it belongs to, so it can be
+/// selected with [TurboTasks::tasks_with_tag] and invalidated with
+/// [TurboTasks::invalidate_tag]. Tags are removed when the task executes
+/// again, so every execution sets its own tags.
+pub fn tag_task(tag: impl Into<String>) {
+    with_turbo_tasks(|tt| tt.add_current_task_tag(tag.into()))
+}
+
 pub fn emit<T: ValueTraitVc>(collectible: T) {
     with_turbo_tasks(|tt| tt.emit_collectible(T::get_trait_type_id(), collectible.into()))
 }
 
 pub async fn spawn_blocking<T: Send + 'static>(func: impl FnOnce() -> T + Send + 'static) -> T {
-    let (r, d) = tokio::task::spawn_blocking(|| {
-        let start = Instant::now();
-        let r = func();
-        (r, start.elapsed())
-    })
-    .await
-    .unwrap();
+    let (r, d) = platform::run_blocking(func).await;
     timed_future::add_duration(d);
     r
 }
 
 pub fn spawn_thread(func: impl FnOnce() + Send + 'static) {
-    let handle = Handle::current();
-    thread::spawn(move || {
-        let guard = handle.enter();
-        func();
-        drop(guard);
-    });
+    platform::run_on_thread(func);
 }
 
 pub(crate) async fn read_task_output(
@@ -1082,9 +2520,43 @@ pub(crate) async fn read_task_output(
     loop {
         match this.try_read_task_output(id, strongly_consistent)? {
             Ok(result) => return Ok(result),
-            Err(listener) => listener.await,
+            Err(listener) => wait_for_read(listener).await,
+        }
+    }
+}
+
+/// Reads all Vcs concurrently. The outputs of the tasks are read in batches,
+/// so the dependencies on all of them are registered at once and the tasks
+/// are awaited together instead of one after another.
+pub async fn join_all<V, T>(vcs: impl IntoIterator<Item = V>) -> Result<Vec<T>>
+where
+    V: Into<RawVc> + From<RawVc> + IntoFuture<Output = Result<T>>,
+{
+    let tt = turbo_tasks();
+    tt.notify_scheduled_tasks();
+    let mut current: Vec<RawVc> = vcs.into_iter().map(Into::into).collect();
+    loop {
+        let (indices, tasks): (Vec<_>, Vec<_>) = current
+            .iter()
+            .enumerate()
+            .filter_map(|(index, vc)| match *vc {
+                RawVc::TaskOutput(task) => Some((index, task)),
+                RawVc::TaskCell(..) => None,
+            })
+            .unzip();
+        if tasks.is_empty() {
+            break;
         }
+        let mut listeners = Vec::new();
+        for (index, result) in indices.into_iter().zip(tt.try_read_task_outputs(&tasks)) {
+            match result? {
+                Ok(vc) => current[index] = vc,
+                Err(listener) => listeners.push(listener),
+            }
+        }
+        future::join_all(listeners.into_iter().map(wait_for_read)).await;
     }
+    future::try_join_all(current.into_iter().map(|vc| V::from(vc).into_future())).await
 }
 
 /// INVALIDATION: Be careful with this, it will not track dependencies, so
@@ -1097,7 +2569,7 @@ pub(crate) async fn read_task_output_untracked(
     loop {
         match this.try_read_task_output_untracked(id, strongly_consistent)? {
             Ok(result) => return Ok(result),
-            Err(listener) => listener.await,
+            Err(listener) => wait_for_read(listener).await,
         }
     }
 }
@@ -1110,7 +2582,7 @@ pub(crate) async fn read_task_cell(
     loop {
         match this.try_read_task_cell(id, index)? {
             Ok(result) => return Ok(result),
-            Err(listener) => listener.await,
+            Err(listener) => wait_for_read(listener).await,
         }
     }
 }
@@ -1125,7 +2597,7 @@ pub(crate) async fn read_task_cell_untracked(
     loop {
         match this.try_read_task_cell_untracked(id, index)? {
             Ok(result) => return Ok(result),
-            Err(listener) => listener.await,
+            Err(listener) => wait_for_read(listener).await,
         }
     }
 }
@@ -1138,7 +2610,7 @@ pub(crate) async fn read_task_collectibles(
     loop {
         match this.try_read_task_collectibles(id, trait_id)? {
             Ok(result) => return Ok(result),
-            Err(listener) => listener.await,
+            Err(listener) => wait_for_read(listener).await,
         }
     }
 }
@@ -1222,6 +2694,9 @@ pub fn find_cell_by_type(type_id: ValueTypeId) -> CurrentCellRef {
         let current_index = map.entry(type_id).or_default();
         let index = *current_index;
         *current_index += 1;
+        if cfg!(debug_assertions) {
+            let _ = CELL_ORDER.try_with(|order| order.borrow_mut().push(type_id));
+        }
         CurrentCellRef {
             current_task,
             index: CellId { type_id, index },
@@ -0,0 +1,134 @@
+use std::{
+    any::{Any, TypeId},
+    hash::Hash,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use dashmap::DashMap;
+
+use crate::manager::OnceKey;
+
+/// Counters of a [MemoTable].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoTableStats {
+    /// The number of cached results.
+    pub entries: usize,
+    /// The number of [crate::memoize] calls that returned a cached result.
+    pub hits: usize,
+    /// The number of [crate::memoize] calls that computed the result.
+    pub misses: usize,
+    /// The number of results that have been evicted because they were unused.
+    pub evictions: usize,
+}
+
+struct MemoEntry {
+    value: Arc<dyn Any + Send + Sync>,
+    /// The update generation the entry has been used in last.
+    last_used: AtomicUsize,
+}
+
+/// Caches the results of small pure computations across tasks, see
+/// [crate::memoize]. It's owned by the backend. Results that haven't been used
+/// for a number of update generations are evicted by a background job after
+/// an update finishes.
+pub struct MemoTable {
+    entries: DashMap<Box<dyn OnceKey>, MemoEntry>,
+    /// The current update generation.
+    generation: AtomicUsize,
+    /// The update generation of the last collection.
+    last_collected: AtomicUsize,
+    /// A collection is due or running.
+    collecting: AtomicBool,
+    max_idle_generations: usize,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+    evictions: AtomicUsize,
+}
+
+impl MemoTable {
+    /// Creates a table that evicts results that haven't been used in the last
+    /// `max_idle_generations` update generations.
+    pub fn new(max_idle_generations: usize) -> Self {
+        Self {
+            entries: DashMap::new(),
+            generation: AtomicUsize::new(0),
+            last_collected: AtomicUsize::new(0),
+            collecting: AtomicBool::new(false),
+            max_idle_generations,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+            evictions: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the cached result for the key, or computes and caches it. The
+    /// type of the result is part of the key. Concurrent calls with the same
+    /// key might compute the result multiple times, the first result is kept.
+    pub fn get_or_compute<K, V>(&self, key: K, compute: impl FnOnce(&K) -> V) -> V
+    where
+        K: Hash + Eq + Send + Sync + 'static,
+        V: Clone + Send + Sync + 'static,
+    {
+        let generation = self.generation.load(Ordering::Acquire);
+        let key = (TypeId::of::<V>(), key);
+        if let Some(entry) = self.entries.get(&key as &dyn OnceKey) {
+            entry.last_used.store(generation, Ordering::Release);
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return entry.value.downcast_ref::<V>().unwrap().clone();
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        // The lock is not held while computing, so the computation can memoize
+        // other results
+        let value = compute(&key.1);
+        let entry = self
+            .entries
+            .entry(Box::new(key))
+            .or_insert_with(|| MemoEntry {
+                value: Arc::new(value),
+                last_used: AtomicUsize::new(generation),
+            });
+        entry.value.downcast_ref::<V>().unwrap().clone()
+    }
+
+    /// Called when an update generation has finished. Returns true when a
+    /// collection is due, which the caller runs with [MemoTable::collect] off
+    /// the hot path. Collections run at most every `max_idle_generations / 2`
+    /// generations, so unused results are evicted after up to 1.5 times
+    /// `max_idle_generations` generations.
+    pub fn finish_generation(&self, generation: usize) -> bool {
+        self.generation.store(generation, Ordering::Release);
+        let interval = (self.max_idle_generations / 2).max(1);
+        !self.entries.is_empty()
+            && generation >= self.last_collected.load(Ordering::Acquire) + interval
+            && !self.collecting.swap(true, Ordering::AcqRel)
+    }
+
+    /// Evicts the results that haven't been used in the last
+    /// `max_idle_generations` generations and returns how many have been
+    /// evicted.
+    pub fn collect(&self) -> usize {
+        let generation = self.generation.load(Ordering::Acquire);
+        let before = self.entries.len();
+        self.entries.retain(|_, entry| {
+            generation.saturating_sub(entry.last_used.load(Ordering::Acquire))
+                <= self.max_idle_generations
+        });
+        let evicted = before.saturating_sub(self.entries.len());
+        self.evictions.fetch_add(evicted, Ordering::Relaxed);
+        self.last_collected.store(generation, Ordering::Release);
+        self.collecting.store(false, Ordering::Release);
+        evicted
+    }
+
+    pub fn stats(&self) -> MemoTableStats {
+        MemoTableStats {
+            entries: self.entries.len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+        }
+    }
+}
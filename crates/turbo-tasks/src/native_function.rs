@@ -18,6 +18,54 @@ type NativeTaskFn = Box<dyn Fn() -> NativeTaskFuture + Send + Sync>;
 type BoundNativeTaskFn =
     Box<dyn (Fn(&Vec<TaskInput>) -> Result<NativeTaskFn>) + Send + Sync + 'static>;
 
+/// How the inputs of a call are resolved before the task of the function is
+/// looked up.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ResolveMode {
+    /// All inputs are resolved, including Vcs nested in collections. Calls
+    /// that resolve to the same values share a task, but every input is read
+    /// by the resolving task.
+    #[default]
+    Deep,
+    /// Only the first input, e.g. `self` of methods, is resolved. The other
+    /// inputs are passed as they are, so they are only read when the function
+    /// reads them. Calls with different unresolved Vcs of the same value get
+    /// different tasks.
+    Shallow,
+}
+
+impl ResolveMode {
+    /// The inputs that need to be resolved in this mode.
+    fn inputs_to_resolve(self, inputs: &[TaskInput]) -> &[TaskInput] {
+        match self {
+            ResolveMode::Deep => inputs,
+            ResolveMode::Shallow => &inputs[..inputs.len().min(1)],
+        }
+    }
+
+    /// Returns true when the inputs can be used for a task of a function
+    /// without resolving them first.
+    pub fn is_resolved(self, inputs: &[TaskInput]) -> bool {
+        self.inputs_to_resolve(inputs)
+            .iter()
+            .all(|i| i.is_resolved() && !i.is_nothing())
+    }
+
+    /// Resolves the inputs as far as this mode requires.
+    pub async fn resolve(self, inputs: Vec<TaskInput>) -> Result<Vec<TaskInput>> {
+        let count = self.inputs_to_resolve(&inputs).len();
+        let mut resolved_inputs = Vec::with_capacity(inputs.len());
+        for (i, input) in inputs.into_iter().enumerate() {
+            if i < count {
+                resolved_inputs.push(input.resolve().await?);
+            } else {
+                resolved_inputs.push(input);
+            }
+        }
+        Ok(resolved_inputs)
+    }
+}
+
 /// A native (rust) turbo-tasks function. It's used internally by
 /// `#[turbo_tasks::function]`.
 #[turbo_tasks::value(cell = "new", serialization = "none", eq = "manual")]
@@ -32,12 +80,46 @@ pub struct NativeFunction {
     /// A counter that tracks total executions of that function
     #[turbo_tasks(debug_ignore, trace_ignore)]
     pub executed_count: AtomicUsize,
+    /// The number of calls of the function that look up or create its task,
+    /// see [crate::call_transient] for calls that don't.
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    pub cached_call_count: AtomicUsize,
+    /// The number of calls with [crate::call_transient]. They are included in
+    /// `executed_count`.
+    #[turbo_tasks(debug_ignore, trace_ignore)]
+    pub transient_call_count: AtomicUsize,
+    /// Tasks of the function are executed on the blocking thread pool instead
+    /// of the async executor, so CPU heavy functions don't starve other
+    /// tasks. Set with `#[turbo_tasks::function(blocking)]`.
+    pub blocking: bool,
+    /// How calls with unresolved inputs are resolved before the task of the
+    /// function is looked up. Set with
+    /// `#[turbo_tasks::function(shallow_resolve)]`.
+    #[turbo_tasks(trace_ignore)]
+    pub resolve_mode: ResolveMode,
+    /// The maximum number of tasks of the function that execute at the same
+    /// time. Further executions wait in the order they have been scheduled.
+    /// Executions that wait for reads of other tasks don't count, so
+    /// recursive calls of the function don't deadlock. Useful for functions
+    /// that wrap external processes or memory hungry libraries. Set with
+    /// `#[turbo_tasks::function(max_concurrency = 8)]`.
+    pub max_concurrency: Option<usize>,
+    /// A hash of the implementation of the function and the versions of its
+    /// crate and turbo-tasks. Persisted tasks of the function are invalidated
+    /// on startup when it differs from the fingerprint they were computed
+    /// with. Changes to code the function calls are only covered by a version
+    /// bump of the crate.
+    pub fingerprint: u64,
 }
 
 impl Debug for NativeFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("NativeFunction")
             .field("name", &self.name)
+            .field("blocking", &self.blocking)
+            .field("resolve_mode", &self.resolve_mode)
+            .field("max_concurrency", &self.max_concurrency)
+            .field("fingerprint", &self.fingerprint)
             .finish_non_exhaustive()
     }
 }
@@ -51,19 +133,55 @@ impl NativeFunction {
             name,
             bind_fn: Box::new(bind_fn),
             executed_count: AtomicUsize::new(0),
+            cached_call_count: AtomicUsize::new(0),
+            transient_call_count: AtomicUsize::new(0),
+            blocking: false,
+            resolve_mode: ResolveMode::Deep,
+            max_concurrency: None,
+            fingerprint: 0,
         }
     }
 
+    /// See [NativeFunction::blocking].
+    pub fn with_blocking(mut self, blocking: bool) -> Self {
+        self.blocking = blocking;
+        self
+    }
+
+    /// See [NativeFunction::resolve_mode].
+    pub fn with_resolve_mode(mut self, resolve_mode: ResolveMode) -> Self {
+        self.resolve_mode = resolve_mode;
+        self
+    }
+
+    /// See [NativeFunction::max_concurrency].
+    pub fn with_max_concurrency(mut self, max_concurrency: Option<usize>) -> Self {
+        self.max_concurrency = max_concurrency;
+        self
+    }
+
+    /// See [NativeFunction::fingerprint]. `implementation` is the hash of the
+    /// function tokens and `crate_version` the name and version of the crate
+    /// defining the function.
+    pub fn with_fingerprint(mut self, implementation: u64, crate_version: &str) -> Self {
+        // FNV-1a, continuing the hash of the implementation
+        self.fingerprint = crate_version
+            .bytes()
+            .chain(env!("CARGO_PKG_VERSION").bytes())
+            .fold(implementation, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+        self
+    }
+
     /// Creates a functor for execution from a fixed set of inputs.
     pub fn bind(&'static self, inputs: &Vec<TaskInput>) -> NativeTaskFn {
         match (self.bind_fn)(inputs) {
             Ok(native_fn) => Box::new(move || {
                 let r = native_fn();
-                if cfg!(feature = "log_function_stats") {
-                    let count = self.executed_count.fetch_add(1, Ordering::Relaxed);
-                    if count > 0 && count % 100000 == 0 {
-                        println!("{} was executed {}k times", self.name, count / 1000);
-                    }
+                let count = self.executed_count.fetch_add(1, Ordering::Relaxed);
+                if cfg!(feature = "log_function_stats") && count > 0 && count % 100000 == 0 {
+                    println!("{} was executed {}k times", self.name, count / 1000);
                 }
                 r
             }),
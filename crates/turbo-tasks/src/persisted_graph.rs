@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     backend::{CellContent, PersistentTaskType},
-    CellId, RawVc, TaskId,
+    CellId, FunctionId, RawVc, TaskId,
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -189,6 +189,43 @@ pub trait PersistedGraph: Sync + Send {
         api: &dyn PersistedGraphApi,
     ) -> Result<(Vec<TaskId>, Vec<TaskId>)>;
 
+    /// Get the fingerprint of the function implementation that the persisted
+    /// tasks of the function were computed with, see
+    /// [crate::NativeFunction::fingerprint]. Returns None when no fingerprint
+    /// has been stored for the function.
+    #[allow(unused_variables)]
+    fn read_function_fingerprint(
+        &self,
+        function: FunctionId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Store the fingerprint of the current function implementation.
+    #[allow(unused_variables)]
+    fn store_function_fingerprint(
+        &self,
+        function: FunctionId,
+        fingerprint: u64,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Make all stored tasks of the function dirty, because its implementation
+    /// has changed. This is called on startup before
+    /// [PersistedGraph::get_dirty_active_tasks], so active tasks are
+    /// scheduled from there.
+    #[allow(unused_variables)]
+    fn make_function_tasks_dirty(
+        &self,
+        function: FunctionId,
+        api: &dyn PersistedGraphApi,
+    ) -> Result<()> {
+        Ok(())
+    }
+
     /// Stop operations
     #[allow(unused_variables)]
     fn stop(&self, api: &dyn PersistedGraphApi) -> Result<()> {
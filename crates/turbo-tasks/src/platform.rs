@@ -0,0 +1,347 @@
+//! Abstractions over the parts of the environment that differ between native
+//! targets and `wasm32-unknown-unknown`.
+//!
+//! On wasm32 there are no threads, no blocking thread pool, no tokio timer
+//! and `std::time::Instant` panics when used. Everything in turbo-tasks (and
+//! in backends) should go through this module instead of using these
+//! directly.
+
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// A monotonic clock. This is `std::time::Instant` on native targets and a
+/// `performance.now()` based clock on wasm32.
+pub use instant::Instant;
+use pin_project_lite::pin_project;
+/// The future returned by [LocalKey::scope] of a task local declared with
+/// [crate::task_local].
+pub use tokio::task::futures::TaskLocalFuture;
+/// See [crate::task_local].
+pub use tokio::task::LocalKey;
+
+/// Declares task locals, which are set for the duration of a future and
+/// follow it between threads. This is [tokio::task_local], which only needs
+/// the current-thread runtime and works on wasm32 too, but task locals should
+/// be declared with this macro, so the implementation can be changed in one
+/// place.
+#[macro_export]
+macro_rules! task_local {
+    ($($tt:tt)*) => {
+        $crate::macro_helpers::tokio::task_local! { $($tt)* }
+    };
+}
+
+/// Runs a blocking function without blocking the async executor. Returns the
+/// result of the function and the time spent running it.
+///
+/// On wasm32 there is no blocking thread pool, so the function is executed
+/// inline.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn run_blocking<T: Send + 'static>(
+    func: impl FnOnce() -> T + Send + 'static,
+) -> (T, Duration) {
+    tokio::task::spawn_blocking(|| {
+        let start = Instant::now();
+        let r = func();
+        (r, start.elapsed())
+    })
+    .await
+    .unwrap()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn run_blocking<T: Send + 'static>(
+    func: impl FnOnce() -> T + Send + 'static,
+) -> (T, Duration) {
+    let start = Instant::now();
+    let r = func();
+    (r, start.elapsed())
+}
+
+/// Runs a function on a dedicated thread that has the current tokio runtime
+/// entered.
+///
+/// On wasm32 there are no threads, so the function is spawned as a task on the
+/// current runtime instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn run_on_thread(func: impl FnOnce() + Send + 'static) {
+    let handle = tokio::runtime::Handle::current();
+    std::thread::spawn(move || {
+        let guard = handle.enter();
+        func();
+        drop(guard);
+    });
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn run_on_thread(func: impl FnOnce() + Send + 'static) {
+    tokio::spawn(async move { func() });
+}
+
+/// Drives a future to completion on a thread of the blocking pool of the
+/// runtime.
+///
+/// On wasm32 there is no blocking thread pool, so the future is spawned as a
+/// task instead.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn spawn_blocking_future(
+    handle: tokio::runtime::Handle,
+    future: impl Future<Output = ()> + Send + 'static,
+) {
+    handle
+        .clone()
+        .spawn_blocking(move || handle.block_on(future));
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn spawn_blocking_future(
+    handle: tokio::runtime::Handle,
+    future: impl Future<Output = ()> + Send + 'static,
+) {
+    handle.spawn(future);
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type Timer = tokio::time::Sleep;
+
+/// The tokio timer isn't available on wasm32, so timers are driven by
+/// `setTimeout` there.
+#[cfg(target_arch = "wasm32")]
+type Timer = wasm_timer::Delay;
+
+pin_project! {
+    /// A future that completes after a duration, see [sleep].
+    pub(crate) struct Sleep {
+        #[pin]
+        timer: Timer,
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        // The wasm32 timer fails when the timer has been dropped, which can't
+        // happen while it's polled
+        self.project().timer.poll(cx).map(|_| ())
+    }
+}
+
+/// Waits for the given duration.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        timer: tokio::time::sleep(duration),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        timer: wasm_timer::Delay::new(duration),
+    }
+}
+
+#[cfg(feature = "hanging_detection")]
+pin_project! {
+    /// A future that fails with [Elapsed] when the inner future doesn't
+    /// complete in time, see [timeout].
+    pub(crate) struct Timeout<F> {
+        #[pin]
+        future: F,
+        #[pin]
+        sleep: Sleep,
+    }
+}
+
+/// The error of a [Timeout].
+#[cfg(feature = "hanging_detection")]
+#[derive(Debug)]
+pub(crate) struct Elapsed;
+
+/// Waits for the future for at most the given duration, like
+/// `tokio::time::timeout`.
+#[cfg(feature = "hanging_detection")]
+pub(crate) fn timeout<F: Future>(duration: Duration, future: F) -> Timeout<F> {
+    Timeout {
+        future,
+        sleep: sleep(duration),
+    }
+}
+
+#[cfg(feature = "hanging_detection")]
+impl<F> Timeout<F> {
+    pub(crate) fn into_inner(self) -> F {
+        self.future
+    }
+}
+
+#[cfg(feature = "hanging_detection")]
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(output) = this.future.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        this.sleep.poll(cx).map(|()| Err(Elapsed))
+    }
+}
+
+/// Options for the tokio runtime that executes tasks. When no option is set,
+/// the ambient tokio runtime is used.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct RuntimeOptions {
+    pub worker_threads: Option<usize>,
+    pub max_blocking_threads: Option<usize>,
+    pub thread_name: Option<String>,
+    pub thread_stack_size: Option<usize>,
+}
+
+/// The runtime that tasks and backend jobs are spawned on. It's either a
+/// runtime owned by the [crate::TurboTasks] instance or the ambient runtime.
+pub(crate) struct Executor {
+    runtime: Option<tokio::runtime::Runtime>,
+    worker_threads: usize,
+    /// A runtime with an additional worker, see [Executor::add_worker].
+    overflow: once_cell::sync::OnceCell<tokio::runtime::Runtime>,
+    /// Whether new work goes to the overflow runtime, see
+    /// [Executor::stall_ended].
+    stalled: std::sync::atomic::AtomicBool,
+}
+
+/// The number of worker threads of a tokio runtime with default options.
+fn default_worker_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+impl Executor {
+    /// Uses the tokio runtime that is current when spawning.
+    pub fn ambient() -> Self {
+        Self {
+            runtime: None,
+            worker_threads: default_worker_threads(),
+            overflow: Default::default(),
+            stalled: Default::default(),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(options: RuntimeOptions) -> std::io::Result<Self> {
+        if options == RuntimeOptions::default() {
+            return Ok(Self::ambient());
+        }
+        let mut builder = tokio::runtime::Builder::new_multi_thread();
+        builder.enable_all();
+        let worker_threads = options
+            .worker_threads
+            .unwrap_or_else(default_worker_threads);
+        builder.worker_threads(worker_threads);
+        if let Some(max_blocking_threads) = options.max_blocking_threads {
+            builder.max_blocking_threads(max_blocking_threads);
+        }
+        if let Some(thread_name) = options.thread_name {
+            builder.thread_name(thread_name);
+        }
+        if let Some(thread_stack_size) = options.thread_stack_size {
+            builder.thread_stack_size(thread_stack_size);
+        }
+        Ok(Self {
+            runtime: Some(builder.build()?),
+            worker_threads,
+            overflow: Default::default(),
+            stalled: Default::default(),
+        })
+    }
+
+    /// There are no threads on wasm32, so the options are ignored and the
+    /// ambient runtime is used.
+    #[cfg(target_arch = "wasm32")]
+    pub fn new(_options: RuntimeOptions) -> std::io::Result<Self> {
+        Ok(Self::ambient())
+    }
+
+    /// The number of worker threads. For the ambient runtime it's assumed to
+    /// use the default of one thread per core.
+    pub fn worker_threads(&self) -> usize {
+        self.worker_threads
+    }
+
+    pub fn handle(&self) -> tokio::runtime::Handle {
+        if self.stalled.load(std::sync::atomic::Ordering::Acquire) {
+            if let Some(overflow) = self.overflow.get() {
+                return overflow.handle().clone();
+            }
+        }
+        match &self.runtime {
+            Some(runtime) => runtime.handle().clone(),
+            None => tokio::runtime::Handle::current(),
+        }
+    }
+
+    /// Routes everything that is spawned afterwards to a runtime with one
+    /// worker thread, until [Executor::stall_ended] is called. Used when the
+    /// workers are blocked. Futures that have been spawned before stay on their
+    /// runtime. The runtime is started on the first call and reused for later
+    /// stalls.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn add_worker(&self) -> std::io::Result<()> {
+        self.overflow.get_or_try_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .worker_threads(1)
+                .thread_name("turbo-tasks-overflow")
+                .enable_all()
+                .build()
+        })?;
+        self.stalled
+            .store(true, std::sync::atomic::Ordering::Release);
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn is_stalled(&self) -> bool {
+        self.stalled.load(std::sync::atomic::Ordering::Acquire)
+    }
+
+    /// Spawns on the regular runtime again once its workers are no longer
+    /// blocked.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn stall_ended(&self) {
+        self.stalled
+            .store(false, std::sync::atomic::Ordering::Release);
+    }
+
+    /// Blocks the current thread until the future has completed. The ambient
+    /// executor has no runtime to block on, so a temporary one is started and
+    /// shut down afterwards.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn block_on<T>(&self, future: impl Future<Output = T>) -> std::io::Result<T> {
+        match &self.runtime {
+            Some(runtime) => Ok(runtime.block_on(future)),
+            None => {
+                let runtime = tokio::runtime::Builder::new_multi_thread()
+                    .enable_all()
+                    .build()?;
+                Ok(runtime.block_on(future))
+            }
+        }
+    }
+}
+
+impl Drop for Executor {
+    fn drop(&mut self) {
+        // The last reference to TurboTasks might be dropped from within one of its
+        // own tasks, where blocking on the runtime shutdown is not allowed.
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+        if let Some(runtime) = self.overflow.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
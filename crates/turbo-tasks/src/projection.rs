@@ -0,0 +1,98 @@
+use std::{any::Any, future::Future, pin::Pin};
+
+use anyhow::{anyhow, Result};
+use once_cell::sync::OnceCell;
+
+use crate::{self as turbo_tasks, manager::find_cell_by_type, registry, RawVc, Typed};
+
+/// A function that projects the value of a cell to a part of it, e.g. the
+/// exports of a parsed module.
+///
+/// Reading a projection with [RawVc::project] creates a task that reads the
+/// cell and stores the projected value in a cell of its own. That cell is
+/// only updated when the projected value changes, so readers of the
+/// projection are not invalidated when other parts of the value change. The
+/// task is cached like a function call, so a projection of a cell is computed
+/// once and shared by all its readers.
+///
+/// Projections are declared as `static` items and registered like functions
+/// by the generated register code, which gives them a global name that
+/// identifies them in the task graph. So they need to be visible from the
+/// crate root.
+///
+/// ```ignore
+/// static EXPORTS: Projection<Module, Exports> =
+///     Projection::new(|module| Exports(module.exports.clone()));
+///
+/// let exports: ExportsVc = module.project(&EXPORTS);
+/// ```
+pub struct Projection<T, P> {
+    name: OnceCell<String>,
+    project: fn(&T) -> P,
+}
+
+impl<T, P> Projection<T, P> {
+    pub const fn new(project: fn(&T) -> P) -> Self {
+        Self {
+            name: OnceCell::new(),
+            project,
+        }
+    }
+
+    /// Returns the global name of the projection, once it's registered.
+    pub fn name(&self) -> Option<&str> {
+        self.name.get().map(|name| name.as_str())
+    }
+}
+
+impl<T, P> Projection<T, P>
+where
+    T: Any + Send + Sync,
+    P: Typed + PartialEq + Send + Sync + 'static,
+{
+    pub fn register(&'static self, global_name: &str) {
+        if self.name.set(global_name.to_string()).is_ok() {
+            registry::register_projection(global_name, self);
+        }
+    }
+}
+
+/// A [Projection] with erased types, so it can be looked up by its name.
+pub(crate) trait AnyProjection: Send + Sync {
+    fn project(&'static self, source: RawVc)
+        -> Pin<Box<dyn Future<Output = Result<RawVc>> + Send>>;
+}
+
+impl<T, P> AnyProjection for Projection<T, P>
+where
+    T: Any + Send + Sync,
+    P: Typed + PartialEq + Send + Sync + 'static,
+{
+    fn project(
+        &'static self,
+        source: RawVc,
+    ) -> Pin<Box<dyn Future<Output = Result<RawVc>> + Send>> {
+        Box::pin(async move {
+            let value = source.into_read::<T>().await?;
+            let cell = find_cell_by_type(P::get_value_type_id());
+            cell.compare_and_update_shared((self.project)(&*value));
+            Ok(cell.into())
+        })
+    }
+}
+
+/// Returns the projection of the cell, see [RawVc::project].
+pub(crate) fn project_cell<T, P>(source: RawVc, projection: &'static Projection<T, P>) -> RawVc {
+    let name = projection.name().expect(
+        "Projection has not been registered (this should happen via the generated register \
+         function)",
+    );
+    project(source, name.to_string())
+}
+
+#[turbo_tasks::function]
+async fn project(source: RawVc, projection: String) -> Result<RawVc> {
+    let projection = registry::get_projection(&projection)
+        .ok_or_else(|| anyhow!("projection {projection} has not been registered"))?;
+    projection.project(source).await
+}
@@ -18,14 +18,16 @@ use crate::{
     backend::CellContent,
     event::EventListener,
     manager::{
-        find_cell_by_type, read_task_cell, read_task_cell_untracked, read_task_output,
-        read_task_output_untracked, CurrentCellRef, TurboTasksApi,
+        find_cell_by_type, mark_waiting_for_read, read_task_cell, read_task_cell_untracked,
+        read_task_output, read_task_output_untracked, CurrentCellRef, TurboTasksApi,
     },
     primitives::{RawVcSet, RawVcSetVc},
+    projection::{project_cell, Projection},
     registry::{self, get_value_type},
     turbo_tasks,
-    value_type::ValueTraitVc,
-    CollectiblesSource, ReadRef, SharedReference, TaskId, TraitTypeId, ValueTypeId,
+    value_type::{Typed, ValueTraitVc},
+    CollectiblesSource, ConsistentReadFuture, ReadConsistency, ReadRef, SharedReference, TaskError,
+    TaskId, TraitTypeId, ValueTypeId,
 };
 
 #[derive(Error, Debug)]
@@ -40,20 +42,32 @@ pub enum ResolveTypeError {
     ReadError { source: anyhow::Error },
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct CellId {
     pub type_id: ValueTypeId,
     pub index: u32,
 }
 
+/// Formats as `cell #3 (ParseResult)`, with the name of the value type from
+/// the registry.
 impl Display for CellId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}#{}",
-            registry::get_value_type(self.type_id).name,
-            self.index
-        )
+        match registry::get_value_type_name(self.type_id) {
+            Some(name) => write!(f, "cell #{} ({})", self.index, name),
+            None => write!(f, "cell #{} ({})", self.index, self.type_id),
+        }
+    }
+}
+
+impl Debug for CellId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("CellId");
+        debug.field("index", &self.index);
+        match registry::get_value_type_name(self.type_id) {
+            Some(name) => debug.field("type", &name),
+            None => debug.field("type_id", &*self.type_id),
+        };
+        debug.finish()
     }
 }
 
@@ -76,6 +90,18 @@ impl RawVc {
         ReadRawVcFuture::new_strongly_consistent(self)
     }
 
+    /// Like [RawVc::into_strongly_consistent_read], but the read only needs
+    /// to reflect invalidations up to the given update generation, e.g. the
+    /// generation when an event arrived. Once all work scheduled up to that
+    /// generation has finished and the task is active, it avoids the cost of a
+    /// strongly consistent read.
+    pub fn into_read_consistent_at_least<T: Any + Send + Sync>(
+        self,
+        generation: usize,
+    ) -> ReadRawVcFuture<T> {
+        ReadRawVcFuture::new_consistent_at_least(self, generation)
+    }
+
     /// # Safety
     ///
     /// T and U must be binary identical (#[repr(transparent)])
@@ -101,6 +127,19 @@ impl RawVc {
         unsafe { ReadRawVcFuture::new_transparent_strongly_consistent(self) }
     }
 
+    /// # Safety
+    ///
+    /// T and U must be binary identical (#[repr(transparent)])
+    pub unsafe fn into_transparent_read_consistent_at_least<
+        T: Any + Send + Sync,
+        U: Any + Send + Sync,
+    >(
+        self,
+        generation: usize,
+    ) -> ReadRawVcFuture<T, U> {
+        unsafe { ReadRawVcFuture::new_transparent_consistent_at_least(self, generation) }
+    }
+
     /// INVALIDATION: Be careful with this, it will not track dependencies, so
     /// using it could break cache invalidation.
     pub async fn into_read_untracked<T: Any + Send + Sync>(
@@ -193,6 +232,20 @@ impl RawVc {
         }
     }
 
+    /// Casts this value to the trait `T`. Returns `None` when the value
+    /// doesn't implement the trait, has no content or isn't typed, instead of
+    /// erroring like [RawVc::resolve_trait]. Only a failed task or read is
+    /// reported as error.
+    pub async fn try_cast<T: ValueTraitVc>(self) -> Result<Option<T>> {
+        match self.resolve_trait(T::get_trait_type_id()).await {
+            Ok(raw_vc) => Ok(raw_vc.map(T::from)),
+            Err(ResolveTypeError::NoContent | ResolveTypeError::UntypedContent) => Ok(None),
+            Err(
+                ResolveTypeError::TaskError { source } | ResolveTypeError::ReadError { source },
+            ) => Err(source),
+        }
+    }
+
     pub async fn resolve_value(
         self,
         value_type: ValueTypeId,
@@ -256,6 +309,21 @@ impl RawVc {
         }
     }
 
+    /// Resolve the reference like [RawVc::resolve], but return an error of the
+    /// task as [TaskError] value instead of propagating it.
+    ///
+    /// The reading task still depends on the failed task, so it is reexecuted
+    /// when the error goes away. Panics and other fatal errors are rethrown.
+    pub async fn into_result(self) -> Result<Result<RawVc, TaskError>> {
+        match self.resolve().await {
+            Ok(vc) => Ok(Ok(vc)),
+            Err(err) => match TaskError::from_error(&err) {
+                Some(task_error) => Ok(Err(task_error)),
+                None => Err(err),
+            },
+        }
+    }
+
     /// Resolve the reference until it points to a cell directly in a strongly
     /// consistent way.
     ///
@@ -324,6 +392,31 @@ impl RawVc {
         self.cell_local_internal(find_cell_by_type).await
     }
 
+    /// Executes the task of the reference ahead of time, so a later read
+    /// hits a warm cache. It runs at background priority when nothing else
+    /// is scheduled and the current task doesn't depend on it. It's only a
+    /// hint and might be dropped.
+    pub fn prefetch(self) {
+        turbo_tasks().prefetch(self.get_task_id(), false);
+    }
+
+    /// Like [RawVc::prefetch], but also executes the tasks that the task
+    /// called in its last execution.
+    pub fn prefetch_with_children(self) {
+        turbo_tasks().prefetch(self.get_task_id(), true);
+    }
+
+    /// Returns a reference to a part of the value of the cell, computed by the
+    /// [Projection]. Unlike reading the value, reading the projection only
+    /// depends on that part, see [Projection].
+    pub fn project<T, P>(self, projection: &'static Projection<T, P>) -> RawVc
+    where
+        T: Any + Send + Sync,
+        P: Typed + PartialEq + Send + Sync + 'static,
+    {
+        project_cell(self, projection)
+    }
+
     pub fn get_task_id(&self) -> TaskId {
         match self {
             RawVc::TaskOutput(t) | RawVc::TaskCell(t, _) => *t,
@@ -374,7 +467,7 @@ impl Display for RawVc {
                 write!(f, "output of {}", task)
             }
             RawVc::TaskCell(task, index) => {
-                write!(f, "value {} of {}", index, task)
+                write!(f, "{} of {}", index, task)
             }
         }
     }
@@ -383,6 +476,9 @@ impl Display for RawVc {
 pub struct ReadRawVcFuture<T: Any + Send + Sync, U: Any + Send + Sync = T> {
     turbo_tasks: Arc<dyn TurboTasksApi>,
     strongly_consistent: bool,
+    /// Reads the outputs of the last executions instead of waiting for the
+    /// current ones, see [ReadRawVcFuture::stop_waiting_for_consistency].
+    stale: bool,
     current: RawVc,
     listener: Option<EventListener>,
     phantom_data: PhantomData<Pin<Box<(T, U)>>>,
@@ -395,6 +491,7 @@ impl<T: Any + Send + Sync> ReadRawVcFuture<T, T> {
         ReadRawVcFuture {
             turbo_tasks: tt,
             strongly_consistent: false,
+            stale: false,
             current: vc,
             listener: None,
             phantom_data: PhantomData,
@@ -407,11 +504,17 @@ impl<T: Any + Send + Sync> ReadRawVcFuture<T, T> {
         ReadRawVcFuture {
             turbo_tasks: tt,
             strongly_consistent: true,
+            stale: false,
             current: vc,
             listener: None,
             phantom_data: PhantomData,
         }
     }
+
+    fn new_consistent_at_least(vc: RawVc, generation: usize) -> Self {
+        // SAFETY: T and T are binary identical
+        unsafe { Self::new_transparent_consistent_at_least(vc, generation) }
+    }
 }
 
 impl<T: Any + Send + Sync, U: Any + Send + Sync> ReadRawVcFuture<T, U> {
@@ -424,6 +527,7 @@ impl<T: Any + Send + Sync, U: Any + Send + Sync> ReadRawVcFuture<T, U> {
         ReadRawVcFuture {
             turbo_tasks: tt,
             strongly_consistent: false,
+            stale: false,
             current: vc,
             listener: None,
             phantom_data: PhantomData,
@@ -439,6 +543,27 @@ impl<T: Any + Send + Sync, U: Any + Send + Sync> ReadRawVcFuture<T, U> {
         ReadRawVcFuture {
             turbo_tasks: tt,
             strongly_consistent: true,
+            stale: false,
+            current: vc,
+            listener: None,
+            phantom_data: PhantomData,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// T and U must be binary identical (#[repr(transparent)])
+    unsafe fn new_transparent_consistent_at_least(vc: RawVc, generation: usize) -> Self {
+        let tt = turbo_tasks();
+        tt.notify_scheduled_tasks();
+        let strongly_consistent = match vc {
+            RawVc::TaskOutput(task) => !tt.is_task_consistent_at_least(task, generation),
+            RawVc::TaskCell(..) => false,
+        };
+        ReadRawVcFuture {
+            turbo_tasks: tt,
+            strongly_consistent,
+            stale: false,
             current: vc,
             listener: None,
             phantom_data: PhantomData,
@@ -446,6 +571,37 @@ impl<T: Any + Send + Sync, U: Any + Send + Sync> ReadRawVcFuture<T, U> {
     }
 }
 
+impl<T: Any + Send + Sync, U: Any + Send + Sync> ReadRawVcFuture<T, U> {
+    /// Reads with the given [ReadConsistency] instead of the consistency this
+    /// future was created with.
+    pub fn with_consistency(mut self, consistency: ReadConsistency) -> ConsistentReadFuture<T, U> {
+        let timeout = match consistency {
+            ReadConsistency::Eventual => {
+                self.strongly_consistent = false;
+                None
+            }
+            ReadConsistency::Strong => {
+                self.strongly_consistent = true;
+                None
+            }
+            ReadConsistency::StrongWithTimeout(timeout) => {
+                self.strongly_consistent = true;
+                Some(timeout)
+            }
+        };
+        ConsistentReadFuture::new(self, timeout)
+    }
+
+    /// Continues with the outputs of the last executions of the tasks instead
+    /// of waiting for the graph to settle or the tasks to finish. Returns true
+    /// when the read was still waiting.
+    pub(crate) fn stop_waiting_for_consistency(&mut self) -> bool {
+        self.strongly_consistent = false;
+        self.stale = true;
+        self.listener.take().is_some()
+    }
+}
+
 impl<T: Any + Send + Sync, U: Any + Send + Sync> Future for ReadRawVcFuture<T, U> {
     type Output = Result<ReadRef<T, U>>;
 
@@ -458,23 +614,29 @@ impl<T: Any + Send + Sync, U: Any + Send + Sync> Future for ReadRawVcFuture<T, U
                 // SAFETY: listener is from previous pinned this
                 let listener = unsafe { Pin::new_unchecked(listener) };
                 if listener.poll(cx).is_pending() {
+                    mark_waiting_for_read();
                     return Poll::Pending;
                 }
                 this.listener = None;
             }
             let mut listener = match this.current {
-                RawVc::TaskOutput(task) => match this
-                    .turbo_tasks
-                    .try_read_task_output(task, this.strongly_consistent)
-                {
-                    Ok(Ok(vc)) => {
-                        this.strongly_consistent = false;
-                        this.current = vc;
-                        continue 'outer;
+                RawVc::TaskOutput(task) => {
+                    let read = if this.stale {
+                        this.turbo_tasks.try_read_task_output_stale(task)
+                    } else {
+                        this.turbo_tasks
+                            .try_read_task_output(task, this.strongly_consistent)
+                    };
+                    match read {
+                        Ok(Ok(vc)) => {
+                            this.strongly_consistent = false;
+                            this.current = vc;
+                            continue 'outer;
+                        }
+                        Ok(Err(listener)) => listener,
+                        Err(err) => return Poll::Ready(Err(err)),
                     }
-                    Ok(Err(listener)) => listener,
-                    Err(err) => return Poll::Ready(Err(err)),
-                },
+                }
                 RawVc::TaskCell(task, index) => {
                     match this.turbo_tasks.try_read_task_cell(task, index) {
                         Ok(Ok(content)) => {
@@ -491,6 +653,7 @@ impl<T: Any + Send + Sync, U: Any + Send + Sync> Future for ReadRawVcFuture<T, U
                 Poll::Ready(_) => continue,
                 Poll::Pending => {
                     this.listener = Some(listener);
+                    mark_waiting_for_read();
                     return Poll::Pending;
                 }
             };
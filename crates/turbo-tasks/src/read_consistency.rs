@@ -0,0 +1,86 @@
+use std::{
+    any::Any,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use anyhow::Result;
+
+use crate::{platform, ReadRawVcFuture, ReadRef};
+
+/// How long a read waits for the graph to settle, see
+/// [ReadRawVcFuture::with_consistency].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReadConsistency {
+    /// Returns the current value, even while changes are still being applied.
+    Eventual,
+    /// Waits until all children and grandchildren have settled.
+    Strong,
+    /// Waits like [ReadConsistency::Strong], but returns the last known value
+    /// marked as stale when settling takes longer than the timeout, e.g. to
+    /// keep a UI responsive.
+    StrongWithTimeout(Duration),
+}
+
+/// The result of a read with a [ReadConsistency].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsistentRead<T> {
+    pub value: T,
+    /// The read gave up waiting for the graph to settle, so the value may not
+    /// reflect all changes yet.
+    pub stale: bool,
+}
+
+pub struct ConsistentReadFuture<T: Any + Send + Sync, U: Any + Send + Sync = T> {
+    inner: ReadRawVcFuture<T, U>,
+    timeout: Option<Duration>,
+    /// Created on the first poll, as the timer needs the runtime.
+    sleep: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    stale: bool,
+}
+
+impl<T: Any + Send + Sync, U: Any + Send + Sync> ConsistentReadFuture<T, U> {
+    pub(crate) fn new(inner: ReadRawVcFuture<T, U>, timeout: Option<Duration>) -> Self {
+        Self {
+            inner,
+            timeout,
+            sleep: None,
+            stale: false,
+        }
+    }
+}
+
+impl<T: Any + Send + Sync, U: Any + Send + Sync> Future for ConsistentReadFuture<T, U> {
+    type Output = Result<ConsistentRead<ReadRef<T, U>>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // SAFETY: we are not moving this
+        let this = unsafe { self.get_unchecked_mut() };
+        loop {
+            // SAFETY: inner is from previous pinned this
+            let inner = unsafe { Pin::new_unchecked(&mut this.inner) };
+            if let Poll::Ready(result) = inner.poll(cx) {
+                return Poll::Ready(result.map(|value| ConsistentRead {
+                    value,
+                    stale: this.stale,
+                }));
+            }
+            let Some(timeout) = this.timeout else {
+                return Poll::Pending;
+            };
+            let sleep = this
+                .sleep
+                .get_or_insert_with(|| Box::pin(platform::sleep(timeout)));
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            // Settling took too long, continue with the values of the last
+            // executions, also of the tasks that are still executing
+            this.timeout = None;
+            this.sleep = None;
+            this.stale = this.inner.stop_waiting_for_consistency();
+        }
+    }
+}
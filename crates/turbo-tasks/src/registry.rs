@@ -1,4 +1,14 @@
-use std::{fmt::Debug, hash::Hash, ops::Deref};
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fmt::Debug,
+    hash::Hash,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
 
 use dashmap::{mapref::entry::Entry, DashMap};
 use once_cell::sync::Lazy;
@@ -7,6 +17,7 @@ use crate::{
     id::{FunctionId, TraitTypeId, ValueTypeId},
     id_factory::IdFactory,
     no_move_vec::NoMoveVec,
+    projection::AnyProjection,
     NativeFunction, TraitType, ValueType,
 };
 
@@ -15,6 +26,15 @@ static FUNCTIONS_BY_NAME: Lazy<DashMap<String, FunctionId>> = Lazy::new(DashMap:
 static FUNCTIONS_BY_VALUE: Lazy<DashMap<&'static NativeFunction, FunctionId>> =
     Lazy::new(DashMap::new);
 static FUNCTIONS: Lazy<NoMoveVec<(&'static NativeFunction, String)>> = Lazy::new(NoMoveVec::new);
+/// The current implementation of functions that have been registered again
+/// under the same global name.
+static REPLACED_FUNCTIONS: Lazy<DashMap<FunctionId, &'static NativeFunction>> =
+    Lazy::new(DashMap::new);
+/// Avoids the lookup in [REPLACED_FUNCTIONS] as long as no function has been
+/// replaced.
+static HAS_REPLACED_FUNCTIONS: AtomicBool = AtomicBool::new(false);
+/// Log of all function replacements, in order.
+static FUNCTION_REPLACEMENTS: Mutex<Vec<FunctionId>> = Mutex::new(Vec::new());
 
 static VALUE_TYPE_ID_FACTORY: IdFactory<ValueTypeId> = IdFactory::new();
 static VALUE_TYPES_BY_NAME: Lazy<DashMap<String, ValueTypeId>> = Lazy::new(DashMap::new);
@@ -28,6 +48,12 @@ static TRAIT_TYPES_BY_VALUE: Lazy<DashMap<&'static TraitType, TraitTypeId>> =
     Lazy::new(DashMap::new);
 static TRAIT_TYPES: Lazy<NoMoveVec<(&'static TraitType, String)>> = Lazy::new(NoMoveVec::new);
 
+static TRAIT_METHOD_CACHE_HITS: AtomicUsize = AtomicUsize::new(0);
+static TRAIT_METHOD_CACHE_MISSES: AtomicUsize = AtomicUsize::new(0);
+
+/// Projections by their name, see [crate::Projection].
+static PROJECTIONS: Lazy<DashMap<String, &'static dyn AnyProjection>> = Lazy::new(DashMap::new);
+
 fn register_thing<
     K: From<usize> + Deref<Target = usize> + Sync + Send + Copy,
     V: Clone + Hash + Ord + Eq + Sync + Send + Copy,
@@ -65,7 +91,21 @@ fn get_thing_id<
     }
 }
 
+/// Registers a function under its global name. Registering a different
+/// function under an already registered global name replaces the
+/// implementation, e.g. for a reloaded plugin. The replacement keeps the
+/// [FunctionId], so existing tasks can be invalidated and reexecuted with the
+/// new implementation, see [crate::TurboTasks::reload_functions].
 pub fn register_function(global_name: &str, func: &'static NativeFunction) {
+    if let Some(id) = get_function_id_by_global_name(global_name) {
+        if let Entry::Vacant(e) = FUNCTIONS_BY_VALUE.entry(func) {
+            e.insert(id);
+            REPLACED_FUNCTIONS.insert(id, func);
+            HAS_REPLACED_FUNCTIONS.store(true, Ordering::Release);
+            FUNCTION_REPLACEMENTS.lock().unwrap().push(id);
+        }
+        return;
+    }
     register_thing(
         global_name,
         func,
@@ -85,9 +125,44 @@ pub fn get_function_id_by_global_name(global_name: &str) -> Option<FunctionId> {
 }
 
 pub fn get_function(id: FunctionId) -> &'static NativeFunction {
+    if HAS_REPLACED_FUNCTIONS.load(Ordering::Acquire) {
+        if let Some(func) = REPLACED_FUNCTIONS.get(&id).map(|func| *func) {
+            return func;
+        }
+    }
     FUNCTIONS.get(*id).unwrap().0
 }
 
+/// Returns true when the function has been replaced with another
+/// implementation after it was registered.
+pub fn is_function_replaced(id: FunctionId) -> bool {
+    HAS_REPLACED_FUNCTIONS.load(Ordering::Acquire) && REPLACED_FUNCTIONS.contains_key(&id)
+}
+
+/// The number of function replacements so far, to be passed to
+/// [replaced_functions_since].
+pub(crate) fn function_replacements_count() -> usize {
+    FUNCTION_REPLACEMENTS.lock().unwrap().len()
+}
+
+/// Returns the functions that have been replaced after `count` replacements.
+pub(crate) fn replaced_functions_since(count: usize) -> Vec<FunctionId> {
+    let mut functions = FUNCTION_REPLACEMENTS.lock().unwrap()[count..].to_vec();
+    functions.sort();
+    functions.dedup();
+    functions
+}
+
+/// Returns the ids of all registered functions.
+pub fn get_function_ids() -> Vec<FunctionId> {
+    let mut ids: Vec<_> = FUNCTIONS_BY_NAME
+        .iter()
+        .map(|entry| *entry.value())
+        .collect();
+    ids.sort();
+    ids
+}
+
 pub fn get_function_global_name(id: FunctionId) -> &'static str {
     &FUNCTIONS.get(*id).unwrap().1
 }
@@ -119,6 +194,13 @@ pub fn get_value_type_global_name(id: ValueTypeId) -> &'static str {
     &VALUE_TYPES.get(*id).unwrap().1
 }
 
+/// Returns the readable name of the value type, e.g. for diagnostics. Unlike
+/// [get_value_type] it doesn't panic for unknown ids, as diagnostics might
+/// refer to ids of another process.
+pub fn get_value_type_name(id: ValueTypeId) -> Option<&'static str> {
+    VALUE_TYPES.get(*id).map(|(ty, _)| ty.name.as_str())
+}
+
 pub fn register_trait_type(global_name: &str, ty: &'static TraitType) {
     register_thing(
         global_name,
@@ -145,3 +227,97 @@ pub fn get_trait(id: TraitTypeId) -> &'static TraitType {
 pub fn get_trait_type_global_name(id: TraitTypeId) -> &'static str {
     &TRAIT_TYPES.get(*id).unwrap().1
 }
+
+/// Returns all registered value types that implement the given trait, sorted
+/// by their global name.
+pub fn get_trait_implementors(trait_type: TraitTypeId) -> Vec<ValueTypeId> {
+    let mut implementors = VALUE_TYPES_BY_VALUE
+        .iter()
+        .filter(|entry| entry.key().has_trait(&trait_type))
+        .map(|entry| *entry.value())
+        .collect::<Vec<_>>();
+    implementors.sort_by_key(|id| get_value_type_global_name(*id));
+    implementors
+}
+
+/// Returns all traits implemented by the given value type, sorted by their
+/// global name.
+pub fn get_value_type_traits(ty: ValueTypeId) -> Vec<TraitTypeId> {
+    let mut traits = get_value_type(ty).traits_iter().collect::<Vec<_>>();
+    traits.sort_by_key(|id| get_trait_type_global_name(*id));
+    traits
+}
+
+/// Returns true when `supertrait` is the same trait as `trait_type` or one of
+/// its direct or indirect supertraits. Every value type implementing
+/// `trait_type` then implements `supertrait` too.
+pub fn is_supertrait(trait_type: TraitTypeId, supertrait: TraitTypeId) -> bool {
+    let mut queue = vec![trait_type];
+    let mut visited = HashSet::new();
+    while let Some(trait_type) = queue.pop() {
+        if trait_type == supertrait {
+            return true;
+        }
+        if visited.insert(trait_type) {
+            queue.extend(get_trait(trait_type).supertraits_iter());
+        }
+    }
+    false
+}
+
+/// Looks up the implementation of a trait method for a value type, falling
+/// back to the default implementation of the trait. The implementations of the
+/// value type are grouped by trait on the first lookup, so repeated trait calls
+/// on the same value type neither search nor allocate, and the lookup doesn't
+/// take a lock. The value type can't change after it has been registered.
+/// Default implementations are not cached, they are looked up in the trait
+/// whenever the value type doesn't implement the method.
+pub fn resolve_trait_method(
+    ty: ValueTypeId,
+    trait_type: TraitTypeId,
+    name: Cow<'static, str>,
+) -> Option<FunctionId> {
+    let value_type = get_value_type(ty);
+    let by_trait = match value_type.trait_methods_by_trait.get() {
+        Some(by_trait) => {
+            TRAIT_METHOD_CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+            by_trait
+        }
+        None => {
+            TRAIT_METHOD_CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+            value_type
+                .trait_methods_by_trait
+                .get_or_init(|| value_type.trait_methods_by_trait())
+        }
+    };
+    by_trait
+        .get(&trait_type)
+        .and_then(|methods| methods.get(&*name))
+        .or_else(|| get_trait(trait_type).default_trait_methods.get(&*name))
+        .copied()
+}
+
+/// Lookups of [resolve_trait_method] that found the trait methods of the
+/// value type grouped already (hits) or had to group them (misses).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TraitMethodCacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+pub fn trait_method_cache_stats() -> TraitMethodCacheStats {
+    TraitMethodCacheStats {
+        hits: TRAIT_METHOD_CACHE_HITS.load(Ordering::Relaxed),
+        misses: TRAIT_METHOD_CACHE_MISSES.load(Ordering::Relaxed),
+    }
+}
+
+/// Registers a projection under its global name, see
+/// [crate::Projection::register].
+pub(crate) fn register_projection(global_name: &str, projection: &'static dyn AnyProjection) {
+    PROJECTIONS.insert(global_name.to_string(), projection);
+}
+
+pub(crate) fn get_projection(name: &str) -> Option<&'static dyn AnyProjection> {
+    PROJECTIONS.get(name).map(|projection| *projection)
+}
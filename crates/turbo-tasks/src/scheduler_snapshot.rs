@@ -0,0 +1,85 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+use dashmap::DashMap;
+use nohash_hasher::BuildNoHashHasher;
+
+use crate::{platform::Instant, util::FormatDuration, TaskId};
+
+/// The state of the scheduler at a point in time, see
+/// [crate::TurboTasks::scheduler_snapshot]. Its [Display] implementation is a
+/// debug dump.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchedulerSnapshot {
+    /// The number of tasks that are scheduled but haven't started executing.
+    /// Only tracked with [crate::TurboTasksBuilder::track_queued_tasks],
+    /// like [SchedulerSnapshot::oldest_queued] and
+    /// [SchedulerSnapshot::top_functions].
+    pub queued_tasks: usize,
+    /// The number of tasks that are waiting for their turn with
+    /// [crate::FairScheduling] before they are queued.
+    pub waiting_tasks: usize,
+    /// The number of task executions and foreground jobs that are queued or
+    /// running.
+    pub in_progress: usize,
+    /// The number of foreground jobs that are queued or running.
+    pub foreground_jobs: usize,
+    /// The number of background jobs that are queued or running.
+    pub background_jobs: usize,
+    /// The number of invalidated tasks deferred by the
+    /// [crate::InvalidationBudget].
+    pub deferred_invalidations: usize,
+    /// How long the task that has been queued the longest is waiting.
+    pub oldest_queued: Option<Duration>,
+    /// The functions with the most queued tasks, with their number of queued
+    /// tasks, most first. Tasks that don't belong to a function are counted
+    /// as `(other)`.
+    pub top_functions: Vec<(String, usize)>,
+}
+
+impl Display for SchedulerSnapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(f, "queued tasks: {}", self.queued_tasks)?;
+        writeln!(f, "waiting tasks: {}", self.waiting_tasks)?;
+        writeln!(f, "in progress: {}", self.in_progress)?;
+        writeln!(f, "foreground jobs: {}", self.foreground_jobs)?;
+        writeln!(f, "background jobs: {}", self.background_jobs)?;
+        writeln!(f, "deferred invalidations: {}", self.deferred_invalidations)?;
+        if let Some(oldest_queued) = self.oldest_queued {
+            writeln!(f, "oldest queued: {}", FormatDuration(oldest_queued))?;
+        }
+        if !self.top_functions.is_empty() {
+            writeln!(f, "top queued functions:")?;
+            for (name, count) in &self.top_functions {
+                writeln!(f, "  {count} {name}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Tracks when tasks were scheduled until they start executing.
+#[derive(Default)]
+pub(crate) struct QueuedTasks {
+    tasks: DashMap<TaskId, Instant, BuildNoHashHasher<TaskId>>,
+}
+
+impl QueuedTasks {
+    pub fn queued(&self, task: TaskId) {
+        self.tasks.entry(task).or_insert_with(Instant::now);
+    }
+
+    pub fn started(&self, task: TaskId) {
+        self.tasks.remove(&task);
+    }
+
+    /// Returns the queued tasks with how long they are waiting.
+    pub fn snapshot(&self) -> Vec<(TaskId, Duration)> {
+        self.tasks
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().elapsed()))
+            .collect()
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    collections::HashSet,
+    fmt::Debug,
+    mem::{replace, take},
+    ops::Deref,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{
+    get_invalidator,
+    trace::{TraceRawVcs, TraceRawVcsContext},
+    Invalidator,
+};
+
+/// A mutable value that is owned by the task system. Tasks that read it with
+/// [State::get] depend on it and are invalidated when it's changed with
+/// [State::set] or [State::update], e.g. from outside of task execution.
+///
+/// It's usually a field of a value with `serialization = "none"` and
+/// `eq = "manual"`, so the value can be passed to the tasks that read it.
+#[derive(Default)]
+pub struct State<T> {
+    inner: Mutex<StateInner<T>>,
+}
+
+struct StateInner<T> {
+    value: T,
+    /// The tasks that read the value since it was changed last.
+    invalidators: HashSet<Invalidator>,
+}
+
+impl<T: Default> Default for StateInner<T> {
+    fn default() -> Self {
+        Self {
+            value: T::default(),
+            invalidators: HashSet::new(),
+        }
+    }
+}
+
+/// The value of a [State] while it's read. The state can't be changed until
+/// this is dropped.
+pub struct StateRef<'a, T> {
+    inner: MutexGuard<'a, StateInner<T>>,
+}
+
+impl<'a, T> Deref for StateRef<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner.value
+    }
+}
+
+impl<T> State<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            inner: Mutex::new(StateInner {
+                value,
+                invalidators: HashSet::new(),
+            }),
+        }
+    }
+
+    /// Reads the value and makes the current task depend on it. Panics when
+    /// called outside of a task.
+    pub fn get(&self) -> StateRef<'_, T> {
+        let invalidator = get_invalidator();
+        let mut inner = self.inner.lock().unwrap();
+        inner.invalidators.insert(invalidator);
+        StateRef { inner }
+    }
+
+    /// Reads the value without making the current task depend on it.
+    pub fn get_untracked(&self) -> StateRef<'_, T> {
+        StateRef {
+            inner: self.inner.lock().unwrap(),
+        }
+    }
+
+    /// Changes the value and invalidates all tasks that read it, even when
+    /// the value is unchanged. Returns the previous value.
+    pub fn set_unconditionally(&self, value: T) -> T {
+        let mut inner = self.inner.lock().unwrap();
+        let old = replace(&mut inner.value, value);
+        invalidate_readers(inner);
+        old
+    }
+
+    /// Changes the value in place and invalidates all tasks that read it.
+    pub fn update(&self, func: impl FnOnce(&mut T)) {
+        let mut inner = self.inner.lock().unwrap();
+        func(&mut inner.value);
+        invalidate_readers(inner);
+    }
+}
+
+impl<T: PartialEq> State<T> {
+    /// Changes the value and invalidates all tasks that read it. Nothing is
+    /// invalidated when the value is equal to the current one. Returns true
+    /// when the value has changed.
+    pub fn set(&self, value: T) -> bool {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.value == value {
+            return false;
+        }
+        inner.value = value;
+        invalidate_readers(inner);
+        true
+    }
+}
+
+/// Invalidates the tasks that read the value. The lock is released before,
+/// so the invalidated tasks can read the new value right away.
+fn invalidate_readers<T>(mut inner: MutexGuard<'_, StateInner<T>>) {
+    let invalidators = take(&mut inner.invalidators);
+    drop(inner);
+    for invalidator in invalidators {
+        invalidator.invalidate();
+    }
+}
+
+impl<T: Debug> Debug for State<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("State")
+            .field("value", &self.inner.lock().unwrap().value)
+            .finish()
+    }
+}
+
+impl<T: TraceRawVcs> TraceRawVcs for State<T> {
+    fn trace_raw_vcs(&self, context: &mut TraceRawVcsContext) {
+        self.inner.lock().unwrap().value.trace_raw_vcs(context);
+    }
+}
@@ -0,0 +1,69 @@
+use std::sync::Mutex;
+
+use anyhow::Result;
+use futures::future;
+
+use crate::{
+    manager::{read_task_output_untracked, turbo_tasks},
+    RawVc, TurboTasksApi,
+};
+
+/// Waits for a group of Vcs until their tasks are done and strongly
+/// consistent, i.e. until the tasks they depend on have settled as well.
+/// Replaces strongly consistent reads of many Vcs one after another, when
+/// only their completion is needed.
+///
+/// INVALIDATION: Waiting doesn't read the values, so it doesn't make the
+/// current task depend on them.
+#[derive(Default)]
+pub struct TaskBarrier {
+    vcs: Mutex<Vec<RawVc>>,
+}
+
+impl TaskBarrier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the Vc to the group. Vcs can be added until and between calls of
+    /// [TaskBarrier::wait].
+    pub fn attach(&self, vc: impl Into<RawVc>) {
+        self.vcs.lock().unwrap().push(vc.into());
+    }
+
+    /// Returns the number of attached Vcs.
+    pub fn len(&self) -> usize {
+        self.vcs.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vcs.lock().unwrap().is_empty()
+    }
+
+    /// Waits until the tasks of all attached Vcs are done and strongly
+    /// consistent. The tasks are awaited concurrently. Fails with the first
+    /// error of a task.
+    pub async fn wait(&self) -> Result<()> {
+        let vcs = self.vcs.lock().unwrap().clone();
+        let tt = turbo_tasks();
+        tt.notify_scheduled_tasks();
+        future::try_join_all(vcs.into_iter().map(|vc| wait_settled(&*tt, vc))).await?;
+        Ok(())
+    }
+}
+
+/// Follows the output links of the Vc until it reaches a cell, waiting for
+/// every task on the way to be strongly consistent.
+async fn wait_settled(tt: &dyn TurboTasksApi, mut vc: RawVc) -> Result<()> {
+    loop {
+        match vc {
+            RawVc::TaskOutput(task) => {
+                vc = read_task_output_untracked(tt, task, true).await?;
+            }
+            RawVc::TaskCell(task, _) => {
+                read_task_output_untracked(tt, task, true).await?;
+                return Ok(());
+            }
+        }
+    }
+}
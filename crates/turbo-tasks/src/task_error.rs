@@ -0,0 +1,63 @@
+use std::fmt::{Display, Formatter};
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    trace::{TraceRawVcs, TraceRawVcsContext},
+    util::SharedError,
+};
+
+/// An error returned by a task, captured as a value by
+/// [crate::RawVc::into_result]. It keeps the messages of the error chain, so
+/// it can be compared, stored in cells and serialized like any other value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TaskError {
+    message: String,
+    causes: Vec<String>,
+}
+
+impl TaskError {
+    /// Captures an error that a task returned. Returns `None` for errors that
+    /// didn't come from a task output, e.g. panics or failures of the
+    /// backend, which should propagate instead.
+    pub fn from_error(err: &Error) -> Option<Self> {
+        if !err.chain().any(|cause| cause.is::<SharedError>()) {
+            return None;
+        }
+        let mut chain = err.chain().map(|cause| cause.to_string());
+        Some(Self {
+            message: chain.next().unwrap_or_default(),
+            causes: chain.collect(),
+        })
+    }
+
+    /// The message of the outermost error.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The messages of the underlying errors, outermost first.
+    pub fn causes(&self) -> &[String] {
+        &self.causes
+    }
+}
+
+/// Like [anyhow::Error], `{:#}` includes the causes.
+impl Display for TaskError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)?;
+        if f.alternate() {
+            for cause in &self.causes {
+                write!(f, ": {cause}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for TaskError {}
+
+impl TraceRawVcs for TaskError {
+    fn trace_raw_vcs(&self, _context: &mut TraceRawVcsContext) {}
+}
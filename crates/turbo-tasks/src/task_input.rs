@@ -4,6 +4,8 @@ use std::{
     fmt::{Debug, Display},
     future::Future,
     hash::Hash,
+    mem::{size_of, size_of_val},
+    ops::Deref,
     pin::Pin,
     sync::Arc,
 };
@@ -321,13 +323,158 @@ impl<'de> Deserialize<'de> for SharedValue {
     }
 }
 
+/// The number of bytes an [InputString] stores inline. It keeps
+/// [TaskInput] at its current size.
+const INLINE_STRING_LEN: usize = 22;
+
+/// The string of a [TaskInput::String]. Short strings, like most path
+/// segments and identifiers, are stored inline without allocating. Longer
+/// ones are shared, as inputs are cloned for every execution of a task.
+#[derive(Clone)]
+pub struct InputString(Repr);
+
+/// The representation is private, so inline strings can only be created from
+/// a str, which keeps `len` in bounds and the bytes valid UTF-8.
+#[derive(Clone)]
+enum Repr {
+    Inline {
+        len: u8,
+        bytes: [u8; INLINE_STRING_LEN],
+    },
+    Shared(Arc<str>),
+}
+
+impl InputString {
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Repr::Inline { len, bytes } => {
+                // SAFETY: the bytes have been copied from a str, see From<&str>
+                unsafe { std::str::from_utf8_unchecked(&bytes[..*len as usize]) }
+            }
+            Repr::Shared(s) => s,
+        }
+    }
+
+    /// Returns the string as `Arc<str>`, which only allocates for inline
+    /// strings.
+    pub fn to_arc(&self) -> Arc<str> {
+        match &self.0 {
+            Repr::Inline { .. } => self.as_str().into(),
+            Repr::Shared(s) => s.clone(),
+        }
+    }
+
+    /// Returns true when the string is stored inline.
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline { .. })
+    }
+
+    /// Returns the number of bytes allocated for the string, which is 0 for
+    /// inline strings.
+    pub fn allocated_len(&self) -> usize {
+        match &self.0 {
+            Repr::Inline { .. } => 0,
+            Repr::Shared(s) => s.len(),
+        }
+    }
+}
+
+impl Deref for InputString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for InputString {
+    fn from(s: &str) -> Self {
+        if s.len() > INLINE_STRING_LEN {
+            return InputString(Repr::Shared(s.into()));
+        }
+        let mut bytes = [0; INLINE_STRING_LEN];
+        bytes[..s.len()].copy_from_slice(s.as_bytes());
+        InputString(Repr::Inline {
+            len: s.len() as u8,
+            bytes,
+        })
+    }
+}
+
+impl From<String> for InputString {
+    fn from(s: String) -> Self {
+        if s.len() > INLINE_STRING_LEN {
+            InputString(Repr::Shared(s.into()))
+        } else {
+            s.as_str().into()
+        }
+    }
+}
+
+impl From<Arc<str>> for InputString {
+    /// Keeps sharing the string, as it has been allocated already.
+    fn from(s: Arc<str>) -> Self {
+        InputString(Repr::Shared(s))
+    }
+}
+
+impl PartialEq for InputString {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for InputString {}
+
+impl PartialOrd for InputString {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for InputString {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for InputString {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl Debug for InputString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Display for InputString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self.as_str(), f)
+    }
+}
+
+impl Serialize for InputString {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for InputString {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(String::deserialize(deserializer)?.into())
+    }
+}
+
 #[allow(clippy::derive_hash_xor_eq)]
 #[derive(Debug, Hash, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum TaskInput {
     TaskOutput(TaskId),
     TaskCell(TaskId, CellId),
     List(Vec<TaskInput>),
-    String(String),
+    String(InputString),
     Bool(bool),
     Usize(usize),
     I32(i32),
@@ -340,6 +487,22 @@ pub enum TaskInput {
 }
 
 impl TaskInput {
+    /// Estimates the memory size of the input in bytes. Values behind shared
+    /// references are only counted shallowly.
+    pub fn estimated_size(&self) -> usize {
+        size_of::<TaskInput>()
+            + match self {
+                TaskInput::List(list) => list.iter().map(|input| input.estimated_size()).sum(),
+                TaskInput::String(s) => s.allocated_len(),
+                TaskInput::SharedValue(SharedValue(_, value))
+                | TaskInput::TransientSharedValue(TransientSharedValue(value)) => {
+                    size_of_val(&**value)
+                }
+                TaskInput::SharedReference(SharedReference(_, value)) => size_of_val(&**value),
+                _ => 0,
+            }
+    }
+
     pub async fn resolve_to_value(self) -> Result<TaskInput> {
         let tt = turbo_tasks();
         let mut current = self;
@@ -401,17 +564,7 @@ impl TaskInput {
             TaskInput::SharedValue(SharedValue(ty, _))
             | TaskInput::SharedReference(SharedReference(ty, _)) => {
                 if let Some(ty) = *ty {
-                    let key = (trait_type, name.into_owned());
-                    if let Some(func) = registry::get_value_type(ty).get_trait_method(&key) {
-                        Ok(*func)
-                    } else if let Some(func) = registry::get_trait(trait_type)
-                        .default_trait_methods
-                        .get(&key.1)
-                    {
-                        Ok(*func)
-                    } else {
-                        Err(Cow::Owned(key.1))
-                    }
+                    registry::resolve_trait_method(ty, trait_type, name.clone()).ok_or(name)
                 } else {
                     Err(name)
                 }
@@ -468,6 +621,12 @@ impl TaskInput {
     pub fn is_nothing(&self) -> bool {
         matches!(self, TaskInput::Nothing)
     }
+
+    /// Converts the input to a typed value. Strings can be borrowed as `&str`
+    /// or shared as `Arc<str>` without copying them.
+    pub fn try_get<'a, T: FromTaskInput<'a>>(&'a self) -> Result<T, T::Error> {
+        T::try_from(self)
+    }
 }
 
 pub trait FromTaskInput<'a>
@@ -501,7 +660,7 @@ impl Display for TaskInput {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             TaskInput::TaskOutput(task) => write!(f, "task output {}", task),
-            TaskInput::TaskCell(task, index) => write!(f, "cell {} in {}", index, task),
+            TaskInput::TaskCell(task, index) => write!(f, "{} in {}", index, task),
             TaskInput::List(list) => write!(
                 f,
                 "list {}",
@@ -528,13 +687,19 @@ impl Display for TaskInput {
 
 impl From<String> for TaskInput {
     fn from(s: String) -> Self {
-        TaskInput::String(s)
+        TaskInput::String(s.into())
     }
 }
 
 impl From<&str> for TaskInput {
     fn from(s: &str) -> Self {
-        TaskInput::String(s.to_string())
+        TaskInput::String(s.into())
+    }
+}
+
+impl From<Arc<str>> for TaskInput {
+    fn from(s: Arc<str>) -> Self {
+        TaskInput::String(s.into())
     }
 }
 
@@ -642,12 +807,23 @@ impl FromTaskInput<'_> for String {
     }
 }
 
+impl FromTaskInput<'_> for Arc<str> {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &TaskInput) -> Result<Self, Self::Error> {
+        match value {
+            TaskInput::String(str) => Ok(str.to_arc()),
+            _ => Err(anyhow!("invalid task input type, expected string")),
+        }
+    }
+}
+
 impl<'a> FromTaskInput<'a> for &'a str {
     type Error = anyhow::Error;
 
     fn try_from(value: &'a TaskInput) -> Result<Self, Self::Error> {
         match value {
-            TaskInput::String(str) => Ok(str),
+            TaskInput::String(str) => Ok(str.as_str()),
             _ => Err(anyhow!("invalid task input type, expected string")),
         }
     }
@@ -822,3 +998,37 @@ impl TryFrom<&TaskInput> for RawVc {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::TaskInput;
+
+    #[test]
+    fn strings_are_shared() {
+        let string: Arc<str> = Arc::from("a large input");
+        let input = TaskInput::from(string.clone());
+        let shared: Arc<str> = input.try_get().unwrap();
+        assert!(Arc::ptr_eq(&string, &shared));
+        assert_eq!(input.try_get::<&str>().unwrap(), "a large input");
+        assert_eq!(input.try_get::<String>().unwrap(), "a large input");
+        assert_eq!(input, TaskInput::from("a large input".to_string()));
+        assert!(input.try_get::<bool>().is_err());
+    }
+
+    #[test]
+    fn short_strings_are_inline() {
+        let input = TaskInput::from("src/index.js".to_string());
+        assert!(matches!(&input, TaskInput::String(s) if s.is_inline()));
+        assert_eq!(input.try_get::<&str>().unwrap(), "src/index.js");
+        assert_eq!(input, TaskInput::from(Arc::<str>::from("src/index.js")));
+
+        let input = TaskInput::from("node_modules/some-package/dist/index.js");
+        assert!(matches!(&input, TaskInput::String(s) if !s.is_inline()));
+        assert_eq!(
+            input.try_get::<String>().unwrap(),
+            "node_modules/some-package/dist/index.js"
+        );
+    }
+}
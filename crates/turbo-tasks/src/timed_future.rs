@@ -3,11 +3,15 @@ use std::{
     pin::Pin,
     sync::{Arc, Mutex},
     task::{Context, Poll},
-    time::{Duration, Instant},
+    time::Duration,
 };
 
 use pin_project_lite::pin_project;
-use tokio::{task::futures::TaskLocalFuture, task_local};
+
+use crate::{
+    platform::{Instant, TaskLocalFuture},
+    task_local,
+};
 
 task_local! {
     static EXTRA_DURATION: Arc<Mutex<Duration>>;
@@ -6,6 +6,7 @@ use std::{
     sync::Arc,
 };
 
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -16,6 +17,9 @@ use crate::{
 };
 
 pub trait Typed {
+    /// The reference to a cell of the value, e.g. `FooVc` for `Foo`.
+    type Vc: From<RawVc>;
+
     fn get_value_type_id() -> ValueTypeId;
 }
 
@@ -58,6 +62,8 @@ pub trait TypedForInput: Typed {}
 
 type MagicSerializationFn = fn(&dyn MagicAny) -> &dyn erased_serde::Serialize;
 type AnySerializationFn = fn(&(dyn Any + Sync + Send)) -> &dyn erased_serde::Serialize;
+type AnyEqFn = fn(&(dyn Any + Sync + Send), &(dyn Any + Sync + Send)) -> bool;
+pub(crate) type TraitMethodsByTrait = HashMap<TraitTypeId, HashMap<String, FunctionId>>;
 
 // TODO this type need some refactoring when multiple languages are added to
 // turbo-task In this case a trait_method might be of a different function type.
@@ -75,10 +81,16 @@ pub struct ValueType {
     pub traits: HashSet<TraitTypeId>,
     /// List of trait methods available
     pub trait_methods: HashMap<(TraitTypeId, String), FunctionId>,
+    /// [ValueType::trait_methods] by trait, built on the first lookup, see
+    /// [crate::registry::resolve_trait_method].
+    pub(crate) trait_methods_by_trait: OnceCell<TraitMethodsByTrait>,
 
     /// Functors for serialization
     magic_serialization: Option<(MagicSerializationFn, MagicAnyDeserializeSeed)>,
     any_serialization: Option<(AnySerializationFn, AnyDeserializeSeed)>,
+
+    /// Compares values when linking task outputs, see [ValueType::link_eq].
+    link_eq: Option<AnyEqFn>,
 }
 
 impl Hash for ValueType {
@@ -129,6 +141,16 @@ pub fn any_as_serialize<T: Any + Serialize + Send + Sync + 'static>(
     );
 }
 
+fn any_eq<T: PartialEq + 'static>(
+    a: &(dyn Any + Sync + Send),
+    b: &(dyn Any + Sync + Send),
+) -> bool {
+    match (a.downcast_ref::<T>(), b.downcast_ref::<T>()) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
 impl ValueType {
     /// This is internally used by `#[turbo_tasks::value]`
     pub fn new<T>() -> Self {
@@ -136,8 +158,10 @@ impl ValueType {
             name: std::any::type_name::<T>().to_string(),
             traits: HashSet::new(),
             trait_methods: HashMap::new(),
+            trait_methods_by_trait: OnceCell::new(),
             magic_serialization: None,
             any_serialization: None,
+            link_eq: None,
         }
     }
 
@@ -149,11 +173,13 @@ impl ValueType {
             name: std::any::type_name::<T>().to_string(),
             traits: HashSet::new(),
             trait_methods: HashMap::new(),
+            trait_methods_by_trait: OnceCell::new(),
             magic_serialization: Some((
                 <dyn MagicAny>::as_serialize::<T>,
                 MagicAnyDeserializeSeed::new::<T>(),
             )),
             any_serialization: Some((any_as_serialize::<T>, AnyDeserializeSeed::new::<T>())),
+            link_eq: None,
         }
     }
 
@@ -165,11 +191,31 @@ impl ValueType {
             name: std::any::type_name::<T>().to_string(),
             traits: HashSet::new(),
             trait_methods: HashMap::new(),
+            trait_methods_by_trait: OnceCell::new(),
             magic_serialization: None,
             any_serialization: Some((any_as_serialize::<T>, AnyDeserializeSeed::new::<T>())),
+            link_eq: None,
         }
     }
 
+    /// This is internally used by `#[turbo_tasks::value(link_eq)]`
+    pub fn with_link_eq<T: PartialEq + 'static>(mut self) -> Self {
+        self.link_eq = Some(any_eq::<T>);
+        self
+    }
+
+    /// Compares two values of this type with [PartialEq] when the type opted
+    /// into deep equality for output linking. A task output that is linked to
+    /// a different but equal cell doesn't notify the readers of the output.
+    /// Returns None when the type didn't opt in.
+    pub fn link_eq(
+        &self,
+        a: &(dyn Any + Sync + Send),
+        b: &(dyn Any + Sync + Send),
+    ) -> Option<bool> {
+        self.link_eq.map(|eq| eq(a, b))
+    }
+
     pub fn magic_as_serializable<'a>(
         &self,
         arc: &'a Arc<dyn MagicAny>,
@@ -218,6 +264,19 @@ impl ValueType {
         self.trait_methods.get(trait_method_key)
     }
 
+    /// Groups the trait methods by trait, so they can be looked up by a
+    /// borrowed name.
+    pub(crate) fn trait_methods_by_trait(&self) -> TraitMethodsByTrait {
+        let mut by_trait = TraitMethodsByTrait::new();
+        for ((trait_type, name), function) in self.trait_methods.iter() {
+            by_trait
+                .entry(*trait_type)
+                .or_default()
+                .insert(name.clone(), *function);
+        }
+        by_trait
+    }
+
     /// This is internally used by `#[turbo_tasks::value_impl]`
     pub fn register_trait(&mut self, trait_type: TraitTypeId) {
         self.traits.insert(trait_type);
@@ -240,6 +299,9 @@ impl ValueType {
 pub struct TraitType {
     pub name: String,
     pub(crate) default_trait_methods: HashMap<String, FunctionId>,
+    /// Ids of the direct supertraits. They are looked up lazily, as the
+    /// supertraits might not be registered yet when this trait is created.
+    supertraits: Vec<fn() -> TraitTypeId>,
 }
 
 impl Hash for TraitType {
@@ -279,6 +341,7 @@ impl TraitType {
         Self {
             name,
             default_trait_methods: HashMap::new(),
+            supertraits: Vec::new(),
         }
     }
 
@@ -286,6 +349,18 @@ impl TraitType {
         self.default_trait_methods.insert(name, native_fn);
     }
 
+    /// This is internally used by `#[turbo_tasks::value_trait]`
+    pub fn register_supertrait(&mut self, get_trait_type_id: fn() -> TraitTypeId) {
+        self.supertraits.push(get_trait_type_id);
+    }
+
+    /// Returns the direct supertraits of this trait.
+    pub fn supertraits_iter(&self) -> impl Iterator<Item = TraitTypeId> + '_ {
+        self.supertraits
+            .iter()
+            .map(|get_trait_type_id| get_trait_type_id())
+    }
+
     pub fn register(&'static self, global_name: &str) {
         register_trait_type(global_name, self);
     }
@@ -0,0 +1,107 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use crate::{platform::Instant, TaskId};
+
+/// Detects when no task execution or foreground job has made progress for a
+/// while, e.g. because a function blocks all worker threads. A stall is logged
+/// as warning with the tasks that are executing at that moment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchdogOptions {
+    /// How long scheduled work can be stuck before it's reported.
+    pub stall_timeout: Duration,
+    /// Starts an additional worker thread on a stall. It executes tasks and
+    /// jobs that are scheduled while the executions that were running at the
+    /// stall haven't finished. Tasks that are already queued behind a blocked
+    /// worker can't be moved to it.
+    pub add_worker: bool,
+}
+
+impl Default for WatchdogOptions {
+    fn default() -> Self {
+        Self {
+            stall_timeout: Duration::from_secs(10),
+            add_worker: false,
+        }
+    }
+}
+
+pub(crate) struct Watchdog {
+    options: WatchdogOptions,
+    /// Incremented whenever a task execution starts or finishes and whenever
+    /// a foreground job finishes.
+    progress: AtomicUsize,
+    /// The start of the executions that are running at the moment.
+    running: Mutex<HashMap<TaskId, Instant>>,
+    /// The executions that were running when the last stall was detected and
+    /// haven't finished yet.
+    blocking: Mutex<HashSet<TaskId>>,
+    stalls: AtomicUsize,
+}
+
+impl Watchdog {
+    pub fn new(options: WatchdogOptions) -> Self {
+        Self {
+            options,
+            progress: AtomicUsize::new(0),
+            running: Mutex::new(HashMap::new()),
+            blocking: Mutex::new(HashSet::new()),
+            stalls: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn options(&self) -> &WatchdogOptions {
+        &self.options
+    }
+
+    pub fn progress(&self) -> usize {
+        self.progress.load(Ordering::Acquire)
+    }
+
+    pub fn execution_started(&self, task: TaskId) {
+        self.running.lock().unwrap().insert(task, Instant::now());
+        self.progress.fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub fn execution_finished(&self, task: TaskId) {
+        self.running.lock().unwrap().remove(&task);
+        self.blocking.lock().unwrap().remove(&task);
+        self.progress.fetch_add(1, Ordering::AcqRel);
+    }
+
+    pub fn job_finished(&self) {
+        self.progress.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Records a stall and returns the executing tasks with their durations,
+    /// the longest running first.
+    pub fn stall_detected(&self) -> Vec<(TaskId, Duration)> {
+        self.stalls.fetch_add(1, Ordering::AcqRel);
+        let mut running = self
+            .running
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(task, start)| (*task, start.elapsed()))
+            .collect::<Vec<_>>();
+        running.sort_by(|a, b| b.1.cmp(&a.1));
+        *self.blocking.lock().unwrap() = running.iter().map(|(task, _)| *task).collect();
+        running
+    }
+
+    /// Whether an execution that was running at the last stall is still
+    /// running.
+    pub fn is_blocked(&self) -> bool {
+        !self.blocking.lock().unwrap().is_empty()
+    }
+
+    pub fn stalls(&self) -> usize {
+        self.stalls.load(Ordering::Acquire)
+    }
+}